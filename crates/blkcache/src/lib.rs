@@ -0,0 +1,1208 @@
+//! # 块缓存（Block Cache）
+//!
+//! ## Overview
+//! 基于内存的块缓存，用于缓存底层块设备（`BlockDevice`）中的固定大小数据块。
+//! 其目标是：
+//! - 减少对块设备的频繁 I/O 操作
+//! - 提供对块内任意偏移位置的类型安全访问
+//! - 在缓存被修改时延迟写回（write-back）
+//!
+//! 由以下几部分组成：
+//! - `BlockDevice`：块设备接口，由内核的磁盘驱动实现
+//! - `CacheData`：负责以 `BLOCK_SZ` 对齐方式管理原始块数据内存
+//! - `BlockCache`：表示单个块的缓存实例
+//! - `BlockCacheManager`：统一管理多个块缓存，按 LRU 顺序淘汰
+//!
+//! 管理器以 `(设备, 块号)` 为键在有序表中查找，缓存项串成双向链表记录最近使用顺序。
+//! 脏块的写回有三种时机：
+//! - 淘汰时写回
+//! - 周期写回：`flush_expired` 把脏了超过 `DIRTY_EXPIRE_MS` 的块写回
+//! - 按需写回：`sync_all`
+//!
+//! 连续两次未命中的块号相邻时视为顺序读，预读随后的 `READ_AHEAD_BLOCKS` 块，
+//! 预读以一次多块请求（`BlockDevice::read_blocks`）完成。
+//! 命中、未命中、淘汰、写回等计数由 `stats` 给出。
+//!
+//! ## Assumptions
+//! - 所有块大小均为常量 `BLOCK_SZ`，由特性 `block_4096` 选择，须与内核的块大小一致
+//! - 块设备的 `read_block` / `write_block` 能正确处理大小为 `BLOCK_SZ` 的缓冲区
+//! - 上层调用者在使用 `get_ref` / `get_mut` 时，确保偏移与类型布局的正确性
+//! - 脏块的时间来自 `set_clock` 设置的毫秒时钟，未设置时恒为 0
+//!
+//! ## Safety
+//! - 本模块内部大量使用 `unsafe`，主要集中在：
+//!   - 手动内存分配与释放
+//!   - 原始指针到引用的转换
+//! - 所有 `unsafe` 均通过边界检查（offset + size <= BLOCK_SZ）
+//!   与模块级不变量保证其安全性
+//!
+//! ## Invariants
+//! - `CacheData` 持有的内存始终满足：
+//!   - 大小为 `BLOCK_SZ`
+//!   - 对齐方式为 `BLOCK_SZ`
+//! - `BlockCache.modified == true` 表示缓存数据与磁盘不一致
+//! - 被淘汰的脏块一定会写回磁盘，写回完成之前仍可以被找回
+//!
+//! ## Behavior
+//! - 当缓存满时，从最久未使用的一端起回收 `Arc` 强引用计数为 1 的缓存块
+//! - 若所有缓存块都在使用中，则暂时超出容量继续分配，并计入 `overflows`
+//! - 周期写回只尝试获取锁，拿不到锁的块留待下一次
+//! - 读写设备时不持有管理器与缓存块的锁：未命中的块在锁外读入后再登记，
+//!   写回时先在块的锁内复制数据
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::alloc::Layout;
+use core::any::Any;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::ptr::{addr_of, addr_of_mut};
+use core::slice;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::{Mutex, Once};
+
+/// 块大小（字节）
+#[cfg(not(feature = "block_4096"))]
+pub const BLOCK_SZ: usize = 512;
+#[cfg(feature = "block_4096")]
+pub const BLOCK_SZ: usize = 4096;
+
+pub trait BlockDevice: Send + Sync + Any {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+    /// 从 `start_block` 起连续读取 `buf.len() / BLOCK_SZ` 块
+    ///
+    /// 默认逐块读取，能够一次完成多块传输的设备应当覆盖它
+    fn read_blocks(&self, start_block: usize, buf: &mut [u8]) {
+        for (i, chunk) in buf.chunks_mut(BLOCK_SZ).enumerate() {
+            self.read_block(start_block + i, chunk);
+        }
+    }
+    /// 设备的块数
+    fn num_blocks(&self) -> usize;
+    /// 处理设备中断
+    fn handle_irq(&self);
+}
+
+/// 毫秒时钟，用于记录块第一次变脏的时间
+static CLOCK: Once<fn() -> usize> = Once::new();
+
+/// 设置毫秒时钟，只有第一次调用生效
+pub fn set_clock(clock: fn() -> usize) {
+    CLOCK.call_once(|| clock);
+}
+
+/// 当前时间（毫秒）
+fn now_ms() -> usize {
+    CLOCK.get().map_or(0, |clock| clock())
+}
+
+/// 使用 `ManuallyDrop` 确保数据以 `BLOCK_SZ` 对齐方式分配和释放
+///
+/// ## Overview
+/// `CacheData` 封装了一个固定大小的块缓冲区，
+/// 通过手动控制内存分配与释放来保证特殊的对齐要求。
+///
+/// ## Safety
+/// - 内部通过 `alloc` / `dealloc` 手动管理内存
+/// - 禁止默认的 `Box` drop 行为，避免使用错误的对齐方式释放
+struct CacheData(ManuallyDrop<Box<[u8; BLOCK_SZ]>>);
+
+impl CacheData {
+    /// 创建新的缓存数据块
+    ///
+    /// ## Behavior
+    /// - 使用自定义 `Layout` 分配内存
+    /// - 保证大小和对齐方式均为 `BLOCK_SZ`
+    pub fn new() -> Self {
+        let data = unsafe {
+            let raw = alloc::alloc::alloc(Self::layout());
+            Box::from_raw(raw as *mut [u8; BLOCK_SZ])
+        };
+        Self(ManuallyDrop::new(data))
+    }
+
+    /// 返回缓存数据的内存布局描述
+    ///
+    /// ## Invariants
+    /// - size == BLOCK_SZ
+    /// - align == BLOCK_SZ
+    fn layout() -> Layout {
+        Layout::from_size_align(BLOCK_SZ, BLOCK_SZ).unwrap()
+    }
+}
+
+impl Drop for CacheData {
+    /// 手动释放缓存数据内存
+    ///
+    /// ## Safety
+    /// - 必须与 `layout()` 使用完全一致的参数释放
+    fn drop(&mut self) {
+        let ptr = self.0.as_mut_ptr();
+        unsafe { alloc::alloc::dealloc(ptr, Self::layout()) };
+    }
+}
+
+impl AsRef<[u8]> for CacheData {
+    /// 以不可变切片形式访问缓存数据
+    fn as_ref(&self) -> &[u8] {
+        let ptr = self.0.as_ptr();
+        unsafe { slice::from_raw_parts(ptr, BLOCK_SZ) }
+    }
+}
+
+impl AsMut<[u8]> for CacheData {
+    /// 以可变切片形式访问缓存数据
+    fn as_mut(&mut self) -> &mut [u8] {
+        let ptr = self.0.as_mut_ptr();
+        unsafe { slice::from_raw_parts_mut(ptr, BLOCK_SZ) }
+    }
+}
+
+/// 内存中的单个块缓存
+///
+/// ## Fields
+/// - `cache`：实际的块数据
+/// - `block_id`：对应的磁盘块号
+/// - `block_device`：底层块设备
+/// - `modified`：是否被修改过
+/// - `dirty_since`：第一次被修改的时间（毫秒）
+///
+/// ## Invariants
+/// - 若 `modified == true`，则缓存数据尚未写回磁盘，且 `dirty_since` 有值
+pub struct BlockCache {
+    /// 缓存的块数据
+    cache: CacheData,
+    /// 对应的磁盘块编号
+    block_id: usize,
+    /// 关联的块设备
+    block_device: Arc<dyn BlockDevice>,
+    /// 是否被修改
+    modified: bool,
+    /// 第一次被修改的时间（毫秒）
+    dirty_since: Option<usize>,
+}
+
+impl BlockCache {
+    /// 从磁盘加载一个新的块缓存
+    ///
+    /// ## Behavior
+    /// - 分配新的缓存内存
+    /// - 从块设备中读取指定块
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        // for alignment and move effciency
+        let mut cache = CacheData::new();
+        block_device.read_block(block_id, cache.as_mut());
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+            dirty_since: None,
+        }
+    }
+
+    /// 用已经读出的数据建立块缓存，用于预读
+    fn from_data(block_id: usize, block_device: Arc<dyn BlockDevice>, data: &[u8]) -> Self {
+        let mut cache = CacheData::new();
+        cache.as_mut().copy_from_slice(data);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+            dirty_since: None,
+        }
+    }
+
+    /// 获取块内指定偏移处的原始地址（只读）
+    fn addr_of_offset(&self, offset: usize) -> *const u8 {
+        addr_of!(self.cache.as_ref()[offset])
+    }
+
+    /// 获取块内指定偏移处的原始地址（可写）
+    fn addr_of_offset_mut(&mut self, offset: usize) -> *mut u8 {
+        addr_of_mut!(self.cache.as_mut()[offset])
+    }
+
+    /// 获取指定偏移处的类型引用
+    ///
+    /// ## Safety
+    /// - 调用者必须保证 `T` 在该偏移处布局正确
+    /// - 本函数仅检查越界，不检查对齐与语义合法性
+    pub fn get_ref<T>(&self, offset: usize) -> &T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset) as *const T;
+        unsafe { &*addr }
+    }
+
+    /// 获取指定偏移处的可变类型引用
+    ///
+    /// ## Behavior
+    /// - 自动将 `modified` 标记为 true
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T
+    where
+        T: Sized,
+    {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        if !self.modified {
+            self.modified = true;
+            self.dirty_since = Some(now_ms());
+        }
+        let addr = self.addr_of_offset_mut(offset) as *mut T;
+        unsafe { &mut *addr }
+    }
+
+    /// 只读访问接口，使用闭包封装
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    /// 可写访问接口，使用闭包封装
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+
+    /// 将缓存数据同步写回磁盘
+    ///
+    /// ## Behavior
+    /// - 仅当数据被修改过才会写回
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.dirty_since = None;
+            self.block_device
+                .write_block(self.block_id, self.cache.as_ref());
+            STATS.writebacks.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    /// 在缓存被丢弃时自动同步数据
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+/// 顺序读时预读的块数
+const READ_AHEAD_BLOCKS: usize = 8;
+/// 脏块在内存中停留的最长时间
+const DIRTY_EXPIRE_MS: usize = 5000;
+/// 两次周期写回之间的最短间隔
+const FLUSH_INTERVAL_MS: usize = 1000;
+
+/// 链表中的空指针
+const NIL: usize = usize::MAX;
+
+/// 缓存计数
+struct Counters {
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    read_ahead: AtomicUsize,
+    evictions: AtomicUsize,
+    writebacks: AtomicUsize,
+    overflows: AtomicUsize,
+}
+
+static STATS: Counters = Counters {
+    hits: AtomicUsize::new(0),
+    misses: AtomicUsize::new(0),
+    read_ahead: AtomicUsize::new(0),
+    evictions: AtomicUsize::new(0),
+    writebacks: AtomicUsize::new(0),
+    overflows: AtomicUsize::new(0),
+};
+
+/// 下一次周期写回的时间
+static NEXT_FLUSH_MS: AtomicUsize = AtomicUsize::new(0);
+
+/// 块缓存统计
+#[derive(Clone, Copy, Debug)]
+pub struct BlockCacheStats {
+    /// 当前缓存的块数与容量
+    pub cached: usize,
+    pub capacity: usize,
+    pub hits: usize,
+    pub misses: usize,
+    /// 预读进缓存的块数
+    pub read_ahead: usize,
+    pub evictions: usize,
+    /// 写回磁盘的块数
+    pub writebacks: usize,
+    /// 所有块都在使用、只能超出容量分配的次数
+    pub overflows: usize,
+}
+
+impl fmt::Display for BlockCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} blocks, {} hits, {} misses, {} read ahead, {} evictions, {} writebacks, {} overflows",
+            self.cached,
+            self.capacity,
+            self.hits,
+            self.misses,
+            self.read_ahead,
+            self.evictions,
+            self.writebacks,
+            self.overflows
+        )
+    }
+}
+
+/// 缓存项，按最近使用顺序串成双向链表
+struct Entry {
+    key: (usize, usize),
+    cache: Arc<Mutex<BlockCache>>,
+    prev: usize,
+    next: usize,
+}
+
+/// 被淘汰后等待写回的脏块，由淘汰它的调用者在释放管理器的锁后写回
+type Victim = ((usize, usize), Arc<Mutex<BlockCache>>);
+
+/// 未命中时需要从设备读入的块
+struct Miss {
+    key: (usize, usize),
+    /// 随后需要预读的块数
+    ahead: usize,
+    /// 未命中时的 `generation`
+    generation: usize,
+}
+
+/// 在管理器的锁之外从设备读入的块
+struct Loaded {
+    key: (usize, usize),
+    cache: BlockCache,
+    ahead: Vec<BlockCache>,
+    generation: usize,
+}
+
+impl Miss {
+    /// 读入请求的块与预读的块，调用者不持有管理器的锁
+    fn load(self, block_device: &Arc<dyn BlockDevice>) -> Loaded {
+        let block_id = self.key.1;
+        let mut ahead = Vec::new();
+        if self.ahead > 0 {
+            let mut buf = vec![0u8; self.ahead * BLOCK_SZ];
+            block_device.read_blocks(block_id + 1, &mut buf);
+            for (i, data) in buf.chunks(BLOCK_SZ).enumerate() {
+                ahead.push(BlockCache::from_data(
+                    block_id + 1 + i,
+                    block_device.clone(),
+                    data,
+                ));
+            }
+        }
+        Loaded {
+            key: self.key,
+            cache: BlockCache::new(block_id, block_device.clone()),
+            ahead,
+            generation: self.generation,
+        }
+    }
+}
+
+/// 块缓存管理器
+///
+/// ## Overview
+/// 负责统一管理所有块缓存，实现 LRU 替换策略。
+///
+/// ## Fields
+/// - `entries`：缓存项，空闲位置记录在 `free` 中以便复用
+/// - `index`：`(设备, block_id)` 到缓存项下标的映射，
+///   不同设备（整盘与各个分区）的同号块互不混淆
+/// - `head` / `tail`：最近使用与最久未使用的缓存项
+/// - `last_miss`：上一次未命中（或预读到）的块，用于识别顺序读
+/// - `evicting`：已被淘汰、正在写回的脏块
+/// - `generation`：淘汰的脏块写回完成的次数
+///
+/// ## Invariants
+/// - 同一个块至多出现在 `index` 与 `evicting` 之一中
+/// - 持有管理器的锁时不访问设备：未命中的块在锁外读入后再登记，
+///   淘汰的脏块在锁外写回，写回完成前仍留在 `evicting` 中，可以被找回
+///
+/// ## Behavior
+/// - 查找命中时把缓存项移到链表头部
+/// - 未命中则可能触发缓存替换
+/// - 读入期间有淘汰的脏块写回完成时，读入的数据可能早于写回，丢弃后重新查找
+pub struct BlockCacheManager {
+    entries: Vec<Option<Entry>>,
+    free: Vec<usize>,
+    index: BTreeMap<(usize, usize), usize>,
+    head: usize,
+    tail: usize,
+    capacity: usize,
+    last_miss: Option<(usize, usize)>,
+    evicting: BTreeMap<(usize, usize), Arc<Mutex<BlockCache>>>,
+    generation: usize,
+}
+
+/// 以设备对象的地址区分设备
+fn device_id(block_device: &Arc<dyn BlockDevice>) -> usize {
+    Arc::as_ptr(block_device) as *const () as usize
+}
+
+/// 写回一个缓存块
+///
+/// ## Behavior
+/// - 持有块的锁时只复制数据并清除脏标志，写盘时不持有任何锁
+/// - 写盘期间块被再次修改时重新标记为脏，留待下一次写回
+fn write_back(cache: &Mutex<BlockCache>) {
+    let (block_device, block_id, data) = {
+        let mut cache = cache.lock();
+        if !cache.modified {
+            return;
+        }
+        cache.modified = false;
+        cache.dirty_since = None;
+        let mut data = CacheData::new();
+        data.as_mut().copy_from_slice(cache.cache.as_ref());
+        (cache.block_device.clone(), cache.block_id, data)
+    };
+    block_device.write_block(block_id, data.as_ref());
+    STATS.writebacks.fetch_add(1, Ordering::Relaxed);
+}
+
+impl BlockCacheManager {
+    /// 创建容量为 `capacity` 块的缓存管理器
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: Vec::new(),
+            free: Vec::new(),
+            index: BTreeMap::new(),
+            head: NIL,
+            tail: NIL,
+            capacity,
+            last_miss: None,
+            evicting: BTreeMap::new(),
+            generation: 0,
+        }
+    }
+
+    fn entry(&self, idx: usize) -> &Entry {
+        self.entries[idx].as_ref().unwrap()
+    }
+
+    fn entry_mut(&mut self, idx: usize) -> &mut Entry {
+        self.entries[idx].as_mut().unwrap()
+    }
+
+    /// 把缓存项从链表中摘下
+    fn unlink(&mut self, idx: usize) {
+        let (prev, next) = {
+            let entry = self.entry(idx);
+            (entry.prev, entry.next)
+        };
+        match prev {
+            NIL => self.head = next,
+            prev => self.entry_mut(prev).next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => self.entry_mut(next).prev = prev,
+        }
+    }
+
+    /// 把缓存项放到链表头部
+    fn push_front(&mut self, idx: usize) {
+        let head = self.head;
+        {
+            let entry = self.entry_mut(idx);
+            entry.prev = NIL;
+            entry.next = head;
+        }
+        match head {
+            NIL => self.tail = idx,
+            head => self.entry_mut(head).prev = idx,
+        }
+        self.head = idx;
+    }
+
+    /// 块已缓存或正在写回
+    fn contains(&self, key: &(usize, usize)) -> bool {
+        self.index.contains_key(key) || self.evicting.contains_key(key)
+    }
+
+    /// 淘汰最久未使用且没有其他引用的缓存项，全部在使用中时返回 `false`
+    ///
+    /// 脏块移入 `evicting` 并加入 `victims`，由调用者在锁外写回
+    fn evict(&mut self, victims: &mut Vec<Victim>) -> bool {
+        let mut idx = self.tail;
+        while idx != NIL {
+            let entry = self.entry(idx);
+            if Arc::strong_count(&entry.cache) == 1 {
+                self.unlink(idx);
+                let entry = self.entries[idx].take().unwrap();
+                self.index.remove(&entry.key);
+                self.free.push(idx);
+                STATS.evictions.fetch_add(1, Ordering::Relaxed);
+                if entry.cache.lock().modified {
+                    self.evicting.insert(entry.key, entry.cache.clone());
+                    victims.push((entry.key, entry.cache));
+                }
+                return true;
+            }
+            idx = entry.prev;
+        }
+        false
+    }
+
+    /// 登记一个新的缓存项，缓存已满时先淘汰
+    fn insert(
+        &mut self,
+        key: (usize, usize),
+        cache: Arc<Mutex<BlockCache>>,
+        victims: &mut Vec<Victim>,
+    ) -> Arc<Mutex<BlockCache>> {
+        if self.index.len() >= self.capacity && !self.evict(victims) {
+            STATS.overflows.fetch_add(1, Ordering::Relaxed);
+        }
+        let entry = Entry {
+            key,
+            cache: cache.clone(),
+            prev: NIL,
+            next: NIL,
+        };
+        let idx = match self.free.pop() {
+            Some(idx) => {
+                self.entries[idx] = Some(entry);
+                idx
+            }
+            None => {
+                self.entries.push(Some(entry));
+                self.entries.len() - 1
+            }
+        };
+        self.index.insert(key, idx);
+        self.push_front(idx);
+        cache
+    }
+
+    /// 查找指定块；未命中时记录是否为顺序读，返回需要读入的块
+    fn lookup(
+        &mut self,
+        block_id: usize,
+        block_device: &Arc<dyn BlockDevice>,
+        victims: &mut Vec<Victim>,
+    ) -> Result<Arc<Mutex<BlockCache>>, Miss> {
+        let device = device_id(block_device);
+        let key = (device, block_id);
+        if let Some(&idx) = self.index.get(&key) {
+            STATS.hits.fetch_add(1, Ordering::Relaxed);
+            self.unlink(idx);
+            self.push_front(idx);
+            return Ok(self.entry(idx).cache.clone());
+        }
+        // 正在写回的块重新登记，写回者发现它已被找回后不再处理
+        if let Some(cache) = self.evicting.remove(&key) {
+            STATS.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(self.insert(key, cache, victims));
+        }
+        STATS.misses.fetch_add(1, Ordering::Relaxed);
+        let sequential = block_id > 0 && self.last_miss == Some((device, block_id - 1));
+        self.last_miss = Some(key);
+        let ahead = if sequential {
+            self.read_ahead_count(block_id + 1, block_device)
+        } else {
+            0
+        };
+        if ahead > 0 {
+            self.last_miss = Some((device, block_id + ahead));
+        }
+        Err(Miss {
+            key,
+            ahead,
+            generation: self.generation,
+        })
+    }
+
+    /// 从 `start` 起尚未缓存的连续块数，遇到已缓存的块或设备末尾时停止
+    fn read_ahead_count(&self, start: usize, block_device: &Arc<dyn BlockDevice>) -> usize {
+        let device = device_id(block_device);
+        let limit = READ_AHEAD_BLOCKS
+            .min(self.capacity / 4)
+            .min(block_device.num_blocks().saturating_sub(start));
+        (0..limit)
+            .take_while(|i| !self.contains(&(device, start + i)))
+            .count()
+    }
+
+    /// 登记在锁外读入的块；读入期间有脏块写回完成时返回 `None`，调用者重新查找
+    ///
+    /// 其他任务已经登记的块以已登记的为准。
+    /// 预读的块先于请求的块插入，比请求的块先被淘汰
+    fn install(
+        &mut self,
+        loaded: Loaded,
+        victims: &mut Vec<Victim>,
+    ) -> Option<Arc<Mutex<BlockCache>>> {
+        if loaded.generation != self.generation {
+            return None;
+        }
+        let (device, _) = loaded.key;
+        for cache in loaded.ahead {
+            let key = (device, cache.block_id);
+            if !self.contains(&key) {
+                self.insert(key, Arc::new(Mutex::new(cache)), victims);
+                STATS.read_ahead.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        match self.lookup_installed(&loaded.key, victims) {
+            Some(cache) => Some(cache),
+            None => Some(self.insert(loaded.key, Arc::new(Mutex::new(loaded.cache)), victims)),
+        }
+    }
+
+    /// 其他任务在锁外读入期间登记的同一个块
+    fn lookup_installed(
+        &mut self,
+        key: &(usize, usize),
+        victims: &mut Vec<Victim>,
+    ) -> Option<Arc<Mutex<BlockCache>>> {
+        if let Some(&idx) = self.index.get(key) {
+            self.unlink(idx);
+            self.push_front(idx);
+            return Some(self.entry(idx).cache.clone());
+        }
+        let cache = self.evicting.remove(key)?;
+        Some(self.insert(*key, cache, victims))
+    }
+
+    /// 淘汰的脏块写回之后调用，块已被找回或写回完成时返回 `true`
+    ///
+    /// 写回期间块又被修改（被找回、修改后再次淘汰）时返回 `false`，需要再写一次
+    fn release_victim(&mut self, victim: &Victim) -> bool {
+        let (key, cache) = victim;
+        match self.evicting.get(key) {
+            Some(pending) if Arc::ptr_eq(pending, cache) => {
+                if cache.lock().modified {
+                    return false;
+                }
+                self.evicting.remove(key);
+                self.generation += 1;
+                true
+            }
+            _ => true,
+        }
+    }
+
+    /// 获取指定块的缓存
+    ///
+    /// ## Behavior
+    /// - 若缓存存在则直接返回
+    /// - 若缓存已满，则回收最久未使用且引用计数为 1 的缓存块
+    /// - 顺序读时预读随后的块
+    pub fn get_block_cache(
+        &mut self,
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Arc<Mutex<BlockCache>> {
+        loop {
+            let mut victims = Vec::new();
+            let cache = match self.lookup(block_id, &block_device, &mut victims) {
+                Ok(cache) => Some(cache),
+                Err(miss) => {
+                    let loaded = miss.load(&block_device);
+                    self.install(loaded, &mut victims)
+                }
+            };
+            for victim in victims.iter() {
+                loop {
+                    write_back(&victim.1);
+                    if self.release_victim(victim) {
+                        break;
+                    }
+                }
+            }
+            if let Some(cache) = cache {
+                return cache;
+            }
+        }
+    }
+
+    /// 所有缓存块，包括正在写回的块
+    fn caches(&self) -> Vec<Arc<Mutex<BlockCache>>> {
+        self.entries
+            .iter()
+            .flatten()
+            .map(|entry| entry.cache.clone())
+            .chain(self.evicting.values().cloned())
+            .collect()
+    }
+}
+
+/// 获取指定块的缓存
+///
+/// ## Behavior
+/// - 与 `BlockCacheManager::get_block_cache` 相同，
+///   但读入未命中的块与写回淘汰的脏块时不持有管理器的锁
+pub fn get_block_cache(
+    manager: &Mutex<BlockCacheManager>,
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    loop {
+        let mut victims = Vec::new();
+        let found = manager.lock().lookup(block_id, &block_device, &mut victims);
+        let cache = match found {
+            Ok(cache) => Some(cache),
+            Err(miss) => {
+                let loaded = miss.load(&block_device);
+                manager.lock().install(loaded, &mut victims)
+            }
+        };
+        for victim in victims.iter() {
+            loop {
+                write_back(&victim.1);
+                if manager.lock().release_victim(victim) {
+                    break;
+                }
+            }
+        }
+        if let Some(cache) = cache {
+            return cache;
+        }
+    }
+}
+
+/// 同步所有缓存块到磁盘
+///
+/// ## Behavior
+/// - 先取出缓存块列表再逐个写回，写回期间不持有管理器与缓存块的锁
+pub fn sync_all(manager: &Mutex<BlockCacheManager>) {
+    let caches = manager.lock().caches();
+    for cache in caches.iter() {
+        write_back(cache);
+    }
+}
+
+/// 周期写回：把脏了超过 `DIRTY_EXPIRE_MS` 的块写回磁盘
+///
+/// ## Behavior
+/// - 两次调用间隔不足 `FLUSH_INTERVAL_MS` 时直接返回
+/// - 只尝试获取锁，正在被使用的块与管理器留待下一次
+/// - 写盘时不持有管理器与缓存块的锁
+pub fn flush_expired(manager: &Mutex<BlockCacheManager>) {
+    let now = now_ms();
+    if now < NEXT_FLUSH_MS.load(Ordering::Relaxed) {
+        return;
+    }
+    NEXT_FLUSH_MS.store(now + FLUSH_INTERVAL_MS, Ordering::Relaxed);
+    let Some(manager) = manager.try_lock() else {
+        return;
+    };
+    let caches = manager.caches();
+    drop(manager);
+    for cache in caches.iter() {
+        let expired = cache.try_lock().map_or(false, |cache| {
+            cache
+                .dirty_since
+                .map_or(false, |since| now.saturating_sub(since) >= DIRTY_EXPIRE_MS)
+        });
+        if expired {
+            write_back(cache);
+        }
+    }
+}
+
+/// 当前的缓存统计，计数为所有管理器之和
+pub fn stats(manager: &Mutex<BlockCacheManager>) -> BlockCacheStats {
+    let (cached, capacity) = {
+        let manager = manager.lock();
+        (manager.index.len(), manager.capacity)
+    };
+    BlockCacheStats {
+        cached,
+        capacity,
+        hits: STATS.hits.load(Ordering::Relaxed),
+        misses: STATS.misses.load(Ordering::Relaxed),
+        read_ahead: STATS.read_ahead.load(Ordering::Relaxed),
+        evictions: STATS.evictions.load(Ordering::Relaxed),
+        writebacks: STATS.writebacks.load(Ordering::Relaxed),
+        overflows: STATS.overflows.load(Ordering::Relaxed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以内存为存储的块设备，记录读写次数
+    struct MemDevice {
+        data: Mutex<Vec<u8>>,
+        reads: AtomicUsize,
+        writes: AtomicUsize,
+    }
+
+    impl MemDevice {
+        fn new(blocks: usize) -> Arc<Self> {
+            Arc::new(Self {
+                data: Mutex::new(vec![0; blocks * BLOCK_SZ]),
+                reads: AtomicUsize::new(0),
+                writes: AtomicUsize::new(0),
+            })
+        }
+
+        fn block(&self, block_id: usize) -> Vec<u8> {
+            self.data.lock()[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ].to_vec()
+        }
+
+        fn reads(&self) -> usize {
+            self.reads.load(Ordering::Relaxed)
+        }
+
+        fn writes(&self) -> usize {
+            self.writes.load(Ordering::Relaxed)
+        }
+    }
+
+    impl BlockDevice for MemDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            buf.copy_from_slice(&self.block(block_id));
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) {
+            self.writes.fetch_add(1, Ordering::Relaxed);
+            self.data.lock()[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ].copy_from_slice(buf);
+        }
+
+        fn num_blocks(&self) -> usize {
+            self.data.lock().len() / BLOCK_SZ
+        }
+
+        fn handle_irq(&self) {}
+    }
+
+    fn as_dyn(device: &Arc<MemDevice>) -> Arc<dyn BlockDevice> {
+        device.clone()
+    }
+
+    fn cached(manager: &BlockCacheManager, device: &Arc<MemDevice>, block_id: usize) -> bool {
+        manager
+            .index
+            .contains_key(&(device_id(&as_dyn(device)), block_id))
+    }
+
+    #[test]
+    fn hit_returns_same_cache() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(4);
+        let a = manager.get_block_cache(3, as_dyn(&device));
+        let b = manager.get_block_cache(3, as_dyn(&device));
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(device.reads(), 1);
+    }
+
+    #[test]
+    fn reads_see_device_contents() {
+        let device = MemDevice::new(8);
+        device.write_block(2, &[0x42; BLOCK_SZ]);
+        let mut manager = BlockCacheManager::new(4);
+        let cache = manager.get_block_cache(2, as_dyn(&device));
+        assert_eq!(cache.lock().read(7, |v: &u8| *v), 0x42);
+    }
+
+    #[test]
+    #[should_panic]
+    fn access_beyond_block_panics() {
+        let device = MemDevice::new(1);
+        let mut manager = BlockCacheManager::new(4);
+        let cache = manager.get_block_cache(0, as_dyn(&device));
+        cache.lock().read(BLOCK_SZ - 2, |v: &u32| *v);
+    }
+
+    #[test]
+    fn sync_writes_back_once() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(4);
+        let cache = manager.get_block_cache(1, as_dyn(&device));
+        cache.lock().modify(8, |v: &mut u32| *v = 0xdead_beef);
+        assert!(cache.lock().modified);
+        assert!(cache.lock().dirty_since.is_some());
+        assert_eq!(device.block(1)[8], 0);
+        cache.lock().sync();
+        assert_eq!(device.block(1)[8..12], 0xdead_beef_u32.to_ne_bytes());
+        assert!(!cache.lock().modified);
+        assert!(cache.lock().dirty_since.is_none());
+        cache.lock().sync();
+        assert_eq!(device.writes(), 1);
+    }
+
+    #[test]
+    fn clean_blocks_are_not_written() {
+        let device = MemDevice::new(8);
+        let manager = Mutex::new(BlockCacheManager::new(2));
+        for block_id in 0..8 {
+            get_block_cache(&manager, block_id, as_dyn(&device))
+                .lock()
+                .read(0, |v: &u8| *v);
+        }
+        sync_all(&manager);
+        drop(manager);
+        assert_eq!(device.writes(), 0);
+    }
+
+    #[test]
+    fn eviction_writes_back_least_recently_used() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(2);
+        manager
+            .get_block_cache(0, as_dyn(&device))
+            .lock()
+            .modify(0, |v: &mut u8| *v = 0x5a);
+        manager.get_block_cache(2, as_dyn(&device));
+        // 访问块 0 后块 2 成为最久未使用者
+        manager.get_block_cache(0, as_dyn(&device));
+        manager.get_block_cache(4, as_dyn(&device));
+        assert!(cached(&manager, &device, 0));
+        assert!(!cached(&manager, &device, 2));
+        assert_eq!(device.block(0)[0], 0);
+        manager.get_block_cache(6, as_dyn(&device));
+        assert!(!cached(&manager, &device, 0));
+        assert_eq!(device.block(0)[0], 0x5a);
+        assert_eq!(device.writes(), 1);
+    }
+
+    #[test]
+    fn blocks_in_use_are_not_evicted() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(2);
+        let held = manager.get_block_cache(0, as_dyn(&device));
+        for block_id in [2, 4, 6] {
+            manager.get_block_cache(block_id, as_dyn(&device));
+        }
+        assert!(Arc::ptr_eq(
+            &held,
+            &manager.get_block_cache(0, as_dyn(&device))
+        ));
+        assert_eq!(manager.index.len(), 2);
+    }
+
+    #[test]
+    fn overflow_when_everything_is_in_use() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(2);
+        let held: Vec<_> = [0, 2, 4]
+            .iter()
+            .map(|&block_id| manager.get_block_cache(block_id, as_dyn(&device)))
+            .collect();
+        assert_eq!(manager.index.len(), 3);
+        drop(held);
+        manager.get_block_cache(6, as_dyn(&device));
+        assert_eq!(manager.index.len(), 3);
+        assert!(!cached(&manager, &device, 0));
+    }
+
+    #[test]
+    fn freed_slots_are_reused() {
+        let device = MemDevice::new(16);
+        let mut manager = BlockCacheManager::new(2);
+        for block_id in (0..16).step_by(2) {
+            manager.get_block_cache(block_id, as_dyn(&device));
+        }
+        assert_eq!(manager.entries.len(), 2);
+        assert_eq!(manager.index.len(), 2);
+        assert!(cached(&manager, &device, 12));
+        assert!(cached(&manager, &device, 14));
+    }
+
+    #[test]
+    fn devices_do_not_share_blocks() {
+        let a = MemDevice::new(4);
+        let b = MemDevice::new(4);
+        b.write_block(1, &[1; BLOCK_SZ]);
+        let mut manager = BlockCacheManager::new(4);
+        let from_a = manager.get_block_cache(1, as_dyn(&a));
+        let from_b = manager.get_block_cache(1, as_dyn(&b));
+        assert!(!Arc::ptr_eq(&from_a, &from_b));
+        assert_eq!(from_a.lock().read(0, |v: &u8| *v), 0);
+        assert_eq!(from_b.lock().read(0, |v: &u8| *v), 1);
+    }
+
+    #[test]
+    fn sequential_misses_read_ahead() {
+        let device = MemDevice::new(64);
+        device.write_block(5, &[0x42; BLOCK_SZ]);
+        let mut manager = BlockCacheManager::new(32);
+        manager.get_block_cache(0, as_dyn(&device));
+        assert_eq!(manager.index.len(), 1);
+        manager.get_block_cache(1, as_dyn(&device));
+        assert!(cached(&manager, &device, 2));
+        assert!(cached(&manager, &device, 9));
+        assert!(!cached(&manager, &device, 10));
+        let reads = device.reads();
+        let cache = manager.get_block_cache(5, as_dyn(&device));
+        assert_eq!(cache.lock().read(0, |v: &u8| *v), 0x42);
+        assert_eq!(device.reads(), reads);
+    }
+
+    #[test]
+    fn read_ahead_stops_at_device_end_and_cached_blocks() {
+        let device = MemDevice::new(6);
+        let mut manager = BlockCacheManager::new(32);
+        manager.get_block_cache(3, as_dyn(&device));
+        manager.get_block_cache(0, as_dyn(&device));
+        manager.get_block_cache(1, as_dyn(&device));
+        // 块 3 已缓存，预读只读到块 2
+        assert!(cached(&manager, &device, 2));
+        assert!(!cached(&manager, &device, 4));
+        assert_eq!(device.reads(), 4);
+        manager.get_block_cache(4, as_dyn(&device));
+        manager.get_block_cache(5, as_dyn(&device));
+        assert_eq!(manager.index.len(), 6);
+    }
+
+    #[test]
+    fn random_access_does_not_read_ahead() {
+        let device = MemDevice::new(64);
+        let mut manager = BlockCacheManager::new(32);
+        for block_id in [7, 3, 40, 12] {
+            manager.get_block_cache(block_id, as_dyn(&device));
+        }
+        assert_eq!(manager.index.len(), 4);
+        assert_eq!(device.reads(), 4);
+    }
+
+    #[test]
+    fn flush_expired_writes_old_dirty_blocks() {
+        static NOW: AtomicUsize = AtomicUsize::new(0);
+        set_clock(|| NOW.load(Ordering::Relaxed));
+        NOW.store(1_000_000, Ordering::Relaxed);
+        let device = MemDevice::new(8);
+        let manager = Mutex::new(BlockCacheManager::new(8));
+        let old = get_block_cache(&manager, 0, as_dyn(&device));
+        old.lock().modify(0, |v: &mut u8| *v = 1);
+        NOW.fetch_add(DIRTY_EXPIRE_MS, Ordering::Relaxed);
+        let young = get_block_cache(&manager, 4, as_dyn(&device));
+        young.lock().modify(0, |v: &mut u8| *v = 2);
+        flush_expired(&manager);
+        assert_eq!(device.block(0)[0], 1);
+        assert_eq!(device.block(4)[0], 0);
+        assert!(!old.lock().modified);
+        assert!(young.lock().modified);
+    }
+
+    /// 读写时检查管理器的锁没有被持有
+    struct LockCheckingDevice {
+        inner: Arc<MemDevice>,
+        manager: &'static Mutex<BlockCacheManager>,
+    }
+
+    impl BlockDevice for LockCheckingDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+            assert!(self.manager.try_lock().is_some());
+            self.inner.read_block(block_id, buf);
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) {
+            assert!(self.manager.try_lock().is_some());
+            self.inner.write_block(block_id, buf);
+        }
+
+        fn num_blocks(&self) -> usize {
+            self.inner.num_blocks()
+        }
+
+        fn handle_irq(&self) {}
+    }
+
+    #[test]
+    fn device_io_does_not_hold_manager_lock() {
+        let manager: &'static Mutex<BlockCacheManager> =
+            Box::leak(Box::new(Mutex::new(BlockCacheManager::new(4))));
+        let inner = MemDevice::new(32);
+        let device: Arc<dyn BlockDevice> = Arc::new(LockCheckingDevice {
+            inner: inner.clone(),
+            manager,
+        });
+        for block_id in 0..16 {
+            get_block_cache(manager, block_id, device.clone())
+                .lock()
+                .modify(0, |v: &mut u8| *v = block_id as u8);
+        }
+        sync_all(manager);
+        assert_eq!(inner.block(15)[0], 15);
+        assert!(inner.writes() >= 16);
+    }
+
+    #[test]
+    fn evicted_dirty_block_is_found_before_write_back() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(1);
+        manager
+            .get_block_cache(0, as_dyn(&device))
+            .lock()
+            .modify(0, |v: &mut u8| *v = 7);
+        let mut victims = Vec::new();
+        let miss = manager
+            .lookup(4, &as_dyn(&device), &mut victims)
+            .err()
+            .unwrap();
+        manager.install(miss.load(&as_dyn(&device)), &mut victims);
+        assert_eq!(victims.len(), 1);
+        // 写回之前再次访问，得到的是同一个缓存块，修改没有丢失
+        let mut more = Vec::new();
+        let found = manager.lookup(0, &as_dyn(&device), &mut more).ok().unwrap();
+        assert!(Arc::ptr_eq(&found, &victims[0].1));
+        assert_eq!(found.lock().read(0, |v: &u8| *v), 7);
+        assert!(manager.release_victim(&victims[0]));
+        assert_eq!(device.writes(), 0);
+    }
+
+    #[test]
+    fn read_racing_with_write_back_is_retried() {
+        let device = MemDevice::new(8);
+        let mut manager = BlockCacheManager::new(1);
+        manager
+            .get_block_cache(0, as_dyn(&device))
+            .lock()
+            .modify(0, |v: &mut u8| *v = 7);
+        let mut victims = Vec::new();
+        let miss = manager
+            .lookup(4, &as_dyn(&device), &mut victims)
+            .err()
+            .unwrap();
+        manager.install(miss.load(&as_dyn(&device)), &mut victims);
+        // 块 0 在写回完成之前被另一个任务读入，读到的是旧数据
+        let stale = manager
+            .lookup(2, &as_dyn(&device), &mut Vec::new())
+            .err()
+            .unwrap()
+            .load(&as_dyn(&device));
+        write_back(&victims[0].1);
+        assert!(manager.release_victim(&victims[0]));
+        assert!(manager.install(stale, &mut Vec::new()).is_none());
+        assert_eq!(device.block(0)[0], 7);
+    }
+
+    #[test]
+    fn sync_all_and_stats() {
+        let device = MemDevice::new(8);
+        let manager = Mutex::new(BlockCacheManager::new(4));
+        for block_id in [0, 2] {
+            get_block_cache(&manager, block_id, as_dyn(&device))
+                .lock()
+                .modify(0, |v: &mut u8| *v = block_id as u8 + 1);
+        }
+        sync_all(&manager);
+        assert_eq!(device.block(0)[0], 1);
+        assert_eq!(device.block(2)[0], 3);
+        let stats = stats(&manager);
+        assert_eq!((stats.cached, stats.capacity), (2, 4));
+    }
+}
@@ -0,0 +1,330 @@
+//! # fatfs 块设备适配
+//!
+//! ## Overview
+//! `FatFsBlockDevice` 把块设备包装成 `fatfs` 需要的按字节读写、可定位的存储，
+//! 所有访问都经过块缓存。
+//!
+//! ## Assumptions
+//! - 设备容量为 `num_blocks() * BLOCK_SZ` 字节
+//! - 块缓存管理器在适配器的整个生命周期内有效
+//!
+//! ## Behavior
+//! - 读写可以跨越块边界，只修改涉及的字节
+//! - 读写不检查设备末尾，越界访问由块设备处理
+//! - `flush` 把块缓存中的所有脏块写回磁盘
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::sync::Arc;
+use blkcache::{get_block_cache, sync_all, BlockCacheManager, BlockDevice, BLOCK_SZ};
+use fatfs::{IoBase, IoError, Read, Seek, SeekFrom, Write};
+use spin::Mutex;
+
+pub struct FatFsBlockDevice {
+    block_device: Arc<dyn BlockDevice>,
+    cache: &'static Mutex<BlockCacheManager>,
+    offset: usize,
+}
+
+impl FatFsBlockDevice {
+    pub fn new(
+        block_device: Arc<dyn BlockDevice>,
+        cache: &'static Mutex<BlockCacheManager>,
+    ) -> Self {
+        Self {
+            block_device,
+            cache,
+            offset: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum FatFsError {
+    IoError,
+    InvalidOffset,
+    ENOENT,
+}
+
+impl IoError for FatFsError {
+    fn is_interrupted(&self) -> bool {
+        false
+    }
+
+    fn new_unexpected_eof_error() -> Self {
+        FatFsError::IoError
+    }
+
+    fn new_write_zero_error() -> Self {
+        FatFsError::IoError
+    }
+}
+
+impl IoBase for FatFsBlockDevice {
+    type Error = FatFsError;
+}
+
+impl Read for FatFsBlockDevice {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut read_size = 0;
+        let mut current_offset = self.offset;
+
+        while read_size < buf.len() {
+            let block_id = current_offset / BLOCK_SZ;
+            let offset_in_block = current_offset % BLOCK_SZ;
+            let size_to_read = (buf.len() - read_size).min(BLOCK_SZ - offset_in_block);
+
+            get_block_cache(self.cache, block_id, self.block_device.clone())
+                .lock()
+                .read(0, |block_data: &[u8; BLOCK_SZ]| {
+                    buf[read_size..read_size + size_to_read].copy_from_slice(
+                        &block_data.as_ref()[offset_in_block..offset_in_block + size_to_read],
+                    );
+                });
+            read_size += size_to_read;
+            current_offset += size_to_read;
+        }
+        self.offset += read_size;
+        Ok(read_size)
+    }
+}
+
+impl Write for FatFsBlockDevice {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut write_size = 0;
+        let mut current_offset = self.offset;
+
+        while write_size < buf.len() {
+            let block_id = current_offset / BLOCK_SZ;
+            let offset_in_block = current_offset % BLOCK_SZ;
+            let size_to_write = (buf.len() - write_size).min(BLOCK_SZ - offset_in_block);
+
+            get_block_cache(self.cache, block_id, self.block_device.clone())
+                .lock()
+                .modify(0, |block_data: &mut [u8; BLOCK_SZ]| {
+                    block_data.as_mut()[offset_in_block..offset_in_block + size_to_write]
+                        .copy_from_slice(&buf[write_size..write_size + size_to_write]);
+                });
+            write_size += size_to_write;
+            current_offset += size_to_write;
+        }
+        self.offset += write_size;
+        Ok(write_size)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        sync_all(self.cache);
+        Ok(())
+    }
+}
+
+impl Seek for FatFsBlockDevice {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
+        let new_offset = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(offset) => self.offset as i64 + offset,
+            SeekFrom::End(offset) => (self.block_device.num_blocks() * BLOCK_SZ) as i64 + offset,
+        };
+
+        if new_offset < 0 {
+            return Err(FatFsError::InvalidOffset);
+        }
+
+        self.offset = new_offset as usize;
+        Ok(new_offset as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fatfs::{format_volume, FileSystem, FormatVolumeOptions, FsOptions};
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// 以内存中的磁盘镜像为存储的块设备
+    struct ImageDevice {
+        image: Mutex<Vec<u8>>,
+    }
+
+    impl ImageDevice {
+        fn new(image: Vec<u8>) -> Arc<Self> {
+            assert_eq!(image.len() % BLOCK_SZ, 0);
+            Arc::new(Self {
+                image: Mutex::new(image),
+            })
+        }
+
+        /// 清零的镜像
+        fn zeroed(blocks: usize) -> Arc<Self> {
+            Self::new(vec![0; blocks * BLOCK_SZ])
+        }
+
+        fn contents(&self) -> Vec<u8> {
+            self.image.lock().clone()
+        }
+    }
+
+    impl BlockDevice for ImageDevice {
+        fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+            let image = self.image.lock();
+            buf.copy_from_slice(&image[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ]);
+        }
+
+        fn write_block(&self, block_id: usize, buf: &[u8]) {
+            let mut image = self.image.lock();
+            image[block_id * BLOCK_SZ..(block_id + 1) * BLOCK_SZ].copy_from_slice(buf);
+        }
+
+        fn num_blocks(&self) -> usize {
+            self.image.lock().len() / BLOCK_SZ
+        }
+
+        fn handle_irq(&self) {}
+    }
+
+    /// 每个测例使用自己的块缓存，避免互相淘汰
+    fn new_cache() -> &'static Mutex<BlockCacheManager> {
+        Box::leak(Box::new(Mutex::new(BlockCacheManager::new(64))))
+    }
+
+    fn adapter(device: &Arc<ImageDevice>) -> FatFsBlockDevice {
+        FatFsBlockDevice::new(device.clone(), new_cache())
+    }
+
+    /// 格式化成 FAT 卷的镜像，容量 `blocks` 块
+    fn formatted(blocks: usize) -> Arc<ImageDevice> {
+        let device = ImageDevice::zeroed(blocks);
+        let mut storage = adapter(&device);
+        format_volume(&mut storage, FormatVolumeOptions::new()).unwrap();
+        storage.flush().unwrap();
+        device
+    }
+
+    #[test]
+    fn read_write_across_blocks() {
+        let device = ImageDevice::zeroed(4);
+        let mut storage = adapter(&device);
+        let data: Vec<u8> = (0..BLOCK_SZ + 100).map(|i| i as u8).collect();
+        storage.seek(SeekFrom::Start(BLOCK_SZ as u64 - 50)).unwrap();
+        assert_eq!(storage.write(&data).unwrap(), data.len());
+        storage.flush().unwrap();
+        let image = device.contents();
+        assert_eq!(&image[BLOCK_SZ - 50..2 * BLOCK_SZ + 50], &data[..]);
+        assert!(image[..BLOCK_SZ - 50].iter().all(|&b| b == 0));
+        assert!(image[2 * BLOCK_SZ + 50..].iter().all(|&b| b == 0));
+
+        let mut buf = vec![0; data.len()];
+        storage
+            .seek(SeekFrom::Current(-(data.len() as i64)))
+            .unwrap();
+        assert_eq!(storage.read(&mut buf).unwrap(), data.len());
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn writes_stay_in_cache_until_flush() {
+        let device = ImageDevice::zeroed(4);
+        let mut storage = adapter(&device);
+        storage.write(b"cached").unwrap();
+        assert!(device.contents()[..6].iter().all(|&b| b == 0));
+        storage.flush().unwrap();
+        assert_eq!(&device.contents()[..6], b"cached");
+    }
+
+    #[test]
+    fn seek_positions() {
+        let device = ImageDevice::zeroed(4);
+        let mut storage = adapter(&device);
+        assert_eq!(storage.seek(SeekFrom::End(0)).unwrap(), 4 * BLOCK_SZ as u64);
+        assert_eq!(
+            storage.seek(SeekFrom::End(-10)).unwrap(),
+            4 * BLOCK_SZ as u64 - 10
+        );
+        assert_eq!(
+            storage.seek(SeekFrom::Current(4)).unwrap(),
+            4 * BLOCK_SZ as u64 - 6
+        );
+        assert_eq!(storage.seek(SeekFrom::Start(7)).unwrap(), 7);
+        assert!(matches!(
+            storage.seek(SeekFrom::Current(-8)),
+            Err(FatFsError::InvalidOffset)
+        ));
+        // 失败的定位不改变当前位置
+        assert_eq!(storage.seek(SeekFrom::Current(0)).unwrap(), 7);
+    }
+
+    #[test]
+    fn empty_buffers() {
+        let device = ImageDevice::zeroed(1);
+        let mut storage = adapter(&device);
+        assert_eq!(storage.read(&mut []).unwrap(), 0);
+        assert_eq!(storage.write(&[]).unwrap(), 0);
+        assert_eq!(storage.seek(SeekFrom::Current(0)).unwrap(), 0);
+    }
+
+    #[test]
+    fn files_survive_remount() {
+        let device = formatted(8192);
+        {
+            let fs = FileSystem::new(adapter(&device), FsOptions::new()).unwrap();
+            let root = fs.root_dir();
+            root.create_dir("dir").unwrap();
+            let mut file = root.create_file("dir/hello.txt").unwrap();
+            file.write_all(b"hello, fat").unwrap();
+            drop(file);
+            drop(root);
+            fs.unmount().unwrap();
+        }
+        let fs = FileSystem::new(adapter(&device), FsOptions::new()).unwrap();
+        let mut file = fs.root_dir().open_file("dir/hello.txt").unwrap();
+        let mut buf = [0u8; 10];
+        file.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hello, fat");
+    }
+
+    #[test]
+    fn large_file_spans_many_clusters() {
+        let device = formatted(8192);
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 7) as u8).collect();
+        {
+            let fs = FileSystem::new(adapter(&device), FsOptions::new()).unwrap();
+            let mut file = fs.root_dir().create_file("big").unwrap();
+            file.write_all(&data).unwrap();
+            drop(file);
+            fs.unmount().unwrap();
+        }
+        let fs = FileSystem::new(adapter(&device), FsOptions::new()).unwrap();
+        let mut file = fs.root_dir().open_file("big").unwrap();
+        let mut buf = vec![0; data.len()];
+        file.read_exact(&mut buf).unwrap();
+        assert!(buf == data);
+    }
+
+    /// `FAT_IMAGE` 指向一个 FAT 镜像文件时，挂载它的副本并列出根目录，
+    /// 用来检查适配器能否读取由其他工具（如 `buildfs.sh`）生成的镜像
+    #[test]
+    fn mount_image_file() {
+        let Some(path) = std::env::var_os("FAT_IMAGE").map(PathBuf::from) else {
+            return;
+        };
+        let mut image = fs::read(&path).unwrap();
+        image.resize(image.len().next_multiple_of(BLOCK_SZ), 0);
+        let device = ImageDevice::new(image);
+        let fs = FileSystem::new(adapter(&device), FsOptions::new()).unwrap();
+        for entry in fs.root_dir().iter() {
+            let entry = entry.unwrap();
+            println!("{} {}", entry.file_name(), entry.len());
+        }
+    }
+}
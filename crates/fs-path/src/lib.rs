@@ -0,0 +1,96 @@
+//! # 路径解析
+//!
+//! ## Overview
+//! 把相对路径按当前目录解析成规范的绝对路径，供文件系统的各个入口使用。
+//!
+//! ## Behavior
+//! - 以 `/` 开头的路径忽略当前目录
+//! - 去掉空分量与 `.`，`..` 回到上一级，在根目录处停止
+//! - 结果总以 `/` 开头，除根目录外不以 `/` 结尾
+//! - 只做字符串处理，不跟随符号链接，也不检查路径是否存在
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+///返回绝对路径，支持相对路径
+pub fn resolve_path(relative: &str, base: &str) -> String {
+    let mut stack: Vec<&str> = Vec::new();
+
+    let is_absolute = relative.starts_with('/');
+
+    if !is_absolute {
+        push_components(&mut stack, base);
+    }
+    push_components(&mut stack, relative);
+
+    let mut result = String::from("/");
+    result.push_str(&stack.join("/"));
+    result
+}
+
+/// 把 `path` 的各个分量依次作用在 `stack` 上
+fn push_components<'a>(stack: &mut Vec<&'a str>, path: &'a str) {
+    for component in path.split('/') {
+        match component {
+            "" | "." => continue,
+            ".." => {
+                stack.pop();
+            }
+            _ => stack.push(component),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_path;
+
+    #[test]
+    fn absolute_path_ignores_base() {
+        assert_eq!(resolve_path("/a/b", "/x/y"), "/a/b");
+        assert_eq!(resolve_path("/", "/x/y"), "/");
+    }
+
+    #[test]
+    fn relative_path_joins_base() {
+        assert_eq!(resolve_path("b/c", "/a"), "/a/b/c");
+        assert_eq!(resolve_path("c", "/a/b/"), "/a/b/c");
+        assert_eq!(resolve_path("", "/a/b"), "/a/b");
+        assert_eq!(resolve_path("a", "/"), "/a");
+    }
+
+    #[test]
+    fn dot_components() {
+        assert_eq!(resolve_path("./b/../c", "/a"), "/a/c");
+        assert_eq!(resolve_path("..", "/a/b/."), "/a");
+        assert_eq!(resolve_path(".", "/a/./b"), "/a/b");
+    }
+
+    #[test]
+    fn dotdot_stops_at_root() {
+        assert_eq!(resolve_path("../../..", "/a"), "/");
+        assert_eq!(resolve_path("/../b", "/a"), "/b");
+        assert_eq!(resolve_path("../../c", "/a/b/../"), "/c");
+    }
+
+    #[test]
+    fn redundant_slashes() {
+        assert_eq!(resolve_path("//a///b/", "/"), "/a/b");
+        assert_eq!(resolve_path("b//c", "//a//"), "/a/b/c");
+    }
+
+    #[test]
+    fn relative_base_is_treated_as_absolute() {
+        assert_eq!(resolve_path("c", "a/b"), "/a/b/c");
+    }
+
+    #[test]
+    fn names_with_dots_are_kept() {
+        assert_eq!(resolve_path("...", "/a"), "/a/...");
+        assert_eq!(resolve_path(".hidden/..x", "/"), "/.hidden/..x");
+    }
+}
@@ -0,0 +1,455 @@
+//! # 体系结构描述
+//!
+//! ## Overview
+//! - `Arch`：寄存器编号、断点指令、目标描述 XML 与单步时的后继指令
+//! - `RiscV64`：`x0`-`x31` 为 0-31 号，`pc` 为 32 号，与 GDB 的 `org.gnu.gdb.riscv.cpu` 一致
+//! - `LoongArch64`：`r0`-`r31` 为 0-31 号，其后为 `orig_a0`、`pc` 与 `badv`，
+//!   与 GDB 的 `org.gnu.gdb.loongarch.base` 一致
+//!
+//! ## Assumptions
+//! - 目标没有硬件单步，单步由在后继指令处放置临时断点实现
+//!
+//! ## Behavior
+//! - 条件分支的两个后继都放置断点；间接跳转按当前寄存器的值计算目标
+//! - 不认识的指令按顺序执行处理
+
+use crate::Target;
+
+pub trait Arch {
+    /// `g` 包中的寄存器个数，每个寄存器一个机器字
+    const REGISTERS: usize;
+    /// 程序计数器的寄存器号
+    const PC: usize;
+    /// 单步时使用的断点种类
+    const STEP_KIND: usize;
+    /// 经 `qXfer:features:read` 交给 GDB 的目标描述
+    const TARGET_XML: &'static str;
+
+    /// `Z0` 包中种类为 `kind` 的断点指令，不支持时返回 `None`
+    fn breakpoint(kind: usize) -> Option<&'static [u8]>;
+
+    /// 执行 `pc` 处的一条指令之后可能到达的地址，指令不可读时返回 `None`
+    fn next_pcs<T: Target + ?Sized>(target: &T, pc: usize) -> Option<[Option<usize>; 2]>;
+}
+
+/// 把 `value` 的低 `bits` 位作为有符号数扩展
+fn sign_extend(value: u32, bits: u32) -> isize {
+    let shift = 32 - bits;
+    ((value << shift) as i32 >> shift) as isize
+}
+
+fn bits(insn: u32, high: u32, low: u32) -> u32 {
+    (insn >> low) & ((1 << (high - low + 1)) - 1)
+}
+
+fn read_u16<T: Target + ?Sized>(target: &T, addr: usize) -> Option<u16> {
+    let mut bytes = [0; 2];
+    (target.read_memory(addr, &mut bytes) == bytes.len()).then(|| u16::from_le_bytes(bytes))
+}
+
+fn read_u32<T: Target + ?Sized>(target: &T, addr: usize) -> Option<u32> {
+    let mut bytes = [0; 4];
+    (target.read_memory(addr, &mut bytes) == bytes.len()).then(|| u32::from_le_bytes(bytes))
+}
+
+pub struct RiscV64;
+
+impl RiscV64 {
+    /// 32 位指令的后继
+    fn next_pcs32<T: Target + ?Sized>(target: &T, pc: usize, insn: u32) -> [Option<usize>; 2] {
+        let next = pc.wrapping_add(4);
+        let rs1 = || {
+            target
+                .read_register(bits(insn, 19, 15) as usize)
+                .unwrap_or(0)
+        };
+        match insn & 0x7f {
+            // JAL
+            0x6f => {
+                let imm = bits(insn, 31, 31) << 20
+                    | bits(insn, 19, 12) << 12
+                    | bits(insn, 20, 20) << 11
+                    | bits(insn, 30, 21) << 1;
+                [Some(pc.wrapping_add_signed(sign_extend(imm, 21))), None]
+            }
+            // JALR
+            0x67 => {
+                let target = rs1().wrapping_add_signed(sign_extend(insn >> 20, 12));
+                [Some(target & !1), None]
+            }
+            // BEQ/BNE/BLT/BGE/BLTU/BGEU
+            0x63 => {
+                let imm = bits(insn, 31, 31) << 12
+                    | bits(insn, 7, 7) << 11
+                    | bits(insn, 30, 25) << 5
+                    | bits(insn, 11, 8) << 1;
+                [
+                    Some(pc.wrapping_add_signed(sign_extend(imm, 13))),
+                    Some(next),
+                ]
+            }
+            _ => [Some(next), None],
+        }
+    }
+
+    /// 16 位压缩指令的后继
+    fn next_pcs16<T: Target + ?Sized>(target: &T, pc: usize, insn: u16) -> [Option<usize>; 2] {
+        let insn = insn as u32;
+        let next = pc.wrapping_add(2);
+        match (insn & 0b11, bits(insn, 15, 13)) {
+            // C.J
+            (0b01, 0b101) => {
+                let imm = bits(insn, 12, 12) << 11
+                    | bits(insn, 8, 8) << 10
+                    | bits(insn, 10, 9) << 8
+                    | bits(insn, 6, 6) << 7
+                    | bits(insn, 7, 7) << 6
+                    | bits(insn, 2, 2) << 5
+                    | bits(insn, 11, 11) << 4
+                    | bits(insn, 5, 3) << 1;
+                [Some(pc.wrapping_add_signed(sign_extend(imm, 12))), None]
+            }
+            // C.BEQZ/C.BNEZ
+            (0b01, 0b110 | 0b111) => {
+                let imm = bits(insn, 12, 12) << 8
+                    | bits(insn, 6, 5) << 6
+                    | bits(insn, 2, 2) << 5
+                    | bits(insn, 11, 10) << 3
+                    | bits(insn, 4, 3) << 1;
+                [
+                    Some(pc.wrapping_add_signed(sign_extend(imm, 9))),
+                    Some(next),
+                ]
+            }
+            // C.JR/C.JALR：rs2 为 0 且 rs1 不为 0
+            (0b10, 0b100) if bits(insn, 6, 2) == 0 && bits(insn, 11, 7) != 0 => {
+                let rs1 = target
+                    .read_register(bits(insn, 11, 7) as usize)
+                    .unwrap_or(0);
+                [Some(rs1 & !1), None]
+            }
+            _ => [Some(next), None],
+        }
+    }
+}
+
+impl Arch for RiscV64 {
+    const REGISTERS: usize = 33;
+    const PC: usize = 32;
+    // 压缩指令集中的 c.ebreak 可以覆盖任何指令的开头
+    const STEP_KIND: usize = 2;
+    const TARGET_XML: &'static str = concat!(
+        r#"<?xml version="1.0"?><!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+        r#"<target version="1.0"><architecture>riscv:rv64</architecture>"#,
+        r#"<feature name="org.gnu.gdb.riscv.cpu">"#,
+        r#"<reg name="zero" bitsize="64" type="int" regnum="0"/>"#,
+        r#"<reg name="ra" bitsize="64" type="code_ptr"/>"#,
+        r#"<reg name="sp" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="gp" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="tp" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="t0" bitsize="64" type="int"/>"#,
+        r#"<reg name="t1" bitsize="64" type="int"/>"#,
+        r#"<reg name="t2" bitsize="64" type="int"/>"#,
+        r#"<reg name="fp" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="s1" bitsize="64" type="int"/>"#,
+        r#"<reg name="a0" bitsize="64" type="int"/>"#,
+        r#"<reg name="a1" bitsize="64" type="int"/>"#,
+        r#"<reg name="a2" bitsize="64" type="int"/>"#,
+        r#"<reg name="a3" bitsize="64" type="int"/>"#,
+        r#"<reg name="a4" bitsize="64" type="int"/>"#,
+        r#"<reg name="a5" bitsize="64" type="int"/>"#,
+        r#"<reg name="a6" bitsize="64" type="int"/>"#,
+        r#"<reg name="a7" bitsize="64" type="int"/>"#,
+        r#"<reg name="s2" bitsize="64" type="int"/>"#,
+        r#"<reg name="s3" bitsize="64" type="int"/>"#,
+        r#"<reg name="s4" bitsize="64" type="int"/>"#,
+        r#"<reg name="s5" bitsize="64" type="int"/>"#,
+        r#"<reg name="s6" bitsize="64" type="int"/>"#,
+        r#"<reg name="s7" bitsize="64" type="int"/>"#,
+        r#"<reg name="s8" bitsize="64" type="int"/>"#,
+        r#"<reg name="s9" bitsize="64" type="int"/>"#,
+        r#"<reg name="s10" bitsize="64" type="int"/>"#,
+        r#"<reg name="s11" bitsize="64" type="int"/>"#,
+        r#"<reg name="t3" bitsize="64" type="int"/>"#,
+        r#"<reg name="t4" bitsize="64" type="int"/>"#,
+        r#"<reg name="t5" bitsize="64" type="int"/>"#,
+        r#"<reg name="t6" bitsize="64" type="int"/>"#,
+        r#"<reg name="pc" bitsize="64" type="code_ptr"/>"#,
+        r#"</feature></target>"#,
+    );
+
+    fn breakpoint(kind: usize) -> Option<&'static [u8]> {
+        match kind {
+            // c.ebreak
+            2 => Some(&[0x02, 0x90]),
+            // ebreak
+            4 => Some(&[0x73, 0x00, 0x10, 0x00]),
+            _ => None,
+        }
+    }
+
+    fn next_pcs<T: Target + ?Sized>(target: &T, pc: usize) -> Option<[Option<usize>; 2]> {
+        let low = read_u16(target, pc)?;
+        if low & 0b11 != 0b11 {
+            return Some(Self::next_pcs16(target, pc, low));
+        }
+        Some(Self::next_pcs32(target, pc, read_u32(target, pc)?))
+    }
+}
+
+pub struct LoongArch64;
+
+impl Arch for LoongArch64 {
+    const REGISTERS: usize = 35;
+    const PC: usize = 33;
+    const STEP_KIND: usize = 4;
+    const TARGET_XML: &'static str = concat!(
+        r#"<?xml version="1.0"?><!DOCTYPE target SYSTEM "gdb-target.dtd">"#,
+        r#"<target version="1.0"><architecture>loongarch64</architecture>"#,
+        r#"<feature name="org.gnu.gdb.loongarch.base">"#,
+        r#"<reg name="r0" bitsize="64" type="uint64" regnum="0"/>"#,
+        r#"<reg name="r1" bitsize="64" type="code_ptr"/>"#,
+        r#"<reg name="r2" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="r3" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="r4" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r5" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r6" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r7" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r8" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r9" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r10" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r11" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r12" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r13" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r14" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r15" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r16" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r17" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r18" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r19" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r20" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r21" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r22" bitsize="64" type="data_ptr"/>"#,
+        r#"<reg name="r23" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r24" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r25" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r26" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r27" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r28" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r29" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r30" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="r31" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="orig_a0" bitsize="64" type="uint64"/>"#,
+        r#"<reg name="pc" bitsize="64" type="code_ptr"/>"#,
+        r#"<reg name="badv" bitsize="64" type="code_ptr"/>"#,
+        r#"</feature></target>"#,
+    );
+
+    fn breakpoint(kind: usize) -> Option<&'static [u8]> {
+        match kind {
+            // break 0
+            4 => Some(&[0x00, 0x00, 0x2a, 0x00]),
+            _ => None,
+        }
+    }
+
+    fn next_pcs<T: Target + ?Sized>(target: &T, pc: usize) -> Option<[Option<usize>; 2]> {
+        let insn = read_u32(target, pc)?;
+        let next = pc.wrapping_add(4);
+        let offs16 = || sign_extend(bits(insn, 25, 10) << 2, 18);
+        let pcs = match insn >> 26 {
+            // BEQZ/BNEZ/BCEQZ/BCNEZ
+            0x10..=0x12 => {
+                let offs = bits(insn, 4, 0) << 16 | bits(insn, 25, 10);
+                [
+                    Some(pc.wrapping_add_signed(sign_extend(offs << 2, 23))),
+                    Some(next),
+                ]
+            }
+            // JIRL
+            0x13 => {
+                let rj = target.read_register(bits(insn, 9, 5) as usize).unwrap_or(0);
+                [Some(rj.wrapping_add_signed(offs16())), None]
+            }
+            // B/BL
+            0x14 | 0x15 => {
+                let offs = bits(insn, 9, 0) << 16 | bits(insn, 25, 10);
+                [
+                    Some(pc.wrapping_add_signed(sign_extend(offs << 2, 28))),
+                    None,
+                ]
+            }
+            // BEQ/BNE/BLT/BGE/BLTU/BGEU
+            0x16..=0x1b => [Some(pc.wrapping_add_signed(offs16())), Some(next)],
+            _ => [Some(next), None],
+        };
+        Some(pcs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Target;
+
+    /// 一段代码与一组寄存器
+    struct Code {
+        base: usize,
+        bytes: Vec<u8>,
+        regs: [usize; 32],
+    }
+
+    impl Code {
+        fn new(base: usize, words: &[u32]) -> Self {
+            Self {
+                base,
+                bytes: words.iter().flat_map(|word| word.to_le_bytes()).collect(),
+                regs: [0; 32],
+            }
+        }
+
+        fn compressed(base: usize, halves: &[u16]) -> Self {
+            Self {
+                base,
+                bytes: halves.iter().flat_map(|half| half.to_le_bytes()).collect(),
+                regs: [0; 32],
+            }
+        }
+    }
+
+    impl Target for Code {
+        fn read_register(&self, n: usize) -> Option<usize> {
+            self.regs.get(n).copied()
+        }
+
+        fn write_register(&mut self, _n: usize, _value: usize) -> bool {
+            false
+        }
+
+        fn read_memory(&self, addr: usize, buf: &mut [u8]) -> usize {
+            let Some(offset) = addr.checked_sub(self.base) else {
+                return 0;
+            };
+            let available = &self.bytes[offset.min(self.bytes.len())..];
+            let len = buf.len().min(available.len());
+            buf[..len].copy_from_slice(&available[..len]);
+            len
+        }
+
+        fn write_memory(&mut self, _addr: usize, _data: &[u8]) -> bool {
+            false
+        }
+    }
+
+    const PC: usize = 0x8020_1000;
+
+    fn rv(insn: u32) -> [Option<usize>; 2] {
+        let mut code = Code::new(PC, &[insn]);
+        code.regs[1] = 0x8020_4000;
+        code.regs[10] = 0x8020_5001;
+        RiscV64::next_pcs(&code, PC).unwrap()
+    }
+
+    fn rvc(insn: u16) -> [Option<usize>; 2] {
+        let mut code = Code::compressed(PC, &[insn]);
+        code.regs[1] = 0x8020_4000;
+        RiscV64::next_pcs(&code, PC).unwrap()
+    }
+
+    #[test]
+    fn riscv_jumps_and_branches() {
+        // addi a0, a0, 1
+        assert_eq!(rv(0x0015_0513), [Some(PC + 4), None]);
+        // jal ra, +0x10 / jal zero, -8
+        assert_eq!(rv(0x0100_00ef), [Some(PC + 0x10), None]);
+        assert_eq!(rv(0xff9f_f06f), [Some(PC - 8), None]);
+        // jalr zero, 8(a0)：最低位清零
+        assert_eq!(rv(0x0085_0067), [Some(0x8020_5008), None]);
+        // ret
+        assert_eq!(rv(0x0000_8067), [Some(0x8020_4000), None]);
+        // beq a0, a1, +0x20 / bne a0, zero, -4
+        assert_eq!(rv(0x02b5_0063), [Some(PC + 0x20), Some(PC + 4)]);
+        assert_eq!(rv(0xfe05_1ee3), [Some(PC - 4), Some(PC + 4)]);
+    }
+
+    #[test]
+    fn riscv_compressed() {
+        // c.addi a0, 1
+        assert_eq!(rvc(0x0505), [Some(PC + 2), None]);
+        // c.j +0x10 / c.j -2
+        assert_eq!(rvc(0xa801), [Some(PC + 0x10), None]);
+        assert_eq!(rvc(0xbffd), [Some(PC - 2), None]);
+        // c.beqz a0, +8 / c.bnez a0, -4
+        assert_eq!(rvc(0xc501), [Some(PC + 8), Some(PC + 2)]);
+        assert_eq!(rvc(0xfd75), [Some(PC - 4), Some(PC + 2)]);
+        // ret（c.jr ra）与 c.jalr ra
+        assert_eq!(rvc(0x8082), [Some(0x8020_4000), None]);
+        assert_eq!(rvc(0x9082), [Some(0x8020_4000), None]);
+        // c.mv a0, ra 不是跳转
+        assert_eq!(rvc(0x8506), [Some(PC + 2), None]);
+    }
+
+    #[test]
+    fn riscv_unreadable_instruction() {
+        let code = Code::new(PC, &[]);
+        assert_eq!(RiscV64::next_pcs(&code, PC), None);
+        // 32 位指令只有前半可读
+        let code = Code::compressed(PC, &[0x0513]);
+        assert_eq!(RiscV64::next_pcs(&code, PC), None);
+    }
+
+    const LA_PC: usize = 0x9000_0000_0020_1000;
+
+    fn la(insn: u32) -> [Option<usize>; 2] {
+        let mut code = Code::new(LA_PC, &[insn]);
+        code.regs[1] = 0x9000_0000_0020_4000;
+        LoongArch64::next_pcs(&code, LA_PC).unwrap()
+    }
+
+    #[test]
+    fn loongarch_jumps_and_branches() {
+        // addi.d $a0, $a0, 1
+        assert_eq!(la(0x02c0_0484), [Some(LA_PC + 4), None]);
+        // b +0x10 / bl -8
+        assert_eq!(la(0x5000_1000), [Some(LA_PC + 0x10), None]);
+        assert_eq!(la(0x57ff_fbff), [Some(LA_PC - 8), None]);
+        // jirl $zero, $ra, 0（ret）/ jirl $ra, $ra, 8
+        assert_eq!(la(0x4c00_0020), [Some(0x9000_0000_0020_4000), None]);
+        assert_eq!(la(0x4c00_0821), [Some(0x9000_0000_0020_4008), None]);
+        // beqz $a0, +0x20 / bnez $a0, -4
+        assert_eq!(la(0x4000_2080), [Some(LA_PC + 0x20), Some(LA_PC + 4)]);
+        assert_eq!(la(0x47ff_fc9f), [Some(LA_PC - 4), Some(LA_PC + 4)]);
+        // beq $a0, $a1, +0x10 / bgeu $a0, $a1, -8
+        assert_eq!(la(0x5800_1085), [Some(LA_PC + 0x10), Some(LA_PC + 4)]);
+        assert_eq!(la(0x6fff_f885), [Some(LA_PC - 8), Some(LA_PC + 4)]);
+    }
+
+    #[test]
+    fn breakpoint_kinds() {
+        assert_eq!(RiscV64::breakpoint(2), Some(&[0x02, 0x90][..]));
+        assert_eq!(RiscV64::breakpoint(4).unwrap().len(), 4);
+        assert_eq!(RiscV64::breakpoint(3), None);
+        assert_eq!(
+            LoongArch64::breakpoint(4),
+            Some(&[0x00, 0x00, 0x2a, 0x00][..])
+        );
+        assert_eq!(LoongArch64::breakpoint(2), None);
+        assert!(RiscV64::breakpoint(RiscV64::STEP_KIND).is_some());
+        assert!(LoongArch64::breakpoint(LoongArch64::STEP_KIND).is_some());
+    }
+
+    #[test]
+    fn target_descriptions_are_safe_to_send() {
+        for xml in [RiscV64::TARGET_XML, LoongArch64::TARGET_XML] {
+            assert!(!xml.contains(['$', '#', '}', '*']));
+        }
+        assert_eq!(
+            RiscV64::TARGET_XML.matches("<reg ").count(),
+            RiscV64::REGISTERS
+        );
+        assert_eq!(
+            LoongArch64::TARGET_XML.matches("<reg ").count(),
+            LoongArch64::REGISTERS
+        );
+    }
+}
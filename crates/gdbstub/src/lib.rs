@@ -0,0 +1,873 @@
+//! # GDB 远程串行协议桩
+//!
+//! ## Overview
+//! - `GdbStub::stop`：目标停下时调用，与 GDB 交互直到它要求继续、单步、断开或结束
+//! - `Connection`：收发字节的通道，如第二个串口或 virtio-console
+//! - `Target`：停下的现场，提供寄存器与内存的读写
+//! - `Registers`：按寄存器号访问的一份陷阱现场，供实现 `Target` 时使用
+//! - `Arch`：寄存器编号、断点指令与单步的后继地址，见 `arch`
+//!
+//! 支持的包：`?`、`g`/`G`、`p`/`P`、`m`/`M`、`c`、`s`、`Z0`/`z0`、`D`、`k`、
+//! `qSupported`、`qXfer:features:read`、`qAttached`，其余回复空包表示不支持。
+//!
+//! ## Assumptions
+//! - 目标是单核的，停下期间不会有别的代码运行；`H`/`T` 线程选择总是成功
+//! - 断点指令执行后陷入的地址就是断点所在的地址，与 GDB 的期望一致
+//!
+//! ## Invariants
+//! - 目标运行时内存中只有 GDB 设置的断点与单步的临时断点；
+//!   每次停下时先撤掉临时断点，断开时撤掉全部断点
+//!
+//! ## Behavior
+//! - 不分配内存，可以在 panic 路径上使用
+//! - 单步在下一条指令的所有可能后继处放置临时断点后继续执行，
+//!   因此单步中途发生的中断与异常不会被跳过
+//! - 只在继续或单步之后的下一次停下时主动发送停止原因，GDB 刚连上时由 `?` 查询
+
+#![cfg_attr(not(test), no_std)]
+
+mod arch;
+mod packet;
+
+pub use arch::{Arch, LoongArch64, RiscV64};
+pub use packet::BUF_SIZE;
+
+use core::marker::PhantomData;
+use packet::{decode_hex, decode_word, hex_pair, parse_hex, Event, PacketReader, Reply};
+
+/// 最多同时设置的软件断点数
+pub const MAX_BREAKPOINTS: usize = 32;
+
+/// 断点指令的最大长度
+const MAX_BREAKPOINT_LEN: usize = 4;
+
+/// 读写内存时每次经栈上缓冲区搬运的字节数，内核栈很小，不能整包放在栈上
+const CHUNK_SIZE: usize = 64;
+
+/// `SIGTRAP`，断点与单步
+pub const SIGTRAP: u8 = 5;
+/// `SIGINT`，GDB 中按下 `Ctrl-C` 或在目标上按下魔术键
+pub const SIGINT: u8 = 2;
+/// `SIGABRT`，内核 panic
+pub const SIGABRT: u8 = 6;
+
+/// 收发字节的通道
+pub trait Connection {
+    /// 取出一个收到的字节，没有时立即返回 `None`
+    fn read_byte(&mut self) -> Option<u8>;
+    /// 发送 `data`，返回时可以复用缓冲区
+    fn write(&mut self, data: &[u8]);
+}
+
+/// 停下的目标
+pub trait Target {
+    /// 读取 `n` 号寄存器，现场中没有保存它时返回 `None`
+    fn read_register(&self, n: usize) -> Option<usize>;
+    /// 修改 `n` 号寄存器，不能修改时返回 `false`
+    fn write_register(&mut self, n: usize, value: usize) -> bool;
+    /// 从 `addr` 起读取内存到 `buf`，返回读到的字节数，遇到不可读的地址即停止
+    fn read_memory(&self, addr: usize, buf: &mut [u8]) -> usize;
+    /// 把 `data` 写入 `addr` 起的内存并使指令缓存看到新内容，有字节不可写时返回 `false`
+    fn write_memory(&mut self, addr: usize, data: &[u8]) -> bool;
+}
+
+/// 按 `Arch` 编号访问的一份寄存器现场，如陷阱上下文；`Target` 的寄存器部分可以交给它
+pub trait Registers {
+    /// 读取 `n` 号寄存器，现场中没有保存它时返回 `None`
+    fn read(&self, n: usize) -> Option<usize>;
+    /// 修改 `n` 号寄存器，修改不会生效时返回 `false`
+    fn write(&mut self, n: usize, value: usize) -> bool;
+}
+
+/// `stop` 返回后目标应做的事
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Resume {
+    /// 从现场中的 `pc` 继续运行，单步也是如此
+    Continue,
+    /// GDB 已断开，断点均已撤掉
+    Detach,
+    /// GDB 要求结束目标，断点均已撤掉
+    Kill,
+}
+
+#[derive(Clone, Copy)]
+struct Breakpoint {
+    addr: usize,
+    len: usize,
+    saved: [u8; MAX_BREAKPOINT_LEN],
+}
+
+impl Breakpoint {
+    /// 保存 `addr` 处原有的指令并写入断点指令
+    fn insert<T: Target + ?Sized>(target: &mut T, addr: usize, insn: &[u8]) -> Option<Self> {
+        let mut saved = [0; MAX_BREAKPOINT_LEN];
+        let len = insn.len();
+        if target.read_memory(addr, &mut saved[..len]) != len || !target.write_memory(addr, insn) {
+            return None;
+        }
+        Some(Self { addr, len, saved })
+    }
+
+    fn remove<T: Target + ?Sized>(&self, target: &mut T) -> bool {
+        target.write_memory(self.addr, &self.saved[..self.len])
+    }
+}
+
+/// 处理完一个包之后的动作
+enum Action {
+    Reply,
+    Resume,
+    Detach,
+    Kill,
+}
+
+pub struct GdbStub<A> {
+    reader: PacketReader,
+    session: Session<A>,
+}
+
+/// 与 GDB 的会话状态，与收包缓冲区分开，处理包时可以直接借用包的内容
+struct Session<A> {
+    /// 最近发出的回复，收到 `-` 时重发
+    reply: Reply,
+    breakpoints: [Option<Breakpoint>; MAX_BREAKPOINTS],
+    /// 单步的临时断点
+    step: [Option<Breakpoint>; 2],
+    /// 上次停下时 GDB 要求了继续或单步，下次停下要发送停止原因
+    resumed: bool,
+    _arch: PhantomData<A>,
+}
+
+impl<A: Arch> GdbStub<A> {
+    pub const fn new() -> Self {
+        Self {
+            reader: PacketReader::new(),
+            session: Session {
+                reply: Reply::new(),
+                breakpoints: [None; MAX_BREAKPOINTS],
+                step: [None; 2],
+                resumed: false,
+                _arch: PhantomData,
+            },
+        }
+    }
+
+    /// GDB 是否正在调试目标，即上次停下后要求了继续或单步
+    pub fn is_attached(&self) -> bool {
+        self.session.resumed
+    }
+
+    /// `addr` 处是否有本桩放置的断点，断点异常据此决定是否交给 GDB
+    pub fn has_breakpoint(&self, addr: usize) -> bool {
+        self.session.has_breakpoint(addr)
+    }
+
+    /// 目标因 `signal` 停下，与 GDB 交互直到它让目标继续运行
+    pub fn stop<C, T>(&mut self, conn: &mut C, target: &mut T, signal: u8) -> Resume
+    where
+        C: Connection + ?Sized,
+        T: Target + ?Sized,
+    {
+        let session = &mut self.session;
+        session.remove_step_breakpoints(target);
+        if session.resumed {
+            session.resumed = false;
+            session.reply.clear();
+            session.push_stop_reason(signal);
+            session.send(conn);
+        }
+        loop {
+            let Some(byte) = conn.read_byte() else {
+                core::hint::spin_loop();
+                continue;
+            };
+            match self.reader.feed(byte) {
+                Some(Event::Packet) => {
+                    conn.write(b"+");
+                    session.reply.clear();
+                    match session.handle(self.reader.packet(), target, signal) {
+                        Action::Reply => session.send(conn),
+                        Action::Resume => {
+                            session.resumed = true;
+                            return Resume::Continue;
+                        }
+                        Action::Detach => {
+                            session.remove_all_breakpoints(target);
+                            session.reply.push_str("OK");
+                            session.send(conn);
+                            return Resume::Detach;
+                        }
+                        Action::Kill => {
+                            session.remove_all_breakpoints(target);
+                            return Resume::Kill;
+                        }
+                    }
+                }
+                Some(Event::BadChecksum) => conn.write(b"-"),
+                Some(Event::Nack) => session.send(conn),
+                Some(Event::Ack | Event::Interrupt) | None => {}
+            }
+        }
+    }
+
+    /// 目标运行时检查 GDB 是否按下了 `Ctrl-C`，按下时调用者应以 `SIGINT` 调用 `stop`
+    pub fn poll_interrupt<C: Connection + ?Sized>(&mut self, conn: &mut C) -> bool {
+        while let Some(byte) = conn.read_byte() {
+            if self.reader.feed(byte) == Some(Event::Interrupt) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl<A: Arch> Default for GdbStub<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Arch> Session<A> {
+    fn has_breakpoint(&self, addr: usize) -> bool {
+        self.breakpoints
+            .iter()
+            .chain(self.step.iter())
+            .flatten()
+            .any(|bp| bp.addr == addr)
+    }
+
+    fn send<C: Connection + ?Sized>(&self, conn: &mut C) {
+        let [high, low] = hex_pair(self.reply.checksum());
+        conn.write(b"$");
+        conn.write(self.reply.as_bytes());
+        conn.write(&[b'#', high, low]);
+    }
+
+    fn push_stop_reason(&mut self, signal: u8) {
+        self.reply.push(b'S');
+        self.reply.push_hex(&[signal]);
+    }
+
+    fn ok(&mut self) -> Action {
+        self.reply.push_str("OK");
+        Action::Reply
+    }
+
+    fn error(&mut self, errno: u8) -> Action {
+        self.reply.push(b'E');
+        self.reply.push_hex(&[errno]);
+        Action::Reply
+    }
+
+    /// 处理一个包，回复写入 `reply`
+    fn handle<T: Target + ?Sized>(&mut self, packet: &[u8], target: &mut T, signal: u8) -> Action {
+        let Some((&command, args)) = packet.split_first() else {
+            return Action::Reply;
+        };
+        match command {
+            b'?' => {
+                self.push_stop_reason(signal);
+                Action::Reply
+            }
+            b'g' => {
+                for n in 0..A::REGISTERS {
+                    match target.read_register(n) {
+                        Some(value) => self.reply.push_word(value),
+                        None => (0..2 * core::mem::size_of::<usize>())
+                            .for_each(|_| self.reply.push(b'x')),
+                    }
+                }
+                Action::Reply
+            }
+            b'G' => self.write_registers(target, args),
+            b'p' => match parse_hex(args).and_then(|n| target.read_register(n)) {
+                Some(value) => {
+                    self.reply.push_word(value);
+                    Action::Reply
+                }
+                None => self.error(1),
+            },
+            b'P' => {
+                let written = split(args, b'=').and_then(|(n, value)| {
+                    Some(target.write_register(parse_hex(n)?, decode_word(value)?))
+                });
+                match written {
+                    Some(true) => self.ok(),
+                    _ => self.error(1),
+                }
+            }
+            b'm' => self.read_memory(target, args),
+            b'M' => self.write_memory(target, args),
+            b'c' => self.resume(target, args, false),
+            b's' => self.resume(target, args, true),
+            b'Z' | b'z' => self.breakpoint(target, args, command == b'Z'),
+            b'q' => self.query(packet),
+            b'H' | b'T' => self.ok(),
+            b'D' => Action::Detach,
+            b'k' => Action::Kill,
+            // 其余包回复空包，包括二进制写内存 `X` 与 `vCont`
+            _ => Action::Reply,
+        }
+    }
+
+    fn write_registers<T: Target + ?Sized>(&mut self, target: &mut T, args: &[u8]) -> Action {
+        let width = 2 * core::mem::size_of::<usize>();
+        if args.len() != width * A::REGISTERS {
+            return self.error(1);
+        }
+        for (n, text) in args.chunks(width).enumerate() {
+            // 现场中没有保存的寄存器由 GDB 以 `x` 填充，跳过即可
+            if let Some(value) = decode_word(text) {
+                target.write_register(n, value);
+            }
+        }
+        self.ok()
+    }
+
+    fn read_memory<T: Target + ?Sized>(&mut self, target: &mut T, args: &[u8]) -> Action {
+        let Some((addr, len)) =
+            split(args, b',').and_then(|(addr, len)| Some((parse_hex(addr)?, parse_hex(len)?)))
+        else {
+            return self.error(1);
+        };
+        let mut chunk = [0; CHUNK_SIZE];
+        let mut done = 0;
+        // 每个字节占两个十六进制字符
+        let len = len.min(BUF_SIZE / 2);
+        while done < len {
+            let want = (len - done).min(chunk.len());
+            let read = target.read_memory(addr.wrapping_add(done), &mut chunk[..want]);
+            self.reply.push_hex(&chunk[..read]);
+            done += read;
+            if read < want {
+                break;
+            }
+        }
+        if done == 0 && len != 0 {
+            return self.error(14);
+        }
+        Action::Reply
+    }
+
+    /// `M addr,len:data`，按块解码并写入
+    fn write_memory<T: Target + ?Sized>(&mut self, target: &mut T, args: &[u8]) -> Action {
+        let Some((addr, len, hex)) = split(args, b':').and_then(|(range, hex)| {
+            let (addr, len) = split(range, b',')?;
+            Some((parse_hex(addr)?, parse_hex(len)?, hex))
+        }) else {
+            return self.error(1);
+        };
+        if hex.len() != 2 * len {
+            return self.error(1);
+        }
+        let mut chunk = [0; CHUNK_SIZE];
+        for (i, text) in hex.chunks(2 * CHUNK_SIZE).enumerate() {
+            let Some(decoded) = decode_hex(text, &mut chunk) else {
+                return self.error(1);
+            };
+            let at = addr.wrapping_add(i * CHUNK_SIZE);
+            if !target.write_memory(at, &chunk[..decoded]) {
+                return self.error(14);
+            }
+        }
+        self.ok()
+    }
+
+    /// `c [addr]` 与 `s [addr]`
+    fn resume<T: Target + ?Sized>(&mut self, target: &mut T, args: &[u8], step: bool) -> Action {
+        if !args.is_empty() {
+            match parse_hex(args) {
+                Some(addr) if target.write_register(A::PC, addr) => {}
+                _ => return self.error(1),
+            }
+        }
+        if step {
+            let Some(pc) = target.read_register(A::PC) else {
+                return self.error(1);
+            };
+            let Some(next_pcs) = A::next_pcs(target, pc) else {
+                return self.error(14);
+            };
+            let insn = A::breakpoint(A::STEP_KIND).expect("step breakpoint kind");
+            for (slot, addr) in next_pcs.iter().enumerate() {
+                let Some(addr) = *addr else {
+                    continue;
+                };
+                // 已有断点的地址不必再放，两个后继相同时也只放一次
+                if self.has_breakpoint(addr) {
+                    continue;
+                }
+                match Breakpoint::insert(target, addr, insn) {
+                    Some(bp) => self.step[slot] = Some(bp),
+                    None => {
+                        self.remove_step_breakpoints(target);
+                        return self.error(14);
+                    }
+                }
+            }
+        }
+        Action::Resume
+    }
+
+    /// `Z0,addr,kind` 与 `z0,addr,kind`，只支持软件断点
+    fn breakpoint<T: Target + ?Sized>(
+        &mut self,
+        target: &mut T,
+        args: &[u8],
+        insert: bool,
+    ) -> Action {
+        let Some((kind, rest)) = split(args, b',') else {
+            return self.error(1);
+        };
+        if kind != b"0" {
+            return Action::Reply;
+        }
+        let Some((addr, kind)) =
+            split(rest, b',').and_then(|(addr, kind)| Some((parse_hex(addr)?, parse_hex(kind)?)))
+        else {
+            return self.error(1);
+        };
+        let existing = self
+            .breakpoints
+            .iter()
+            .position(|bp| bp.map_or(false, |bp| bp.addr == addr));
+        if !insert {
+            if let Some(bp) = existing.and_then(|index| self.breakpoints[index].take()) {
+                if !bp.remove(target) {
+                    return self.error(14);
+                }
+            }
+            return self.ok();
+        }
+        if existing.is_some() {
+            return self.ok();
+        }
+        let Some(insn) = A::breakpoint(kind) else {
+            return self.error(22);
+        };
+        let Some(slot) = self.breakpoints.iter().position(Option::is_none) else {
+            return self.error(12);
+        };
+        match Breakpoint::insert(target, addr, insn) {
+            Some(bp) => {
+                self.breakpoints[slot] = Some(bp);
+                self.ok()
+            }
+            None => self.error(14),
+        }
+    }
+
+    fn query(&mut self, packet: &[u8]) -> Action {
+        const XFER: &[u8] = b"qXfer:features:read:target.xml:";
+        if packet.starts_with(b"qSupported") {
+            self.reply.push_str("PacketSize=");
+            let size = (BUF_SIZE as u32).to_be_bytes();
+            let start = size.iter().position(|&byte| byte != 0).unwrap_or(3);
+            self.reply.push_hex(&size[start..]);
+            self.reply.push_str(";qXfer:features:read+");
+        } else if packet == b"qAttached" {
+            // 目标不是由 GDB 启动的，断开时不应结束它
+            self.reply.push(b'1');
+        } else if let Some(range) = packet.strip_prefix(XFER) {
+            let Some((offset, len)) = split(range, b',')
+                .and_then(|(offset, len)| Some((parse_hex(offset)?, parse_hex(len)?)))
+            else {
+                return self.error(1);
+            };
+            let xml = A::TARGET_XML.as_bytes();
+            let start = offset.min(xml.len());
+            let end = start + len.min(BUF_SIZE - 1).min(xml.len() - start);
+            self.reply.push(if end == xml.len() { b'l' } else { b'm' });
+            xml[start..end]
+                .iter()
+                .for_each(|&byte| self.reply.push(byte));
+        }
+        Action::Reply
+    }
+
+    fn remove_step_breakpoints<T: Target + ?Sized>(&mut self, target: &mut T) {
+        // 按放置的相反顺序恢复
+        for bp in self.step.iter_mut().rev() {
+            if let Some(bp) = bp.take() {
+                bp.remove(target);
+            }
+        }
+    }
+
+    fn remove_all_breakpoints<T: Target + ?Sized>(&mut self, target: &mut T) {
+        self.remove_step_breakpoints(target);
+        for bp in self.breakpoints.iter_mut() {
+            if let Some(bp) = bp.take() {
+                bp.remove(target);
+            }
+        }
+        self.resumed = false;
+    }
+}
+
+/// 在第一个 `delimiter` 处把参数分成两段
+fn split(args: &[u8], delimiter: u8) -> Option<(&[u8], &[u8])> {
+    let at = args.iter().position(|&byte| byte == delimiter)?;
+    Some((&args[..at], &args[at + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+
+    /// GDB 一侧：预先写好的输入与收到的全部输出
+    struct Pipe {
+        input: VecDeque<u8>,
+        output: Vec<u8>,
+    }
+
+    impl Pipe {
+        /// 依次发送 `packets`，每个包都带上正确的校验和
+        fn with_packets(packets: &[&str]) -> Self {
+            let mut input = VecDeque::new();
+            for packet in packets {
+                let sum = packet.bytes().fold(0u8, |sum, byte| sum.wrapping_add(byte));
+                input.extend(format!("${}#{:02x}", packet, sum).bytes());
+            }
+            Self {
+                input,
+                output: Vec::new(),
+            }
+        }
+
+        /// 输出中的全部回复，去掉应答与校验和
+        fn replies(&self) -> Vec<String> {
+            let text = String::from_utf8(self.output.clone()).unwrap();
+            text.split('$')
+                .skip(1)
+                .map(|reply| reply.split('#').next().unwrap().to_string())
+                .collect()
+        }
+    }
+
+    impl Connection for Pipe {
+        fn read_byte(&mut self) -> Option<u8> {
+            // 输入耗尽说明测试没有以继续或断开结束
+            Some(self.input.pop_front().expect("stub waits for more input"))
+        }
+
+        fn write(&mut self, data: &[u8]) {
+            self.output.extend_from_slice(data);
+        }
+    }
+
+    /// 33 个寄存器与一段可读写的内存，`ro` 之后的内存只读
+    struct Machine {
+        regs: [usize; 33],
+        memory: HashMap<usize, u8>,
+        ro: usize,
+    }
+
+    const BASE: usize = 0x8020_0000;
+
+    impl Machine {
+        fn new(code: &[u8]) -> Self {
+            let mut regs = [0; 33];
+            regs[32] = BASE;
+            Self {
+                regs,
+                memory: code
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &byte)| (BASE + i, byte))
+                    .collect(),
+                ro: usize::MAX,
+            }
+        }
+
+        fn bytes(&self, addr: usize, len: usize) -> Vec<u8> {
+            (addr..addr + len).map(|addr| self.memory[&addr]).collect()
+        }
+    }
+
+    impl Target for Machine {
+        fn read_register(&self, n: usize) -> Option<usize> {
+            // 0 号恒为零，4 号（tp）不在现场中
+            match n {
+                0 => Some(0),
+                4 => None,
+                _ => self.regs.get(n).copied(),
+            }
+        }
+
+        fn write_register(&mut self, n: usize, value: usize) -> bool {
+            match self.regs.get_mut(n) {
+                Some(reg) if n != 0 && n != 4 => {
+                    *reg = value;
+                    true
+                }
+                _ => false,
+            }
+        }
+
+        fn read_memory(&self, addr: usize, buf: &mut [u8]) -> usize {
+            for (i, byte) in buf.iter_mut().enumerate() {
+                match self.memory.get(&(addr + i)) {
+                    Some(&value) => *byte = value,
+                    None => return i,
+                }
+            }
+            buf.len()
+        }
+
+        fn write_memory(&mut self, addr: usize, data: &[u8]) -> bool {
+            let end = addr + data.len();
+            if end > self.ro || (addr..end).any(|addr| !self.memory.contains_key(&addr)) {
+                return false;
+            }
+            for (i, &byte) in data.iter().enumerate() {
+                self.memory.insert(addr + i, byte);
+            }
+            true
+        }
+    }
+
+    /// addi a0, a0, 1；beq a0, a1, +8；c.nop；c.nop；c.nop
+    const CODE: [u8; 14] = [
+        0x13, 0x05, 0x15, 0x00, 0x63, 0x04, 0xb5, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01, 0x00,
+    ];
+
+    fn run(machine: &mut Machine, stub: &mut GdbStub<RiscV64>, packets: &[&str]) -> Vec<String> {
+        let mut pipe = Pipe::with_packets(packets);
+        stub.stop(&mut pipe, machine, SIGTRAP);
+        assert!(pipe.input.is_empty());
+        pipe.replies()
+    }
+
+    #[test]
+    fn reports_stop_reason_and_detaches() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        let mut pipe = Pipe::with_packets(&["?", "qAttached", "Hg0", "vMustReplyEmpty", "D"]);
+        assert_eq!(stub.stop(&mut pipe, &mut machine, SIGABRT), Resume::Detach);
+        assert_eq!(pipe.replies(), ["S06", "1", "OK", "", "OK"]);
+        // 每个包都被确认
+        assert_eq!(pipe.output.iter().filter(|&&byte| byte == b'+').count(), 5);
+        assert!(String::from_utf8(pipe.output).unwrap().ends_with("$OK#9a"));
+    }
+
+    #[test]
+    fn reads_and_writes_registers() {
+        let mut machine = Machine::new(&CODE);
+        machine.regs[1] = 0x8020_0abc;
+        let mut stub = GdbStub::<RiscV64>::new();
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &[
+                "g",
+                "p1",
+                "p4",
+                "p40",
+                "P2=0010000000000000",
+                "P0=0100000000000000",
+                "D",
+            ],
+        );
+        let g = &replies[0];
+        assert_eq!(g.len(), 33 * 16);
+        assert_eq!(&g[16..32], "bc0a208000000000");
+        assert_eq!(&g[64..80], "xxxxxxxxxxxxxxxx");
+        assert_eq!(&g[32 * 16..], "0000208000000000");
+        assert_eq!(
+            replies[1..],
+            ["bc0a208000000000", "E01", "E01", "OK", "E01", "OK"]
+        );
+        assert_eq!(machine.regs[2], 0x1000);
+
+        // `G` 写回全部寄存器，`x` 填充的寄存器被跳过
+        let mut all = String::new();
+        for n in 0..33 {
+            if n == 4 {
+                all.push_str("xxxxxxxxxxxxxxxx");
+            } else {
+                all.push_str(&format!("{:016x}", (n as u64).swap_bytes()));
+            }
+        }
+        let packet = format!("G{}", all);
+        let replies = run(&mut machine, &mut stub, &[&packet, "GFF", "D"]);
+        assert_eq!(replies[..2], ["OK", "E01"]);
+        assert_eq!(machine.regs[7], 7);
+        assert_eq!(machine.regs[32], 32);
+    }
+
+    #[test]
+    fn reads_and_writes_memory() {
+        let mut machine = Machine::new(&CODE);
+        machine.ro = BASE + 8;
+        let mut stub = GdbStub::<RiscV64>::new();
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &[
+                "m80200000,4",
+                // 读到不可读的地址时返回已读到的部分
+                "m8020000c,8",
+                "m90000000,4",
+                "M80200000,2:aabb",
+                "M80200006,4:00000000",
+                "M80200000,3:aabb",
+                "X80200000,0:",
+                "D",
+            ],
+        );
+        assert_eq!(replies[..3], ["13051500", "0100", "E0e"]);
+        assert_eq!(replies[3..7], ["OK", "E0e", "E01", ""]);
+        assert_eq!(machine.bytes(BASE, 2), [0xaa, 0xbb]);
+    }
+
+    #[test]
+    fn software_breakpoints() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &[
+                "Z0,80200004,4",
+                "Z0,8020000c,2",
+                "Z0,80200008,3",
+                "Z1,80200000,4",
+                "c",
+            ],
+        );
+        assert_eq!(replies, ["OK", "OK", "E16", ""]);
+        assert_eq!(machine.bytes(BASE + 4, 4), [0x73, 0x00, 0x10, 0x00]);
+        assert_eq!(machine.bytes(BASE + 12, 2), [0x02, 0x90]);
+        assert!(stub.is_attached());
+        assert!(stub.has_breakpoint(BASE + 4));
+        assert!(!stub.has_breakpoint(BASE + 8));
+
+        // 停在断点处：先报告停止原因，再删除断点
+        machine.regs[32] = BASE + 4;
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &["z0,80200004,4", "z0,80200004,4", "k"],
+        );
+        assert_eq!(replies, ["S05", "OK", "OK"]);
+        assert_eq!(machine.bytes(BASE + 4, 4), CODE[4..8]);
+        // 结束时撤掉其余断点
+        assert_eq!(machine.bytes(BASE + 12, 2), CODE[12..14]);
+        assert!(!stub.has_breakpoint(BASE + 12));
+        assert!(!stub.is_attached());
+    }
+
+    #[test]
+    fn breakpoint_in_read_only_memory_fails() {
+        let mut machine = Machine::new(&CODE);
+        machine.ro = BASE;
+        let mut stub = GdbStub::<RiscV64>::new();
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &["Z0,80200000,4", "Z0,90000000,4", "D"],
+        );
+        assert_eq!(replies[..2], ["E0e", "E0e"]);
+        assert!(!stub.has_breakpoint(BASE));
+    }
+
+    #[test]
+    fn single_step_uses_temporary_breakpoints() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        // 顺序执行的指令：临时断点放在下一条
+        assert!(run(&mut machine, &mut stub, &["s"]).is_empty());
+        assert_eq!(machine.bytes(BASE + 4, 2), [0x02, 0x90]);
+        assert!(stub.has_breakpoint(BASE + 4));
+
+        // 停下时先撤掉临时断点；条件分支的两个后继都放临时断点
+        machine.regs[32] = BASE + 4;
+        let replies = run(&mut machine, &mut stub, &["m80200004,4", "s"]);
+        assert_eq!(replies, ["S05", "6304b500"]);
+        assert_eq!(machine.bytes(BASE + 8, 2), [0x02, 0x90]);
+        assert_eq!(machine.bytes(BASE + 12, 2), [0x02, 0x90]);
+
+        // `s addr` 先修改 pc
+        machine.regs[32] = BASE + 12;
+        let replies = run(&mut machine, &mut stub, &["s80200000"]);
+        assert_eq!(replies, ["S05"]);
+        assert_eq!(machine.regs[32], BASE);
+        assert_eq!(machine.bytes(BASE + 8, 6), CODE[8..14]);
+        assert_eq!(machine.bytes(BASE + 4, 2), [0x02, 0x90]);
+
+        let replies = run(&mut machine, &mut stub, &["D"]);
+        assert_eq!(replies, ["S05", "OK"]);
+        assert_eq!(machine.bytes(BASE, CODE.len()), CODE);
+    }
+
+    #[test]
+    fn step_keeps_user_breakpoints() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        run(&mut machine, &mut stub, &["Z0,80200004,4", "s"]);
+        // 后继处已有断点，单步不再覆盖它
+        assert_eq!(machine.bytes(BASE + 4, 4), [0x73, 0x00, 0x10, 0x00]);
+        machine.regs[32] = BASE + 4;
+        run(&mut machine, &mut stub, &["D"]);
+        assert_eq!(machine.bytes(BASE, CODE.len()), CODE);
+    }
+
+    #[test]
+    fn queries() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        let xml = RiscV64::TARGET_XML;
+        let replies = run(
+            &mut machine,
+            &mut stub,
+            &[
+                "qSupported:multiprocess+;swbreak+",
+                "qXfer:features:read:target.xml:0,10",
+                &format!("qXfer:features:read:target.xml:10,{:x}", xml.len()),
+                &format!("qXfer:features:read:target.xml:{:x},10", xml.len()),
+                "qC",
+                "D",
+            ],
+        );
+        assert_eq!(replies[0], "PacketSize=1000;qXfer:features:read+");
+        assert_eq!(replies[1], format!("m{}", &xml[..16]));
+        assert_eq!(replies[2], format!("l{}", &xml[16..]));
+        assert_eq!(replies[3..5], ["l", ""]);
+    }
+
+    #[test]
+    fn resends_on_nack_and_rejects_bad_checksums() {
+        let mut machine = Machine::new(&CODE);
+        let mut stub = GdbStub::<RiscV64>::new();
+        let mut pipe = Pipe::with_packets(&[]);
+        pipe.input.extend(b"$?#00$?#3f-".iter());
+        pipe.input.extend(Pipe::with_packets(&["D"]).input);
+        stub.stop(&mut pipe, &mut machine, SIGINT);
+        let output = String::from_utf8(pipe.output).unwrap();
+        assert_eq!(output, "-+$S02#b5$S02#b5+$OK#9a");
+    }
+
+    #[test]
+    fn polls_for_interrupts() {
+        let mut stub = GdbStub::<RiscV64>::new();
+        let mut pipe = Pipe::with_packets(&[]);
+        pipe.input.extend(b"+\x03");
+        assert!(stub.poll_interrupt(&mut NonBlocking(&mut pipe)));
+        assert!(!stub.poll_interrupt(&mut NonBlocking(&mut pipe)));
+    }
+
+    /// 输入耗尽时返回 `None` 的连接
+    struct NonBlocking<'a>(&'a mut Pipe);
+
+    impl Connection for NonBlocking<'_> {
+        fn read_byte(&mut self) -> Option<u8> {
+            self.0.input.pop_front()
+        }
+
+        fn write(&mut self, data: &[u8]) {
+            self.0.write(data);
+        }
+    }
+}
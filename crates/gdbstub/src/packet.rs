@@ -0,0 +1,297 @@
+//! # 包的收发与十六进制编码
+//!
+//! ## Overview
+//! - `PacketReader`：逐字节识别 `$数据#校验和` 形式的包、`Ctrl-C` 中断与 `+`/`-` 应答
+//! - `Reply`：在固定缓冲区中拼装回复，`checksum` 给出发送时的校验和
+//! - 十六进制数与字节串的解析
+//!
+//! ## Behavior
+//! - 超过 `BUF_SIZE` 的包按校验错误处理，请求方会重发
+//! - 回复超过 `BUF_SIZE` 的部分被丢弃，调用者按缓冲区大小限制请求的长度
+
+/// 收发缓冲区的大小，也是在 `qSupported` 中告知 GDB 的 `PacketSize`
+pub const BUF_SIZE: usize = 0x1000;
+
+/// `PacketReader` 识别出的事件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// 收到完整且校验正确的包，内容见 `PacketReader::packet`
+    Packet,
+    /// 包的校验和不对或过长，应回复 `-`
+    BadChecksum,
+    /// 对方确认收到上一个回复
+    Ack,
+    /// 对方要求重发上一个回复
+    Nack,
+    /// 包之外的 `0x03`，即 GDB 中按下的 `Ctrl-C`
+    Interrupt,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Idle,
+    Data,
+    Checksum,
+    /// 已读到校验和的高四位
+    ChecksumLow(u8),
+}
+
+pub struct PacketReader {
+    buf: [u8; BUF_SIZE],
+    len: usize,
+    sum: u8,
+    overflow: bool,
+    state: State,
+}
+
+impl PacketReader {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; BUF_SIZE],
+            len: 0,
+            sum: 0,
+            overflow: false,
+            state: State::Idle,
+        }
+    }
+
+    /// 最近一个完整的包，不含 `$` 与校验和
+    pub fn packet(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// 处理收到的一个字节
+    pub fn feed(&mut self, byte: u8) -> Option<Event> {
+        match self.state {
+            State::Idle => match byte {
+                b'$' => self.start(),
+                b'+' => return Some(Event::Ack),
+                b'-' => return Some(Event::Nack),
+                0x03 => return Some(Event::Interrupt),
+                _ => {}
+            },
+            State::Data => match byte {
+                b'#' => self.state = State::Checksum,
+                // 丢掉不完整的包，重新开始
+                b'$' => self.start(),
+                _ => {
+                    self.sum = self.sum.wrapping_add(byte);
+                    if self.len < BUF_SIZE {
+                        self.buf[self.len] = byte;
+                        self.len += 1;
+                    } else {
+                        self.overflow = true;
+                    }
+                }
+            },
+            State::Checksum => {
+                let Some(high) = hex_digit(byte) else {
+                    self.state = State::Idle;
+                    return Some(Event::BadChecksum);
+                };
+                self.state = State::ChecksumLow(high);
+            }
+            State::ChecksumLow(high) => {
+                self.state = State::Idle;
+                let valid = hex_digit(byte).map(|low| high << 4 | low) == Some(self.sum);
+                return Some(if valid && !self.overflow {
+                    Event::Packet
+                } else {
+                    Event::BadChecksum
+                });
+            }
+        }
+        None
+    }
+
+    fn start(&mut self) {
+        self.len = 0;
+        self.sum = 0;
+        self.overflow = false;
+        self.state = State::Data;
+    }
+}
+
+impl Default for PacketReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 正在拼装或最近发出的回复
+pub struct Reply {
+    buf: [u8; BUF_SIZE],
+    len: usize,
+}
+
+impl Reply {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; BUF_SIZE],
+            len: 0,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        if self.len < BUF_SIZE {
+            self.buf[self.len] = byte;
+            self.len += 1;
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        s.bytes().for_each(|byte| self.push(byte));
+    }
+
+    /// 以两位十六进制追加每个字节
+    pub fn push_hex(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let [high, low] = hex_pair(byte);
+            self.push(high);
+            self.push(low);
+        }
+    }
+
+    /// 以目标的字节序（小端）追加一个机器字
+    pub fn push_word(&mut self, value: usize) {
+        self.push_hex(&value.to_le_bytes());
+    }
+
+    /// 发送时的校验和
+    pub fn checksum(&self) -> u8 {
+        self.as_bytes()
+            .iter()
+            .fold(0, |sum, &byte| sum.wrapping_add(byte))
+    }
+}
+
+impl Default for Reply {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 一个字节的两位小写十六进制
+pub fn hex_pair(byte: u8) -> [u8; 2] {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    [DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xf) as usize]]
+}
+
+pub fn hex_digit(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// 解析大端书写的十六进制数，如地址与长度
+pub fn parse_hex(text: &[u8]) -> Option<usize> {
+    if text.is_empty() || text.len() > 2 * core::mem::size_of::<usize>() {
+        return None;
+    }
+    text.iter().try_fold(0, |value, &byte| {
+        Some(value << 4 | hex_digit(byte)? as usize)
+    })
+}
+
+/// 把十六进制字节串解码到 `out`，返回字节数；长度为奇数或 `out` 放不下时返回 `None`
+pub fn decode_hex(text: &[u8], out: &mut [u8]) -> Option<usize> {
+    if text.len() % 2 != 0 || text.len() / 2 > out.len() {
+        return None;
+    }
+    for (byte, pair) in out.iter_mut().zip(text.chunks(2)) {
+        *byte = hex_digit(pair[0])? << 4 | hex_digit(pair[1])?;
+    }
+    Some(text.len() / 2)
+}
+
+/// 解码以目标字节序（小端）书写的一个机器字
+pub fn decode_word(text: &[u8]) -> Option<usize> {
+    let mut bytes = [0; core::mem::size_of::<usize>()];
+    if text.len() != 2 * bytes.len() {
+        return None;
+    }
+    decode_hex(text, &mut bytes)?;
+    Some(usize::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(reader: &mut PacketReader, bytes: &[u8]) -> Vec<Event> {
+        bytes.iter().filter_map(|&byte| reader.feed(byte)).collect()
+    }
+
+    #[test]
+    fn reads_packets_and_control_bytes() {
+        let mut reader = PacketReader::new();
+        assert_eq!(
+            feed_all(&mut reader, b"+$g#67"),
+            [Event::Ack, Event::Packet]
+        );
+        assert_eq!(reader.packet(), b"g");
+        assert_eq!(
+            feed_all(&mut reader, b"-\x03"),
+            [Event::Nack, Event::Interrupt]
+        );
+        // 包之外的其他字节被忽略，校验和大小写均可
+        assert_eq!(feed_all(&mut reader, b"xx$?#3F"), [Event::Packet]);
+        assert_eq!(reader.packet(), b"?");
+    }
+
+    #[test]
+    fn rejects_bad_packets() {
+        let mut reader = PacketReader::new();
+        assert_eq!(feed_all(&mut reader, b"$g#00"), [Event::BadChecksum]);
+        assert_eq!(feed_all(&mut reader, b"$g#z"), [Event::BadChecksum]);
+        // 新的 `$` 丢弃不完整的包
+        assert_eq!(feed_all(&mut reader, b"$m0,$g#67"), [Event::Packet]);
+        assert_eq!(reader.packet(), b"g");
+        // 过长的包
+        let mut long = vec![b'$'];
+        long.extend(std::iter::repeat(b'0').take(BUF_SIZE + 1));
+        let sum = (BUF_SIZE + 1) as u32 * b'0' as u32;
+        long.extend(format!("#{:02x}", sum & 0xff).bytes());
+        assert_eq!(feed_all(&mut reader, &long), [Event::BadChecksum]);
+    }
+
+    #[test]
+    fn builds_replies() {
+        let mut reply = Reply::new();
+        reply.push_str("OK");
+        assert_eq!(reply.checksum(), 0x9a);
+        reply.clear();
+        reply.push_hex(&[0x00, 0xab, 0x7f]);
+        assert_eq!(reply.as_bytes(), b"00ab7f");
+        reply.clear();
+        reply.push_word(0x8020_0000);
+        assert_eq!(reply.as_bytes(), b"0000208000000000");
+    }
+
+    #[test]
+    fn parses_hex() {
+        assert_eq!(parse_hex(b"80200000"), Some(0x8020_0000));
+        assert_eq!(parse_hex(b"FFFFFFFFFFFFFFFF"), Some(usize::MAX));
+        assert_eq!(parse_hex(b""), None);
+        assert_eq!(parse_hex(b"1g"), None);
+        assert_eq!(parse_hex(b"10000000000000000"), None);
+        let mut out = [0; 4];
+        assert_eq!(decode_hex(b"7300", &mut out), Some(2));
+        assert_eq!(out[..2], [0x73, 0x00]);
+        assert_eq!(decode_hex(b"730", &mut out), None);
+        assert_eq!(decode_hex(b"0011223344", &mut out), None);
+        assert_eq!(decode_word(b"0000208000000000"), Some(0x8020_0000));
+        assert_eq!(decode_word(b"00002080"), None);
+    }
+}
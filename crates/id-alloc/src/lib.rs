@@ -0,0 +1,329 @@
+//! # 编号分配器
+//!
+//! ## Overview
+//! - `RecycleAllocator`：回收式递增分配器，用于 PID、线程资源编号与内核栈编号
+//! - `StackAllocator`：在区间 `[current, end)` 内顺序分配、回收后复用的分配器，
+//!   支持预留一段区间，是物理页帧分配器的核心
+//!
+//! ## Invariants
+//! - 已分配的编号不会被重复分配
+//! - 回收的编号必然小于 `current`，且在回收栈中只出现一次
+//!
+//! ## Behavior
+//! - 两个分配器都优先复用最近回收的编号
+//! - 回收未分配或已回收的编号会 panic
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+/// 可回收的递增编号分配器
+///
+/// ## Fields
+/// - `current`：
+///   - 下一个未分配过的编号
+/// - `recycled`：
+///   - 回收池，存放已经释放的编号
+#[derive(Default)]
+pub struct RecycleAllocator {
+    current: usize,
+    recycled: Vec<usize>,
+}
+
+impl RecycleAllocator {
+    /// 创建新的分配器
+    ///
+    /// ## Invariants
+    /// - 初始时 `current = 0`，`recycled` 为空
+    pub const fn new() -> Self {
+        RecycleAllocator {
+            current: 0,
+            recycled: Vec::new(),
+        }
+    }
+
+    /// 分配一个编号
+    ///
+    /// ## Behavior
+    /// - 优先返回回收的编号
+    /// - 若无回收的编号，递增分配新编号
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycled.pop() {
+            id
+        } else {
+            self.current += 1;
+            self.current - 1
+        }
+    }
+
+    /// 回收一个编号
+    ///
+    /// ## Panics
+    /// - 编号超出 `current` 或已在回收池中存在
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id < self.current);
+        assert!(
+            !self.recycled.iter().any(|i| *i == id),
+            "id {} has been deallocated!",
+            id
+        );
+        self.recycled.push(id);
+    }
+}
+
+/// 基于栈的区间分配器
+///
+/// 分配策略：
+/// - 顺序分配未使用的编号
+/// - 回收的编号放入 recycled 栈中复用
+#[derive(Default)]
+pub struct StackAllocator {
+    /// 当前尚未分配的起始编号
+    current: usize,
+    /// 可分配编号的上界（不包含）
+    end: usize,
+    /// 已回收、可再次分配的编号
+    recycled: Vec<usize>,
+    /// `[current, end)` 中不参与顺序分配的区间
+    reserved: Option<(usize, usize)>,
+}
+
+impl StackAllocator {
+    /// 创建一个空的分配器，`init` 之前不能分配
+    pub const fn new() -> Self {
+        Self {
+            current: 0,
+            end: 0,
+            recycled: Vec::new(),
+            reserved: None,
+        }
+    }
+
+    /// 初始化分配区间，`[l, r)` 区间内的编号将被纳入管理
+    pub fn init(&mut self, l: usize, r: usize) {
+        self.current = l;
+        self.end = r;
+    }
+
+    /// 预留 `[l, r)`，只取与尚未分配部分的交集
+    pub fn reserve(&mut self, l: usize, r: usize) {
+        let l = l.max(self.current);
+        let r = r.min(self.end);
+        if l < r {
+            self.reserved = Some((l, r));
+        }
+    }
+
+    /// 撤销预留：还未越过预留区间时直接取消，否则把区间内的编号放入回收栈
+    pub fn release_reserved(&mut self) {
+        if let Some((l, r)) = self.reserved.take() {
+            if self.current >= r {
+                self.recycled.extend(l..r);
+            }
+        }
+    }
+
+    /// 顺序分配 `count` 个编号前跳过预留区间；
+    /// 跨越区间起点的连续分配从区间终点开始，区间前的零头不再分配
+    fn skip_reserved(&mut self, count: usize) {
+        if let Some((l, r)) = self.reserved {
+            if self.current < r && self.current + count > l {
+                self.current = r;
+            }
+        }
+    }
+
+    /// 分配一个编号
+    pub fn alloc(&mut self) -> Option<usize> {
+        if let Some(id) = self.recycled.pop() {
+            Some(id)
+        } else {
+            self.skip_reserved(1);
+            if self.current == self.end {
+                None
+            } else {
+                self.current += 1;
+                Some(self.current - 1)
+            }
+        }
+    }
+
+    /// 分配 `count` 个连续编号，从大到小返回
+    pub fn alloc_more(&mut self, count: usize) -> Option<Vec<usize>> {
+        self.skip_reserved(count);
+        if self.current + count >= self.end {
+            None
+        } else {
+            self.current += count;
+            Some((1..count + 1).map(|x| self.current - x).collect())
+        }
+    }
+
+    /// 回收一个编号
+    ///
+    /// ## Panics
+    /// - 编号未分配过或已被回收
+    pub fn dealloc(&mut self, id: usize) {
+        if id >= self.current || self.recycled.iter().any(|&v| v == id) {
+            panic!("id {:#x} has not been allocated!", id);
+        }
+        self.recycled.push(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecycleAllocator, StackAllocator};
+
+    fn with_range(l: usize, r: usize) -> StackAllocator {
+        let mut allocator = StackAllocator::new();
+        allocator.init(l, r);
+        allocator
+    }
+
+    #[test]
+    fn recycle_alloc_is_sequential() {
+        let mut allocator = RecycleAllocator::new();
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 2);
+    }
+
+    #[test]
+    fn recycled_ids_are_reused_last_in_first_out() {
+        let mut allocator = RecycleAllocator::new();
+        for _ in 0..4 {
+            allocator.alloc();
+        }
+        allocator.dealloc(1);
+        allocator.dealloc(3);
+        assert_eq!(allocator.alloc(), 3);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "has been deallocated")]
+    fn recycle_double_free_panics() {
+        let mut allocator = RecycleAllocator::new();
+        let id = allocator.alloc();
+        allocator.dealloc(id);
+        allocator.dealloc(id);
+    }
+
+    #[test]
+    #[should_panic]
+    fn recycle_free_unallocated_panics() {
+        RecycleAllocator::new().dealloc(0);
+    }
+
+    #[test]
+    fn stack_alloc_until_exhausted() {
+        let mut allocator = with_range(10, 13);
+        assert_eq!(allocator.alloc(), Some(10));
+        assert_eq!(allocator.alloc(), Some(11));
+        assert_eq!(allocator.alloc(), Some(12));
+        assert_eq!(allocator.alloc(), None);
+        allocator.dealloc(11);
+        assert_eq!(allocator.alloc(), Some(11));
+        assert_eq!(allocator.alloc(), None);
+    }
+
+    #[test]
+    fn stack_uninitialized_is_empty() {
+        assert_eq!(StackAllocator::new().alloc(), None);
+    }
+
+    #[test]
+    fn stack_dealloc_then_reuse() {
+        let mut allocator = with_range(10, 20);
+        let a = allocator.alloc().unwrap();
+        let b = allocator.alloc().unwrap();
+        allocator.dealloc(a);
+        allocator.dealloc(b);
+        assert_eq!(allocator.alloc(), Some(b));
+        assert_eq!(allocator.alloc(), Some(a));
+        assert_eq!(allocator.alloc(), Some(12));
+    }
+
+    #[test]
+    fn stack_alloc_more_is_contiguous() {
+        let mut allocator = with_range(10, 20);
+        assert_eq!(allocator.alloc_more(3), Some(vec![12, 11, 10]));
+        assert_eq!(allocator.alloc(), Some(13));
+        assert_eq!(allocator.alloc_more(10), None);
+    }
+
+    #[test]
+    fn stack_alloc_more_ignores_recycled() {
+        let mut allocator = with_range(0, 10);
+        let id = allocator.alloc().unwrap();
+        allocator.dealloc(id);
+        assert_eq!(allocator.alloc_more(2), Some(vec![2, 1]));
+        assert_eq!(allocator.alloc(), Some(0));
+    }
+
+    #[test]
+    fn stack_reserved_range_is_skipped() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(12, 15);
+        assert_eq!(allocator.alloc(), Some(10));
+        assert_eq!(allocator.alloc(), Some(11));
+        assert_eq!(allocator.alloc(), Some(15));
+    }
+
+    #[test]
+    fn stack_alloc_more_jumps_over_reserved() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(12, 15);
+        assert_eq!(allocator.alloc_more(3), Some(vec![17, 16, 15]));
+        // 区间前的零头不再参与顺序分配
+        assert_eq!(allocator.alloc(), Some(18));
+    }
+
+    #[test]
+    fn stack_reserve_is_clamped() {
+        let mut allocator = with_range(10, 20);
+        allocator.alloc();
+        allocator.reserve(0, 12);
+        assert_eq!(allocator.alloc(), Some(12));
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(30, 40);
+        assert_eq!(allocator.alloc(), Some(10));
+    }
+
+    #[test]
+    fn stack_release_reserved_returns_frames() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(12, 15);
+        for _ in 0..3 {
+            allocator.alloc();
+        }
+        allocator.release_reserved();
+        let mut ids: Vec<usize> = (0..3).map(|_| allocator.alloc().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, [12, 13, 14]);
+        assert_eq!(allocator.alloc(), Some(16));
+    }
+
+    #[test]
+    fn stack_release_reserved_before_reaching_it() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(12, 15);
+        allocator.alloc();
+        allocator.release_reserved();
+        assert_eq!(allocator.alloc(), Some(11));
+        assert_eq!(allocator.alloc(), Some(12));
+    }
+
+    #[test]
+    #[should_panic(expected = "has not been allocated")]
+    fn stack_double_free_panics() {
+        let mut allocator = with_range(0, 4);
+        let id = allocator.alloc().unwrap();
+        allocator.dealloc(id);
+        allocator.dealloc(id);
+    }
+}
@@ -0,0 +1,189 @@
+//! # 环形缓冲区
+//!
+//! ## Overview
+//! 固定容量的字节环形缓冲区，管道用它在读端与写端之间传递数据。
+//!
+//! ## Assumptions
+//! - 调用者在读之前检查 `available_read`，在写之前检查 `available_write`
+//!
+//! ## Invariants
+//! - `head` 指向下一个要读的字节，`tail` 指向下一个要写的位置
+//! - `head == tail` 时由 `status` 区分满与空
+//!
+//! ## Behavior
+//! - 对空缓冲区读或对满缓冲区写会 panic
+
+#![cfg_attr(not(test), no_std)]
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RingBufferStatus {
+    Full,
+    Empty,
+    Normal,
+}
+
+/// 容量为 `N` 字节的环形缓冲区
+pub struct RingBuffer<const N: usize> {
+    arr: [u8; N],
+    head: usize,
+    tail: usize,
+    status: RingBufferStatus,
+}
+
+impl<const N: usize> RingBuffer<N> {
+    pub const fn new() -> Self {
+        Self {
+            arr: [0; N],
+            head: 0,
+            tail: 0,
+            status: RingBufferStatus::Empty,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    pub fn write_byte(&mut self, byte: u8) {
+        assert!(self.status != RingBufferStatus::Full, "ring buffer is full");
+        self.status = RingBufferStatus::Normal;
+        self.arr[self.tail] = byte;
+        self.tail = (self.tail + 1) % N;
+        if self.tail == self.head {
+            self.status = RingBufferStatus::Full;
+        }
+    }
+
+    pub fn read_byte(&mut self) -> u8 {
+        assert!(
+            self.status != RingBufferStatus::Empty,
+            "ring buffer is empty"
+        );
+        self.status = RingBufferStatus::Normal;
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % N;
+        if self.head == self.tail {
+            self.status = RingBufferStatus::Empty;
+        }
+        c
+    }
+
+    /// 可以读出的字节数
+    pub fn available_read(&self) -> usize {
+        if self.status == RingBufferStatus::Empty {
+            0
+        } else if self.tail > self.head {
+            self.tail - self.head
+        } else {
+            self.tail + N - self.head
+        }
+    }
+
+    /// 还能写入的字节数
+    pub fn available_write(&self) -> usize {
+        if self.status == RingBufferStatus::Full {
+            0
+        } else {
+            N - self.available_read()
+        }
+    }
+}
+
+impl<const N: usize> Default for RingBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RingBuffer;
+
+    const SIZE: usize = 32;
+
+    #[test]
+    fn new_buffer_is_empty() {
+        let buffer = RingBuffer::<SIZE>::new();
+        assert_eq!(buffer.capacity(), SIZE);
+        assert_eq!(buffer.available_read(), 0);
+        assert_eq!(buffer.available_write(), SIZE);
+    }
+
+    #[test]
+    fn bytes_come_out_in_order() {
+        let mut buffer = RingBuffer::<SIZE>::new();
+        for byte in b"pipe" {
+            buffer.write_byte(*byte);
+        }
+        assert_eq!(buffer.available_read(), 4);
+        assert_eq!(buffer.available_write(), SIZE - 4);
+        let out: Vec<u8> = (0..4).map(|_| buffer.read_byte()).collect();
+        assert_eq!(out, b"pipe");
+        assert_eq!(buffer.available_read(), 0);
+    }
+
+    #[test]
+    fn fill_and_drain() {
+        let mut buffer = RingBuffer::<SIZE>::new();
+        for i in 0..SIZE {
+            buffer.write_byte(i as u8);
+        }
+        assert_eq!(buffer.available_read(), SIZE);
+        assert_eq!(buffer.available_write(), 0);
+        for i in 0..SIZE {
+            assert_eq!(buffer.read_byte(), i as u8);
+        }
+        assert_eq!(buffer.available_read(), 0);
+        assert_eq!(buffer.available_write(), SIZE);
+    }
+
+    #[test]
+    fn wraps_around() {
+        let mut buffer = RingBuffer::<SIZE>::new();
+        for round in 0..3 * SIZE {
+            buffer.write_byte(round as u8);
+            buffer.write_byte(!(round as u8));
+            assert_eq!(buffer.available_read(), 2);
+            assert_eq!(buffer.read_byte(), round as u8);
+            assert_eq!(buffer.read_byte(), !(round as u8));
+        }
+        // 头尾不在起点时写满，仍能区分满与空
+        for i in 0..SIZE {
+            buffer.write_byte(i as u8);
+        }
+        assert_eq!(buffer.available_read(), SIZE);
+        assert_eq!(buffer.available_write(), 0);
+        assert_eq!(buffer.read_byte(), 0);
+        assert_eq!(buffer.available_write(), 1);
+    }
+
+    #[test]
+    fn partial_reads_across_the_end() {
+        let mut buffer = RingBuffer::<4>::new();
+        for byte in [1, 2, 3] {
+            buffer.write_byte(byte);
+        }
+        assert_eq!(buffer.read_byte(), 1);
+        assert_eq!(buffer.read_byte(), 2);
+        for byte in [4, 5, 6] {
+            buffer.write_byte(byte);
+        }
+        assert_eq!(buffer.available_read(), 4);
+        let out: Vec<u8> = (0..4).map(|_| buffer.read_byte()).collect();
+        assert_eq!(out, [3, 4, 5, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty")]
+    fn read_from_empty_panics() {
+        RingBuffer::<SIZE>::new().read_byte();
+    }
+
+    #[test]
+    #[should_panic(expected = "full")]
+    fn write_to_full_panics() {
+        let mut buffer = RingBuffer::<1>::new();
+        buffer.write_byte(0);
+        buffer.write_byte(1);
+    }
+}
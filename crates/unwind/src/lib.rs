@@ -0,0 +1,295 @@
+//! # 栈回溯与符号查找
+//!
+//! ## Overview
+//! - `SymbolTable`：解析 `nm -n` 格式的文本符号表，把地址翻译为 `符号+偏移`
+//! - `FrameWalker`：沿帧指针链回溯调用栈，依次给出每一帧保存的返回地址
+//!
+//! ## Assumptions
+//! - 被回溯的代码以 `-Cforce-frame-pointers=yes` 编译；
+//!   RISC-V 与 LoongArch 的帧布局相同：帧指针指向进入函数时的栈顶，
+//!   `fp - 8` 处是返回地址，`fp - 16` 处是调用者的帧指针
+//! - 栈向低地址增长，调用者的帧指针严格大于被调用者的
+//!
+//! ## Behavior
+//! - 两者都不分配内存，可以在 panic 路径上使用
+//! - 读取内存由调用者提供的闭包完成，读不到时回溯结束；
+//!   帧指针不对齐、越出栈区间或不再增长时同样结束，不会读取栈区间以外的地址
+
+#![cfg_attr(not(test), no_std)]
+
+use core::ops::Range;
+
+/// 函数符号
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol<'a> {
+    /// 符号名，已去掉 Rust 符号末尾的哈希
+    pub name: &'a str,
+    /// 符号起始地址
+    pub addr: usize,
+}
+
+/// `nm -n --defined-only -C` 输出的符号表，每行为 `地址 类型 名字`
+///
+/// ## Behavior
+/// - 只使用类型为 `t`/`T` 的代码符号，格式不对的行被忽略
+/// - 内容不是 UTF-8 时视为空表
+#[derive(Clone, Copy)]
+pub struct SymbolTable<'a> {
+    text: &'a str,
+}
+
+impl<'a> SymbolTable<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            text: core::str::from_utf8(bytes).unwrap_or(""),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols().next().is_none()
+    }
+
+    /// 表中的全部代码符号
+    pub fn symbols(&self) -> impl Iterator<Item = Symbol<'a>> + 'a {
+        self.text.lines().filter_map(parse_line)
+    }
+
+    /// 包含 `addr` 的符号，即起始地址不超过 `addr` 的最后一个符号
+    pub fn lookup(&self, addr: usize) -> Option<Symbol<'a>> {
+        self.symbols()
+            .filter(|sym| sym.addr <= addr)
+            .max_by_key(|sym| sym.addr)
+    }
+}
+
+fn parse_line(line: &str) -> Option<Symbol<'_>> {
+    let (addr, rest) = line.trim().split_once(' ')?;
+    let (kind, name) = rest.split_once(' ')?;
+    if kind != "t" && kind != "T" {
+        return None;
+    }
+    Some(Symbol {
+        name: strip_hash(name.trim()),
+        addr: usize::from_str_radix(addr, 16).ok()?,
+    })
+}
+
+/// 去掉 Rust 旧式修饰名还原后末尾的 `::h` 加 16 位十六进制哈希
+fn strip_hash(name: &str) -> &str {
+    match name.rsplit_once("::h") {
+        Some((path, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            path
+        }
+        _ => name,
+    }
+}
+
+/// 回溯得到的一帧
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// 本帧的帧指针
+    pub fp: usize,
+    /// 本帧保存的返回地址，即调用者中 `call` 的下一条指令
+    pub ra: usize,
+}
+
+/// 沿帧指针链回溯的迭代器
+///
+/// ## Fields
+/// - `stack`：帧指针允许的范围，`fp - 16` 与 `fp` 都须落在其中
+/// - `read`：读取一个机器字，地址不可读时返回 `None`
+pub struct FrameWalker<F> {
+    fp: usize,
+    stack: Range<usize>,
+    depth: usize,
+    read: F,
+}
+
+impl<F: FnMut(usize) -> Option<usize>> FrameWalker<F> {
+    /// 从帧指针 `fp` 开始，最多回溯 `max_depth` 帧
+    pub fn new(fp: usize, stack: Range<usize>, max_depth: usize, read: F) -> Self {
+        Self {
+            fp,
+            stack,
+            depth: max_depth,
+            read,
+        }
+    }
+
+    fn frame_is_valid(&self, fp: usize) -> bool {
+        fp % core::mem::size_of::<usize>() == 0
+            && fp >= self.stack.start.saturating_add(16)
+            && fp <= self.stack.end
+    }
+}
+
+impl<F: FnMut(usize) -> Option<usize>> Iterator for FrameWalker<F> {
+    type Item = Frame;
+
+    fn next(&mut self) -> Option<Frame> {
+        if self.depth == 0 || !self.frame_is_valid(self.fp) {
+            return None;
+        }
+        self.depth -= 1;
+        let fp = self.fp;
+        let ra = (self.read)(fp - 8)?;
+        let prev_fp = (self.read)(fp - 16)?;
+        if ra == 0 {
+            return None;
+        }
+        // 调用者的帧必须在更高的地址，否则下一次迭代结束回溯
+        self.fp = if prev_fp > fp { prev_fp } else { 0 };
+        Some(Frame { fp, ra })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Frame, FrameWalker, Symbol, SymbolTable};
+    use std::collections::HashMap;
+
+    const NM: &str = "\
+0000000080200000 T _start
+0000000080201000 T strampoline
+0000000080201000 t __alltraps
+0000000080202000 T os::rust_main::h0123456789abcdef
+0000000080202400 r anon.rodata
+0000000080202800 t <os::fs::Pipe as os::fs::File>::read::hfedcba9876543210
+0000000080203000 T core::panicking::panic::hnot_a_hash
+malformed line
+";
+
+    #[test]
+    fn lookup_finds_enclosing_symbol() {
+        let table = SymbolTable::new(NM.as_bytes());
+        assert_eq!(
+            table.lookup(0x8020_2010),
+            Some(Symbol {
+                name: "os::rust_main",
+                addr: 0x8020_2000
+            })
+        );
+        assert_eq!(table.lookup(0x8020_0000).unwrap().name, "_start");
+        assert_eq!(table.lookup(0x8020_0fff).unwrap().name, "_start");
+        assert_eq!(table.lookup(0x801f_ffff), None);
+    }
+
+    #[test]
+    fn data_symbols_are_ignored() {
+        let table = SymbolTable::new(NM.as_bytes());
+        assert_eq!(table.lookup(0x8020_2500).unwrap().name, "os::rust_main");
+        assert_eq!(table.symbols().count(), 6);
+    }
+
+    #[test]
+    fn names_with_spaces_and_hashes() {
+        let table = SymbolTable::new(NM.as_bytes());
+        assert_eq!(
+            table.lookup(0x8020_2810).unwrap().name,
+            "<os::fs::Pipe as os::fs::File>::read"
+        );
+        // 不是 16 位十六进制的后缀保持原样
+        assert_eq!(
+            table.lookup(0x8020_3000).unwrap().name,
+            "core::panicking::panic::hnot_a_hash"
+        );
+    }
+
+    #[test]
+    fn empty_and_invalid_tables() {
+        assert!(SymbolTable::new(b"").is_empty());
+        assert!(SymbolTable::new(&[0xff, 0xfe]).is_empty());
+        assert!(SymbolTable::new(b"0000000080202400 r anon.rodata\n").is_empty());
+        assert!(!SymbolTable::new(NM.as_bytes()).is_empty());
+        assert_eq!(SymbolTable::new(b"").lookup(0x8020_0000), None);
+    }
+
+    /// 模拟的栈：地址到机器字的映射
+    struct Stack(HashMap<usize, usize>);
+
+    impl Stack {
+        /// 按 `(fp, ra, prev_fp)` 依次压入栈帧
+        fn with_frames(frames: &[(usize, usize, usize)]) -> Self {
+            let mut words = HashMap::new();
+            for &(fp, ra, prev_fp) in frames {
+                words.insert(fp - 8, ra);
+                words.insert(fp - 16, prev_fp);
+            }
+            Self(words)
+        }
+
+        fn read(&self, addr: usize) -> Option<usize> {
+            self.0.get(&addr).copied()
+        }
+    }
+
+    fn walk(stack: &Stack, fp: usize, range: std::ops::Range<usize>, depth: usize) -> Vec<Frame> {
+        FrameWalker::new(fp, range, depth, |addr| stack.read(addr)).collect()
+    }
+
+    #[test]
+    fn walks_the_frame_chain() {
+        let stack = Stack::with_frames(&[
+            (0x1100, 0x8020_0010, 0x1200),
+            (0x1200, 0x8020_0020, 0x1300),
+            (0x1300, 0x8020_0030, 0),
+        ]);
+        let frames = walk(&stack, 0x1100, 0x1000..0x2000, 16);
+        assert_eq!(
+            frames,
+            [
+                Frame {
+                    fp: 0x1100,
+                    ra: 0x8020_0010
+                },
+                Frame {
+                    fp: 0x1200,
+                    ra: 0x8020_0020
+                },
+                Frame {
+                    fp: 0x1300,
+                    ra: 0x8020_0030
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_depth_limit() {
+        let stack = Stack::with_frames(&[
+            (0x1100, 1, 0x1200),
+            (0x1200, 2, 0x1300),
+            (0x1300, 3, 0x1400),
+        ]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 2).len(), 2);
+        assert!(walk(&stack, 0x1100, 0x1000..0x2000, 0).is_empty());
+    }
+
+    #[test]
+    fn stops_when_leaving_the_stack() {
+        // 第二帧的帧指针越过栈顶，不再读取
+        let stack = Stack::with_frames(&[(0x1100, 1, 0x3000), (0x3000, 2, 0x3100)]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 16).len(), 1);
+        // 帧指针正好在栈顶时仍可读取它下面的两个字
+        let stack = Stack::with_frames(&[(0x1100, 1, 0x2000), (0x2000, 2, 0x4000)]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 16).len(), 2);
+        // 起始帧指针低于栈底
+        assert!(walk(&stack, 0x1008, 0x1000..0x2000, 16).is_empty());
+    }
+
+    #[test]
+    fn stops_on_bad_chain() {
+        // 帧指针不增长
+        let stack = Stack::with_frames(&[(0x1200, 1, 0x1100), (0x1100, 2, 0x1200)]);
+        assert_eq!(walk(&stack, 0x1200, 0x1000..0x2000, 16).len(), 1);
+        // 帧指针不对齐
+        let stack = Stack::with_frames(&[(0x1100, 1, 0x1203), (0x1203, 2, 0)]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 16).len(), 1);
+        // 返回地址为零
+        let stack = Stack::with_frames(&[(0x1100, 1, 0x1200), (0x1200, 0, 0x1300)]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 16).len(), 1);
+        // 内存不可读
+        let stack = Stack::with_frames(&[(0x1100, 1, 0x1200)]);
+        assert_eq!(walk(&stack, 0x1100, 0x1000..0x2000, 16).len(), 1);
+    }
+}
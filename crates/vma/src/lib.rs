@@ -0,0 +1,276 @@
+//! # 虚拟内存区域的区间运算
+//!
+//! ## Overview
+//! `MemorySet` / `MapArea` 中与页表无关的区间逻辑：
+//! - `PageRange`：左闭右开的虚拟页号区间
+//! - 区间相交、在中间拆成三段、从两端收缩
+//! - `find_free_area`：从给定地址起寻找一段不与已有区域冲突的空闲地址
+//!
+//! ## Invariants
+//! - `PageRange` 总满足 `start <= end`
+//!
+//! ## Behavior
+//! - 相交判断按闭区间比较两端，首尾相接的区域也视为冲突，
+//!   因此相邻的映射之间总会留出至少一页空隙
+
+#![cfg_attr(not(test), no_std)]
+
+/// 左闭右开的页号区间 `[start, end)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl PageRange {
+    pub fn new(start: usize, end: usize) -> Self {
+        assert!(
+            start <= end,
+            "invalid page range [{:#x}, {:#x})",
+            start,
+            end
+        );
+        Self { start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, page: usize) -> bool {
+        self.start <= page && page < self.end
+    }
+
+    /// 与 `other` 的交集，两端按闭区间比较，相接时返回空区间
+    pub fn check_overlapping(&self, other: PageRange) -> Option<PageRange> {
+        if other.end < self.start || other.start > self.end {
+            None
+        } else {
+            Some(PageRange::new(
+                self.start.max(other.start),
+                self.end.min(other.end),
+            ))
+        }
+    }
+
+    /// 以 `[start, end)` 把区间拆成左、中、右三段，
+    /// `[start, end)` 必须严格位于区间内部
+    pub fn split_three(
+        &self,
+        start: usize,
+        end: usize,
+    ) -> Option<(PageRange, PageRange, PageRange)> {
+        if !(self.start < start && start < end && end < self.end) {
+            return None;
+        }
+        Some((
+            PageRange::new(self.start, start),
+            PageRange::new(start, end),
+            PageRange::new(end, self.end),
+        ))
+    }
+
+    /// 把终点收缩到 `new_end`，返回被去掉的 `[new_end, end)`；
+    /// `new_end` 必须严格位于区间内部
+    pub fn shrink_to(&mut self, new_end: usize) -> Option<PageRange> {
+        if !(self.start < new_end && new_end < self.end) {
+            return None;
+        }
+        let removed = PageRange::new(new_end, self.end);
+        self.end = new_end;
+        Some(removed)
+    }
+
+    /// 把起点收缩到 `new_start`，返回被去掉的 `[start, new_start)`；
+    /// `new_start` 必须严格位于区间内部
+    pub fn rshrink_to(&mut self, new_start: usize) -> Option<PageRange> {
+        if !(self.start < new_start && new_start < self.end) {
+            return None;
+        }
+        let removed = PageRange::new(self.start, new_start);
+        self.start = new_start;
+        Some(removed)
+    }
+}
+
+/// 从地址 `addr` 起寻找一段长 `len` 字节、不与 `areas` 冲突的空闲区域，返回其起始地址
+///
+/// ## Behavior
+/// - `len` 向上对齐到页
+/// - 候选区域落在某个已有区域内时，跳到该区域的终点继续；否则前进一页
+pub fn find_free_area<I>(areas: I, mut addr: usize, len: usize, page_size: usize) -> usize
+where
+    I: IntoIterator<Item = PageRange> + Clone,
+{
+    let len = (len + page_size - 1) & !(page_size - 1);
+    loop {
+        let candidate = PageRange::new(addr / page_size, (addr + len).div_ceil(page_size));
+        let conflict = areas
+            .clone()
+            .into_iter()
+            .any(|area| area.check_overlapping(candidate).is_some());
+        if !conflict {
+            return addr;
+        }
+        addr = areas
+            .clone()
+            .into_iter()
+            .find(|area| area.start * page_size <= addr && addr < area.end * page_size)
+            .map_or(addr + page_size, |area| area.end * page_size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_free_area, PageRange};
+
+    const PAGE_SIZE: usize = 0x1000;
+
+    fn range(start: usize, end: usize) -> PageRange {
+        PageRange::new(start, end)
+    }
+
+    #[test]
+    fn basic_queries() {
+        let r = range(0x10, 0x14);
+        assert_eq!(r.len(), 4);
+        assert!(!r.is_empty());
+        assert!(range(3, 3).is_empty());
+        assert!(r.contains(0x10));
+        assert!(r.contains(0x13));
+        assert!(!r.contains(0x14));
+        assert!(!r.contains(0x0f));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid page range")]
+    fn reversed_range_panics() {
+        range(2, 1);
+    }
+
+    #[test]
+    fn overlapping() {
+        let area = range(0x10, 0x20);
+        assert_eq!(
+            area.check_overlapping(range(0x08, 0x14)),
+            Some(range(0x10, 0x14))
+        );
+        assert_eq!(
+            area.check_overlapping(range(0x18, 0x28)),
+            Some(range(0x18, 0x20))
+        );
+        assert_eq!(
+            area.check_overlapping(range(0x12, 0x14)),
+            Some(range(0x12, 0x14))
+        );
+        assert_eq!(
+            area.check_overlapping(range(0x00, 0x30)),
+            Some(range(0x10, 0x20))
+        );
+        assert_eq!(area.check_overlapping(range(0x00, 0x08)), None);
+        assert_eq!(area.check_overlapping(range(0x21, 0x30)), None);
+    }
+
+    #[test]
+    fn adjacent_ranges_conflict() {
+        let area = range(0x10, 0x20);
+        assert_eq!(
+            area.check_overlapping(range(0x20, 0x30)),
+            Some(range(0x20, 0x20))
+        );
+        assert_eq!(
+            area.check_overlapping(range(0x08, 0x10)),
+            Some(range(0x10, 0x10))
+        );
+    }
+
+    #[test]
+    fn split_three_in_the_middle() {
+        let area = range(0x10, 0x20);
+        assert_eq!(
+            area.split_three(0x14, 0x18),
+            Some((range(0x10, 0x14), range(0x14, 0x18), range(0x18, 0x20)))
+        );
+        assert_eq!(
+            area.split_three(0x11, 0x12),
+            Some((range(0x10, 0x11), range(0x11, 0x12), range(0x12, 0x20)))
+        );
+    }
+
+    #[test]
+    fn split_three_rejects_boundaries() {
+        let area = range(0x10, 0x20);
+        assert_eq!(area.split_three(0x10, 0x18), None);
+        assert_eq!(area.split_three(0x14, 0x20), None);
+        assert_eq!(area.split_three(0x18, 0x14), None);
+        assert_eq!(area.split_three(0x14, 0x14), None);
+        assert_eq!(area.split_three(0x08, 0x28), None);
+    }
+
+    #[test]
+    fn shrink_from_either_end() {
+        let mut area = range(0x10, 0x20);
+        assert_eq!(area.shrink_to(0x18), Some(range(0x18, 0x20)));
+        assert_eq!(area, range(0x10, 0x18));
+        assert_eq!(area.rshrink_to(0x12), Some(range(0x10, 0x12)));
+        assert_eq!(area, range(0x12, 0x18));
+    }
+
+    #[test]
+    fn shrink_rejects_boundaries() {
+        let mut area = range(0x10, 0x20);
+        assert_eq!(area.shrink_to(0x10), None);
+        assert_eq!(area.shrink_to(0x20), None);
+        assert_eq!(area.shrink_to(0x28), None);
+        assert_eq!(area.rshrink_to(0x10), None);
+        assert_eq!(area.rshrink_to(0x20), None);
+        assert_eq!(area.rshrink_to(0x08), None);
+        assert_eq!(area, range(0x10, 0x20));
+    }
+
+    #[test]
+    fn free_area_without_conflict() {
+        let areas = [range(0x10, 0x20)];
+        assert_eq!(find_free_area(areas, 0x40000, 0x2000, PAGE_SIZE), 0x40000);
+        assert_eq!(find_free_area([], 0x1234, 1, PAGE_SIZE), 0x1234);
+    }
+
+    #[test]
+    fn free_area_skips_existing_areas() {
+        let areas = [range(0x10, 0x20), range(0x22, 0x30)];
+        // 落在区域内时跳到区域终点，区域终点与下一区域之间只差一页空隙时继续前进
+        assert_eq!(
+            find_free_area(areas, 0x10000, PAGE_SIZE, PAGE_SIZE),
+            0x31000
+        );
+        // 区域之前的空间不够时同样向后寻找
+        assert_eq!(find_free_area(areas, 0x0c000, 0x4000, PAGE_SIZE), 0x31000);
+    }
+
+    #[test]
+    fn free_area_leaves_a_gap() {
+        let areas = [range(0x10, 0x20)];
+        assert_eq!(
+            find_free_area(areas, 0x20000, PAGE_SIZE, PAGE_SIZE),
+            0x21000
+        );
+        assert_eq!(
+            find_free_area(areas, 0x0e000, PAGE_SIZE, PAGE_SIZE),
+            0x0e000
+        );
+    }
+
+    #[test]
+    fn free_area_rounds_len_up() {
+        let areas = [range(0x12, 0x20)];
+        assert_eq!(find_free_area(areas, 0x10000, 1, PAGE_SIZE), 0x10000);
+        assert_eq!(
+            find_free_area(areas, 0x10000, PAGE_SIZE + 1, PAGE_SIZE),
+            0x21000
+        );
+    }
+}
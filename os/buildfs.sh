@@ -19,8 +19,9 @@ dd if=/dev/zero of=${U_FS_DIR}/${U_FS} bs=${BLK_SZ} count=${COUNT}
 # -F 32 指定 FAT32, -s 8 表示每个簇 8 个扇区 (4KB per cluster)
 mkfs.vfat -F 32 ${U_FS_DIR}/${U_FS}
 
-# 3. 创建 bin 目录
+# 3. 创建 bin 目录与测例目录，测例由 runtests 在 /tests 中发现并运行
 mmd -i ${U_FS_DIR}/${U_FS} ::/bin
+mmd -i ${U_FS_DIR}/${U_FS} ::/tests
 
 # 4. 循环拷贝 ELF 文件
 for program_rs in $(ls ${TEST_DIR}); do
@@ -37,7 +38,7 @@ for program_rs in $(ls ${TEST_DIR}); do
 done
 
 for program in $(ls ../test/testsuits-for-oskernel/riscv-syscalls-testing/user/riscv64); do
-    mcopy -i ${U_FS_DIR}/${U_FS} ../test/testsuits-for-oskernel/riscv-syscalls-testing/user/riscv64/${program} ::/
+    mcopy -i ${U_FS_DIR}/${U_FS} ../test/testsuits-for-oskernel/riscv-syscalls-testing/user/riscv64/${program} ::/tests/
 done
 
 echo "DONE"
\ No newline at end of file
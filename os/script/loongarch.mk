@@ -5,12 +5,24 @@ KERNEL_BIN := $(KERNEL_ELF).bin
 KERNEL_QEMU := ../bin/kernel-laqemu
 
 BOARD := laqemu
+
+# 挂在 PCIe 上的磁盘与网卡，启动后分别为 vda 与唯一的网卡
+FS_IMG ?= ../fs-img/fs.img
+NETDEV ?= user,id=net0
 SBI ?=
+
+# 内核 GDB 桩的管道，例如 GDB=/tmp/gdb，需先 mkfifo /tmp/gdb.in /tmp/gdb.out，
+# 并在启动盘的 cmdline 文件中写上 gdb。桩使用第二个串口，GDB 经 socat 接入：
+#   socat TCP-LISTEN:1234,reuseaddr 'OPEN:/tmp/gdb.out!!OPEN:/tmp/gdb.in'
+#   gdb target/loongarch64-unknown-none/release/os -ex 'target remote :1234'
+GDB ?=
+
 BOOTLOADER := ../bootloader/u-boot-with-spl.bin
 
 OBJCOPY := loongarch64-linux-gnu-objcopy
 OBJDUMP := loongarch64-linux-gnu-objdump
 READELF := loongarch64-linux-gnu-readelf
+NM := loongarch64-linux-gnu-nm
 
 build: kernel mv
 
@@ -22,10 +34,20 @@ $(KERNEL_BIN): kernel
 	@$(OBJCOPY) ${KERNEL_ELF} --strip-all -O binary $@
 
 
+# 内核编译两遍：第一遍之后导出代码符号，第二遍以 ksyms 特性把符号表编译进内核，
+# panic 时据此符号化栈回溯。符号表在 .text 之后的 .rodata 中，两遍的代码地址相同
+KERNEL_SYMS := $(KERNEL_ELF).syms
+TEXT_SYMBOLS = $(NM) -n --defined-only -C $(KERNEL_ELF) | awk '$$2 ~ /^[tT]$$/'
+
 kernel: pre
 	@echo Platform: $(BOARD), SBI: $(SBI)
 	@cp src/hal/arch/loongarch/linker-$(BOARD).ld src/hal/arch/loongarch/linker.ld
 	@LOG=${LOG} cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD)"
+	@$(TEXT_SYMBOLS) > $(KERNEL_SYMS)
+	@LOG=${LOG} KERNEL_SYMBOLS=$(CURDIR)/$(KERNEL_SYMS) \
+		cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD) ksyms"
+	@$(TEXT_SYMBOLS) | cmp -s - $(KERNEL_SYMS) || \
+		echo "warning: kernel code moved after embedding symbols, backtraces may be wrong"
 
 pre:
 	@rm .cargo/config.toml || true
@@ -36,6 +58,7 @@ clean:
 	@rm -f src/hal/arch/loongarch/linker.ld
 
 
+# GED 关机不带状态，QEMU 总是以 0 退出；退出状态见输出中的 "run shutdown, status N"
 run:
 	qemu-system-loongarch64 \
 	-kernel $(KERNEL_QEMU) \
@@ -44,9 +67,25 @@ run:
 	-smp 1 \
 	-no-reboot \
 	-rtc base=utc \
-	-snapshot
+	-snapshot \
+	-drive file=$(FS_IMG),if=none,format=raw,id=x0 \
+	-device virtio-blk-pci,drive=x0 \
+	-netdev $(NETDEV) \
+	-device virtio-net-pci,netdev=net0 \
+	-device virtio-rng-pci \
+	$(if $(GDB),-serial mon:stdio -serial pipe:$(GDB))
 
 
+# 内核单元测试；GED 关机不带状态，QEMU 总是以 0 退出，结果以输出中的 test result 为准
+test: pre
+	@cp src/hal/arch/loongarch/linker-$(BOARD).ld src/hal/arch/loongarch/linker.ld
+	@cargo rustc --${MODE} --target $(TARGET) --features "board_$(BOARD) ktest" -- --test
+	qemu-system-loongarch64 \
+	-kernel $(KERNEL_ELF) \
+	-m 1G \
+	-nographic \
+	-smp 1 \
+	-no-reboot
 
 #run:
 #	qemu-system-loongarch64 \
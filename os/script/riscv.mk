@@ -7,12 +7,37 @@ FS_IMG := ../fs-img/fs.img
 
 BOARD := rvqemu
 
+# 网卡后端，默认使用 QEMU 用户态网络；两个 QEMU 互连时可改为
+# NETDEV=socket,id=net0,listen=:1234 与 NETDEV=socket,id=net0,connect=:1234
+NETDEV ?= user,id=net0
+
+# 内核命令行，经设备树 /chosen/bootargs 传入，例如
+# BOOTARGS="loglevel=info -- shell" 跳过测例直接进入 shell
+BOOTARGS ?=
+
+# 由 QEMU 加载到内存的启动镜像（cpio 归档或磁盘镜像），例如 INITRD=../fs-img/initramfs.cpio
+INITRD ?=
+
+# 挂在 PCIe 上的第二块磁盘，例如 PCI_DISK=../fs-img/data.img，启动后为 vdb
+PCI_DISK ?=
+
+# $(if) 的参数以逗号分隔，PCI_DISK 等 QEMU 选项中的逗号经此变量写入
+comma := ,
+
+# 内核 GDB 桩的管道，例如 GDB=/tmp/gdb BOOTARGS=gdb，需先 mkfifo /tmp/gdb.in /tmp/gdb.out。
+# virt 只有一个 NS16550A，桩经 virtio-console 连接；GDB 经 socat 接入：
+#   socat TCP-LISTEN:1234,reuseaddr 'OPEN:/tmp/gdb.out!!OPEN:/tmp/gdb.in'
+#   gdb target/riscv64gc-unknown-none-elf/release/os -ex 'target remote :1234'
+# 之后在控制台上按 Ctrl-G 或在内核 panic 时停下
+GDB ?=
+
 # KERNEL ENTRY
 KERNEL_ENTRY_PA := 0x80200000
 
 # Binutils
 OBJDUMP := rust-objdump --arch-name=riscv64
 OBJCOPY := rust-objcopy --binary-architecture=riscv64
+NM := rust-nm
 
 
 build: $(KERNEL_BIN) mv
@@ -23,10 +48,20 @@ mv:
 $(KERNEL_BIN): kernel
 	@$(OBJCOPY) ${KERNEL_ELF} --strip-all -O binary $@
 
+# 内核编译两遍：第一遍之后导出代码符号，第二遍以 ksyms 特性把符号表编译进内核，
+# panic 时据此符号化栈回溯。符号表在 .text 之后的 .rodata 中，两遍的代码地址相同
+KERNEL_SYMS := $(KERNEL_ELF).syms
+TEXT_SYMBOLS = $(NM) -n --defined-only -C $(KERNEL_ELF) | awk '$$2 ~ /^[tT]$$/'
+
 kernel: pre fs-img
 	@echo Platform: $(BOARD), SBI: $(SBI)
 	@cp src/hal/arch/riscv/linker-$(BOARD).ld src/hal/arch/riscv/linker.ld
 	@LOG=${LOG} cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD)"
+	@$(TEXT_SYMBOLS) > $(KERNEL_SYMS)
+	@LOG=${LOG} KERNEL_SYMBOLS=$(CURDIR)/$(KERNEL_SYMS) \
+		cargo build --${MODE} --target $(TARGET) --features "board_$(BOARD) ksyms"
+	@$(TEXT_SYMBOLS) | cmp -s - $(KERNEL_SYMS) || \
+		echo "warning: kernel code moved after embedding symbols, backtraces may be wrong"
 
 pre:
 	@rm .cargo/config.toml || true
@@ -38,16 +73,42 @@ fs-img: user
 user:
 	@cd ../user && make build
 
+# init 退出后 QEMU 以它的退出状态退出（被信号终止时为 128 + 信号），内核 panic 时为 134
 run:
 	qemu-system-riscv64 \
 	-machine virt \
 	-kernel $(KERNEL_QEMU) \
+	-append "$(BOOTARGS)" \
+	$(if $(INITRD),-initrd $(INITRD)) \
 	-m 128M \
 	-nographic \
 	-smp 2	\
 	-drive file=$(FS_IMG),if=none,format=raw,id=x0 \
-	-device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0
+	-device virtio-blk-device,drive=x0,bus=virtio-mmio-bus.0 \
+	-netdev $(NETDEV) \
+	-device virtio-net-device,netdev=net0,bus=virtio-mmio-bus.1 \
+	$(if $(PCI_DISK),-drive file=$(PCI_DISK)$(comma)if=none$(comma)format=raw$(comma)id=x1 \
+		-device virtio-blk-pci$(comma)drive=x1) \
+	$(if $(GDB),-chardev pipe$(comma)id=gdb$(comma)path=$(GDB) \
+		-device virtio-serial-device$(comma)bus=virtio-mmio-bus.2 \
+		-device virtconsole$(comma)chardev=gdb)
+
+# 内核单元测试：以 ktest 特性和 --test 编译内核，不需要文件系统镜像；
+# 全部通过时 QEMU 以 0 退出，测例 panic 时以非零状态退出
+test: pre
+	@cp src/hal/arch/riscv/linker-$(BOARD).ld src/hal/arch/riscv/linker.ld
+	@cargo rustc --${MODE} --target $(TARGET) --features "board_$(BOARD) ktest" -- --test
+	@$(OBJCOPY) ${KERNEL_ELF} --strip-all -O binary $(KERNEL_BIN)
+	qemu-system-riscv64 \
+	-machine virt \
+	-kernel $(KERNEL_BIN) \
+	-m 128M \
+	-nographic \
+	-no-reboot \
+	-smp 1
 
-#	-device virtio-net-device,netdev=net \
-#	-netdev user,id=net \
-#	-initrd initrd.img
\ No newline at end of file
+# 把用户程序打包成 initramfs
+initramfs: user
+	@mkdir -p ../fs-img
+	@cd ../user/target/$(TARGET)/release && \
+		find . -maxdepth 1 -type f -executable | cpio -o -H newc > $(CURDIR)/../fs-img/initramfs.cpio
\ No newline at end of file
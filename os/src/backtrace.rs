@@ -0,0 +1,168 @@
+//! # 栈回溯与陷阱现场打印
+//!
+//! ## Overview
+//! - `backtrace`：从调用处回溯内核栈，由 panic 处理调用
+//! - `dump_kernel_trap`：打印内核态陷阱的寄存器现场、当前任务，并从陷阱处回溯内核栈
+//! - `dump_user_trap`：打印用户态陷阱现场，并经用户页表回溯用户栈，
+//!   进程因 SIGSEGV 终止时调用
+//!
+//! ## Assumptions
+//! - 内核与用户程序都以 `-Cforce-frame-pointers=yes` 编译
+//! - 打开 `ksyms` 特性时，`KERNEL_SYMBOLS` 指向同一内核第一遍编译后 `nm -n` 的输出；
+//!   符号表放在排在 `.text` 之后的 `.rodata` 中，嵌入它不会移动代码的地址
+//!
+//! ## Behavior
+//! - 不分配内存，不等待可能已被持有的锁，可以在 panic 路径上使用
+//! - 内核帧只在启动栈或当前任务的内核栈内回溯，返回地址离开内核代码段时停止
+//! - 用户栈经页表逐字读取，遇到未映射的页即停止；用户地址不做符号化
+
+use crate::hal::{frame_pointer, PageTableImpl, TrapContext, KERNEL_STACK_SIZE, TRAP_CONTEXT_BASE};
+use crate::mm::{PageTable, VirtAddr};
+use crate::task::try_current_task;
+use core::fmt::Display;
+use core::ops::Range;
+use unwind::{FrameWalker, SymbolTable};
+
+/// 最多回溯的帧数
+const MAX_DEPTH: usize = 32;
+
+#[cfg(feature = "ksyms")]
+static KSYMS: &[u8] = include_bytes!(env!("KERNEL_SYMBOLS"));
+#[cfg(not(feature = "ksyms"))]
+static KSYMS: &[u8] = &[];
+
+fn symbols() -> SymbolTable<'static> {
+    // 不让编译器按符号表的长度折叠代码，两遍编译得到的代码才完全相同
+    SymbolTable::new(core::hint::black_box(KSYMS))
+}
+
+fn in_kernel_text(addr: usize) -> bool {
+    extern "C" {
+        fn stext();
+        fn etext();
+    }
+    (stext as usize..etext as usize).contains(&addr)
+}
+
+/// 包含帧指针 `fp` 的内核栈：启动栈或当前任务的内核栈
+fn kernel_stack(fp: usize) -> Option<Range<usize>> {
+    extern "C" {
+        fn boot_stack();
+        fn boot_stack_top();
+    }
+    let boot = boot_stack as usize..boot_stack_top as usize;
+    if boot.start < fp && fp <= boot.end {
+        return Some(boot);
+    }
+    let top = try_current_task()?.kstack.get_top();
+    let kstack = top - KERNEL_STACK_SIZE..top;
+    (kstack.start < fp && fp <= kstack.end).then_some(kstack)
+}
+
+/// 打印一帧；`is_return` 表示 `addr` 是返回地址，
+/// 它指向调用指令的下一条，减一后查找才落在调用者内部
+fn print_kernel_frame(index: usize, addr: usize, is_return: bool) {
+    let lookup = if is_return { addr - 1 } else { addr };
+    match symbols().lookup(lookup) {
+        Some(sym) => println!(
+            "#{}: {:#x} {}+{:#x}",
+            index,
+            addr,
+            sym.name,
+            addr - sym.addr
+        ),
+        None => println!("#{}: {:#x}", index, addr),
+    }
+}
+
+/// 从帧指针 `fp` 回溯内核栈，`pc` 给出时作为第 0 帧
+fn kernel_backtrace(pc: Option<usize>, fp: usize) {
+    println!("----START BACKTRACE----");
+    let first = match pc {
+        Some(pc) => {
+            print_kernel_frame(0, pc, false);
+            1
+        }
+        None => 0,
+    };
+    match kernel_stack(fp) {
+        Some(stack) => {
+            let frames = FrameWalker::new(fp, stack, MAX_DEPTH, |addr| {
+                Some(unsafe { *(addr as *const usize) })
+            })
+            .take_while(|frame| in_kernel_text(frame.ra));
+            for (i, frame) in frames.enumerate() {
+                print_kernel_frame(first + i, frame.ra, true);
+            }
+        }
+        None => println!("fp {:#x} is not on a kernel stack", fp),
+    }
+    if symbols().is_empty() {
+        println!("(no kernel symbols embedded, build with `make kernel` to get them)");
+    }
+    println!("----END OF BACKTRACE----");
+}
+
+/// 打印当前任务的 pid 与 tid
+fn print_current_task() {
+    let Some(task) = try_current_task() else {
+        println!("[kernel] no current task");
+        return;
+    };
+    let pid = task.process.upgrade().map(|process| process.getpid());
+    let tid = task
+        .inner
+        .try_exclusive_access()
+        .and_then(|inner| inner.res.as_ref().map(|res| res.tid));
+    match (pid, tid) {
+        (Some(pid), Some(tid)) => println!("[kernel] current task: pid {} tid {}", pid, tid),
+        _ => println!("[kernel] current task: pid/tid unavailable"),
+    }
+}
+
+/// 从调用处回溯内核栈
+pub fn backtrace() {
+    print_current_task();
+    kernel_backtrace(None, frame_pointer());
+}
+
+/// 打印内核态陷阱的现场 `regs` 与当前任务，并从陷阱处回溯内核栈
+///
+/// `pc` 与 `fp` 是陷阱发生时的程序计数器与帧指针
+pub fn dump_kernel_trap(regs: &impl Display, pc: usize, fp: usize) {
+    print_current_task();
+    println!("{}", regs);
+    kernel_backtrace(Some(pc), fp);
+}
+
+/// 经页表读取用户地址处的一个字，页未映射或不可读时返回 `None`
+// LoongArch 的用户态陷阱处理尚未实现，暂时没有调用者
+#[cfg_attr(feature = "loongarch", allow(dead_code))]
+fn read_user(page_table: &PageTableImpl, addr: usize) -> Option<usize> {
+    let va = VirtAddr::from(addr);
+    let pte = page_table
+        .translate(va.floor())
+        .filter(|pte| pte.is_valid() && pte.readable())?;
+    let offset = va.page_offset();
+    let bytes = &pte.ppn().get_bytes_array()[offset..offset + core::mem::size_of::<usize>()];
+    Some(usize::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+/// 打印用户态陷阱现场 `cx` 与当前任务，并回溯用户栈
+///
+/// `token` 是进程的用户页表，`pc`、`fp` 与 `sp` 取自 `cx`
+#[cfg_attr(feature = "loongarch", allow(dead_code))]
+pub fn dump_user_trap(cx: &TrapContext, token: usize, pc: usize, fp: usize, sp: usize) {
+    print_current_task();
+    println!("{}", cx);
+    let page_table = PageTableImpl::from_token(token);
+    println!("----START USER BACKTRACE----");
+    println!("#0: {:#x}", pc);
+    let frames = FrameWalker::new(fp, sp..TRAP_CONTEXT_BASE, MAX_DEPTH, |addr| {
+        read_user(&page_table, addr)
+    });
+    for (i, frame) in frames.enumerate() {
+        println!("#{}: {:#x}", i + 1, frame.ra);
+    }
+    println!("----END OF USER BACKTRACE----");
+}
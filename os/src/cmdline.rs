@@ -0,0 +1,198 @@
+//! 内核命令行
+//!
+//! # Overview
+//! 启动参数有两个来源，按优先级从高到低：
+//! - 设备树 `/chosen/bootargs`（QEMU 的 `-append`），由 `init` 在堆建立后解析
+//! - 启动盘根目录下的配置文件 `cmdline`，由 `load_config` 在文件系统可用后读取，
+//!   每行若干参数，`#` 之后为注释；只补充命令行中没有出现的键
+//!
+//! 参数以空白分隔，形如 `key=value` 或单独的 `key`（值为空串），
+//! `--` 之后的内容原样作为 init 进程的参数。内核自身识别的参数：
+//! - `init=PATH`：init 程序在启动盘上的路径，默认 `initproc`
+//! - `root=DEV`：根文件系统所在的块设备（如 `/dev/vda1`），缺省时有内存盘则用 `ram0`，
+//!   否则用 `vda`；根文件系统在读取配置文件之前挂载，因此只能在命令行中给出
+//! - `ramdisk_size=KIB`：initramfs 解包成的内存盘大小
+//! - `block_cache=BLOCKS`：块缓存的容量，缺省取内核堆的 1/8
+//! - `fsync_on_close=POLICY`：可写文件最后一次关闭时的写回策略，`never`/`data`（缺省）/`full`
+//! - `loglevel=LEVEL`：日志级别，`off`/`error`/`warn`/`info`/`debug`/`trace` 或 Linux 风格的 0-8
+//! - `quiet`：不打印启动过程中的硬件与文件列表信息，未指定 `loglevel` 时关闭日志
+//!
+//! 其余参数保存在表中，供各子系统通过 `get` / `contains` 查询。
+//!
+//! # Assumptions
+//! - 参数值不含空白，不支持引号
+//!
+//! # Invariants
+//! - 同一个键只保留一个值：命令行中后出现的覆盖先出现的，配置文件不覆盖命令行
+//!
+//! # Behavior
+//! - 无法识别的 `loglevel` 被忽略，保留编译期 `LOG` 给出的级别
+
+use crate::fs::{open_boot_file, OpenFlags};
+use crate::hal::MACHINE;
+use crate::sync::UPIntrFreeCell;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::str::FromStr;
+use lazy_static::lazy_static;
+use log::LevelFilter;
+
+/// 启动盘上的配置文件
+pub const CONFIG_FILE: &str = "cmdline";
+/// 默认的 init 程序
+pub const DEFAULT_INIT: &str = "initproc";
+
+struct Cmdline {
+    params: BTreeMap<String, String>,
+    /// `--` 之后的参数
+    init_args: Option<Vec<String>>,
+}
+
+lazy_static! {
+    static ref CMDLINE: UPIntrFreeCell<Cmdline> = unsafe {
+        UPIntrFreeCell::new(Cmdline {
+            params: BTreeMap::new(),
+            init_args: None,
+        })
+    };
+}
+
+impl Cmdline {
+    /// 解析一行参数，`overwrite` 为 `false` 时不覆盖已有的键
+    fn parse(&mut self, line: &str, overwrite: bool) {
+        let mut words = line.split_whitespace();
+        while let Some(word) = words.next() {
+            if word == "--" {
+                if overwrite || self.init_args.is_none() {
+                    self.init_args = Some(words.map(String::from).collect());
+                }
+                return;
+            }
+            let (key, value) = word.split_once('=').unwrap_or((word, ""));
+            if key.is_empty() {
+                continue;
+            }
+            if overwrite || !self.params.contains_key(key) {
+                self.params.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+}
+
+/// 解析日志级别
+fn parse_level(value: &str) -> Option<LevelFilter> {
+    match value {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        // Linux 的 console_loglevel：0-3 只输出错误，4 警告，5-6 信息，7 调试
+        _ => match value.parse::<usize>().ok()? {
+            0..=3 => Some(LevelFilter::Error),
+            4 => Some(LevelFilter::Warn),
+            5 | 6 => Some(LevelFilter::Info),
+            7 => Some(LevelFilter::Debug),
+            _ => Some(LevelFilter::Trace),
+        },
+    }
+}
+
+/// 根据当前参数调整日志级别
+fn apply() {
+    match get("loglevel") {
+        Some(value) => match parse_level(&value) {
+            Some(level) => log::set_max_level(level),
+            None => log::warn!("[kernel] cmdline: unknown loglevel {}", value),
+        },
+        None if quiet() => log::set_max_level(LevelFilter::Off),
+        None => {}
+    }
+}
+
+/// 解析设备树给出的命令行，在堆建立后调用
+pub fn init() {
+    CMDLINE.exclusive_access().parse(MACHINE.bootargs(), true);
+    apply();
+}
+
+/// 读取启动盘上的配置文件，在文件系统可用后调用
+pub fn load_config() {
+    if let Some(inode) = open_boot_file(CONFIG_FILE, OpenFlags::RDONLY) {
+        let data = inode.read_all();
+        let text = String::from_utf8_lossy(&data);
+        let mut cmdline = CMDLINE.exclusive_access();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or("");
+            cmdline.parse(line, false);
+        }
+        drop(cmdline);
+        apply();
+    }
+    if !quiet() {
+        print();
+    }
+}
+
+/// 参数 `key` 的值，单独出现的 `key` 值为空串
+pub fn get(key: &str) -> Option<String> {
+    CMDLINE.exclusive_access().params.get(key).cloned()
+}
+
+/// 参数 `key` 解析为 `T`，不存在或无法解析时返回 `None`
+pub fn get_parsed<T: FromStr>(key: &str) -> Option<T> {
+    get(key)?.parse().ok()
+}
+
+/// 是否给出了参数 `key`
+pub fn contains(key: &str) -> bool {
+    CMDLINE.exclusive_access().params.contains_key(key)
+}
+
+/// init 程序的路径
+pub fn init_path() -> String {
+    get("init")
+        .filter(|path| !path.is_empty())
+        .unwrap_or_else(|| DEFAULT_INIT.to_string())
+}
+
+/// 传给 init 程序的参数（不含 `argv[0]`）
+pub fn init_args() -> Vec<String> {
+    CMDLINE
+        .exclusive_access()
+        .init_args
+        .clone()
+        .unwrap_or_default()
+}
+
+/// 命令行指定的根设备
+pub fn root() -> Option<String> {
+    get("root").filter(|root| !root.is_empty())
+}
+
+/// 是否安静启动
+pub fn quiet() -> bool {
+    contains("quiet")
+}
+
+/// 打印全部参数
+pub fn print() {
+    let cmdline = CMDLINE.exclusive_access();
+    print!("[kernel] cmdline:");
+    for (key, value) in cmdline.params.iter() {
+        if value.is_empty() {
+            print!(" {}", key);
+        } else {
+            print!(" {}={}", key, value);
+        }
+    }
+    if let Some(args) = cmdline.init_args.as_ref() {
+        print!(" --");
+        for arg in args {
+            print!(" {}", arg);
+        }
+    }
+    println!("");
+}
@@ -95,7 +95,8 @@ macro_rules! println {
 /// 初始化日志系统。
 ///
 /// 使用 `log` crate 的全局日志接口，
-/// 并通过编译期环境变量 `LOG` 设置日志级别。
+/// 并通过编译期环境变量 `LOG` 设置默认日志级别，
+/// 启动后可由命令行参数 `loglevel=` 覆盖（见 `cmdline`）。
 ///
 /// 支持的日志级别：
 /// - error
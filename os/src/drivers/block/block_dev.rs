@@ -1,6 +1,3 @@
-use core::any::Any;
+//! 块设备接口定义在 `blkcache` 中，以便块缓存与 `fatfs` 适配层在主机上测试
 
-pub trait BlockDevice: Send + Sync + Any {
-    fn read_block(&self, block_id: usize, buf: &mut [u8]);
-    fn write_block(&self, block_id: usize, buf: &[u8]);
-}
+pub use blkcache::BlockDevice;
@@ -1,11 +1,219 @@
+//! # 块设备
+//!
+//! ## Overview
+//! 启动时枚举所有 virtio-blk 磁盘（先 virtio-mmio 槽位，后 PCI 总线），按发现顺序命名为 `vda`、`vdb`……，
+//! 内存盘（`ramdisk`）由 `fs::initramfs` 登记，命名为 `ram0`、`ram1`……。
+//! 登记磁盘时读取其分区表，分区命名为 `vda1`、`ram0p1`……。
+//! 磁盘与分区都登记在同一张表中，按名字查找（`block_device`），
+//! `BLOCK_DEVICE` 是根设备：命令行 `root=` 指定，缺省时有内存盘则用 `ram0`，否则用 `vda`。
+//!
+//! ## Assumptions
+//! - 枚举在第一次访问设备表时进行，此时还没有当前任务，磁盘请求以轮询方式完成
+//! - 磁盘请求缺省以轮询方式完成；只有确知自己不持有自旋锁、也不在 `UPIntrFreeCell`
+//!   临界区中的调用者才创建 `BlockingIo`，让当前任务的请求睡眠等待中断
+//! - 内存盘在根文件系统挂载（第一次访问 `BLOCK_DEVICE`）之前登记
+//!
+//! ## Invariants
+//! - 设备只增不减，名字唯一
+//!
+//! ## Behavior
+//! - 设备名可以带 `/dev/` 前缀
+//! - PCI 磁盘可能与其他设备共享 INTx 中断号，中断分发给所有使用该中断号的磁盘
+//! - 找不到根设备时 panic
+
 pub mod block_dev;
-mod virtio_blk_mmio;
+mod partition;
+pub mod ramdisk;
+mod virtio_blk;
 
+use crate::drivers::virtio::mmio::MmioTransport;
+use crate::drivers::virtio::pci::PciTransport;
+use crate::drivers::virtio::{mmio, pci, DEVICE_BLOCK};
+use crate::hal::BLOCK_SZ;
+use crate::sync::UPIntrFreeCell;
+use crate::task::current_task;
+use alloc::format;
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use block_dev::BlockDevice;
 use lazy_static::lazy_static;
-use virtio_blk_mmio::VirtIOBlock;
+use partition::Partition;
+use virtio_blk::VirtIOBlk;
+
+/// 设备表中的一项
+#[derive(Clone)]
+pub struct BlockDeviceInfo {
+    pub name: String,
+    pub device: Arc<dyn BlockDevice>,
+    /// 磁盘的中断号；分区和没有中断的磁盘为 0
+    pub irq: usize,
+}
 
 lazy_static! {
-    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = Arc::new(VirtIOBlock::new());
+    static ref BLOCK_DEVICES: UPIntrFreeCell<Vec<BlockDeviceInfo>> =
+        unsafe { UPIntrFreeCell::new(probe()) };
+    /// 根文件系统所在的块设备
+    pub static ref BLOCK_DEVICE: Arc<dyn BlockDevice> = {
+        let root = crate::cmdline::root().unwrap_or_else(|| {
+            String::from(if block_device("ram0").is_some() { "ram0" } else { "vda" })
+        });
+        block_device(&root).unwrap_or_else(|| panic!("root device {} not found", root))
+    };
+}
+
+/// 第 `index` 块磁盘的名字
+fn disk_name(index: usize) -> String {
+    format!("vd{}", (b'a' + index as u8) as char)
+}
+
+/// 磁盘及其分区的表项
+fn disk_entries(name: String, disk: Arc<dyn BlockDevice>, irq: usize) -> Vec<BlockDeviceInfo> {
+    // 名字以数字结尾的磁盘（如 ram0）在分区号前加 `p`
+    let separator = if name.ends_with(|c: char| c.is_ascii_digit()) { "p" } else { "" };
+    let mut entries = Vec::new();
+    for entry in partition::scan(disk.as_ref()) {
+        entries.push(BlockDeviceInfo {
+            name: format!("{}{}{}", name, separator, entry.number),
+            device: Arc::new(Partition::new(disk.clone(), entry.start, entry.blocks)),
+            irq: 0,
+        });
+    }
+    entries.insert(
+        0,
+        BlockDeviceInfo {
+            name,
+            device: disk,
+            irq,
+        },
+    );
+    entries
+}
+
+/// 枚举 virtio 磁盘与分区
+fn probe() -> Vec<BlockDeviceInfo> {
+    let mmio_disks = mmio::find_all(DEVICE_BLOCK).filter_map(|slot| {
+        let transport = unsafe { MmioTransport::new(slot.base) }?;
+        match VirtIOBlk::new(transport, slot.irq != 0) {
+            Ok(disk) => Some((Arc::new(disk) as Arc<dyn BlockDevice>, slot.irq)),
+            Err(_) => {
+                log::warn!(
+                    "[kernel] block: failed to initialize virtio-blk at mmio {:#x}",
+                    slot.base
+                );
+                None
+            }
+        }
+    });
+    let pci_disks = pci::find_all(DEVICE_BLOCK).filter_map(|device| {
+        let transport = PciTransport::new(device)?;
+        match VirtIOBlk::new(transport, device.irq != 0) {
+            Ok(disk) => Some((Arc::new(disk) as Arc<dyn BlockDevice>, device.irq)),
+            Err(_) => {
+                log::warn!(
+                    "[kernel] block: failed to initialize virtio-blk at pci {}",
+                    device.addr
+                );
+                None
+            }
+        }
+    });
+    let mut devices = Vec::new();
+    for (index, (disk, irq)) in mmio_disks.chain(pci_disks).enumerate() {
+        devices.extend(disk_entries(disk_name(index), disk, irq));
+    }
+    devices
+}
+
+/// 登记一块内存盘及其分区，返回其名字
+pub fn register_ramdisk(disk: Arc<dyn BlockDevice>) -> String {
+    let index = BLOCK_DEVICES
+        .exclusive_access()
+        .iter()
+        .filter(|info| info.name.starts_with("ram") && !info.name.contains('p'))
+        .count();
+    let name = format!("ram{}", index);
+    // 读分区表时不能持有设备表
+    let entries = disk_entries(name.clone(), disk, 0);
+    BLOCK_DEVICES.exclusive_access().extend(entries);
+    name
+}
+
+/// 按名字查找块设备，例如 `vda`、`/dev/vdb1`
+pub fn block_device(name: &str) -> Option<Arc<dyn BlockDevice>> {
+    let name = name.strip_prefix("/dev/").unwrap_or(name);
+    BLOCK_DEVICES
+        .exclusive_access()
+        .iter()
+        .find(|info| info.name == name)
+        .map(|info| info.device.clone())
+}
+
+/// 所有块设备
+pub fn block_devices() -> Vec<BlockDeviceInfo> {
+    BLOCK_DEVICES.exclusive_access().clone()
+}
+
+/// 枚举块设备并使能磁盘中断
+pub fn init() {
+    for info in block_devices().iter() {
+        if info.irq != 0 {
+            crate::hal::enable_irq(info.irq);
+        }
+        if !crate::cmdline::quiet() {
+            println!(
+                "[kernel] block: {} ({} KiB)",
+                info.name,
+                info.device.num_blocks() * BLOCK_SZ / 1024
+            );
+        }
+    }
+}
+
+/// 分发磁盘中断，`irq` 不属于任何磁盘时返回 `false`
+pub fn handle_irq(irq: usize) -> bool {
+    let devices: Vec<Arc<dyn BlockDevice>> = BLOCK_DEVICES
+        .exclusive_access()
+        .iter()
+        .filter(|info| info.irq == irq && irq != 0)
+        .map(|info| info.device.clone())
+        .collect();
+    for device in devices.iter() {
+        device.handle_irq();
+    }
+    !devices.is_empty()
+}
+
+/// 允许当前任务的磁盘请求睡眠等待完成，销毁时恢复原来的设置
+///
+/// 只能由不持有自旋锁、不在 `UPIntrFreeCell` 临界区中的调用者创建，
+/// 并在它存在期间保持这一点；没有当前任务时不起作用
+pub struct BlockingIo(bool);
+
+impl BlockingIo {
+    pub fn enter() -> Self {
+        Self(set_blocking_io(true))
+    }
+}
+
+impl Drop for BlockingIo {
+    fn drop(&mut self) {
+        set_blocking_io(self.0);
+    }
+}
+
+/// 设置当前任务的磁盘请求能否睡眠，返回原来的设置
+fn set_blocking_io(allowed: bool) -> bool {
+    current_task().map_or(false, |task| {
+        core::mem::replace(&mut task.inner_exclusive_access().blocking_io, allowed)
+    })
+}
+
+/// 当前任务的磁盘请求能否睡眠
+fn blocking_io_allowed() -> bool {
+    current_task().map_or(false, |task| {
+        task.inner
+            .try_exclusive_access()
+            .map_or(false, |inner| inner.blocking_io)
+    })
 }
@@ -0,0 +1,212 @@
+//! # 分区表
+//!
+//! ## Overview
+//! 读取磁盘开头的分区表，把每个分区包装成独立的 `BlockDevice`：
+//! - MBR：四个主分区，分区号为表项序号加 1
+//! - GPT：MBR 中出现类型为 `0xEE` 的保护分区时改读 LBA 1 处的 GPT 头，
+//!   分区号为分区项序号加 1
+//!
+//! ## Assumptions
+//! - 扇区大小为 `BLOCK_SZ`（512 字节），GPT 分区项大小整除扇区大小
+//!
+//! ## Invariants
+//! - `Partition` 只访问 `[start, start + blocks)` 范围内的块：
+//!   越界的读取记录错误并得到全零，越界的写入记录错误后丢弃
+//!
+//! ## Behavior
+//! - 扩展分区（逻辑分区）不解析
+//! - 扇区 0 是 FAT 引导扇区时视为没有分区表：它同样以 `0x55AA` 结尾，
+//!   但分区表位置上是引导代码
+//! - 超出磁盘范围的分区被截断，起点超出磁盘的分区被忽略
+
+use super::block_dev::BlockDevice;
+use crate::hal::BLOCK_SZ;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// MBR 分区表偏移与表项大小
+const MBR_TABLE: usize = 446;
+const MBR_ENTRY_SIZE: usize = 16;
+/// GPT 保护分区类型
+const MBR_TYPE_GPT: u8 = 0xEE;
+/// 扩展分区类型
+const MBR_TYPE_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+
+const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// 最多读取的 GPT 分区项数
+const GPT_MAX_ENTRIES: usize = 128;
+
+/// 磁盘上的一个分区
+pub struct Partition {
+    disk: Arc<dyn BlockDevice>,
+    start: usize,
+    blocks: usize,
+}
+
+impl Partition {
+    pub fn new(disk: Arc<dyn BlockDevice>, start: usize, blocks: usize) -> Self {
+        Self {
+            disk,
+            start,
+            blocks,
+        }
+    }
+}
+
+impl BlockDevice for Partition {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        if block_id >= self.blocks {
+            log::error!("[partition] read of block {} beyond partition", block_id);
+            buf.fill(0);
+            return;
+        }
+        self.disk.read_block(self.start + block_id, buf);
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        if block_id >= self.blocks {
+            log::error!(
+                "[partition] write of block {} beyond partition dropped",
+                block_id
+            );
+            return;
+        }
+        self.disk.write_block(self.start + block_id, buf);
+    }
+
+    fn read_blocks(&self, start_block: usize, buf: &mut [u8]) {
+        let count = buf.len() / BLOCK_SZ;
+        let valid = self.blocks.saturating_sub(start_block).min(count);
+        if valid < count {
+            log::error!(
+                "[partition] read of blocks {}..{} beyond partition",
+                start_block,
+                start_block + count
+            );
+        }
+        let (inside, outside) = buf.split_at_mut(valid * BLOCK_SZ);
+        if valid > 0 {
+            self.disk.read_blocks(self.start + start_block, inside);
+        }
+        outside.fill(0);
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// 中断由所在的磁盘处理
+    fn handle_irq(&self) {}
+}
+
+/// 分区表中的一项
+#[derive(Clone, Copy, Debug)]
+pub struct PartitionEntry {
+    /// 分区号，从 1 开始
+    pub number: usize,
+    pub start: usize,
+    pub blocks: usize,
+}
+
+fn le32(bytes: &[u8], offset: usize) -> usize {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+}
+
+fn le64(bytes: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+/// 扇区 0 是否为 FAT 引导扇区
+fn is_fat_boot_sector(sector: &[u8]) -> bool {
+    (sector[0] == 0xEB || sector[0] == 0xE9)
+        && (&sector[82..87] == b"FAT32" || &sector[54..57] == b"FAT")
+}
+
+/// 读取磁盘的分区表，没有分区表时返回空
+pub fn scan(disk: &dyn BlockDevice) -> Vec<PartitionEntry> {
+    let mut sector = [0u8; BLOCK_SZ];
+    disk.read_block(0, &mut sector);
+    if sector[510..512] != [0x55, 0xAA] || is_fat_boot_sector(&sector) {
+        return Vec::new();
+    }
+    let entries: Vec<&[u8]> = (0..4)
+        .map(|i| &sector[MBR_TABLE + i * MBR_ENTRY_SIZE..MBR_TABLE + (i + 1) * MBR_ENTRY_SIZE])
+        .collect();
+    // 活动标志只能是 0 或 0x80，否则这不是分区表
+    if entries.iter().any(|entry| entry[0] & 0x7F != 0) {
+        return Vec::new();
+    }
+    if entries.iter().any(|entry| entry[4] == MBR_TYPE_GPT) {
+        return scan_gpt(disk);
+    }
+    let mut partitions = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let kind = entry[4];
+        if kind == 0 || MBR_TYPE_EXTENDED.contains(&kind) {
+            continue;
+        }
+        push(
+            &mut partitions,
+            disk,
+            i + 1,
+            le32(entry, 8),
+            le32(entry, 12),
+        );
+    }
+    partitions
+}
+
+fn scan_gpt(disk: &dyn BlockDevice) -> Vec<PartitionEntry> {
+    let mut header = [0u8; BLOCK_SZ];
+    disk.read_block(1, &mut header);
+    if &header[0..8] != GPT_SIGNATURE {
+        return Vec::new();
+    }
+    let entries_lba = le64(&header, 72);
+    let count = le32(&header, 80).min(GPT_MAX_ENTRIES);
+    let entry_size = le32(&header, 84);
+    if entry_size < 128 || entry_size > BLOCK_SZ || BLOCK_SZ % entry_size != 0 {
+        return Vec::new();
+    }
+
+    let mut partitions = Vec::new();
+    let mut sector = [0u8; BLOCK_SZ];
+    let mut loaded = None;
+    for i in 0..count {
+        let offset = i * entry_size;
+        let lba = entries_lba + offset / BLOCK_SZ;
+        if loaded != Some(lba) {
+            disk.read_block(lba, &mut sector);
+            loaded = Some(lba);
+        }
+        let entry = &sector[offset % BLOCK_SZ..offset % BLOCK_SZ + entry_size];
+        // 类型 GUID 全零的分区项未使用
+        if entry[0..16].iter().all(|&b| b == 0) {
+            continue;
+        }
+        let first = le64(entry, 32);
+        let last = le64(entry, 40);
+        if last >= first {
+            push(&mut partitions, disk, i + 1, first, last - first + 1);
+        }
+    }
+    partitions
+}
+
+fn push(
+    partitions: &mut Vec<PartitionEntry>,
+    disk: &dyn BlockDevice,
+    number: usize,
+    start: usize,
+    blocks: usize,
+) {
+    let total = disk.num_blocks();
+    if blocks == 0 || start >= total {
+        return;
+    }
+    partitions.push(PartitionEntry {
+        number,
+        start,
+        blocks: blocks.min(total - start),
+    });
+}
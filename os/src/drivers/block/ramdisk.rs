@@ -0,0 +1,89 @@
+//! # 内存盘
+//!
+//! ## Overview
+//! 以内存为存储的 `BlockDevice`，有两种后端：
+//! - `from_region`：直接使用一段物理内存，例如引导程序加载的磁盘镜像
+//! - `new`：按需分配页帧，用于把 initramfs 解包成文件系统
+//!
+//! ## Assumptions
+//! - `BLOCK_SZ` 整除 `PAGE_SIZE`
+//! - 物理内存在内核页表中恒等映射（RISC-V）或经由直接映射窗口访问（LoongArch）
+//!
+//! ## Safety
+//! - 读写通过裸指针完成，同一块的并发访问由块缓存的锁串行化
+//!
+//! ## Invariants
+//! - 页帧后端的第 `n` 块位于第 `n * BLOCK_SZ / PAGE_SIZE` 个页帧中
+
+use super::block_dev::BlockDevice;
+use crate::hal::{BLOCK_SZ, PAGE_SIZE};
+use crate::mm::{frame_alloc, FrameTracker};
+use alloc::vec::Vec;
+
+enum Storage {
+    /// 起始地址
+    Region(usize),
+    Frames(Vec<FrameTracker>),
+}
+
+pub struct RamDisk {
+    storage: Storage,
+    blocks: usize,
+}
+
+impl RamDisk {
+    /// 使用 `[base, base + size)` 处的内存，不足一块的尾部被忽略
+    ///
+    /// # Safety
+    /// 该区间必须可读写，并且在内存盘的生命周期内不被他用
+    pub unsafe fn from_region(base: usize, size: usize) -> Self {
+        Self {
+            storage: Storage::Region(base),
+            blocks: size / BLOCK_SZ,
+        }
+    }
+
+    /// 分配 `blocks` 块清零的内存，页帧不足时返回 `None`
+    pub fn new(blocks: usize) -> Option<Self> {
+        let pages = (blocks * BLOCK_SZ + PAGE_SIZE - 1) / PAGE_SIZE;
+        let frames = (0..pages)
+            .map(|_| frame_alloc())
+            .collect::<Option<Vec<FrameTracker>>>()?;
+        Some(Self {
+            storage: Storage::Frames(frames),
+            blocks,
+        })
+    }
+
+    /// 第 `block_id` 块的地址
+    fn block_ptr(&self, block_id: usize) -> *mut u8 {
+        assert!(block_id < self.blocks, "block {} beyond ramdisk", block_id);
+        let offset = block_id * BLOCK_SZ;
+        match &self.storage {
+            Storage::Region(base) => (base + offset) as *mut u8,
+            Storage::Frames(frames) => {
+                let page = frames[offset / PAGE_SIZE].ppn.get_bytes_array();
+                page[offset % PAGE_SIZE..].as_mut_ptr()
+            }
+        }
+    }
+}
+
+impl BlockDevice for RamDisk {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let src = self.block_ptr(block_id);
+        unsafe { core::ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), BLOCK_SZ) };
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let dst = self.block_ptr(block_id);
+        unsafe { core::ptr::copy_nonoverlapping(buf.as_ptr(), dst, BLOCK_SZ) };
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.blocks
+    }
+
+    /// 内存盘没有中断
+    fn handle_irq(&self) {}
+}
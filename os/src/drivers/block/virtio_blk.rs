@@ -0,0 +1,244 @@
+//! # virtio-blk 驱动
+//!
+//! ## Overview
+//! 驱动对传输层泛型，virtio-mmio 与 PCI 上的磁盘使用同一份代码。
+//! 每个请求是三个描述符组成的链：请求头、数据缓冲区、状态字节。
+//! 队列按三个描述符一组划分成若干“请求槽”，第 `n` 个槽使用描述符 `3n..3n + 3`，
+//! 每个槽对应一个条件变量：
+//! - 设备有中断且当前任务持有 `BlockingIo` 时，提交者睡在槽的条件变量上，由中断处理函数唤醒
+//! - 否则提交者轮询已用环；顺带取出的其他请求同样唤醒其提交者
+//!
+//! 每个槽记录完成过的请求数，提交者以提交时的值加一作为票号，完成数达到票号才返回：
+//! 被唤醒不代表请求已完成，槽在完成后也可能立即被其他请求复用
+//!
+//! ## Assumptions
+//! - `BLOCK_SZ` 是 512 字节扇区的整数倍
+//! - 请求头与数据缓冲区位于内核地址空间，物理地址由 `VirtIOHal::virt_to_phys` 得到，
+//!   数据缓冲区（包括 `read_blocks` 的多块缓冲区）在物理上连续
+//!
+//! ## Safety
+//! - 请求头放在提交者的内核栈上，提交者在请求完成前不会返回
+//!
+//! ## Invariants
+//! - 每个请求槽要么在空闲列表中，要么恰好对应一个未完成的请求
+//! - 提交者在自己的请求完成之前不会返回，设备不会写入已经失效的请求头
+//!
+//! ## Behavior
+//! - 设备返回错误状态时 panic
+
+use super::block_dev::BlockDevice;
+use super::blocking_io_allowed;
+use crate::drivers::virtio::queue::VirtQueue;
+use crate::drivers::virtio::{Transport, VirtIOHal, F_VERSION_1};
+use crate::hal::BLOCK_SZ;
+use crate::sync::{Condvar, UPIntrFreeCell};
+use crate::task::{current_task, schedule};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hint::spin_loop;
+use core::ptr::{addr_of, addr_of_mut, read_volatile};
+use virtio_drivers::Hal;
+
+const QUEUE_REQUEST: u16 = 0;
+const QUEUE_SIZE: u16 = 16;
+/// 每个请求占用的描述符数
+const DESCS_PER_REQUEST: u16 = 3;
+
+const SECTOR_SIZE: usize = 512;
+
+const REQUEST_IN: u32 = 0;
+const REQUEST_OUT: u32 = 1;
+const STATUS_OK: u8 = 0;
+
+/// 请求头与状态字节，对齐到 32 字节以保证不跨页
+#[repr(C, align(32))]
+struct Request {
+    kind: u32,
+    reserved: u32,
+    sector: u64,
+    status: u8,
+}
+
+/// 请求头（不含状态字节）的长度
+const HEADER_LEN: usize = 16;
+
+struct VirtIOBlkInner<T: Transport> {
+    transport: T,
+    queue: VirtQueue,
+    /// 空闲的请求槽
+    free: Vec<u16>,
+    /// 每个请求槽完成过的请求数
+    completed: Vec<u64>,
+}
+
+pub struct VirtIOBlk<T: Transport> {
+    inner: UPIntrFreeCell<VirtIOBlkInner<T>>,
+    condvars: Vec<Condvar>,
+    /// 磁盘容量（扇区数）
+    capacity: usize,
+    /// 设备中断是否接入了中断控制器
+    irq_driven: bool,
+}
+
+impl<T: Transport> VirtIOBlk<T> {
+    pub fn new(mut transport: T, irq_driven: bool) -> Result<Self, ()> {
+        transport.begin_init(F_VERSION_1)?;
+        // 设备配置空间的第一个字段是以扇区计的容量
+        let mut capacity = 0u64;
+        for i in (0..8).rev() {
+            capacity = (capacity << 8) | transport.read_config(i) as u64;
+        }
+        let queue = VirtQueue::new(&mut transport, QUEUE_REQUEST, QUEUE_SIZE)?;
+        let slots = queue.size() / DESCS_PER_REQUEST;
+        if slots == 0 {
+            return Err(());
+        }
+        transport.finish_init();
+        Ok(Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(VirtIOBlkInner {
+                    transport,
+                    queue,
+                    free: (0..slots).collect(),
+                    completed: vec![0; slots as usize],
+                })
+            },
+            condvars: (0..slots).map(|_| Condvar::new()).collect(),
+            capacity: capacity as usize,
+            irq_driven,
+        })
+    }
+
+    /// 提交一个请求并等待其完成，返回设备写入的状态
+    ///
+    /// 数据缓冲区 `buf` 长 `len` 字节，是 `BLOCK_SZ` 的整数倍
+    fn request(
+        &self,
+        kind: u32,
+        block_id: usize,
+        buf: usize,
+        len: usize,
+        device_writable: bool,
+    ) -> u8 {
+        let mut request = Request {
+            kind,
+            reserved: 0,
+            sector: (block_id * (BLOCK_SZ / SECTOR_SIZE)) as u64,
+            status: u8::MAX,
+        };
+        let header = VirtIOHal::virt_to_phys(addr_of_mut!(request) as usize);
+        let bufs = [
+            (header, HEADER_LEN, false),
+            (VirtIOHal::virt_to_phys(buf), len, device_writable),
+            (header + HEADER_LEN, 1, true),
+        ];
+        let (slot, ticket) = self.inner.exclusive_session(|inner| {
+            let slot = self.submit(inner, &bufs);
+            (slot as usize, inner.completed[slot as usize] + 1)
+        });
+        let can_block = self.irq_driven && blocking_io_allowed();
+        if can_block {
+            let task = current_task().unwrap();
+            let condvar = &self.condvars[slot];
+            loop {
+                // 检查与入队在同一临界区内，不会错过中断；醒来时可能仍在队列中，先移除
+                let task_cx_ptr = self.inner.exclusive_session(|inner| {
+                    condvar.remove(&task);
+                    (inner.completed[slot] < ticket).then(|| condvar.wait_no_sched())
+                });
+                match task_cx_ptr {
+                    Some(task_cx_ptr) => schedule(task_cx_ptr),
+                    None => break,
+                }
+            }
+        } else {
+            self.inner.exclusive_session(|inner| {
+                while inner.completed[slot] < ticket {
+                    match Self::pop(inner) {
+                        Some(done) => self.condvars[done as usize].signal(),
+                        None => spin_loop(),
+                    }
+                }
+            });
+        }
+        // 状态字节由设备写入
+        unsafe { read_volatile(addr_of!(request.status)) }
+    }
+
+    /// 提交请求，返回请求槽；没有空闲槽时先回收已完成的请求
+    fn submit(&self, inner: &mut VirtIOBlkInner<T>, bufs: &[(usize, usize, bool)]) -> u16 {
+        loop {
+            if let Some(slot) = inner.free.pop() {
+                inner.queue.push_chain(slot * DESCS_PER_REQUEST, bufs);
+                inner.transport.notify(QUEUE_REQUEST);
+                return slot;
+            }
+            match Self::pop(inner) {
+                Some(done) => self.condvars[done as usize].signal(),
+                None => spin_loop(),
+            }
+        }
+    }
+
+    /// 取出一个完成的请求，记录完成并归还其请求槽
+    fn pop(inner: &mut VirtIOBlkInner<T>) -> Option<u16> {
+        let (head, _) = inner.queue.pop_used()?;
+        let slot = head / DESCS_PER_REQUEST;
+        inner.completed[slot as usize] += 1;
+        inner.free.push(slot);
+        Some(slot)
+    }
+}
+
+impl<T: Transport> BlockDevice for VirtIOBlk<T> {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let status = self.request(
+            REQUEST_IN,
+            block_id,
+            buf.as_mut_ptr() as usize,
+            BLOCK_SZ,
+            true,
+        );
+        assert_eq!(status, STATUS_OK, "Error when reading VirtIOBlk");
+    }
+
+    /// 一个请求读取全部块
+    fn read_blocks(&self, start_block: usize, buf: &mut [u8]) {
+        let len = buf.len() / BLOCK_SZ * BLOCK_SZ;
+        if len == 0 {
+            return;
+        }
+        let status = self.request(
+            REQUEST_IN,
+            start_block,
+            buf.as_mut_ptr() as usize,
+            len,
+            true,
+        );
+        assert_eq!(status, STATUS_OK, "Error when reading VirtIOBlk");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let status = self.request(
+            REQUEST_OUT,
+            block_id,
+            buf.as_ptr() as usize,
+            BLOCK_SZ,
+            false,
+        );
+        assert_eq!(status, STATUS_OK, "Error when writing VirtIOBlk");
+    }
+
+    fn num_blocks(&self) -> usize {
+        self.capacity * SECTOR_SIZE / BLOCK_SZ
+    }
+
+    fn handle_irq(&self) {
+        self.inner.exclusive_session(|inner| {
+            inner.transport.ack_interrupt();
+            while let Some(slot) = Self::pop(inner) {
+                self.condvars[slot as usize].signal();
+            }
+        });
+    }
+}
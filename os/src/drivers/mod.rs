@@ -1,6 +1,32 @@
 mod block;
+pub mod net;
+pub mod pci;
 pub mod serial;
+mod virtio;
 
 pub use block::block_dev::BlockDevice;
-pub use block::BLOCK_DEVICE;
+pub use block::ramdisk::RamDisk;
+pub use block::{
+    block_device, block_devices, register_ramdisk, BlockDeviceInfo, BlockingIo, BLOCK_DEVICE,
+};
 pub use serial::ns16550a::Ns16550a;
+pub use virtio::console::VIRTIO_CONSOLE;
+pub use virtio::rng::fill_random;
+
+/// 使能驱动使用的外部中断
+pub fn init() {
+    serial::init();
+    pci::init();
+    block::init();
+    virtio::init();
+}
+
+/// 外部中断分发，由中断控制器在 claim 到中断号后调用
+pub fn handle_irq(irq: usize) {
+    // 中断号在启动时探测得到，不能作为 match 的常量模式
+    if irq == serial::uart_irq() {
+        serial::handle_irq();
+    } else if !block::handle_irq(irq) {
+        log::warn!("[kernel] unexpected IRQ {}", irq);
+    }
+}
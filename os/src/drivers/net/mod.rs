@@ -0,0 +1,67 @@
+//! # 网络设备
+//!
+//! ## Overview
+//! `NetDevice` 是网卡的原始帧接口：发送一帧、取出一帧，不涉及协议栈。
+//! 用户态经设备文件 `/dev/net0`（见 `fs::dev`）收发原始帧。
+//! 启动时探测网卡并保存在 `NET_DEVICE` 中：
+//! - 先扫描 virtio-mmio 槽位（RISC-V QEMU）
+//! - 再查找 PCI 总线上的 virtio-net（LoongArch QEMU，或 RISC-V QEMU 的 `-device virtio-net-pci`）
+//!
+//! ## Assumptions
+//! - 至多使用一块网卡
+//!
+//! ## Safety
+//! - 设备内部状态由驱动自行加锁，`NetDevice` 可以在任意上下文中共享
+//!
+//! ## Invariants
+//! - 收发的帧都不含 FCS
+//!
+//! ## Behavior
+//! - 接口全部是非阻塞的，没有数据或没有空间时返回 `EAGAIN`
+//! - 可以用 QEMU 的 `-netdev user` 或两个 QEMU 之间的 `-netdev socket` 测试
+
+mod virtio_net;
+
+use virtio_net::VirtIONet;
+pub use virtio_net::MAX_FRAME_LEN;
+
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+
+pub trait NetDevice: Send + Sync {
+    /// 网卡的 MAC 地址
+    fn mac(&self) -> [u8; 6];
+    /// 发送一个以太网帧
+    fn transmit(&self, frame: &[u8]) -> Result<(), isize>;
+    /// 取出一个到达的帧，返回拷贝到 `buf` 中的字节数；`buf` 不够长时帧被截断
+    fn receive(&self, buf: &mut [u8]) -> Result<usize, isize>;
+    /// 应答设备中断，返回是否有中断待处理
+    fn ack_interrupt(&self) -> bool;
+}
+
+lazy_static! {
+    pub static ref NET_DEVICE: Option<Arc<dyn NetDevice>> = probe();
+}
+
+fn probe() -> Option<Arc<dyn NetDevice>> {
+    use crate::drivers::virtio::{mmio, pci, DEVICE_NET};
+    if let Some(transport) = mmio::probe(DEVICE_NET) {
+        return Some(Arc::new(VirtIONet::new(transport).ok()?));
+    }
+    let transport = pci::probe(DEVICE_NET)?;
+    Some(Arc::new(VirtIONet::new(transport).ok()?))
+}
+
+/// 探测网卡并打印结果
+pub fn init() {
+    match NET_DEVICE.as_ref() {
+        Some(device) => {
+            let mac = device.mac();
+            println!(
+                "[kernel] net: virtio-net {:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+                mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]
+            );
+        }
+        None => println!("[kernel] net: no network device"),
+    }
+}
@@ -0,0 +1,154 @@
+//! # virtio-net 驱动
+//!
+//! ## Overview
+//! 收发原始以太网帧。接收队列与发送队列各有 `QUEUE_SIZE` 个固定缓冲区，
+//! 缓冲区 `id` 与描述符 `id` 一一对应：
+//! - 接收缓冲区在初始化时全部提交给设备，取走一帧后立刻重新提交
+//! - 发送缓冲区在设备归还后回到空闲列表
+//!
+//! ## Assumptions
+//! - 不协商校验和卸载、GSO 与合并接收缓冲区，帧头全部为 0
+//!
+//! ## Safety
+//! - 缓冲区内存通过 `VirtIOHal::dma_alloc` 分配，在驱动存活期间不释放
+//!
+//! ## Invariants
+//! - 每个接收缓冲区要么在设备手中，要么正在被 `receive` 拷贝
+//!
+//! ## Behavior
+//! - 没有到达的帧时 `receive` 返回 `EAGAIN`，不会忙等
+//! - 发送队列满时 `transmit` 返回 `EAGAIN`
+
+use super::NetDevice;
+use crate::drivers::virtio::queue::VirtQueue;
+use crate::drivers::virtio::{Transport, VirtIOHal, F_VERSION_1};
+use crate::hal::PAGE_SIZE;
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{EAGAIN, EMSGSIZE};
+use alloc::vec::Vec;
+use virtio_drivers::Hal;
+
+const QUEUE_RECEIVE: u16 = 0;
+const QUEUE_TRANSMIT: u16 = 1;
+const QUEUE_SIZE: u16 = 16;
+
+/// 设备配置空间中提供 MAC 地址
+const F_MAC: u64 = 1 << 5;
+
+/// 每个缓冲区的大小，足够放下帧头与最大帧
+const BUF_SIZE: usize = 2048;
+/// 不含 FCS 的最大以太网帧长度
+pub const MAX_FRAME_LEN: usize = 1514;
+
+/// 设备不提供 MAC 时使用的本地管理地址
+const DEFAULT_MAC: [u8; 6] = [0x52, 0x54, 0x00, 0x12, 0x34, 0x56];
+
+struct VirtIONetInner<T: Transport> {
+    transport: T,
+    rx: VirtQueue,
+    tx: VirtQueue,
+    rx_buf: usize,
+    tx_buf: usize,
+    tx_free: Vec<u16>,
+}
+
+pub struct VirtIONet<T: Transport> {
+    mac: [u8; 6],
+    /// `virtio_net_hdr` 的长度：legacy 为 10，modern 多一个 `num_buffers` 为 12
+    hdr_len: usize,
+    inner: UPIntrFreeCell<VirtIONetInner<T>>,
+}
+
+impl<T: Transport> VirtIONet<T> {
+    pub fn new(mut transport: T) -> Result<Self, ()> {
+        let features = transport.begin_init(F_MAC | F_VERSION_1)?;
+        let mac = if features & F_MAC != 0 {
+            let mut mac = [0u8; 6];
+            for (i, byte) in mac.iter_mut().enumerate() {
+                *byte = transport.read_config(i);
+            }
+            mac
+        } else {
+            DEFAULT_MAC
+        };
+        let hdr_len = if features & F_VERSION_1 != 0 { 12 } else { 10 };
+        let mut rx = VirtQueue::new(&mut transport, QUEUE_RECEIVE, QUEUE_SIZE)?;
+        let mut tx = VirtQueue::new(&mut transport, QUEUE_TRANSMIT, QUEUE_SIZE)?;
+        // 收发都由调用者轮询，PCI 上的 INTx 可能与磁盘共享，不能让网卡一直拉着中断线
+        rx.disable_interrupts();
+        tx.disable_interrupts();
+        let rx_buf = Self::alloc_buffers(rx.size());
+        let tx_buf = Self::alloc_buffers(tx.size());
+        for id in 0..rx.size() {
+            rx.push(id, rx_buf + id as usize * BUF_SIZE, BUF_SIZE, true);
+        }
+        let tx_free = (0..tx.size()).collect();
+        transport.finish_init();
+        transport.notify(QUEUE_RECEIVE);
+        Ok(Self {
+            mac,
+            hdr_len,
+            inner: unsafe {
+                UPIntrFreeCell::new(VirtIONetInner {
+                    transport,
+                    rx,
+                    tx,
+                    rx_buf,
+                    tx_buf,
+                    tx_free,
+                })
+            },
+        })
+    }
+
+    /// 分配 `count` 个缓冲区，返回首个缓冲区的物理地址
+    fn alloc_buffers(count: u16) -> usize {
+        let pages = (count as usize * BUF_SIZE + PAGE_SIZE - 1) / PAGE_SIZE;
+        VirtIOHal::dma_alloc(pages)
+    }
+}
+
+impl<T: Transport> NetDevice for VirtIONet<T> {
+    fn mac(&self) -> [u8; 6] {
+        self.mac
+    }
+
+    fn transmit(&self, frame: &[u8]) -> Result<(), isize> {
+        if frame.len() > MAX_FRAME_LEN {
+            return Err(-EMSGSIZE);
+        }
+        let mut inner = self.inner.exclusive_access();
+        while let Some((id, _)) = inner.tx.pop_used() {
+            inner.tx_free.push(id);
+        }
+        let id = inner.tx_free.pop().ok_or(-EAGAIN)?;
+        let paddr = inner.tx_buf + id as usize * BUF_SIZE;
+        let buf = unsafe {
+            core::slice::from_raw_parts_mut(VirtIOHal::phys_to_virt(paddr) as *mut u8, BUF_SIZE)
+        };
+        buf[..self.hdr_len].fill(0);
+        buf[self.hdr_len..self.hdr_len + frame.len()].copy_from_slice(frame);
+        inner.tx.push(id, paddr, self.hdr_len + frame.len(), false);
+        inner.transport.notify(QUEUE_TRANSMIT);
+        Ok(())
+    }
+
+    fn receive(&self, buf: &mut [u8]) -> Result<usize, isize> {
+        let mut inner = self.inner.exclusive_access();
+        let (id, len) = inner.rx.pop_used().ok_or(-EAGAIN)?;
+        let paddr = inner.rx_buf + id as usize * BUF_SIZE;
+        let data = unsafe {
+            core::slice::from_raw_parts(VirtIOHal::phys_to_virt(paddr) as *const u8, BUF_SIZE)
+        };
+        let frame_len = len.saturating_sub(self.hdr_len).min(BUF_SIZE - self.hdr_len);
+        let copied = frame_len.min(buf.len());
+        buf[..copied].copy_from_slice(&data[self.hdr_len..self.hdr_len + copied]);
+        inner.rx.push(id, paddr, BUF_SIZE, true);
+        inner.transport.notify(QUEUE_RECEIVE);
+        Ok(copied)
+    }
+
+    fn ack_interrupt(&self) -> bool {
+        self.inner.exclusive_access().transport.ack_interrupt()
+    }
+}
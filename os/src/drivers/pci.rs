@@ -0,0 +1,414 @@
+//! # PCI 总线
+//!
+//! ## Overview
+//! 通过 ECAM 访问 `MACHINE.pci` 描述的 PCIe 主桥，启动时深度优先枚举所有总线：
+//! - 为每个功能的内存 BAR 从主桥的 32 位 MMIO 窗口中按大小对齐分配地址，
+//!   并打开内存访问与总线主控
+//! - 遇到 PCI-PCI 桥时分配下级总线号，枚举完下级总线后把其占用的 MMIO 区间写入桥的窗口
+//! - 按标准 INTx 轮转规则计算每个功能的中断号，写入 `Interrupt Line`
+//!
+//! 枚举结果保存在 `PCI_DEVICES` 中，设备驱动按厂商号、设备号或能力表查找自己的设备。
+//!
+//! ## Assumptions
+//! - 枚举在分页开启之后、任何 PCI 设备驱动初始化之前进行，固件分配的 BAR 全部被覆盖
+//! - MMIO 窗口中 CPU 地址与总线地址相同
+//!
+//! ## Safety
+//! - 配置空间与 BAR 通过 `mmio_addr` 换算出的地址访问，RISC-V 上需在内核页表中映射
+//!
+//! ## Invariants
+//! - 分配出的 BAR 互不重叠，且都落在 MMIO 窗口（RISC-V 上为已映射的前 `PCI_MEM_MAP_MAX` 字节）内
+//! - 桥下的设备占用的 MMIO 区间按 1 MiB 对齐，与桥的窗口粒度一致
+//!
+//! ## Behavior
+//! - I/O BAR 不分配，virtio 等设备只使用内存 BAR
+//! - 窗口耗尽时对应 BAR 不分配并打印警告，设备仍被登记
+
+use crate::hal::{PciHost, MACHINE};
+use alloc::vec::Vec;
+use core::fmt;
+use core::ptr::{read_volatile, write_volatile};
+use lazy_static::lazy_static;
+
+/// 配置空间寄存器偏移
+mod offsets {
+    pub const VENDOR_ID: usize = 0x00;
+    pub const DEVICE_ID: usize = 0x02;
+    pub const COMMAND: usize = 0x04;
+    pub const STATUS: usize = 0x06;
+    pub const CLASS: usize = 0x08;
+    pub const HEADER_TYPE: usize = 0x0e;
+    pub const BAR0: usize = 0x10;
+    pub const PRIMARY_BUS: usize = 0x18;
+    pub const SECONDARY_BUS: usize = 0x19;
+    pub const SUBORDINATE_BUS: usize = 0x1a;
+    pub const MEMORY_BASE: usize = 0x20;
+    pub const MEMORY_LIMIT: usize = 0x22;
+    pub const SUBSYSTEM_ID: usize = 0x2e;
+    pub const CAPABILITIES: usize = 0x34;
+    pub const INTERRUPT_LINE: usize = 0x3c;
+    pub const INTERRUPT_PIN: usize = 0x3d;
+}
+
+const COMMAND_MEMORY: u16 = 1 << 1;
+const COMMAND_BUS_MASTER: u16 = 1 << 2;
+const STATUS_CAPABILITIES: u16 = 1 << 4;
+
+const HEADER_TYPE_MASK: u8 = 0x7f;
+const HEADER_TYPE_BRIDGE: u8 = 1;
+const HEADER_MULTI_FUNCTION: u8 = 0x80;
+
+/// 桥的 MMIO 窗口粒度
+const BRIDGE_WINDOW_ALIGN: usize = 0x10_0000;
+
+/// 设备物理地址在内核中的访问地址
+pub fn mmio_addr(paddr: usize) -> usize {
+    #[cfg(feature = "loongarch")]
+    {
+        paddr | crate::hal::HIGH_BASE_EIGHT
+    }
+    #[cfg(feature = "riscv")]
+    {
+        paddr
+    }
+}
+
+fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// 总线号、设备号、功能号
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
+
+/// 一个 PCI 功能的配置空间
+#[derive(Clone, Copy)]
+struct Config {
+    base: usize,
+}
+
+impl Config {
+    fn new(host: &PciHost, addr: PciAddress) -> Self {
+        let offset = ((addr.bus as usize - host.bus_start) << 20)
+            | ((addr.device as usize) << 15)
+            | ((addr.function as usize) << 12);
+        Self {
+            base: mmio_addr(host.ecam.base) + offset,
+        }
+    }
+
+    fn read8(&self, offset: usize) -> u8 {
+        unsafe { read_volatile((self.base + offset) as *const u8) }
+    }
+
+    fn read16(&self, offset: usize) -> u16 {
+        unsafe { read_volatile((self.base + offset) as *const u16) }
+    }
+
+    fn read32(&self, offset: usize) -> u32 {
+        unsafe { read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write8(&self, offset: usize, value: u8) {
+        unsafe { write_volatile((self.base + offset) as *mut u8, value) }
+    }
+
+    fn write16(&self, offset: usize, value: u16) {
+        unsafe { write_volatile((self.base + offset) as *mut u16, value) }
+    }
+
+    fn write32(&self, offset: usize, value: u32) {
+        unsafe { write_volatile((self.base + offset) as *mut u32, value) }
+    }
+}
+
+/// 枚举得到的一个 PCI 功能
+#[derive(Clone)]
+pub struct PciDevice {
+    pub addr: PciAddress,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub subsystem_id: u16,
+    /// 类代码与子类代码
+    pub class: u8,
+    pub subclass: u8,
+    /// 已分配的内存 BAR `(物理地址, 大小)`
+    pub bars: [Option<(usize, usize)>; 6],
+    /// INTx 对应的中断号，0 表示不使用中断
+    pub irq: usize,
+    config: Config,
+}
+
+impl PciDevice {
+    pub fn read_config8(&self, offset: usize) -> u8 {
+        self.config.read8(offset)
+    }
+
+    pub fn read_config32(&self, offset: usize) -> u32 {
+        self.config.read32(offset)
+    }
+
+    /// 第 `index` 个 BAR 在内核中的访问地址
+    pub fn bar_addr(&self, index: usize) -> Option<usize> {
+        let (paddr, _) = (*self.bars.get(index)?)?;
+        Some(mmio_addr(paddr))
+    }
+
+    /// 能力表，依次返回 `(偏移, 能力 ID)`
+    pub fn capabilities(&self) -> impl Iterator<Item = (usize, u8)> + '_ {
+        let mut next = if self.config.read16(offsets::STATUS) & STATUS_CAPABILITIES != 0 {
+            self.config.read8(offsets::CAPABILITIES) as usize & !3
+        } else {
+            0
+        };
+        // 能力表最多 48 项，防止损坏的链表成环
+        let mut budget = 48;
+        core::iter::from_fn(move || {
+            if next == 0 || budget == 0 {
+                return None;
+            }
+            budget -= 1;
+            let offset = next;
+            let id = self.config.read8(offset);
+            next = self.config.read8(offset + 1) as usize & !3;
+            Some((offset, id))
+        })
+    }
+}
+
+/// 枚举过程的状态
+struct Scanner {
+    host: PciHost,
+    /// 下一个可分配的 MMIO 地址与窗口末尾
+    mem_next: usize,
+    mem_end: usize,
+    /// 下一个可分配的总线号与总线号上限（不含）
+    next_bus: usize,
+    bus_end: usize,
+    /// 从根总线到当前总线经过的桥的设备号
+    bridges: Vec<u8>,
+    devices: Vec<PciDevice>,
+}
+
+impl Scanner {
+    fn new(host: PciHost) -> Self {
+        let mut buses = host.ecam.size >> 20;
+        let mut mem_size = host.mem.1;
+        #[cfg(feature = "riscv")]
+        {
+            use crate::hal::{PCI_MAX_BUSES, PCI_MEM_MAP_MAX};
+            buses = buses.min(PCI_MAX_BUSES);
+            mem_size = mem_size.min(PCI_MEM_MAP_MAX);
+        }
+        Self {
+            host,
+            mem_next: host.mem.0,
+            mem_end: host.mem.0 + mem_size,
+            next_bus: host.bus_start + 1,
+            bus_end: (host.bus_start + buses).min(256),
+            bridges: Vec::new(),
+            devices: Vec::new(),
+        }
+    }
+
+    fn config(&self, bus: usize, device: usize, function: usize) -> Config {
+        Config::new(
+            &self.host,
+            PciAddress {
+                bus: bus as u8,
+                device: device as u8,
+                function: function as u8,
+            },
+        )
+    }
+
+    fn scan_bus(&mut self, bus: usize) {
+        for device in 0..32 {
+            let config = self.config(bus, device, 0);
+            if config.read16(offsets::VENDOR_ID) == 0xffff {
+                continue;
+            }
+            let functions = if config.read8(offsets::HEADER_TYPE) & HEADER_MULTI_FUNCTION != 0 {
+                8
+            } else {
+                1
+            };
+            for function in 0..functions {
+                let config = self.config(bus, device, function);
+                if config.read16(offsets::VENDOR_ID) != 0xffff {
+                    self.scan_function(bus, device, function, config);
+                }
+            }
+        }
+    }
+
+    fn scan_function(&mut self, bus: usize, device: usize, function: usize, config: Config) {
+        let header_type = config.read8(offsets::HEADER_TYPE) & HEADER_TYPE_MASK;
+        let bar_count = match header_type {
+            0 => 6,
+            HEADER_TYPE_BRIDGE => 2,
+            _ => 0,
+        };
+        let command = config.read16(offsets::COMMAND);
+        config.write16(offsets::COMMAND, command & !COMMAND_MEMORY);
+        let bars = self.assign_bars(config, bar_count);
+        if header_type == HEADER_TYPE_BRIDGE {
+            self.scan_bridge(bus, device, config);
+        }
+        config.write16(
+            offsets::COMMAND,
+            command | COMMAND_MEMORY | COMMAND_BUS_MASTER,
+        );
+
+        let irq = self.route_irq(device, config.read8(offsets::INTERRUPT_PIN));
+        config.write8(offsets::INTERRUPT_LINE, irq as u8);
+        let class = config.read32(offsets::CLASS);
+        self.devices.push(PciDevice {
+            addr: PciAddress {
+                bus: bus as u8,
+                device: device as u8,
+                function: function as u8,
+            },
+            vendor_id: config.read16(offsets::VENDOR_ID),
+            device_id: config.read16(offsets::DEVICE_ID),
+            subsystem_id: config.read16(offsets::SUBSYSTEM_ID),
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            bars,
+            irq,
+            config,
+        });
+    }
+
+    /// 测量并分配内存 BAR
+    fn assign_bars(&mut self, config: Config, count: usize) -> [Option<(usize, usize)>; 6] {
+        let mut bars = [None; 6];
+        let mut index = 0;
+        while index < count {
+            let offset = offsets::BAR0 + 4 * index;
+            let original = config.read32(offset);
+            // I/O BAR
+            if original & 1 != 0 {
+                index += 1;
+                continue;
+            }
+            let is_64bit = (original >> 1) & 0b11 == 0b10;
+            config.write32(offset, u32::MAX);
+            let mut mask = (config.read32(offset) & !0xf) as u64;
+            if is_64bit {
+                config.write32(offset + 4, u32::MAX);
+                mask |= (config.read32(offset + 4) as u64) << 32;
+            } else {
+                mask |= 0xffff_ffff_0000_0000;
+            }
+            let size = (!mask).wrapping_add(1) as usize;
+            let mut assigned = 0;
+            if mask as u32 != 0 {
+                let base = align_up(self.mem_next, size);
+                if base + size <= self.mem_end {
+                    self.mem_next = base + size;
+                    assigned = base;
+                    bars[index] = Some((base, size));
+                } else {
+                    log::warn!("[kernel] pci: no room for BAR{} ({:#x} bytes)", index, size);
+                }
+            }
+            config.write32(offset, assigned as u32);
+            if is_64bit {
+                config.write32(offset + 4, (assigned as u64 >> 32) as u32);
+                index += 2;
+            } else {
+                index += 1;
+            }
+        }
+        bars
+    }
+
+    /// 为桥分配下级总线号，枚举下级总线并设置桥的 MMIO 窗口
+    fn scan_bridge(&mut self, bus: usize, device: usize, config: Config) {
+        if self.next_bus >= self.bus_end {
+            log::warn!("[kernel] pci: out of bus numbers behind bridge");
+            return;
+        }
+        let secondary = self.next_bus;
+        self.next_bus += 1;
+        config.write8(offsets::PRIMARY_BUS, bus as u8);
+        config.write8(offsets::SECONDARY_BUS, secondary as u8);
+        config.write8(offsets::SUBORDINATE_BUS, 0xff);
+
+        self.mem_next = align_up(self.mem_next, BRIDGE_WINDOW_ALIGN);
+        let window_start = self.mem_next;
+        self.bridges.push(device as u8);
+        self.scan_bus(secondary);
+        self.bridges.pop();
+        self.mem_next = align_up(self.mem_next, BRIDGE_WINDOW_ALIGN).min(self.mem_end);
+
+        config.write8(offsets::SUBORDINATE_BUS, (self.next_bus - 1) as u8);
+        if self.mem_next > window_start {
+            config.write16(offsets::MEMORY_BASE, (window_start >> 16) as u16 & 0xfff0);
+            config.write16(
+                offsets::MEMORY_LIMIT,
+                ((self.mem_next - 1) >> 16) as u16 & 0xfff0,
+            );
+        } else {
+            // 基址大于上限表示关闭窗口
+            config.write16(offsets::MEMORY_BASE, 0xfff0);
+            config.write16(offsets::MEMORY_LIMIT, 0);
+        }
+    }
+
+    /// 引脚 `pin`（1～4 对应 INTA～INTD）经各级桥轮转后对应的中断号
+    fn route_irq(&self, device: usize, pin: u8) -> usize {
+        if pin == 0 || pin > 4 || self.host.irq_base == 0 {
+            return 0;
+        }
+        let mut slot = device;
+        let mut pin = pin as usize - 1;
+        for &bridge in self.bridges.iter().rev() {
+            pin = (slot + pin) % 4;
+            slot = bridge as usize;
+        }
+        self.host.irq_base + (slot + pin) % 4
+    }
+}
+
+lazy_static! {
+    /// 枚举得到的所有 PCI 功能
+    pub static ref PCI_DEVICES: Vec<PciDevice> = match MACHINE.pci {
+        Some(host) => {
+            let mut scanner = Scanner::new(host);
+            scanner.scan_bus(host.bus_start);
+            scanner.devices
+        }
+        None => Vec::new(),
+    };
+}
+
+/// 枚举 PCI 总线并打印结果
+pub fn init() {
+    if crate::cmdline::quiet() {
+        lazy_static::initialize(&PCI_DEVICES);
+        return;
+    }
+    for device in PCI_DEVICES.iter() {
+        println!(
+            "[kernel] pci {} {:04x}:{:04x} class {:02x}.{:02x} irq {}",
+            device.addr,
+            device.vendor_id,
+            device.device_id,
+            device.class,
+            device.subclass,
+            device.irq
+        );
+    }
+}
@@ -1 +1,72 @@
+//! # 串口
+//!
+//! ## Overview
+//! 控制台串口的中断处理：收到的字节交给 `fs::tty` 的行规程处理，
+//! 发送端由 `hal` 的控制台实现。
+//! - 串口接入了中断控制器（`MACHINE.uart` 的中断号非 0）：使能 NS16550A 的接收中断，
+//!   在中断中把 FIFO 中的数据全部取出，并让控制台改用发送中断
+//! - 否则（如 2K1000 开发板）由读终端的任务主动轮询，轮询后送出回显
+//! - 启用了 GDB 桩时，`gdb::MAGIC_KEY` 不交给终端，而是请求进入桩
+//!
+//! ## Assumptions
+//! - 控制台只有一个串口，输出经由 `hal::console_putchar`
+//!
+//! ## Safety
+//! - 寄存器地址来自 `MACHINE.uart`，没有时为平台常量 `UART_BASE`
+//!
+//! ## Invariants
+//! - 每次中断都会读空接收 FIFO，电平触发的中断随之撤销
+//!
+//! ## Behavior
+//! - `rx_interrupt()` 为 `false` 时终端读者用 `poll` 代替阻塞等待
+
 pub mod ns16550a;
+
+use crate::fs::TTY;
+use crate::hal::{console_flush, MACHINE, UART_BASE};
+use embedded_hal::serial::nb::Read;
+use ns16550a::Ns16550a;
+
+fn uart_base() -> usize {
+    MACHINE.uart.map_or(UART_BASE, |uart| uart.base)
+}
+
+/// 串口的中断号，0 表示没有接入中断控制器
+pub fn uart_irq() -> usize {
+    MACHINE.uart.map_or(0, |uart| uart.irq)
+}
+
+/// 串口接收是否由中断驱动
+pub fn rx_interrupt() -> bool {
+    uart_irq() != 0
+}
+
+/// 使能串口接收中断
+pub fn init() {
+    if rx_interrupt() {
+        Ns16550a::new(uart_base()).enable_rx_interrupt();
+        crate::hal::enable_irq(uart_irq());
+        crate::hal::console_enable_tx_interrupt();
+    }
+}
+
+/// 串口中断：接收数据并继续发送控制台缓冲区
+pub fn handle_irq() {
+    poll();
+    crate::hal::console_transmit();
+}
+
+/// 取出接收 FIFO 中的全部数据交给终端，由中断处理或轮询调用
+///
+/// 轮询时没有发送中断继续发送，取完后立即送出终端的回显
+pub fn poll() {
+    let mut uart = Ns16550a::new(uart_base());
+    while let Ok(byte) = uart.read() {
+        if !crate::gdb::magic_key(byte) {
+            TTY.receive(byte);
+        }
+    }
+    if !rx_interrupt() {
+        console_flush();
+    }
+}
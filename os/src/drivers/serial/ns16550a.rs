@@ -10,8 +10,65 @@ impl Ns16550a {
     pub fn new(base: usize) -> Self {
         Self { base }
     }
+
+    /// 通过暂存寄存器（SCR）探测 `base` 处是否真有串口：写入的值能读回才算存在
+    pub fn probe(&self) -> bool {
+        let scr = (self.base + offsets::SCR) as *mut u8;
+        unsafe {
+            let old = read_volatile(scr);
+            let present = [0x55u8, 0xAA].iter().all(|&pattern| {
+                write_volatile(scr, pattern);
+                read_volatile(scr) == pattern
+            });
+            write_volatile(scr, old);
+            present
+        }
+    }
+
+    /// 打开并清空收发 FIFO
+    pub fn enable_fifo(&mut self) {
+        unsafe {
+            write_volatile(
+                (self.base + offsets::FCR) as *mut u8,
+                masks::FCR_ENABLE | masks::FCR_CLEAR_RX | masks::FCR_CLEAR_TX,
+            );
+        }
+    }
+
+    /// 打开 FIFO 并使能接收中断，之后每收到数据都会触发一次外部中断
+    pub fn enable_rx_interrupt(&mut self) {
+        unsafe {
+            write_volatile((self.base + offsets::FCR) as *mut u8, masks::FCR_ENABLE);
+            write_volatile(
+                (self.base + offsets::MCR) as *mut u8,
+                masks::MCR_DTR | masks::MCR_RTS | masks::MCR_OUT2,
+            );
+        }
+        self.update_ier(masks::IER_RDA, true);
+    }
+
+    /// 使能或关闭发送 FIFO 空中断
+    pub fn set_tx_interrupt(&mut self, enabled: bool) {
+        self.update_ier(masks::IER_THRI, enabled);
+    }
+
+    /// 发送 FIFO 是否已空，空时可以连续写入 `FIFO_SIZE` 个字节
+    pub fn tx_empty(&self) -> bool {
+        unsafe { read_volatile((self.base + offsets::LSR) as *const u8) & masks::THRE != 0 }
+    }
+
+    fn update_ier(&mut self, mask: u8, set: bool) {
+        let ier = (self.base + offsets::IER) as *mut u8;
+        unsafe {
+            let old = read_volatile(ier);
+            write_volatile(ier, if set { old | mask } else { old & !mask });
+        }
+    }
 }
 
+/// 收发 FIFO 的深度
+pub const FIFO_SIZE: usize = 16;
+
 impl embedded_hal::serial::ErrorType for Ns16550a {
     type Error = Infallible;
 }
@@ -62,6 +119,7 @@ mod offsets {
     pub const LCR: usize = 0x3;
     pub const MCR: usize = 0x4;
     pub const LSR: usize = 0x5;
+    pub const SCR: usize = 0x7;
 
     pub const DLL: usize = 0x0;
     pub const DLH: usize = 0x1;
@@ -70,4 +128,15 @@ mod offsets {
 mod masks {
     pub const THRE: u8 = 1 << 5;
     pub const DR: u8 = 1;
+    /// 接收数据可用中断
+    pub const IER_RDA: u8 = 1;
+    /// 发送保持寄存器空中断
+    pub const IER_THRI: u8 = 1 << 1;
+    pub const FCR_ENABLE: u8 = 1;
+    pub const FCR_CLEAR_RX: u8 = 1 << 1;
+    pub const FCR_CLEAR_TX: u8 = 1 << 2;
+    pub const MCR_DTR: u8 = 1;
+    pub const MCR_RTS: u8 = 1 << 1;
+    /// PC 兼容的 16550 需要 OUT2 才会把中断送出芯片
+    pub const MCR_OUT2: u8 = 1 << 3;
 }
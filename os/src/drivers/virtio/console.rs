@@ -0,0 +1,112 @@
+//! # virtio-console 驱动
+//!
+//! ## Overview
+//! 只使用 0 号端口（`hvc0`）：接收队列 `receiveq0` 与发送队列 `transmitq0`。
+//! 启动时探测到的设备保存在 `VIRTIO_CONSOLE` 中，作为串口之外的第二个字符设备：
+//! - `write` 把数据拷贝到发送缓冲区并等待设备取走
+//! - `read` 取出一个到达的输入缓冲区，随后立刻重新提交
+//!
+//! ## Assumptions
+//! - 不协商 `VIRTIO_CONSOLE_F_MULTIPORT`，没有控制队列
+//!
+//! ## Safety
+//! - 缓冲区内存通过 `VirtIOHal::dma_alloc` 分配，在驱动存活期间不释放
+//!
+//! ## Invariants
+//! - 接收缓冲区要么在设备手中，要么正在被 `read` 拷贝
+//!
+//! ## Behavior
+//! - `read` 没有输入时返回 0，不会忙等；`buf` 不够长时多出的输入被丢弃
+
+use super::queue::VirtQueue;
+use super::{Transport, DEVICE_CONSOLE, F_VERSION_1};
+use crate::drivers::virtio::VirtIOHal;
+use crate::hal::PAGE_SIZE;
+use crate::sync::UPIntrFreeCell;
+use alloc::boxed::Box;
+use core::hint::spin_loop;
+use lazy_static::lazy_static;
+use virtio_drivers::Hal;
+
+const QUEUE_RECEIVE: u16 = 0;
+const QUEUE_TRANSMIT: u16 = 1;
+
+struct VirtIOConsoleInner {
+    transport: Box<dyn Transport>,
+    rx: VirtQueue,
+    tx: VirtQueue,
+    rx_buf: usize,
+    tx_buf: usize,
+}
+
+pub struct VirtIOConsole {
+    inner: UPIntrFreeCell<VirtIOConsoleInner>,
+}
+
+impl VirtIOConsole {
+    pub fn new(mut transport: Box<dyn Transport>) -> Result<Self, ()> {
+        transport.begin_init(F_VERSION_1)?;
+        let mut rx = VirtQueue::new(transport.as_mut(), QUEUE_RECEIVE, 1)?;
+        let mut tx = VirtQueue::new(transport.as_mut(), QUEUE_TRANSMIT, 1)?;
+        rx.disable_interrupts();
+        tx.disable_interrupts();
+        let rx_buf = VirtIOHal::dma_alloc(1);
+        let tx_buf = VirtIOHal::dma_alloc(1);
+        rx.push(0, rx_buf, PAGE_SIZE, true);
+        transport.finish_init();
+        transport.notify(QUEUE_RECEIVE);
+        Ok(Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(VirtIOConsoleInner {
+                    transport,
+                    rx,
+                    tx,
+                    rx_buf,
+                    tx_buf,
+                })
+            },
+        })
+    }
+
+    /// 输出 `data`，返回时设备已经取走全部数据
+    pub fn write(&self, data: &[u8]) {
+        let mut inner = self.inner.exclusive_access();
+        let tx_buf = inner.tx_buf;
+        for chunk in data.chunks(PAGE_SIZE) {
+            unsafe {
+                core::slice::from_raw_parts_mut(
+                    VirtIOHal::phys_to_virt(tx_buf) as *mut u8,
+                    chunk.len(),
+                )
+                .copy_from_slice(chunk);
+            }
+            inner.tx.push(0, tx_buf, chunk.len(), false);
+            inner.transport.notify(QUEUE_TRANSMIT);
+            while inner.tx.pop_used().is_none() {
+                spin_loop();
+            }
+        }
+    }
+
+    /// 取出到达的输入，返回拷贝到 `buf` 中的字节数
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let Some((_, len)) = inner.rx.pop_used() else {
+            return 0;
+        };
+        let rx_buf = inner.rx_buf;
+        let copied = len.min(PAGE_SIZE).min(buf.len());
+        let data = unsafe {
+            core::slice::from_raw_parts(VirtIOHal::phys_to_virt(rx_buf) as *const u8, copied)
+        };
+        buf[..copied].copy_from_slice(data);
+        inner.rx.push(0, rx_buf, PAGE_SIZE, true);
+        inner.transport.notify(QUEUE_RECEIVE);
+        copied
+    }
+}
+
+lazy_static! {
+    pub static ref VIRTIO_CONSOLE: Option<VirtIOConsole> =
+        super::probe(DEVICE_CONSOLE).and_then(|transport| VirtIOConsole::new(transport).ok());
+}
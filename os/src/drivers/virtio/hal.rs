@@ -1,4 +1,14 @@
-use crate::drivers::block::block_dev::BlockDevice;
+//! # VirtIO DMA 内存
+//!
+//! ## Overview
+//! 实现 `virtio_drivers::Hal`，给所有 VirtIO 驱动分配 DMA 内存并做地址转换。
+//!
+//! ## Assumptions
+//! - 内核地址空间中物理内存恒等映射，`phys_to_virt` 不需要查页表
+//!
+//! ## Invariants
+//! - `dma_alloc` 分配的物理页在物理上连续，其 `FrameTracker` 保存在 `QUEUE_FRAMES` 中
+
 use crate::hal::PageTableImpl;
 use crate::mm;
 use crate::mm::{
@@ -7,39 +17,6 @@ use crate::mm::{
 use crate::sync::UPIntrFreeCell;
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
-use virtio_drivers::VirtIOBlk;
-
-const VIRTIO0: usize = 0x10001000;
-
-pub struct VirtIOBlock(UPIntrFreeCell<VirtIOBlk<'static, VirtIOHal>>);
-
-impl BlockDevice for VirtIOBlock {
-    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
-        self.0
-            .exclusive_access()
-            .read_block(block_id, buf)
-            .expect("Error when reading VirtIOBlk");
-    }
-
-    fn write_block(&self, block_id: usize, buf: &[u8]) {
-        self.0
-            .exclusive_access()
-            .write_block(block_id, buf)
-            .expect("Error when writing VirtIOBlk");
-    }
-}
-
-impl VirtIOBlock {
-    #[allow(unused)]
-    pub fn new() -> Self {
-        unsafe {
-            Self(UPIntrFreeCell::new(
-                VirtIOBlk::<VirtIOHal>::new(&mut *(VIRTIO0 as *mut virtio_drivers::VirtIOHeader))
-                    .unwrap(),
-            ))
-        }
-    }
-}
 
 lazy_static! {
     static ref QUEUE_FRAMES: UPIntrFreeCell<Vec<FrameTracker>> =
@@ -0,0 +1,189 @@
+//! # virtio-mmio 传输层
+//!
+//! ## Overview
+//! virtio-mmio 槽位的地址与中断号来自 `hal::MACHINE`（RISC-V 上由设备树给出），
+//! 命令行中 `bus=virtio-mmio-bus.N` 决定设备所在槽位。
+//! `find_all` / `find` / `probe` 按设备类型扫描槽位，`MmioTransport` 同时支持 legacy（version 1）
+//! 与 modern（version 2）寄存器布局。
+//!
+//! ## Assumptions
+//! - 槽位所在区间已经在 `MACHINE.mmio()` 中映射进内核页表
+//!
+//! ## Safety
+//! - 所有寄存器访问都是 volatile 的
+//!
+//! ## Invariants
+//! - legacy 设备的队列按 4096 字节对齐，由 `queue` 保证内存布局
+//!
+//! ## Behavior
+//! - 空槽位的设备类型为 0，探测时跳过
+
+use super::Transport;
+use crate::hal::{MmioDevice, MACHINE};
+use core::ptr::{read_volatile, write_volatile};
+
+/// "virt" 的小端表示
+const MAGIC: u32 = 0x7472_6976;
+/// legacy 接口使用的页大小
+const LEGACY_PAGE_SIZE: u32 = 4096;
+
+#[allow(unused)]
+mod offsets {
+    pub const MAGIC: usize = 0x000;
+    pub const VERSION: usize = 0x004;
+    pub const DEVICE_ID: usize = 0x008;
+    pub const VENDOR_ID: usize = 0x00c;
+    pub const DEVICE_FEATURES: usize = 0x010;
+    pub const DEVICE_FEATURES_SEL: usize = 0x014;
+    pub const DRIVER_FEATURES: usize = 0x020;
+    pub const DRIVER_FEATURES_SEL: usize = 0x024;
+    pub const GUEST_PAGE_SIZE: usize = 0x028;
+    pub const QUEUE_SEL: usize = 0x030;
+    pub const QUEUE_NUM_MAX: usize = 0x034;
+    pub const QUEUE_NUM: usize = 0x038;
+    pub const QUEUE_ALIGN: usize = 0x03c;
+    pub const QUEUE_PFN: usize = 0x040;
+    pub const QUEUE_READY: usize = 0x044;
+    pub const QUEUE_NOTIFY: usize = 0x050;
+    pub const INTERRUPT_STATUS: usize = 0x060;
+    pub const INTERRUPT_ACK: usize = 0x064;
+    pub const STATUS: usize = 0x070;
+    pub const QUEUE_DESC_LOW: usize = 0x080;
+    pub const QUEUE_DESC_HIGH: usize = 0x084;
+    pub const QUEUE_DRIVER_LOW: usize = 0x090;
+    pub const QUEUE_DRIVER_HIGH: usize = 0x094;
+    pub const QUEUE_DEVICE_LOW: usize = 0x0a0;
+    pub const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+    pub const CONFIG: usize = 0x100;
+}
+
+pub struct MmioTransport {
+    base: usize,
+    version: u32,
+    device_type: u32,
+}
+
+impl MmioTransport {
+    /// 检查 `base` 处是否有 VirtIO 设备
+    ///
+    /// # Safety
+    /// `base` 必须是已映射的 virtio-mmio 槽位
+    pub unsafe fn new(base: usize) -> Option<Self> {
+        let read = |offset: usize| read_volatile((base + offset) as *const u32);
+        if read(offsets::MAGIC) != MAGIC {
+            return None;
+        }
+        let version = read(offsets::VERSION);
+        let device_type = read(offsets::DEVICE_ID);
+        if !(version == 1 || version == 2) || device_type == 0 {
+            return None;
+        }
+        Some(Self {
+            base,
+            version,
+            device_type,
+        })
+    }
+
+    fn read(&self, offset: usize) -> u32 {
+        unsafe { read_volatile((self.base + offset) as *const u32) }
+    }
+
+    fn write(&mut self, offset: usize, value: u32) {
+        unsafe { write_volatile((self.base + offset) as *mut u32, value) }
+    }
+}
+
+impl Transport for MmioTransport {
+    fn device_type(&self) -> u32 {
+        self.device_type
+    }
+
+    fn is_legacy(&self) -> bool {
+        self.version == 1
+    }
+
+    fn device_features(&mut self) -> u64 {
+        self.write(offsets::DEVICE_FEATURES_SEL, 0);
+        let low = self.read(offsets::DEVICE_FEATURES) as u64;
+        self.write(offsets::DEVICE_FEATURES_SEL, 1);
+        let high = self.read(offsets::DEVICE_FEATURES) as u64;
+        (high << 32) | low
+    }
+
+    fn set_driver_features(&mut self, features: u64) {
+        self.write(offsets::DRIVER_FEATURES_SEL, 0);
+        self.write(offsets::DRIVER_FEATURES, features as u32);
+        self.write(offsets::DRIVER_FEATURES_SEL, 1);
+        self.write(offsets::DRIVER_FEATURES, (features >> 32) as u32);
+        if self.is_legacy() {
+            self.write(offsets::GUEST_PAGE_SIZE, LEGACY_PAGE_SIZE);
+        }
+    }
+
+    fn status(&self) -> u32 {
+        self.read(offsets::STATUS)
+    }
+
+    fn set_status(&mut self, status: u32) {
+        self.write(offsets::STATUS, status);
+    }
+
+    fn max_queue_size(&mut self, queue: u16) -> u16 {
+        self.write(offsets::QUEUE_SEL, queue as u32);
+        self.read(offsets::QUEUE_NUM_MAX).min(u16::MAX as u32) as u16
+    }
+
+    fn setup_queue(&mut self, queue: u16, size: u16, desc: usize, avail: usize, used: usize) {
+        self.write(offsets::QUEUE_SEL, queue as u32);
+        self.write(offsets::QUEUE_NUM, size as u32);
+        if self.is_legacy() {
+            // legacy 接口只接受一个页号，三段内存必须连续
+            debug_assert_eq!(desc % LEGACY_PAGE_SIZE as usize, 0);
+            self.write(offsets::QUEUE_ALIGN, LEGACY_PAGE_SIZE);
+            self.write(offsets::QUEUE_PFN, (desc / LEGACY_PAGE_SIZE as usize) as u32);
+        } else {
+            self.write(offsets::QUEUE_DESC_LOW, desc as u32);
+            self.write(offsets::QUEUE_DESC_HIGH, (desc >> 32) as u32);
+            self.write(offsets::QUEUE_DRIVER_LOW, avail as u32);
+            self.write(offsets::QUEUE_DRIVER_HIGH, (avail >> 32) as u32);
+            self.write(offsets::QUEUE_DEVICE_LOW, used as u32);
+            self.write(offsets::QUEUE_DEVICE_HIGH, (used >> 32) as u32);
+            self.write(offsets::QUEUE_READY, 1);
+        }
+    }
+
+    fn notify(&mut self, queue: u16) {
+        self.write(offsets::QUEUE_NOTIFY, queue as u32);
+    }
+
+    fn ack_interrupt(&mut self) -> bool {
+        let pending = self.read(offsets::INTERRUPT_STATUS);
+        if pending != 0 {
+            self.write(offsets::INTERRUPT_ACK, pending);
+        }
+        pending != 0
+    }
+
+    fn read_config(&self, offset: usize) -> u8 {
+        unsafe { read_volatile((self.base + offsets::CONFIG + offset) as *const u8) }
+    }
+}
+
+/// 按槽位顺序返回所有类型为 `device_type` 的设备所在槽位
+pub fn find_all(device_type: u32) -> impl Iterator<Item = MmioDevice> {
+    MACHINE.virtio_mmio().iter().copied().filter(move |slot| {
+        unsafe { MmioTransport::new(slot.base) }
+            .map_or(false, |transport| transport.device_type() == device_type)
+    })
+}
+
+/// 扫描所有槽位，返回第一个类型为 `device_type` 的设备所在槽位
+pub fn find(device_type: u32) -> Option<MmioDevice> {
+    find_all(device_type).next()
+}
+
+/// 扫描所有槽位，返回第一个类型为 `device_type` 的设备
+pub fn probe(device_type: u32) -> Option<MmioTransport> {
+    find(device_type).and_then(|slot| unsafe { MmioTransport::new(slot.base) })
+}
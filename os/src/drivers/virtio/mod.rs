@@ -0,0 +1,122 @@
+//! # VirtIO 公共部分
+//!
+//! ## Overview
+//! `virtio-drivers` 只提供 MMIO 传输层，且收包接口会忙等。
+//! 本模块给内核自带的 VirtIO 驱动（virtio-blk、virtio-net、virtio-rng、virtio-console）提供：
+//! - `Transport`：传输层抽象，驱动不关心设备挂在 MMIO 还是 PCI 上
+//! - `mmio`：virtio-mmio 传输层（legacy 与 modern 两个版本）及探测
+//! - `pci`：virtio-pci 传输层（modern 接口）及探测
+//! - `queue`：split virtqueue
+//! - `hal`：DMA 内存分配与地址转换（`VirtIOHal`）
+//!
+//! 没有独立设备类别的小设备驱动也放在这里：`rng` 与 `console`。
+//!
+//! ## Assumptions
+//! - DMA 内存通过 `VirtIOHal::dma_alloc` 分配
+//!
+//! ## Safety
+//! - 传输层直接读写设备寄存器，地址必须来自探测结果
+//!
+//! ## Invariants
+//! - 设备状态只按 ACKNOWLEDGE → DRIVER → FEATURES_OK → DRIVER_OK 的顺序推进
+//!
+//! ## Behavior
+//! - 特性协商失败时设备被置为 FAILED，驱动初始化返回错误
+
+pub mod console;
+mod hal;
+pub mod mmio;
+pub mod pci;
+pub mod queue;
+pub mod rng;
+
+use alloc::boxed::Box;
+
+pub use hal::VirtIOHal;
+
+/// VirtIO 设备类型
+pub const DEVICE_NET: u32 = 1;
+pub const DEVICE_BLOCK: u32 = 2;
+pub const DEVICE_CONSOLE: u32 = 3;
+pub const DEVICE_RNG: u32 = 4;
+
+/// 设备状态位
+pub const STATUS_ACKNOWLEDGE: u32 = 1;
+pub const STATUS_DRIVER: u32 = 2;
+pub const STATUS_DRIVER_OK: u32 = 4;
+pub const STATUS_FEATURES_OK: u32 = 8;
+pub const STATUS_FAILED: u32 = 128;
+
+/// 与设备类型无关的特性位
+pub const F_VERSION_1: u64 = 1 << 32;
+
+/// VirtIO 传输层
+pub trait Transport: Send {
+    /// 设备类型，见 `DEVICE_*`
+    fn device_type(&self) -> u32;
+    /// 是否为 VirtIO 1.0 之前的 legacy 接口
+    fn is_legacy(&self) -> bool;
+    fn device_features(&mut self) -> u64;
+    fn set_driver_features(&mut self, features: u64);
+    fn status(&self) -> u32;
+    fn set_status(&mut self, status: u32);
+    /// 队列支持的最大长度，0 表示队列不存在
+    fn max_queue_size(&mut self, queue: u16) -> u16;
+    /// 把队列的三段内存告知设备并启用队列
+    fn setup_queue(&mut self, queue: u16, size: u16, desc: usize, avail: usize, used: usize);
+    fn notify(&mut self, queue: u16);
+    /// 读取并应答中断，返回是否有中断待处理
+    fn ack_interrupt(&mut self) -> bool;
+    /// 读取设备配置空间
+    fn read_config(&self, offset: usize) -> u8;
+
+    /// 完成设备初始化的前半段：复位、应答、协商特性
+    ///
+    /// `wanted` 为驱动支持的特性，返回协商后的特性
+    fn begin_init(&mut self, wanted: u64) -> Result<u64, ()> {
+        self.set_status(0);
+        self.set_status(STATUS_ACKNOWLEDGE);
+        self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER);
+        let mut features = self.device_features() & wanted;
+        if self.is_legacy() {
+            features &= !F_VERSION_1;
+        } else if features & F_VERSION_1 == 0 {
+            self.set_status(STATUS_FAILED);
+            return Err(());
+        }
+        self.set_driver_features(features);
+        if !self.is_legacy() {
+            self.set_status(STATUS_ACKNOWLEDGE | STATUS_DRIVER | STATUS_FEATURES_OK);
+            if self.status() & STATUS_FEATURES_OK == 0 {
+                self.set_status(STATUS_FAILED);
+                return Err(());
+            }
+        }
+        Ok(features)
+    }
+
+    /// 队列配置完成后调用，设备开始工作
+    fn finish_init(&mut self) {
+        let status = self.status();
+        self.set_status(status | STATUS_DRIVER_OK);
+    }
+}
+
+/// 依次在 virtio-mmio 槽位与 PCI 总线上查找第一个类型为 `device_type` 的设备
+pub fn probe(device_type: u32) -> Option<Box<dyn Transport>> {
+    if let Some(transport) = mmio::probe(device_type) {
+        return Some(Box::new(transport));
+    }
+    pci::probe(device_type).map(|transport| Box::new(transport) as Box<dyn Transport>)
+}
+
+/// 初始化没有独立设备类别的 VirtIO 设备并打印结果
+pub fn init() {
+    let quiet = crate::cmdline::quiet();
+    if rng::VIRTIO_RNG.is_some() && !quiet {
+        println!("[kernel] virtio-rng ready");
+    }
+    if console::VIRTIO_CONSOLE.is_some() && !quiet {
+        println!("[kernel] virtio-console ready");
+    }
+}
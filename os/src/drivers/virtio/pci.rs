@@ -0,0 +1,210 @@
+//! # virtio-pci 传输层
+//!
+//! ## Overview
+//! 在 `drivers::pci` 枚举出的设备中查找 VirtIO 设备，按 VirtIO 1.0 的 modern 接口访问：
+//! 厂商专用能力（cfg_type 1～4）分别给出公共配置、通知、中断状态与设备配置所在的 BAR 与偏移。
+//! - 设备号 `0x1040 + 类型` 为 modern 设备
+//! - 设备号 `0x1000..0x1040` 为 transitional 设备，类型由子系统号给出，同样通过能力访问
+//!
+//! ## Assumptions
+//! - 所需的能力所在的 BAR 都已由 PCI 枚举分配
+//!
+//! ## Safety
+//! - 所有寄存器访问都是 volatile 的
+//!
+//! ## Invariants
+//! - 选择寄存器（特性、队列）与随后的读写在同一次调用中完成
+//!
+//! ## Behavior
+//! - 不使用 MSI-X，中断经 INTx 送达，读取 ISR 即应答
+//! - 缺少任意一种必需能力的设备被忽略
+
+use super::Transport;
+use crate::drivers::pci::{PciDevice, PCI_DEVICES};
+use core::ptr::{read_volatile, write_volatile};
+
+const VIRTIO_VENDOR_ID: u16 = 0x1af4;
+/// 厂商专用能力
+const CAP_VENDOR: u8 = 0x09;
+
+/// 能力中的 `cfg_type`
+const CFG_COMMON: u8 = 1;
+const CFG_NOTIFY: u8 = 2;
+const CFG_ISR: u8 = 3;
+const CFG_DEVICE: u8 = 4;
+
+/// 公共配置结构中的偏移
+#[allow(unused)]
+mod common {
+    pub const DEVICE_FEATURE_SELECT: usize = 0x00;
+    pub const DEVICE_FEATURE: usize = 0x04;
+    pub const DRIVER_FEATURE_SELECT: usize = 0x08;
+    pub const DRIVER_FEATURE: usize = 0x0c;
+    pub const MSIX_CONFIG: usize = 0x10;
+    pub const NUM_QUEUES: usize = 0x12;
+    pub const DEVICE_STATUS: usize = 0x14;
+    pub const CONFIG_GENERATION: usize = 0x15;
+    pub const QUEUE_SELECT: usize = 0x16;
+    pub const QUEUE_SIZE: usize = 0x18;
+    pub const QUEUE_MSIX_VECTOR: usize = 0x1a;
+    pub const QUEUE_ENABLE: usize = 0x1c;
+    pub const QUEUE_NOTIFY_OFF: usize = 0x1e;
+    pub const QUEUE_DESC: usize = 0x20;
+    pub const QUEUE_DRIVER: usize = 0x28;
+    pub const QUEUE_DEVICE: usize = 0x30;
+}
+
+/// PCI 设备的 VirtIO 设备类型，不是 VirtIO 设备时返回 `None`
+pub fn device_type(device: &PciDevice) -> Option<u32> {
+    if device.vendor_id != VIRTIO_VENDOR_ID {
+        return None;
+    }
+    match device.device_id {
+        0x1000..=0x103f => Some(device.subsystem_id as u32),
+        0x1040..=0x107f => Some((device.device_id - 0x1040) as u32),
+        _ => None,
+    }
+}
+
+pub struct PciTransport {
+    device_type: u32,
+    common: usize,
+    notify: usize,
+    notify_multiplier: usize,
+    isr: usize,
+    config: usize,
+}
+
+impl PciTransport {
+    /// 解析设备的 VirtIO 能力，`device` 不是 VirtIO 设备或能力不全时返回 `None`
+    pub fn new(device: &PciDevice) -> Option<Self> {
+        let device_type = device_type(device)?;
+        let (mut common, mut notify, mut isr, mut config) = (None, None, None, None);
+        let mut notify_multiplier = 0;
+        for (offset, id) in device.capabilities() {
+            if id != CAP_VENDOR {
+                continue;
+            }
+            let cfg_type = device.read_config8(offset + 3);
+            let bar = device.read_config8(offset + 4) as usize;
+            let Some(base) = device.bar_addr(bar) else {
+                continue;
+            };
+            let addr = base + device.read_config32(offset + 8) as usize;
+            // 同一类型的能力可能出现多次，使用第一个
+            match cfg_type {
+                CFG_COMMON if common.is_none() => common = Some(addr),
+                CFG_NOTIFY if notify.is_none() => {
+                    notify = Some(addr);
+                    notify_multiplier = device.read_config32(offset + 16) as usize;
+                }
+                CFG_ISR if isr.is_none() => isr = Some(addr),
+                CFG_DEVICE if config.is_none() => config = Some(addr),
+                _ => {}
+            }
+        }
+        Some(Self {
+            device_type,
+            common: common?,
+            notify: notify?,
+            notify_multiplier,
+            isr: isr?,
+            // 没有设备配置的设备（如 virtio-rng）不需要这一项
+            config: config.unwrap_or(0),
+        })
+    }
+
+    fn read<T: Copy>(&self, offset: usize) -> T {
+        unsafe { read_volatile((self.common + offset) as *const T) }
+    }
+
+    fn write<T: Copy>(&mut self, offset: usize, value: T) {
+        unsafe { write_volatile((self.common + offset) as *mut T, value) }
+    }
+
+    /// 64 位寄存器分两次 32 位写入
+    fn write64(&mut self, offset: usize, value: usize) {
+        self.write(offset, value as u32);
+        self.write(offset + 4, (value as u64 >> 32) as u32);
+    }
+}
+
+impl Transport for PciTransport {
+    fn device_type(&self) -> u32 {
+        self.device_type
+    }
+
+    fn is_legacy(&self) -> bool {
+        false
+    }
+
+    fn device_features(&mut self) -> u64 {
+        self.write(common::DEVICE_FEATURE_SELECT, 0u32);
+        let low = self.read::<u32>(common::DEVICE_FEATURE) as u64;
+        self.write(common::DEVICE_FEATURE_SELECT, 1u32);
+        let high = self.read::<u32>(common::DEVICE_FEATURE) as u64;
+        (high << 32) | low
+    }
+
+    fn set_driver_features(&mut self, features: u64) {
+        self.write(common::DRIVER_FEATURE_SELECT, 0u32);
+        self.write(common::DRIVER_FEATURE, features as u32);
+        self.write(common::DRIVER_FEATURE_SELECT, 1u32);
+        self.write(common::DRIVER_FEATURE, (features >> 32) as u32);
+    }
+
+    fn status(&self) -> u32 {
+        self.read::<u8>(common::DEVICE_STATUS) as u32
+    }
+
+    fn set_status(&mut self, status: u32) {
+        self.write(common::DEVICE_STATUS, status as u8);
+    }
+
+    fn max_queue_size(&mut self, queue: u16) -> u16 {
+        if queue >= self.read::<u16>(common::NUM_QUEUES) {
+            return 0;
+        }
+        self.write(common::QUEUE_SELECT, queue);
+        self.read(common::QUEUE_SIZE)
+    }
+
+    fn setup_queue(&mut self, queue: u16, size: u16, desc: usize, avail: usize, used: usize) {
+        self.write(common::QUEUE_SELECT, queue);
+        self.write(common::QUEUE_SIZE, size);
+        self.write64(common::QUEUE_DESC, desc);
+        self.write64(common::QUEUE_DRIVER, avail);
+        self.write64(common::QUEUE_DEVICE, used);
+        self.write(common::QUEUE_ENABLE, 1u16);
+    }
+
+    fn notify(&mut self, queue: u16) {
+        self.write(common::QUEUE_SELECT, queue);
+        let offset = self.read::<u16>(common::QUEUE_NOTIFY_OFF) as usize;
+        let addr = self.notify + offset * self.notify_multiplier;
+        unsafe { write_volatile(addr as *mut u16, queue) };
+    }
+
+    fn ack_interrupt(&mut self) -> bool {
+        // 读取 ISR 即清除中断
+        let pending = unsafe { read_volatile(self.isr as *const u8) };
+        pending != 0
+    }
+
+    fn read_config(&self, offset: usize) -> u8 {
+        assert!(self.config != 0, "virtio-pci device has no device config");
+        unsafe { read_volatile((self.config + offset) as *const u8) }
+    }
+}
+
+/// 按枚举顺序返回所有类型为 `device_type` 的 VirtIO PCI 设备
+pub fn find_all(device_type: u32) -> impl Iterator<Item = &'static PciDevice> {
+    PCI_DEVICES
+        .iter()
+        .filter(move |device| self::device_type(device) == Some(device_type))
+}
+
+/// 返回第一个类型为 `device_type` 的设备
+pub fn probe(device_type: u32) -> Option<PciTransport> {
+    find_all(device_type).find_map(PciTransport::new)
+}
@@ -0,0 +1,174 @@
+//! # split virtqueue
+//!
+//! ## Overview
+//! 描述符表、可用环、已用环放在同一段 DMA 内存里，
+//! 布局满足 legacy 接口的要求（已用环按页对齐），modern 接口同样可用。
+//!
+//! 驱动以“槽位”管理缓冲区：描述符 `id` 与驱动自己的第 `id` 个缓冲区一一对应，
+//! 每次请求占一个描述符（`push`），或者占从 `head` 起连续的若干个描述符组成的链（`push_chain`），
+//! 设备归还时只给出链头。
+//!
+//! ## Assumptions
+//! - 同一个描述符在被设备归还之前不会被再次提交，由驱动保证
+//!
+//! ## Safety
+//! - 环内存可能被设备并发修改，全部使用 volatile 访问并以 fence 排序
+//!
+//! ## Invariants
+//! - `avail_idx - last_used` 不超过队列长度
+//!
+//! ## Behavior
+//! - 队列销毁时归还 DMA 内存
+
+use super::{Transport, VirtIOHal};
+use crate::hal::PAGE_SIZE;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{fence, Ordering};
+use virtio_drivers::Hal;
+
+/// 描述符链未结束
+const DESC_F_NEXT: u16 = 1;
+/// 描述符由设备写入
+const DESC_F_WRITE: u16 = 2;
+/// 可用环标志：不需要设备发送中断
+const AVAIL_F_NO_INTERRUPT: u16 = 1;
+
+const DESC_SIZE: usize = 16;
+
+pub struct VirtQueue {
+    size: u16,
+    paddr: usize,
+    pages: usize,
+    desc: usize,
+    avail: usize,
+    used: usize,
+    /// 下一个要写入可用环的位置
+    avail_idx: u16,
+    /// 下一个要读取的已用环位置
+    last_used: u16,
+}
+
+// 环内存只通过 `&mut self` 访问
+unsafe impl Send for VirtQueue {}
+
+impl VirtQueue {
+    /// 创建第 `index` 个队列并告知设备；`size` 必须是 2 的幂
+    pub fn new<T: Transport + ?Sized>(
+        transport: &mut T,
+        index: u16,
+        size: u16,
+    ) -> Result<Self, ()> {
+        let max = transport.max_queue_size(index);
+        if max == 0 || !size.is_power_of_two() {
+            return Err(());
+        }
+        let size = size.min(max);
+        let n = size as usize;
+        let avail_offset = DESC_SIZE * n;
+        let used_offset = (avail_offset + 6 + 2 * n + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+        let total = used_offset + 6 + 8 * n;
+        let pages = (total + PAGE_SIZE - 1) / PAGE_SIZE;
+        let paddr = VirtIOHal::dma_alloc(pages);
+        let vaddr = VirtIOHal::phys_to_virt(paddr);
+        unsafe {
+            core::slice::from_raw_parts_mut(vaddr as *mut u8, pages * PAGE_SIZE).fill(0);
+        }
+        transport.setup_queue(
+            index,
+            size,
+            paddr,
+            paddr + avail_offset,
+            paddr + used_offset,
+        );
+        Ok(Self {
+            size,
+            paddr,
+            pages,
+            desc: vaddr,
+            avail: vaddr + avail_offset,
+            used: vaddr + used_offset,
+            avail_idx: 0,
+            last_used: 0,
+        })
+    }
+
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// 把物理地址 `paddr` 处长 `len` 的缓冲区作为描述符 `id` 提交给设备
+    ///
+    /// 提交后需要调用 `Transport::notify` 通知设备
+    pub fn push(&mut self, id: u16, paddr: usize, len: usize, device_writable: bool) {
+        self.push_chain(id, &[(paddr, len, device_writable)]);
+    }
+
+    /// 把 `bufs` 中的 `(物理地址, 长度, 是否由设备写入)` 依次写入从 `head` 起的描述符，
+    /// 串成一条链提交给设备
+    ///
+    /// 提交后需要调用 `Transport::notify` 通知设备
+    pub fn push_chain(&mut self, head: u16, bufs: &[(usize, usize, bool)]) {
+        assert!(!bufs.is_empty() && head as usize + bufs.len() <= self.size as usize);
+        for (i, &(paddr, len, device_writable)) in bufs.iter().enumerate() {
+            let id = head + i as u16;
+            let desc = self.desc + id as usize * DESC_SIZE;
+            let mut flags = if device_writable { DESC_F_WRITE } else { 0 };
+            if i + 1 < bufs.len() {
+                flags |= DESC_F_NEXT;
+            }
+            unsafe {
+                write_volatile(desc as *mut u64, paddr as u64);
+                write_volatile((desc + 8) as *mut u32, len as u32);
+                write_volatile((desc + 12) as *mut u16, flags);
+                write_volatile((desc + 14) as *mut u16, id + 1);
+            }
+        }
+        unsafe {
+            let slot = (self.avail_idx % self.size) as usize;
+            write_volatile((self.avail + 4 + 2 * slot) as *mut u16, head);
+            // 描述符与环项必须先于 idx 对设备可见
+            fence(Ordering::SeqCst);
+            self.avail_idx = self.avail_idx.wrapping_add(1);
+            write_volatile((self.avail + 2) as *mut u16, self.avail_idx);
+        }
+        fence(Ordering::SeqCst);
+    }
+
+    /// 请求设备在归还描述符时不发送中断，用于轮询的驱动
+    ///
+    /// 这只是提示，设备仍可能发送中断
+    pub fn disable_interrupts(&mut self) {
+        unsafe { write_volatile(self.avail as *mut u16, AVAIL_F_NO_INTERRUPT) };
+        fence(Ordering::SeqCst);
+    }
+
+    /// 设备是否归还了新的描述符
+    pub fn can_pop(&self) -> bool {
+        fence(Ordering::SeqCst);
+        let used_idx = unsafe { read_volatile((self.used + 2) as *const u16) };
+        used_idx != self.last_used
+    }
+
+    /// 取出一个被设备归还的描述符，返回 `(id, 设备写入的字节数)`
+    pub fn pop_used(&mut self) -> Option<(u16, usize)> {
+        if !self.can_pop() {
+            return None;
+        }
+        let slot = (self.last_used % self.size) as usize;
+        let elem = self.used + 4 + 8 * slot;
+        let (id, len) = unsafe {
+            (
+                read_volatile(elem as *const u32),
+                read_volatile((elem + 4) as *const u32),
+            )
+        };
+        self.last_used = self.last_used.wrapping_add(1);
+        Some((id as u16, len as usize))
+    }
+}
+
+impl Drop for VirtQueue {
+    fn drop(&mut self) {
+        VirtIOHal::dma_dealloc(self.paddr, self.pages);
+    }
+}
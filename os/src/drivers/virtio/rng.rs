@@ -0,0 +1,97 @@
+//! # virtio-rng 驱动
+//!
+//! ## Overview
+//! 设备只有一个请求队列：驱动提交一个由设备写入的缓冲区，设备填入随机字节后归还。
+//! 启动时探测到的设备保存在 `VIRTIO_RNG` 中，`fill` 以轮询方式取得随机字节。
+//!
+//! ## Assumptions
+//! - 设备很快归还缓冲区（QEMU 从宿主机的 `/dev/urandom` 读取），轮询不会长时间占用 CPU
+//!
+//! ## Safety
+//! - 缓冲区内存通过 `VirtIOHal::dma_alloc` 分配，在驱动存活期间不释放
+//!
+//! ## Invariants
+//! - 队列中至多有一个未归还的缓冲区
+//!
+//! ## Behavior
+//! - 一次请求至多取得一页随机字节，`fill` 按需多次请求
+
+use super::queue::VirtQueue;
+use super::{Transport, DEVICE_RNG, F_VERSION_1};
+use crate::drivers::virtio::VirtIOHal;
+use crate::hal::PAGE_SIZE;
+use crate::sync::UPIntrFreeCell;
+use alloc::boxed::Box;
+use core::hint::spin_loop;
+use lazy_static::lazy_static;
+use virtio_drivers::Hal;
+
+const QUEUE_REQUEST: u16 = 0;
+
+struct VirtIORngInner {
+    transport: Box<dyn Transport>,
+    queue: VirtQueue,
+    buf: usize,
+}
+
+pub struct VirtIORng {
+    inner: UPIntrFreeCell<VirtIORngInner>,
+}
+
+impl VirtIORng {
+    pub fn new(mut transport: Box<dyn Transport>) -> Result<Self, ()> {
+        transport.begin_init(F_VERSION_1)?;
+        let mut queue = VirtQueue::new(transport.as_mut(), QUEUE_REQUEST, 1)?;
+        queue.disable_interrupts();
+        let buf = VirtIOHal::dma_alloc(1);
+        transport.finish_init();
+        Ok(Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(VirtIORngInner {
+                    transport,
+                    queue,
+                    buf,
+                })
+            },
+        })
+    }
+
+    /// 用随机字节填满 `buf`
+    pub fn fill(&self, buf: &mut [u8]) {
+        let mut inner = self.inner.exclusive_access();
+        let mut filled = 0;
+        while filled < buf.len() {
+            let want = (buf.len() - filled).min(PAGE_SIZE);
+            let paddr = inner.buf;
+            inner.queue.push(0, paddr, want, true);
+            inner.transport.notify(QUEUE_REQUEST);
+            let len = loop {
+                match inner.queue.pop_used() {
+                    Some((_, len)) => break len.min(want),
+                    None => spin_loop(),
+                }
+            };
+            let data = unsafe {
+                core::slice::from_raw_parts(VirtIOHal::phys_to_virt(paddr) as *const u8, len)
+            };
+            buf[filled..filled + len].copy_from_slice(data);
+            filled += len;
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref VIRTIO_RNG: Option<VirtIORng> =
+        super::probe(DEVICE_RNG).and_then(|transport| VirtIORng::new(transport).ok());
+}
+
+/// 从 virtio-rng 取得随机字节，没有设备时返回 `false`
+pub fn fill_random(buf: &mut [u8]) -> bool {
+    match VIRTIO_RNG.as_ref() {
+        Some(rng) => {
+            rng.fill(buf);
+            true
+        }
+        None => false,
+    }
+}
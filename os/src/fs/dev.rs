@@ -0,0 +1,150 @@
+//! # 设备文件
+//!
+//! ## Overview
+//! `/dev` 下不在磁盘上的设备文件：
+//! - `/dev/net0`：网卡的原始帧接口，一次 `write` 发送一个以太网帧，一次 `read` 取出一个帧
+//!
+//! ## Assumptions
+//! - 路径已经是规范化的绝对路径（`resolve_path` 的结果）
+//! - 至多一块网卡（`NET_DEVICE`），多个描述符共享同一个接收队列
+//!
+//! ## Behavior
+//! - 没有网卡时 `/dev/net0` 不存在
+//! - 读缓冲区比帧短时帧被截断；没有帧可读时阻塞轮询，直到有帧或收到信号，
+//!   带 `O_NONBLOCK` 时返回 0
+//! - 超过 `MAX_FRAME_LEN` 或网卡没有空间时写入返回 0
+//! - `ioctl(SIOCGIFHWADDR)` 按 `struct ifreq` 的布局返回 MAC 地址
+//! - 不在目录中列出
+
+use super::file::{UserStat, BLK_SIZE, S_IFCHR};
+use super::{File, OpenFlags};
+use crate::drivers::net::{NetDevice, MAX_FRAME_LEN, NET_DEVICE};
+use crate::mm::{copy_to_user, UserBuffer};
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{EFAULT, ENOTTY};
+use crate::task::{check_signals_of_current, current_user_token, suspend_current_and_run_next};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// 取得网卡硬件地址
+const SIOCGIFHWADDR: usize = 0x8927;
+/// `struct ifreq` 中 `ifr_hwaddr` 的偏移（接口名占 16 字节）
+const IFREQ_HWADDR_OFFSET: usize = 16;
+/// 以太网的硬件类型
+const ARPHRD_ETHER: u16 = 1;
+
+/// 打开 `/dev` 下的设备文件，不是设备文件时返回 `None`
+pub fn open_dev(path: &str, flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+    match path {
+        "/dev/net0" => {
+            let device = NET_DEVICE.as_ref()?.clone();
+            Some(Arc::new(NetFile {
+                device,
+                nonblocking: unsafe { UPIntrFreeCell::new(flags.contains(OpenFlags::NONBLOCK)) },
+            }))
+        }
+        _ => None,
+    }
+}
+
+/// `/dev/net0`
+pub struct NetFile {
+    device: Arc<dyn NetDevice>,
+    nonblocking: UPIntrFreeCell<bool>,
+}
+
+impl File for NetFile {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        let mut frame = vec![0u8; MAX_FRAME_LEN];
+        loop {
+            if let Ok(n) = self.device.receive(&mut frame) {
+                return buf.write_buffer(None, &frame[..n]);
+            }
+            if *self.nonblocking.exclusive_access() || check_signals_of_current().is_some() {
+                return 0;
+            }
+            suspend_current_and_run_next();
+        }
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        let mut frame = Vec::with_capacity(buf.len());
+        for slice in buf.buffers.iter() {
+            frame.extend_from_slice(slice);
+        }
+        match self.device.transmit(&frame) {
+            Ok(()) => frame.len(),
+            Err(_) => 0,
+        }
+    }
+    fn get_stat(&self) -> UserStat {
+        UserStat {
+            st_dev: 0,
+            st_ino: 0,
+            st_mode: S_IFCHR | 0o666,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: 0,
+            st_blksize: BLK_SIZE,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime_sec: 0,
+            st_atime_nsec: 0,
+            st_mtime_sec: 0,
+            st_mtime_nsec: 0,
+            st_ctime_sec: 0,
+            st_ctime_nsec: 0,
+            __unused: [0; 2],
+        }
+    }
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn get_path(&self) -> String {
+        String::from("/dev/net0")
+    }
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
+        self.device.receive(buf)
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> Result<usize, isize> {
+        self.device.transmit(buf).map(|_| buf.len())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn status_flags(&self) -> OpenFlags {
+        if *self.nonblocking.exclusive_access() {
+            OpenFlags::NONBLOCK
+        } else {
+            OpenFlags::empty()
+        }
+    }
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *self.nonblocking.exclusive_access() = flags.contains(OpenFlags::NONBLOCK);
+    }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        if cmd != SIOCGIFHWADDR {
+            return -ENOTTY;
+        }
+        // sockaddr：2 字节的地址族后跟硬件地址
+        let mut hwaddr = [0u8; 8];
+        hwaddr[..2].copy_from_slice(&ARPHRD_ETHER.to_ne_bytes());
+        hwaddr[2..].copy_from_slice(&self.device.mac());
+        let token = current_user_token();
+        match copy_to_user(token, &hwaddr, (arg + IFREQ_HWADDR_OFFSET) as *mut [u8; 8]) {
+            Ok(()) => 0,
+            Err(_) => -EFAULT,
+        }
+    }
+}
@@ -1,138 +1,53 @@
+use super::block_cache::BLOCK_CACHE_MANAGER;
 use crate::drivers::{BlockDevice, BLOCK_DEVICE};
-use crate::fs::{block_cache_sync_all, get_block_cache};
-use crate::hal::BLOCK_SZ;
-use alloc::boxed::Box;
+use crate::fs::inode::{root_dir, ROOT_DIR};
+use crate::fs::{block_cache_sync_all, page_cache_shutdown};
 use alloc::sync::Arc;
-use fatfs::{IoBase, IoError, Read, Seek, SeekFrom, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+pub use fat_adapter::FatFsBlockDevice;
 use lazy_static::lazy_static;
 use spin::Mutex;
 
 lazy_static! {
     pub static ref FAT_FS: Mutex<fatfs::FileSystem<FatFsBlockDevice>> = Mutex::new({
-        let fat_device = FatFsBlockDevice::new(BLOCK_DEVICE.clone());
-        let fs = fatfs::FileSystem::new(fat_device, fatfs::FsOptions::new())
+        let device = fat_device(BLOCK_DEVICE.clone());
+        let fs = fatfs::FileSystem::new(device, fatfs::FsOptions::new())
             .expect("Failed to mount FAT filesystem");
         fs
     });
 }
 
-pub struct FatFsBlockDevice {
-    block_device: Arc<dyn BlockDevice>,
-    offset: usize,
-}
+/// 根文件系统已经卸载
+static UNMOUNTED: AtomicBool = AtomicBool::new(false);
 
-impl FatFsBlockDevice {
-    pub fn new(block_device: Arc<dyn BlockDevice>) -> Self {
-        Self {
-            block_device,
-            offset: 0,
-        }
+/// 卸载根文件系统：写回页缓存，写回 FSInfo 并清除卷的脏标志，最后刷新块缓存
+///
+/// fatfs 在 `FileSystem` 被销毁时完成卸载，而 `FAT_FS` 是静态的，
+/// 这里销毁它的一份按位副本，并让它与根目录的锁保持持有状态。
+///
+/// # Safety
+/// - 卸载之后不能再访问文件系统，只在关机或重启前调用；
+///   重复调用不做任何事
+pub fn unmount_root() {
+    if UNMOUNTED.swap(true, Ordering::AcqRel) {
+        return;
     }
-}
-
-#[derive(Debug)]
-pub enum FatFsError {
-    IoError,
-    InvalidOffset,
-    ENOENT,
-}
-
-impl IoError for FatFsError {
-    fn is_interrupted(&self) -> bool {
-        false
-    }
-
-    fn new_unexpected_eof_error() -> Self {
-        FatFsError::IoError
+    let failed = page_cache_shutdown();
+    if failed > 0 {
+        log::warn!("[fs] {} file(s) could not be written back", failed);
     }
-
-    fn new_write_zero_error() -> Self {
-        FatFsError::IoError
+    // 释放根目录锁时销毁被推迟的 fatfs 对象，之后不再允许访问 fatfs
+    drop(root_dir());
+    core::mem::forget(ROOT_DIR.lock());
+    let fs = FAT_FS.lock();
+    if let Err(err) = unsafe { core::ptr::read(&*fs) }.unmount() {
+        log::warn!("[fs] unmount failed: {:?}", err);
     }
+    core::mem::forget(fs);
+    block_cache_sync_all();
 }
 
-impl IoBase for FatFsBlockDevice {
-    type Error = FatFsError;
-}
-
-impl Read for FatFsBlockDevice {
-    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
-        if buf.is_empty() {
-            return Ok(0);
-        }
-
-        let mut read_size = 0;
-        let mut current_offset = self.offset;
-
-        while read_size < buf.len() {
-            let block_id = current_offset / BLOCK_SZ;
-            let offset_in_block = current_offset % BLOCK_SZ;
-            let size_to_read = (buf.len() - read_size).min(BLOCK_SZ - offset_in_block);
-
-            get_block_cache(block_id, self.block_device.clone())
-                .lock()
-                .read(0, |block_data: &[u8; BLOCK_SZ]| {
-                    buf[read_size..read_size + size_to_read].copy_from_slice(
-                        &block_data.as_ref()[offset_in_block..offset_in_block + size_to_read],
-                    );
-                });
-            read_size += size_to_read;
-            current_offset += size_to_read;
-        }
-        self.offset += read_size;
-        Ok(read_size)
-    }
-}
-
-impl Write for FatFsBlockDevice {
-    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
-        if buf.is_empty() {
-            return Ok(0);
-        }
-
-        let mut write_size = 0;
-        let mut current_offset = self.offset;
-
-        while write_size < buf.len() {
-            let block_id = current_offset / BLOCK_SZ;
-            let offset_in_block = current_offset % BLOCK_SZ;
-            let size_to_write = (buf.len() - write_size).min(BLOCK_SZ - offset_in_block);
-
-            get_block_cache(block_id, self.block_device.clone())
-                .lock()
-                .modify(0, |block_data: &mut [u8; BLOCK_SZ]| {
-                    block_data.as_mut()[offset_in_block..offset_in_block + size_to_write]
-                        .copy_from_slice(&buf[write_size..write_size + size_to_write]);
-                });
-            write_size += size_to_write;
-            current_offset += size_to_write;
-        }
-        self.offset += write_size;
-        Ok(write_size)
-    }
-
-    fn flush(&mut self) -> Result<(), Self::Error> {
-        block_cache_sync_all();
-        Ok(())
-    }
-}
-
-impl Seek for FatFsBlockDevice {
-    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Self::Error> {
-        let new_offset = match pos {
-            SeekFrom::Start(offset) => offset as i64,
-            SeekFrom::Current(offset) => self.offset as i64 + offset,
-            SeekFrom::End(offset) => {
-                // TODO: 暂时未能实现此功能
-                return Err(FatFsError::InvalidOffset);
-            }
-        };
-
-        if new_offset < 0 {
-            return Err(FatFsError::InvalidOffset);
-        }
-
-        self.offset = new_offset as usize;
-        Ok(new_offset as u64)
-    }
+/// 经由全局块缓存访问 `block_device` 的 fatfs 存储
+pub fn fat_device(block_device: Arc<dyn BlockDevice>) -> FatFsBlockDevice {
+    FatFsBlockDevice::new(block_device, &BLOCK_CACHE_MANAGER)
 }
@@ -1,5 +1,7 @@
-use crate::fs::inode::{FatType, OSInode};
+use crate::fs::inode::{FatType, OSInode, OpenFlags};
 use crate::mm::UserBuffer;
+use crate::net::Socket;
+use crate::syscall::errno::ENOTTY;
 use alloc::string::String;
 use core::any::Any;
 use core::cell::UnsafeCell;
@@ -18,10 +20,26 @@ pub trait File: Send + Sync {
     fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, isize>;
     ///可以获得OsInode结构体
     fn as_any(&self) -> &dyn Any;
+    /// 文件状态标志（O_APPEND、O_NONBLOCK），由共享同一打开文件的所有描述符共同可见
+    fn status_flags(&self) -> OpenFlags {
+        OpenFlags::empty()
+    }
+    /// 设置文件状态标志，只有 `OpenFlags::STATUS_MASK` 中的位会生效
+    fn set_status_flags(&self, _flags: OpenFlags) {}
+    /// 套接字返回自身的套接字接口，其余文件返回 `None`
+    fn as_socket(&self) -> Option<&dyn Socket> {
+        None
+    }
+    /// 设备控制操作，`arg` 的含义由 `cmd` 决定；不是终端的文件返回 `ENOTTY`
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> isize {
+        -ENOTTY
+    }
 }
 
 pub const S_IFREG: u32 = 0o100000; //普通文件
 pub const S_IFDIR: u32 = 0o040000; //目录
+pub const S_IFSOCK: u32 = 0o140000; //套接字
+pub const S_IFCHR: u32 = 0o020000; //字符设备
 pub const BLK_SIZE: u32 = 512;
 
 pub struct Stat {
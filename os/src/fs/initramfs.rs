@@ -0,0 +1,205 @@
+//! # 启动镜像（initrd / initramfs）
+//!
+//! ## Overview
+//! 不依赖 virtio 磁盘启动：引导程序加载到内存中的镜像（`MACHINE.initrd`），
+//! 或者打开 `initramfs` 特性后由环境变量 `INITRAMFS` 指定、编译进内核的镜像，
+//! 都被登记为内存盘 `ramN`，没有指定 `root=` 时 `ram0` 成为根设备：
+//! - cpio（newc）归档：在新分配的内存盘上建立 FAT 文件系统并解包，
+//!   随后把 initrd 占用的页帧交还给分配器
+//! - 磁盘镜像（以 `0x55AA` 结尾的 FAT 引导扇区或 MBR）：initrd 原地作为内存盘，
+//!   编译进内核的镜像先复制到新分配的内存盘
+//!
+//! ## Assumptions
+//! - 在根文件系统挂载之前调用 `load_initrd`
+//!
+//! ## Invariants
+//! - 解包出的文件系统与 cpio 中的目录结构一致，路径去掉开头的 `./` 与 `/`
+//!
+//! ## Behavior
+//! - 符号链接与设备文件被跳过，FAT 无法表示它们
+//! - 内存盘大小由命令行 `ramdisk_size=`（KiB）给出，缺省按归档内容估算
+
+use super::fat32::fat_device;
+use crate::drivers::{register_ramdisk, BlockDevice, RamDisk};
+use crate::hal::{BLOCK_SZ, MACHINE};
+use crate::mm::frame_release_reserved;
+use alloc::sync::Arc;
+use fatfs::{FileSystem, FormatVolumeOptions, FsOptions, Write};
+
+#[cfg(feature = "initramfs")]
+static EMBEDDED: &[u8] = include_bytes!(env!("INITRAMFS"));
+
+const CPIO_HEADER_SIZE: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+/// 估算大小时每个文件额外占用的空间（一个簇）
+const PER_FILE_OVERHEAD: usize = 4096;
+/// 内存盘的最小大小，FAT 需要一定数量的簇
+const MIN_RAMDISK_SIZE: usize = 8 * 1024 * 1024;
+
+/// cpio 归档中的一项
+struct CpioEntry<'a> {
+    name: &'a str,
+    mode: u32,
+    data: &'a [u8],
+}
+
+fn is_cpio(image: &[u8]) -> bool {
+    image.starts_with(b"070701") || image.starts_with(b"070702")
+}
+
+fn is_disk_image(image: &[u8]) -> bool {
+    image.len() >= BLOCK_SZ && image[510..512] == [0x55, 0xAA]
+}
+
+/// `image` 是否以 cpio 归档或磁盘镜像开头，`MACHINE` 据此决定是否预留固定位置的镜像
+pub fn is_boot_image(image: &[u8]) -> bool {
+    is_cpio(image) || is_disk_image(image)
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// 读取 `offset` 处的 8 位十六进制数
+fn hex(image: &[u8], offset: usize) -> Option<usize> {
+    let text = core::str::from_utf8(image.get(offset..offset + 8)?).ok()?;
+    usize::from_str_radix(text, 16).ok()
+}
+
+/// 遍历 cpio 归档，遇到格式错误或结尾标记时停止
+fn cpio_entries(image: &[u8]) -> impl Iterator<Item = CpioEntry<'_>> {
+    let mut offset = 0;
+    core::iter::from_fn(move || {
+        let header = image.get(offset..offset + CPIO_HEADER_SIZE)?;
+        if !is_cpio(header) {
+            return None;
+        }
+        let mode = hex(header, 14)? as u32;
+        let file_size = hex(header, 54)?;
+        let name_size = hex(header, 94)?;
+        let name_start = offset + CPIO_HEADER_SIZE;
+        // 名字包含结尾的 0
+        let name = image.get(name_start..name_start + name_size.checked_sub(1)?)?;
+        let name = core::str::from_utf8(name).ok()?;
+        let data_start = align4(name_start + name_size);
+        let data = image.get(data_start..data_start + file_size)?;
+        offset = align4(data_start + file_size);
+        if name == CPIO_TRAILER {
+            return None;
+        }
+        Some(CpioEntry { name, mode, data })
+    })
+}
+
+/// 解包 cpio 归档所需的内存盘块数
+fn ramdisk_blocks(image: &[u8]) -> usize {
+    let size = match crate::cmdline::get_parsed::<usize>("ramdisk_size") {
+        Some(kib) => kib * 1024,
+        None => {
+            let content: usize = cpio_entries(image)
+                .map(|entry| entry.data.len() + PER_FILE_OVERHEAD)
+                .sum();
+            (content + content / 4).max(MIN_RAMDISK_SIZE)
+        }
+    };
+    size / BLOCK_SZ
+}
+
+/// 在新的内存盘上建立 FAT 文件系统并解包 cpio 归档，返回解包的文件数
+fn unpack_cpio(image: &[u8]) -> Option<(Arc<dyn BlockDevice>, usize)> {
+    let blocks = ramdisk_blocks(image);
+    let disk: Arc<dyn BlockDevice> = Arc::new(RamDisk::new(blocks)?);
+    let mut device = fat_device(disk.clone());
+    fatfs::format_volume(
+        &mut device,
+        FormatVolumeOptions::new()
+            .total_sectors(blocks as u32)
+            .bytes_per_sector(BLOCK_SZ as u16)
+            .volume_label(*b"INITRAMFS  "),
+    )
+    .ok()?;
+    let fs = FileSystem::new(fat_device(disk.clone()), FsOptions::new()).ok()?;
+    let root = fs.root_dir();
+    let mut files = 0;
+    for entry in cpio_entries(image) {
+        let path = entry.name.trim_start_matches("./").trim_start_matches('/');
+        if path.is_empty() || path == "." {
+            continue;
+        }
+        let result = match entry.mode & S_IFMT {
+            S_IFDIR => root.create_dir(path).map(|_| ()),
+            S_IFREG => root.create_file(path).and_then(|mut file| {
+                files += 1;
+                file.write_all(entry.data)
+            }),
+            _ => {
+                log::warn!("[kernel] initramfs: skipping special file {}", path);
+                continue;
+            }
+        };
+        if result.is_err() {
+            log::warn!("[kernel] initramfs: failed to unpack {}", path);
+        }
+    }
+    drop(root);
+    fs.unmount().ok()?;
+    Some((disk, files))
+}
+
+/// 登记一个内存中的镜像，`region` 为 initrd 所在的物理内存区间
+fn load_image(image: &[u8], region: Option<(usize, usize)>) {
+    let quiet = crate::cmdline::quiet();
+    if is_cpio(image) {
+        match unpack_cpio(image) {
+            Some((disk, files)) => {
+                let name = register_ramdisk(disk);
+                if !quiet {
+                    println!("[kernel] initramfs: {} files unpacked into {}", files, name);
+                }
+            }
+            None => log::error!("[kernel] initramfs: failed to unpack cpio archive"),
+        }
+        if region.is_some() {
+            frame_release_reserved();
+        }
+    } else if is_disk_image(image) {
+        let disk: Arc<dyn BlockDevice> = match region {
+            Some((start, end)) => Arc::new(unsafe { RamDisk::from_region(start, end - start) }),
+            None => {
+                let blocks = (image.len() + BLOCK_SZ - 1) / BLOCK_SZ;
+                let Some(disk) = RamDisk::new(blocks) else {
+                    log::error!("[kernel] initrd: out of memory copying disk image");
+                    return;
+                };
+                let mut buf = [0u8; BLOCK_SZ];
+                for (block_id, chunk) in image.chunks(BLOCK_SZ).enumerate() {
+                    buf[..chunk.len()].copy_from_slice(chunk);
+                    buf[chunk.len()..].fill(0);
+                    disk.write_block(block_id, &buf);
+                }
+                Arc::new(disk)
+            }
+        };
+        let name = register_ramdisk(disk);
+        if !quiet {
+            println!("[kernel] initrd: disk image registered as {}", name);
+        }
+    } else if region.is_some() {
+        log::info!("[kernel] initrd: no cpio archive or disk image found");
+    }
+}
+
+/// 登记引导程序加载的镜像和编译进内核的镜像，在根文件系统挂载之前调用
+pub fn load_initrd() {
+    if let Some((start, end)) = MACHINE.initrd {
+        let image = unsafe { core::slice::from_raw_parts(start as *const u8, end - start) };
+        load_image(image, Some((start, end)));
+    }
+    #[cfg(feature = "initramfs")]
+    load_image(EMBEDDED, None);
+}
@@ -1,19 +1,26 @@
+use crate::drivers::BlockingIo;
 use crate::fs::fat32::FAT_FS;
 use crate::fs::file::{Stat, UserStat, BLK_SIZE};
+use crate::fs::lock::release_flocks;
+use crate::fs::page_cache::{page_cache, PageCache};
+use crate::fs::File as _;
 use crate::fs::{DirEntry, FatFsBlockDevice};
 use crate::mm::UserBuffer;
-use crate::sync::UPIntrFreeCell;
+use crate::sync::{SleepMutex, SleepMutexGuard, UPIntrFreeCell};
 use crate::syscall::StatMode;
 use crate::task::current_process;
+use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use core::any::Any;
 use core::cell::UnsafeCell;
-use fatfs::{
-    DefaultTimeProvider, Dir, File, FileSystem, LossyOemCpConverter, Read, Seek, SeekFrom, Write,
-};
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, Ordering};
+use fatfs::{DefaultTimeProvider, Dir, File, FileSystem, LossyOemCpConverter, Seek, SeekFrom};
+pub use fs_path::resolve_path;
 use lazy_static::lazy_static;
 
 pub struct OSInode {
@@ -21,9 +28,18 @@ pub struct OSInode {
     writable: bool,
     stat: Stat,
     // 未来如果需要支持多核，则需要改用更强的同步机制（如 spin::Mutex）。
-    file: UPIntrFreeCell<FatType>,
+    // 销毁时取出，在根目录锁下销毁（见 `drop_fat_object`）
+    file: UPIntrFreeCell<Option<FatType>>,
     pub is_directory: bool, // 是否是目录
     path: String,           // 文件的完整路径
+    status_flags: UPIntrFreeCell<OpenFlags>, // O_APPEND / O_NONBLOCK
+    // 普通文件的内容都经由同一路径共享的页缓存读写，目录为 None
+    cache: Option<Arc<PageCache>>,
+    // 当前读写偏移（页缓存中的逻辑长度可能超过磁盘上的长度，不能用 fatfs 的偏移）；
+    // 读写期间一直持有，页缓存填充时可能睡眠
+    pos: SleepMutex<usize>,
+    // 已由 `close` 按策略写回，销毁时不再写回
+    closed: AtomicBool,
 }
 
 pub enum FatType {
@@ -42,7 +58,14 @@ unsafe impl Send for OSInode {}
 unsafe impl Sync for OSInode {}
 
 impl OSInode {
-    pub fn new(readable: bool, writable: bool, file: FatType, is_dir: bool, path: String) -> Self {
+    /// 调用者持有根目录锁：取得文件长度需要经由 fatfs 访问磁盘
+    pub fn new(
+        readable: bool,
+        writable: bool,
+        mut file: FatType,
+        is_dir: bool,
+        path: String,
+    ) -> Self {
         let mut st_mode = if is_dir { 0o040000 } else { 0o100000 }; // S_IFDIR / S_IFREG
         if readable {
             st_mode |= 0o444
@@ -51,7 +74,11 @@ impl OSInode {
             st_mode |= 0o222
         } // -w-
 
-        let st_size = 0;
+        let cache = match &mut file {
+            FatType::File(file) => Some(page_cache(&path, get_size(file) as usize)),
+            FatType::Dir(_) => None,
+        };
+        let st_size = cache.as_ref().map_or(0, |cache| cache.size() as i64);
         let st_blocks = ((st_size + 511) / 512) as u64;
         let is_directory = is_dir;
         Self {
@@ -78,47 +105,93 @@ impl OSInode {
                 st_ctime_nsec: 0,
                 __unused: [0; 2],
             },
-            file: unsafe { UPIntrFreeCell::new(file) },
+            file: unsafe { UPIntrFreeCell::new(Some(file)) },
             is_directory,
             path,
+            status_flags: unsafe { UPIntrFreeCell::new(OpenFlags::empty()) },
+            cache,
+            pos: SleepMutex::new(0),
+            closed: AtomicBool::new(false),
         }
     }
 
-    /// 当前 read_all 时从 offset 到 EOF 而不是从文件开始到 EOF
-    /// 把注释部分取消则从文件开始到 EOF
-    pub fn read_all(&self) -> Vec<u8> {
-        let mut inner = self.file.exclusive_access();
-        let mut buffer = [0u8; 512];
-        let mut v: Vec<u8> = Vec::new();
-        match &mut *inner {
-            FatType::File(file) => {
-                // file.seek(SeekFrom::Start(0)).unwrap();
-                loop {
-                    let len = file.read(&mut buffer);
-                    let size = len.unwrap();
-                    if size == 0 {
-                        break;
-                    }
-                    v.extend_from_slice(&buffer[..size]);
-                }
-            }
-            FatType::Dir(_) => {
-                log::debug!("Get a Dir to read, which is not supported");
+    /// 当前读写偏移，供 fcntl 记录锁按 SEEK_CUR 计算区间
+    pub fn offset(&self) -> usize {
+        *self.pos.lock()
+    }
+
+    /// 设置读写位置；目录的位置是下一个要读取的目录项序号
+    pub fn set_offset(&self, offset: usize) {
+        *self.pos.lock() = offset;
+    }
+
+    /// 文件当前大小（含尚未写回的部分），目录返回 0
+    pub fn size(&self) -> usize {
+        self.cache.as_ref().map_or(0, |cache| cache.size())
+    }
+
+    /// 文件的页缓存，目录返回 None
+    pub fn page_cache(&self) -> Option<&Arc<PageCache>> {
+        self.cache.as_ref()
+    }
+
+    /// 写回文件的脏页并刷新块缓存
+    pub fn sync(&self) -> Result<(), isize> {
+        if let Some(cache) = &self.cache {
+            cache.sync().map_err(|_| -1isize)?;
+        }
+        let _io = BlockingIo::enter();
+        crate::fs::block_cache_sync_all();
+        Ok(())
+    }
+
+    /// 最后一个描述符关闭时由 `close` 调用，此时可以睡眠：
+    /// 以可写方式打开的文件按 `fsync_on_close` 策略写回（`Drop` 中只能不睡眠地尝试）
+    pub fn close(&self) {
+        if self.writable {
+            if let Some(cache) = &self.cache {
+                cache.close(true);
             }
         }
+        self.closed.store(true, Ordering::Release);
+    }
+
+    /// 把文件截断为空
+    pub fn truncate(&self) -> Result<(), isize> {
+        let cache = self.cache.as_ref().ok_or(-1isize)?;
+        cache.truncate().map_err(|_| -1isize)?;
+        *self.pos.lock() = 0;
+        self.stat.set_size(0);
+        Ok(())
+    }
+
+    /// 当前 read_all 时从 offset 到 EOF 而不是从文件开始到 EOF
+    pub fn read_all(&self) -> Vec<u8> {
+        let Some(cache) = &self.cache else {
+            log::debug!("Get a Dir to read, which is not supported");
+            return Vec::new();
+        };
+        let mut pos = self.pos.lock();
+        let mut v = vec![0u8; cache.size().saturating_sub(*pos)];
+        let size = cache.read_at(*pos, &mut v);
+        v.truncate(size);
+        *pos += size;
         v
     }
     pub fn is_dir(&self) -> bool {
         let inner = self.file.exclusive_access();
-        match *inner {
-            FatType::Dir(_) => true,
-            FatType::File(_) => false,
-        }
+        matches!(*inner, Some(FatType::Dir(_)))
     }
 }
 
+type RootDir = Dir<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>;
+
 lazy_static! {
-    pub static ref ROOT_DIR: UPIntrFreeCell<Dir<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>> = {
+    /// 根目录，同时是整个 FAT 卷的锁
+    ///
+    /// fatfs 以 `RefCell` 记录对卷的借用，任务在磁盘请求上睡眠时借用仍然存在，
+    /// 所以打开、读写和销毁 fatfs 对象都在持有它时进行
+    pub static ref ROOT_DIR: SleepMutex<RootDir> = {
         // 获取文件系统的锁
         let fs_guard = FAT_FS.lock();
         // 关键点：fatfs 的 root_dir() 会借用 FileSystem。
@@ -127,15 +200,72 @@ lazy_static! {
             unsafe { &*(fs_guard.deref() as *const _) };
 
         let root_dir = fs_static.root_dir();
-        unsafe {
-            UPIntrFreeCell::new(root_dir)
-        }
+        SleepMutex::new(root_dir)
     };
+    /// 销毁时没能取得根目录锁的 fatfs 对象，由下一个释放根目录锁的任务销毁
+    static ref DEFERRED_DROPS: UPIntrFreeCell<Vec<FatType>> =
+        unsafe { UPIntrFreeCell::new(Vec::new()) };
+}
+
+/// 根目录锁的守卫；睡眠取得的守卫同时允许当前任务的磁盘请求睡眠
+pub struct FsGuard {
+    root: SleepMutexGuard<'static, RootDir>,
+    _io: Option<BlockingIo>,
+}
+
+/// 取得根目录锁，可能睡眠；调用者不能持有自旋锁或处于 `UPIntrFreeCell` 临界区中
+pub fn root_dir() -> FsGuard {
+    let root = ROOT_DIR.lock();
+    FsGuard {
+        root,
+        _io: Some(BlockingIo::enter()),
+    }
+}
+
+/// 不睡眠地尝试取得根目录锁，其间的磁盘请求以轮询方式完成
+pub fn try_root_dir() -> Option<FsGuard> {
+    Some(FsGuard {
+        root: ROOT_DIR.try_lock()?,
+        _io: None,
+    })
+}
+
+/// 销毁一个 fatfs 对象：文件对象销毁时会写回目录项并刷新设备，
+/// 取不到根目录锁时推迟到锁被释放之前
+pub fn drop_fat_object(object: FatType) {
+    match try_root_dir() {
+        Some(root) => {
+            drop(object);
+            drop(root);
+        }
+        None => DEFERRED_DROPS.exclusive_access().push(object),
+    }
+}
+
+impl Deref for FsGuard {
+    type Target = RootDir;
+    fn deref(&self) -> &RootDir {
+        &self.root
+    }
+}
+
+impl Drop for FsGuard {
+    /// 释放锁之前销毁被推迟的 fatfs 对象
+    fn drop(&mut self) {
+        loop {
+            let objects = core::mem::take(&mut *DEFERRED_DROPS.exclusive_access());
+            if objects.is_empty() {
+                break;
+            }
+            drop(objects);
+        }
+    }
 }
 
 pub fn list_apps() {
     println!("List of applications:");
-    for entry in ROOT_DIR.exclusive_access().iter() {
+    let root_dir = root_dir();
+    for entry in root_dir.iter() {
         let entry = entry.expect("Failed to read directory entry");
         let file_name = entry.file_name();
         let attributes = if entry.is_dir() { "DIR" } else { "FILE" };
@@ -148,6 +278,7 @@ pub fn list_apps() {
 }
 
 bitflags! {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub struct OpenFlags: u32 {
         // 只读
         const RDONLY = 0;
@@ -158,11 +289,13 @@ bitflags! {
         // 创建
         const CREATE = 1 << 6;
         // 截断（若存在则以可写方式打开，但是长度清空为0）
-        const TRUNC = 1 << 10;
+        const TRUNC = 1 << 9;
+        // 追加写，每次写之前将偏移移到文件末尾
+        const APPEND = 1 << 10;
         // 非阻塞模式
-        const NONBLOCK = 1 << 12;
+        const NONBLOCK = 1 << 11;
         // 执行时关闭
-        const CLOEXEC = 1 << 20;
+        const CLOEXEC = 1 << 19;
         //
         const DIRECTORY = 1 << 21;
         // 尽量减少缓存影响（如O_DIRECT）
@@ -171,6 +304,9 @@ bitflags! {
 }
 
 impl OpenFlags {
+    /// 可以通过 fcntl(F_SETFL) 修改的文件状态标志
+    pub const STATUS_MASK: OpenFlags = OpenFlags::APPEND.union(OpenFlags::NONBLOCK);
+
     pub fn read_write(&self) -> (bool, bool) {
         if self.contains(Self::WRONLY) {
             (false, true)
@@ -192,43 +328,43 @@ impl super::File for OSInode {
     }
 
     fn read(&self, mut buf: UserBuffer) -> usize {
-        let mut inner = self.file.exclusive_access();
+        let Some(cache) = &self.cache else {
+            log::debug!("Get a Dir to read, which is not supported");
+            return 0;
+        };
+        let mut pos = self.pos.lock();
         let mut total_read_size = 0usize;
         for slice in buf.buffers.iter_mut() {
-            match &mut *inner {
-                FatType::File(file) => {
-                    let read_size = file.read(slice).unwrap();
-                    total_read_size += read_size;
-                    if read_size < slice.len() {
-                        break;
-                    }
-                }
-                FatType::Dir(_) => {
-                    log::debug!("Get a Dir to read, which is not supported");
-                }
+            let read_size = cache.read_at(*pos, slice);
+            *pos += read_size;
+            total_read_size += read_size;
+            if read_size < slice.len() {
+                break;
             }
         }
         total_read_size
     }
 
     fn write(&self, buf: UserBuffer) -> usize {
-        let mut inner = self.file.exclusive_access();
+        let Some(cache) = &self.cache else {
+            log::debug!("Get a Dir to write, which is not supported");
+            return 0;
+        };
+        let append = self.status_flags().contains(OpenFlags::APPEND);
+        let mut pos = self.pos.lock();
+        if append {
+            *pos = cache.size();
+        }
         let mut total_write_size = 0usize;
         for slice in buf.buffers.iter() {
-            match &mut *inner {
-                FatType::File(file) => {
-                    let write_size = file.write(slice).unwrap();
-                    total_write_size += write_size;
-                    if write_size < slice.len() {
-                        break;
-                    }
-                }
-                FatType::Dir(_) => {
-                    log::debug!("Get a Dir to write, which is not supported");
-                }
+            let write_size = cache.write_at(*pos, slice);
+            *pos += write_size;
+            total_write_size += write_size;
+            if write_size < slice.len() {
+                break;
             }
         }
-        self.stat.update_after_write(total_write_size);
+        self.stat.set_size(cache.size());
         total_write_size
     }
     fn get_stat(&self) -> UserStat {
@@ -267,55 +403,46 @@ impl super::File for OSInode {
 
     /// 从 offset 读取文件内容
     fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
-        // 获取内部可变访问权限
-        let mut inner = self.file.exclusive_access();
-        match &mut *inner {
-            FatType::File(file) => {
-                // fatfs::File 需要 &mut 来读写
-                let mut file_ref = file; // File 类型本身可能在 UPIntrFreeCell 内部
-
-                // seek 到 offset
-                file_ref
-                    .seek(SeekFrom::Start(offset as u64))
-                    .map_err(|_| -1isize)?;
-                // 读取数据
-                let n = file_ref.read(buf).map_err(|_| -1isize)?;
-                Ok(n)
-            }
-            FatType::Dir(_) => Err(-1),
-        }
+        let cache = self.cache.as_ref().ok_or(-1isize)?;
+        Ok(cache.read_at(offset, buf))
     }
 
     fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize, isize> {
-        let mut inner = self.file.exclusive_access();
-        match &mut *inner {
-            FatType::File(file) => {
-                let mut file_ref = file;
-                // seek 到 offset
-                file_ref
-                    .seek(SeekFrom::Start(offset as u64))
-                    .map_err(|_| -1isize)?;
-                // 写入数据
-                let n = file_ref.write(buf).map_err(|_| -1isize)?;
-
-                // 更新 stat
-                let file_size = file_ref.seek(SeekFrom::End(0)).map_err(|_| -1isize)? as i64;
-                unsafe {
-                    *self.stat.st_size.get() = file_size;
-                }
-                unsafe {
-                    *self.stat.st_blocks.get() = ((file_size as usize + 511) / 512) as u64;
-                }
-                drop(self.file.exclusive_access());
-                Ok(n)
-            }
-            FatType::Dir(_) => Err(-1),
-        }
+        let cache = self.cache.as_ref().ok_or(-1isize)?;
+        let n = cache.write_at(offset, buf);
+        // 更新 stat
+        self.stat.set_size(cache.size());
+        Ok(n)
     }
     ///可以直接获得OsInode结构体
     fn as_any(&self) -> &dyn Any {
         self
     }
+
+    fn status_flags(&self) -> OpenFlags {
+        *self.status_flags.exclusive_access()
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        *self.status_flags.exclusive_access() = flags & OpenFlags::STATUS_MASK;
+    }
+}
+
+impl Drop for OSInode {
+    /// 最后一个引用该打开文件的描述符关闭时，释放其上的 flock 锁，
+    /// 可写打开的文件按 `fsync_on_close` 策略写回；这里可能处于临界区，不睡眠
+    fn drop(&mut self) {
+        release_flocks(self as *const _ as usize);
+        if self.writable && !self.closed.load(Ordering::Acquire) {
+            if let Some(cache) = &self.cache {
+                cache.close(false);
+            }
+        }
+        let file = self.file.exclusive_access().take();
+        if let Some(file) = file {
+            drop_fat_object(file);
+        }
+    }
 }
 impl OSInode {
     pub fn list_dir(&self) -> Result<Vec<DirEntry>, isize> {
@@ -323,88 +450,51 @@ impl OSInode {
             return Err(-1); // ENOTDIR
         }
 
-        let inner = self.file.exclusive_access();
-
-        match &*inner {
-            FatType::Dir(dir) => {
-                let mut v = Vec::new();
-                for entry in dir.iter() {
-                    let entry = entry.map_err(|_| -1isize)?;
-                    v.push(DirEntry {
-                        d_name: entry.file_name(),
-                        is_dir: entry.is_dir(),
-                    });
-                }
-                Ok(v)
-            }
-            _ => Err(-1),
+        // 遍历目录会访问磁盘，复制一份目录对象，在根目录锁下遍历
+        let root_dir = root_dir();
+        let dir = match &*self.file.exclusive_access() {
+            Some(FatType::Dir(dir)) => dir.clone(),
+            _ => return Err(-1),
+        };
+        let mut v = Vec::new();
+        for entry in dir.iter() {
+            let entry = entry.map_err(|_| -1isize)?;
+            v.push(DirEntry {
+                d_name: entry.file_name(),
+                is_dir: entry.is_dir(),
+            });
         }
+        drop(dir);
+        drop(root_dir);
+        Ok(v)
     }
 }
 
 impl DirEntry {}
 
 impl Stat {
-    pub fn update_after_write(&self, written: usize) {
+    pub fn set_size(&self, size: usize) {
         unsafe {
-            // 累加写入字节数
-            *self.st_size.get() += written as i64;
-            let size = *self.st_size.get();
+            *self.st_size.get() = size as i64;
             // 向上取整 512B 块
-            *self.st_blocks.get() = ((size as usize + 511) / 512) as u64;
-        }
-    }
-}
-
-///返回绝对路径，支持相对路径
-pub fn resolve_path(relative: &str, base: &str) -> String {
-    let mut stack: Vec<&str> = Vec::new();
-
-    let is_absolute = relative.starts_with("/");
-
-    if !is_absolute {
-        for component in base.split("/") {
-            match component {
-                "" | "." => continue,
-                ".." => {
-                    if !stack.is_empty() {
-                        stack.pop();
-                    }
-                }
-                _ => stack.push(component),
-            }
+            *self.st_blocks.get() = ((size + 511) / 512) as u64;
         }
     }
-
-    for component in relative.split("/") {
-        match component {
-            "" | "." => continue,
-            ".." => {
-                if !stack.is_empty() {
-                    stack.pop();
-                }
-            }
-            _ => stack.push(component),
-        }
-    }
-
-    let mut result = String::from("/");
-    result.push_str(&stack.join("/"));
-    result
 }
 
-pub fn open_initproc(flags: OpenFlags) -> Option<Arc<OSInode>> {
+/// 按相对启动盘根目录的路径打开文件，不依赖当前进程（用于 init 程序和配置文件）
+pub fn open_boot_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     let (readable, writable) = flags.read_write();
-    let root_dir = ROOT_DIR.exclusive_access();
-    root_dir.open_file("initproc").ok().map(|inode| {
-        Arc::new(OSInode::new(
-            readable,
-            writable,
-            FatType::File(inode),
-            false,
-            String::from("/initproc"),
-        ))
-    })
+    let path = path.trim_start_matches('/');
+    let root_dir = root_dir();
+    let inode = root_dir.open_file(path).ok()?;
+    Some(Arc::new(OSInode::new(
+        readable,
+        writable,
+        FatType::File(inode),
+        false,
+        format!("/{}", path),
+    )))
 }
 
 // 实现不完整，还未支持文件的所有权描述
@@ -420,7 +510,7 @@ pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
 
     let path_in_fs = full_path.strip_prefix("/").unwrap_or(&full_path);
 
-    let root_dir = ROOT_DIR.exclusive_access();
+    let root_dir = root_dir();
 
     let maybe_inode = if flags.contains(OpenFlags::CREATE) {
         root_dir
@@ -430,11 +520,7 @@ pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     } else {
         root_dir.open_file(path_in_fs).ok()
     };
-
-    maybe_inode.map(|mut inode| {
-        if flags.contains(OpenFlags::TRUNC) {
-            inode.truncate().expect("Truncation failed");
-        }
+    let maybe_inode = maybe_inode.map(|inode| {
         Arc::new(OSInode::new(
             readable,
             writable,
@@ -442,6 +528,16 @@ pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
             false,
             full_path, // 传入完整路径
         ))
+    });
+    // 截断经由页缓存的后备文件完成，需要再次取得根目录锁
+    drop(root_dir);
+
+    maybe_inode.map(|inode| {
+        if flags.contains(OpenFlags::TRUNC) {
+            inode.truncate().expect("Truncation failed");
+        }
+        inode.set_status_flags(flags);
+        inode
     })
 }
 
@@ -456,7 +552,7 @@ pub fn open_file_at(
     if full_path == "/" {
         return Some(current_root_inode());
     }
-    let root_dir = ROOT_DIR.exclusive_access();
+    let root_dir = root_dir();
 
     // 尝试打开目录
     if let Ok(dir) = root_dir.open_dir(&full_path) {
@@ -477,8 +573,7 @@ pub fn open_file_at(
     } else {
         root_dir.open_file(&full_path)
     };
-
-    file_result.ok().map(|file| {
+    let file_result = file_result.ok().map(|file| {
         Arc::new(OSInode::new(
             flags.contains(OpenFlags::RDONLY) || flags.contains(OpenFlags::RDWR),
             flags.contains(OpenFlags::WRONLY) || flags.contains(OpenFlags::RDWR),
@@ -486,6 +581,16 @@ pub fn open_file_at(
             false, // 不是目录
             full_path,
         ))
+    });
+    drop(root_dir);
+
+    file_result.map(|inode| {
+        if flags.contains(OpenFlags::TRUNC) && inode.writable {
+            // 截断失败时保留原有内容，与打开失败相比影响更小
+            let _ = inode.truncate();
+        }
+        inode.set_status_flags(flags);
+        inode
     })
 }
 
@@ -511,7 +616,7 @@ pub fn create_dir(path: &str) -> Result<Arc<OSInode>, isize> {
         return Err(-1);
     }
 
-    let root_dir = ROOT_DIR.exclusive_access();
+    let root_dir = root_dir();
 
     // 3. 打开父目录
     let mut parent_dir = if parent_path.is_empty() {
@@ -549,7 +654,7 @@ pub fn open_dir(path: &str) -> Result<Arc<OSInode>, isize> {
     };
 
     let path_in_fs = full_path.strip_prefix("/").unwrap_or(&full_path);
-    let root_dir = ROOT_DIR.exclusive_access();
+    let root_dir = root_dir();
 
     root_dir
         .open_dir(path_in_fs)
@@ -578,7 +683,7 @@ pub fn get_size<IO: fatfs::ReadWriteSeek, TP: fatfs::TimeProvider, OCC: fatfs::O
 }
 
 pub fn current_root_inode() -> Arc<OSInode> {
-    let root_dir = ROOT_DIR.exclusive_access();
+    let root_dir = root_dir();
     Arc::new(OSInode::new(
         true,
         false,
@@ -587,3 +692,36 @@ pub fn current_root_inode() -> Arc<OSInode> {
         String::from("/"),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_path;
+
+    #[test_case]
+    fn absolute_path_ignores_base() {
+        assert_eq!(resolve_path("/a/b", "/x/y"), "/a/b");
+    }
+
+    #[test_case]
+    fn relative_path_joins_base() {
+        assert_eq!(resolve_path("b/c", "/a"), "/a/b/c");
+        assert_eq!(resolve_path("", "/a/b"), "/a/b");
+    }
+
+    #[test_case]
+    fn dot_components() {
+        assert_eq!(resolve_path("./b/../c", "/a"), "/a/c");
+        assert_eq!(resolve_path("..", "/a/b/."), "/a");
+    }
+
+    #[test_case]
+    fn dotdot_stops_at_root() {
+        assert_eq!(resolve_path("../../..", "/a"), "/");
+        assert_eq!(resolve_path("/../b", "/a"), "/b");
+    }
+
+    #[test_case]
+    fn redundant_slashes() {
+        assert_eq!(resolve_path("//a///b/", "/"), "/a/b");
+    }
+}
@@ -0,0 +1,330 @@
+//! # 建议性文件锁模块
+//!
+//! ## Overview
+//! 本模块实现两类互相独立的建议性（advisory）文件锁：
+//! - POSIX 记录锁（`fcntl` 的 `F_GETLK` / `F_SETLK` / `F_SETLKW`）：
+//!   以字节区间为粒度，归属于进程；进程关闭该文件的任意描述符或退出时全部释放
+//! - BSD 整文件锁（`flock`）：归属于打开文件描述（open file description），
+//!   最后一个引用它的描述符关闭时释放
+//!
+//! ## Assumptions
+//! - FAT32 每次打开都会生成新的 `OSInode`，因此以文件的完整路径标识同一个文件
+//! - 单处理器环境，锁表由 `UPIntrFreeCell` 保护
+//!
+//! ## Safety
+//! - 阻塞前先将当前任务放入等待队列并释放锁表，再触发调度
+//! - 被唤醒的任务会从等待队列中移除自身，避免被重复加入就绪队列
+//!
+//! ## Invariants
+//! - 同一文件上，不同拥有者的重叠锁中不存在排他锁
+//! - 同一拥有者在同一文件上的锁区间互不重叠
+//!
+//! ## Behavior
+//! - 任意锁被释放或降级时唤醒全部等待者，由等待者自行重新检查冲突
+//! - 等待期间进程收到信号则返回 `EINTR`
+
+use crate::fs::inode::OSInode;
+use crate::fs::File;
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{EAGAIN, EINTR};
+use crate::task::{
+    block_current_and_run_next_interruptible, current_process, current_task, wake_interruptible,
+    TaskControlBlock,
+};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// 用户态 `struct flock`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Flock {
+    /// 锁类型：F_RDLCK / F_WRLCK / F_UNLCK
+    pub l_type: i16,
+    /// `l_start` 的基准：SEEK_SET / SEEK_CUR / SEEK_END
+    pub l_whence: i16,
+    /// 区间起点偏移
+    pub l_start: i64,
+    /// 区间长度，0 表示一直到文件末尾之后
+    pub l_len: i64,
+    /// F_GETLK 时返回持有冲突锁的进程
+    pub l_pid: i32,
+}
+
+/// 锁的类型
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LockKind {
+    /// 共享锁（读锁）
+    Shared,
+    /// 排他锁（写锁）
+    Exclusive,
+}
+
+/// 锁的拥有者
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum LockOwner {
+    /// POSIX 记录锁，归属于进程
+    Process(usize),
+    /// flock 锁，归属于打开文件描述（以其地址标识）
+    OpenFile(usize),
+}
+
+impl LockOwner {
+    /// POSIX 锁与 flock 锁互不影响
+    fn same_class(&self, other: &LockOwner) -> bool {
+        matches!(
+            (self, other),
+            (LockOwner::Process(_), LockOwner::Process(_))
+                | (LockOwner::OpenFile(_), LockOwner::OpenFile(_))
+        )
+    }
+}
+
+/// 一把已持有的锁，覆盖区间 `[start, end)`
+#[derive(Copy, Clone, Debug)]
+struct FileLock {
+    owner: LockOwner,
+    kind: LockKind,
+    start: usize,
+    end: usize,
+}
+
+impl FileLock {
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        self.start < end && start < self.end
+    }
+
+    /// 判断 `owner` 以 `kind` 锁住 `[start, end)` 是否与本锁冲突
+    fn conflicts(&self, owner: LockOwner, kind: LockKind, start: usize, end: usize) -> bool {
+        self.owner != owner
+            && self.owner.same_class(&owner)
+            && self.overlaps(start, end)
+            && (self.kind == LockKind::Exclusive || kind == LockKind::Exclusive)
+    }
+}
+
+/// F_GETLK 查询到的冲突锁
+pub struct LockConflict {
+    pub kind: LockKind,
+    pub start: usize,
+    pub end: usize,
+    pub pid: usize,
+}
+
+/// 全局锁表
+struct LockTable {
+    /// 文件路径 -> 该文件上的全部锁
+    files: BTreeMap<String, Vec<FileLock>>,
+    /// 因锁冲突而阻塞的任务
+    wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+impl LockTable {
+    /// 从 `owner` 持有的锁中挖掉 `[start, end)`，必要时把一把锁拆成两段
+    fn remove_range(&mut self, path: &str, owner: LockOwner, start: usize, end: usize) {
+        let Some(locks) = self.files.get_mut(path) else {
+            return;
+        };
+        let mut kept = Vec::with_capacity(locks.len());
+        for lock in locks.drain(..) {
+            if lock.owner != owner || !lock.overlaps(start, end) {
+                kept.push(lock);
+                continue;
+            }
+            if lock.start < start {
+                kept.push(FileLock { end: start, ..lock });
+            }
+            if lock.end > end {
+                kept.push(FileLock { start: end, ..lock });
+            }
+        }
+        if kept.is_empty() {
+            self.files.remove(path);
+        } else {
+            *locks = kept;
+        }
+    }
+
+    /// 唤醒所有等待者，让它们重新检查冲突
+    fn wake_all(&mut self) {
+        while let Some(task) = self.wait_queue.pop_front() {
+            wake_interruptible(task);
+        }
+    }
+}
+
+lazy_static! {
+    static ref FILE_LOCKS: UPIntrFreeCell<LockTable> = unsafe {
+        UPIntrFreeCell::new(LockTable {
+            files: BTreeMap::new(),
+            wait_queue: VecDeque::new(),
+        })
+    };
+}
+
+/// 加锁，冲突时按 `wait` 决定阻塞还是返回 `EAGAIN`
+fn acquire(
+    path: &str,
+    owner: LockOwner,
+    kind: LockKind,
+    start: usize,
+    end: usize,
+    wait: bool,
+) -> Result<(), isize> {
+    loop {
+        let mut table = FILE_LOCKS.exclusive_access();
+        let conflict = table
+            .files
+            .get(path)
+            .map_or(false, |locks| {
+                locks.iter().any(|l| l.conflicts(owner, kind, start, end))
+            });
+        if !conflict {
+            table.remove_range(path, owner, start, end);
+            table
+                .files
+                .entry(path.to_string())
+                .or_insert_with(Vec::new)
+                .push(FileLock {
+                    owner,
+                    kind,
+                    start,
+                    end,
+                });
+            // 排他锁降级为共享锁后，可能有等待者可以继续
+            table.wake_all();
+            return Ok(());
+        }
+        if !wait {
+            return Err(-EAGAIN);
+        }
+        let task = current_task().unwrap();
+        table.wait_queue.push_back(task.clone());
+        drop(table);
+        block_current_and_run_next_interruptible();
+        // 可能是被信号唤醒的，此时自己仍在等待队列中
+        FILE_LOCKS
+            .exclusive_access()
+            .wait_queue
+            .retain(|t| !Arc::ptr_eq(t, &task));
+        if !current_process().inner_exclusive_access().signals.is_empty() {
+            return Err(-EINTR);
+        }
+    }
+}
+
+/// 释放 `owner` 在 `[start, end)` 上的锁并唤醒等待者
+fn release(path: &str, owner: LockOwner, start: usize, end: usize) {
+    let mut table = FILE_LOCKS.exclusive_access();
+    table.remove_range(path, owner, start, end);
+    table.wake_all();
+}
+
+/// 取得记录锁所作用的普通文件路径
+fn lockable_path(file: &Arc<dyn File + Send + Sync>) -> Option<String> {
+    file.as_any()
+        .downcast_ref::<OSInode>()
+        .filter(|inode| !inode.is_dir())
+        .map(|inode| inode.get_path())
+}
+
+/// 设置或清除 POSIX 记录锁（F_SETLK / F_SETLKW）
+///
+/// `kind` 为 `None` 表示解锁
+pub fn set_posix_lock(
+    path: &str,
+    pid: usize,
+    kind: Option<LockKind>,
+    start: usize,
+    end: usize,
+    wait: bool,
+) -> Result<(), isize> {
+    let owner = LockOwner::Process(pid);
+    match kind {
+        Some(kind) => acquire(path, owner, kind, start, end, wait),
+        None => {
+            release(path, owner, start, end);
+            Ok(())
+        }
+    }
+}
+
+/// 查询 `pid` 以 `kind` 锁住 `[start, end)` 时会与哪把锁冲突（F_GETLK）
+pub fn test_posix_lock(
+    path: &str,
+    pid: usize,
+    kind: LockKind,
+    start: usize,
+    end: usize,
+) -> Option<LockConflict> {
+    let table = FILE_LOCKS.exclusive_access();
+    let locks = table.files.get(path)?;
+    locks
+        .iter()
+        .find(|l| l.conflicts(LockOwner::Process(pid), kind, start, end))
+        .map(|l| LockConflict {
+            kind: l.kind,
+            start: l.start,
+            end: l.end,
+            pid: match l.owner {
+                LockOwner::Process(pid) => pid,
+                LockOwner::OpenFile(_) => 0,
+            },
+        })
+}
+
+/// 进程关闭了 `file` 的某个描述符：释放该进程在此文件上的全部记录锁
+pub fn release_posix_locks(file: &Arc<dyn File + Send + Sync>, pid: usize) {
+    if let Some(path) = lockable_path(file) {
+        release(&path, LockOwner::Process(pid), 0, usize::MAX);
+    }
+}
+
+/// 进程退出：释放它持有的全部记录锁
+pub fn release_all_posix_locks(pid: usize) {
+    let mut table = FILE_LOCKS.exclusive_access();
+    let paths: Vec<String> = table.files.keys().cloned().collect();
+    for path in paths {
+        table.remove_range(&path, LockOwner::Process(pid), 0, usize::MAX);
+    }
+    table.wake_all();
+}
+
+/// 对整个文件加 / 解 flock 锁
+///
+/// `file_id` 为打开文件描述的地址，`kind` 为 `None` 表示解锁
+pub fn flock(path: &str, file_id: usize, kind: Option<LockKind>, wait: bool) -> Result<(), isize> {
+    let owner = LockOwner::OpenFile(file_id);
+    match kind {
+        Some(kind) => {
+            // flock 的锁转换不是原子的：先放掉旧锁，再重新申请
+            release(path, owner, 0, usize::MAX);
+            acquire(path, owner, kind, 0, usize::MAX, wait)
+        }
+        None => {
+            release(path, owner, 0, usize::MAX);
+            Ok(())
+        }
+    }
+}
+
+/// 打开文件描述被销毁：释放其上的 flock 锁
+pub fn release_flocks(file_id: usize) {
+    let mut table = FILE_LOCKS.exclusive_access();
+    let owner = LockOwner::OpenFile(file_id);
+    let paths: Vec<String> = table
+        .files
+        .iter()
+        .filter(|(_, locks)| locks.iter().any(|l| l.owner == owner))
+        .map(|(path, _)| path.clone())
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    for path in paths {
+        table.remove_range(&path, owner, 0, usize::MAX);
+    }
+    table.wake_all();
+}
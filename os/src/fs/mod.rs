@@ -1,16 +1,36 @@
 mod block_cache;
+mod dev;
 mod fat32;
-mod file;
+pub(crate) mod file;
 pub(crate) mod inode;
+mod initramfs;
+mod lock;
+mod page_cache;
 mod pipe;
+mod proc;
 mod stdio;
+mod tty;
 
-pub use block_cache::{block_cache_sync_all, get_block_cache};
-pub use fat32::FatFsBlockDevice;
+pub use block_cache::{
+    block_cache_flush_expired, block_cache_stats, block_cache_sync_all, get_block_cache,
+    BlockCacheStats,
+};
+pub use dev::open_dev;
+pub use fat32::{unmount_root, FatFsBlockDevice};
 pub use file::{DirEntry, File, LinuxDirent64, UserStat};
+pub use initramfs::{is_boot_image, load_initrd};
 pub use inode::{
-    current_root_inode, list_apps, open_dir, open_file, open_file_at, open_initproc, resolve_path,
+    current_root_inode, list_apps, open_boot_file, open_dir, open_file, open_file_at, resolve_path,
     OpenFlags,
 };
+pub use lock::{
+    flock, release_all_posix_locks, release_flocks, release_posix_locks, set_posix_lock,
+    test_posix_lock, Flock, LockKind,
+};
+pub use page_cache::{
+    page_cache_reclaim, page_cache_shutdown, page_cache_sync_all, page_cache_unlink, PageCache,
+};
 pub use pipe::make_pipe;
+pub use proc::open_proc;
 pub use stdio::{Stdin, Stdout};
+pub use tty::TTY;
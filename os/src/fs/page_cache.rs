@@ -0,0 +1,603 @@
+//! # 页缓存模块（Page Cache Module）
+//!
+//! ## Overview
+//! 以 4 KiB 物理页帧为单位缓存普通文件的内容，按文件内页号索引。
+//! 同一路径的文件只有一个 `PageCache`，所有打开该文件的 `OSInode` 共享它：
+//! - `File::read` / `write` / `read_at` / `write_at` 经由页缓存读写
+//! - 文件映射（`mmap`）与 ELF 只读段直接映射缓存页帧，不再复制
+//! - 写入只修改缓存页并标记为脏，`fsync` / `page_cache_sync_all` 时写回
+//!
+//! 每个页缓存持有一个自己打开的 `fatfs` 文件对象作为后备，
+//! 缺页填充、写回与截断都经由它完成，避免多个文件对象各自缓存的
+//! 簇链、长度等元数据互相覆盖。
+//!
+//! ## Assumptions
+//! - 单核，文件系统只有启动盘上的一个 FAT 卷
+//! - FAT 文件名大小写不敏感，注册表以小写的绝对路径为键
+//!
+//! ## Safety
+//! - `fatfs::File` 不是 `Send`，与 `OSInode` 一样依赖单核串行访问
+//! - 加锁顺序为后备文件锁、根目录锁、`inner`；`inner` 是自旋锁，持有时不睡眠也不访问磁盘，
+//!   缺页填充与写回在 `inner` 之外进行
+//!
+//! ## Invariants
+//! - `size` 是文件的逻辑长度（含尚未写回的部分），`disk_size` 是磁盘上的长度
+//! - 页号不小于 `disk_size` 对应页号的缓存页不从磁盘填充，初始为零
+//! - 被映射到用户地址空间的页帧 `Arc` 强引用计数大于 1，不会被回收
+//! - 共享可写映射的页在映射存在期间始终视为脏页
+//!
+//! ## Behavior
+//! - 写回按页号升序进行；页之间的空洞写入零
+//! - 以可写方式打开的文件的最后一个描述符关闭时，按命令行 `fsync_on_close=` 的策略写回：
+//!   `never` 不写回，`data`（缺省）写回本文件的脏页，`full` 另外刷新块缓存
+//! - 卸载（`page_cache_shutdown`）时写回全部脏页并关闭后备文件
+//! - 物理页帧不足时 `frame_alloc` 调用 `page_cache_reclaim`，
+//!   以时钟算法回收干净且未被映射的页：最近访问过的页先清除访问位，第二轮才回收
+//! - 回收只尝试获取锁，持锁中的缓存不参与本轮回收
+//! - 脏页不能直接回收：可以睡眠的读写路径分配不到页帧时先写回所有脏页，回收后再试一次
+//! - 建立映射时持有进程锁，`map_page` / `try_read_at` 不睡眠，`mmap` 事先以 `prefetch` 读入
+//! - 文件被删除时没有打开者则丢弃其页缓存（包括脏页）；仍有打开者时先读入全部内容，
+//!   之后缓存与磁盘脱离，不再填充、写回或回收，最后一个打开者关闭时释放
+
+use crate::drivers::BlockingIo;
+use crate::fs::inode::{drop_fat_object, root_dir, try_root_dir, FatType, FsGuard};
+use crate::fs::FatFsBlockDevice;
+use crate::hal::PAGE_SIZE;
+use crate::mm::{frame_alloc, FrameTracker};
+use crate::sync::{SleepMutex, SleepMutexGuard};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use fatfs::{DefaultTimeProvider, LossyOemCpConverter, Read, Seek, SeekFrom, Write};
+use lazy_static::*;
+use spin::Mutex;
+
+type FatFile = fatfs::File<'static, FatFsBlockDevice, DefaultTimeProvider, LossyOemCpConverter>;
+
+/// 一次回收的目标页数
+const RECLAIM_BATCH: usize = 64;
+
+/// 最后一个描述符关闭时的写回策略
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ClosePolicy {
+    Never,
+    Data,
+    Full,
+}
+
+/// 单个缓存页
+struct CachedPage {
+    frame: Arc<FrameTracker>,
+    /// 内容与磁盘不一致
+    dirty: bool,
+    /// 被共享可写映射，映射解除前一直视为脏页
+    mapped_writable: bool,
+    /// 时钟算法的访问位
+    accessed: bool,
+}
+
+impl CachedPage {
+    fn is_mapped(&self) -> bool {
+        Arc::strong_count(&self.frame) > 1
+    }
+}
+
+struct PageCacheInner {
+    pages: BTreeMap<usize, CachedPage>,
+    size: usize,
+    disk_size: usize,
+    /// 文件已被删除而仍有打开者，内容只在缓存中
+    detached: bool,
+}
+
+/// 一个文件的页缓存
+pub struct PageCache {
+    path: String,
+    inner: Mutex<PageCacheInner>,
+    /// 后备文件对象，首次需要访问磁盘时打开；缺页填充、写回与截断持有它进行
+    backing: SleepMutex<Option<FatFile>>,
+}
+
+// 理由同 `OSInode`：单核下同一时间只有一个任务通过锁访问后备文件对象
+unsafe impl Send for PageCache {}
+unsafe impl Sync for PageCache {}
+
+lazy_static! {
+    /// 路径（小写）到页缓存的注册表
+    static ref PAGE_CACHES: Mutex<BTreeMap<String, Arc<PageCache>>> = Mutex::new(BTreeMap::new());
+    static ref CLOSE_POLICY: ClosePolicy = match crate::cmdline::get("fsync_on_close").as_deref() {
+        Some("never") => ClosePolicy::Never,
+        Some("full") => ClosePolicy::Full,
+        _ => ClosePolicy::Data,
+    };
+}
+
+fn cache_key(path: &str) -> String {
+    path.to_ascii_lowercase()
+}
+
+/// 取得 `path` 的页缓存，不存在时以磁盘长度 `disk_size` 新建
+pub fn page_cache(path: &str, disk_size: usize) -> Arc<PageCache> {
+    PAGE_CACHES
+        .lock()
+        .entry(cache_key(path))
+        .or_insert_with(|| {
+            Arc::new(PageCache {
+                path: String::from(path),
+                inner: Mutex::new(PageCacheInner {
+                    pages: BTreeMap::new(),
+                    size: disk_size,
+                    disk_size,
+                    detached: false,
+                }),
+                backing: SleepMutex::new(None),
+            })
+        })
+        .clone()
+}
+
+/// 删除文件 `path`：`remove` 在根目录锁下删除目录项，返回是否成功
+///
+/// 删除成功后，没有打开者的页缓存被丢弃，脏页不再写回；
+/// 仍有打开者时事先读入全部内容，缓存与磁盘脱离，打开者照常读写直到关闭
+pub fn page_cache_unlink(path: &str, remove: impl FnOnce(&FsGuard) -> bool) -> bool {
+    let key = cache_key(path);
+    let root = root_dir();
+    let cache = PAGE_CACHES.lock().get(&key).cloned();
+    let Some(cache) = cache else {
+        return remove(&root);
+    };
+    // 后备文件的锁在根目录锁之前获取
+    drop(root);
+    let mut backing = cache.backing.lock();
+    let root = root_dir();
+    // 注册表与这里各持有一个引用；打开文件需要根目录锁，此时打开者不会增加
+    let opened = Arc::strong_count(&cache) > 2;
+    if opened {
+        // 目录项删除后文件的簇随时会被重用，先读入尚未缓存的内容
+        cache.load_all(&mut backing, &root);
+    }
+    // 在目录项被删除之前关闭后备文件，避免它在之后写回目录项
+    drop(backing.take());
+    if !remove(&root) {
+        return false;
+    }
+    drop(root);
+    PAGE_CACHES.lock().remove(&key);
+    let mut inner = cache.inner.lock();
+    if opened {
+        inner.detached = true;
+        inner.disk_size = 0;
+    } else {
+        inner.pages.clear();
+    }
+    true
+}
+
+/// 写回所有页缓存中的脏页，返回写回失败的文件数
+pub fn page_cache_sync_all() -> usize {
+    let caches: Vec<Arc<PageCache>> = PAGE_CACHES.lock().values().cloned().collect();
+    caches.iter().filter(|cache| cache.sync().is_err()).count()
+}
+
+/// 卸载前写回所有脏页并关闭后备文件，返回写回失败的文件数
+pub fn page_cache_shutdown() -> usize {
+    let caches: Vec<Arc<PageCache>> = PAGE_CACHES.lock().values().cloned().collect();
+    let failed = caches.iter().filter(|cache| cache.sync().is_err()).count();
+    for cache in caches.iter() {
+        if let Some(file) = cache.backing.lock().take() {
+            let root = root_dir();
+            drop(file);
+            drop(root);
+        }
+    }
+    failed
+}
+
+/// 回收干净且未被映射的缓存页，返回回收的页数
+///
+/// 由 `frame_alloc` 在物理页帧耗尽时调用，此时不能持有分配器的锁
+pub fn page_cache_reclaim() -> usize {
+    let Some(mut caches) = PAGE_CACHES.try_lock() else {
+        return 0;
+    };
+    let mut freed = 0;
+    for second_chance in [true, false] {
+        for cache in caches.values() {
+            if let Some(mut inner) = cache.inner.try_lock() {
+                freed += inner.evict(RECLAIM_BATCH - freed, second_chance);
+            }
+            if freed >= RECLAIM_BATCH {
+                return freed;
+            }
+        }
+    }
+    // 顺便释放已经没有打开者、也没有缓存页的页缓存
+    caches.retain(|_, cache| {
+        Arc::strong_count(cache) > 1
+            || cache
+                .inner
+                .try_lock()
+                .map_or(true, |inner| !inner.pages.is_empty())
+    });
+    freed
+}
+
+/// 物理页帧不足时在可以睡眠的路径上调用：写回所有脏页使它们可以被回收，再回收一批
+fn relieve_memory_pressure() -> bool {
+    page_cache_sync_all();
+    page_cache_reclaim() > 0
+}
+
+/// 取得根目录锁；`may_block` 为假时不睡眠，锁被占用时返回 `None`
+fn lock_fs(may_block: bool) -> Option<FsGuard> {
+    if may_block {
+        Some(root_dir())
+    } else {
+        try_root_dir()
+    }
+}
+
+/// 后备文件对象，还没有打开时经由根目录打开
+fn open_backing<'a>(
+    backing: &'a mut Option<FatFile>,
+    root: &FsGuard,
+    path: &str,
+) -> Result<&'a mut FatFile, ()> {
+    if backing.is_none() {
+        let file = root
+            .open_file(path.trim_start_matches('/'))
+            .map_err(|_| ())?;
+        *backing = Some(file);
+    }
+    Ok(backing.as_mut().unwrap())
+}
+
+/// 按页号升序写回 `pages`，`disk_size` 随写入更新
+fn write_pages(
+    file: &mut FatFile,
+    size: usize,
+    disk_size: &mut usize,
+    pages: &[(usize, Arc<FrameTracker>)],
+) -> Result<(), ()> {
+    for (index, frame) in pages.iter() {
+        let start = index * PAGE_SIZE;
+        if start >= size {
+            continue;
+        }
+        // fatfs 不能越过文件末尾定位，先以零填补空洞
+        if start > *disk_size {
+            file.seek(SeekFrom::End(0)).map_err(|_| ())?;
+            let zeros = [0u8; 512];
+            while *disk_size < start {
+                let n = zeros.len().min(start - *disk_size);
+                file.write_all(&zeros[..n]).map_err(|_| ())?;
+                *disk_size += n;
+            }
+        }
+        let len = PAGE_SIZE.min(size - start);
+        file.seek(SeekFrom::Start(start as u64)).map_err(|_| ())?;
+        file.write_all(&frame.ppn.get_bytes_array()[..len])
+            .map_err(|_| ())?;
+        *disk_size = (*disk_size).max(start + len);
+    }
+    file.flush().map_err(|_| ())
+}
+
+impl PageCacheInner {
+    /// 回收至多 `limit` 页；`second_chance` 时跳过并清除最近访问过的页
+    fn evict(&mut self, limit: usize, second_chance: bool) -> usize {
+        // 脱离磁盘的缓存中的页回收后无法再读回
+        if self.detached {
+            return 0;
+        }
+        let mut victims = Vec::new();
+        for (&index, page) in self.pages.iter_mut() {
+            if victims.len() >= limit {
+                break;
+            }
+            if page.dirty || page.is_mapped() {
+                continue;
+            }
+            if second_chance && page.accessed {
+                page.accessed = false;
+                continue;
+            }
+            victims.push(index);
+        }
+        for index in victims.iter() {
+            self.pages.remove(index);
+        }
+        victims.len()
+    }
+
+    /// 把页重新标记为脏页（写回失败时）
+    fn mark_dirty(&mut self, pages: &[(usize, Arc<FrameTracker>)]) {
+        for (index, frame) in pages.iter() {
+            if let Some(page) = self.pages.get_mut(index) {
+                if Arc::ptr_eq(&page.frame, frame) {
+                    page.dirty = true;
+                }
+            }
+        }
+    }
+}
+
+impl PageCache {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 文件的逻辑长度
+    pub fn size(&self) -> usize {
+        self.inner.lock().size
+    }
+
+    fn lock_backing(&self, may_block: bool) -> Option<SleepMutexGuard<'_, Option<FatFile>>> {
+        if may_block {
+            Some(self.backing.lock())
+        } else {
+            self.backing.try_lock()
+        }
+    }
+
+    /// 已在缓存中的第 `index` 页
+    fn cached(&self, index: usize) -> Option<Arc<FrameTracker>> {
+        let mut inner = self.inner.lock();
+        let page = inner.pages.get_mut(&index)?;
+        page.accessed = true;
+        Some(page.frame.clone())
+    }
+
+    /// 放入新的一页；读入期间这一页已被放入（如被整页写入）时保留已有的页
+    fn insert(&self, index: usize, frame: FrameTracker) -> Arc<FrameTracker> {
+        let mut inner = self.inner.lock();
+        let page = inner.pages.entry(index).or_insert_with(|| CachedPage {
+            frame: Arc::new(frame),
+            dirty: false,
+            mapped_writable: false,
+            accessed: true,
+        });
+        page.frame.clone()
+    }
+
+    /// 在持有后备文件锁与根目录锁时，从磁盘把第 `index` 页读入 `frame`
+    fn read_page(
+        &self,
+        backing: &mut Option<FatFile>,
+        root: &FsGuard,
+        index: usize,
+        frame: &FrameTracker,
+    ) -> Option<()> {
+        let start = index * PAGE_SIZE;
+        let disk_size = self.inner.lock().disk_size;
+        if start < disk_size {
+            let len = PAGE_SIZE.min(disk_size - start);
+            let file = open_backing(backing, root, &self.path).ok()?;
+            file.seek(SeekFrom::Start(start as u64)).ok()?;
+            file.read_exact(&mut frame.ppn.get_bytes_array()[..len])
+                .ok()?;
+        }
+        Some(())
+    }
+
+    /// 读入磁盘上尚未缓存的全部页，在持有后备文件锁与根目录锁时调用
+    fn load_all(&self, backing: &mut Option<FatFile>, root: &FsGuard) {
+        let pages = (self.inner.lock().disk_size + PAGE_SIZE - 1) / PAGE_SIZE;
+        for index in 0..pages {
+            if self.cached(index).is_some() {
+                continue;
+            }
+            let loaded = frame_alloc().and_then(|frame| {
+                self.read_page(backing, root, index, &frame)?;
+                Some(frame)
+            });
+            let Some(frame) = loaded else {
+                log::warn!("[page_cache] {} lost unread pages after unlink", self.path);
+                return;
+            };
+            self.insert(index, frame);
+        }
+    }
+
+    /// 取得第 `index` 页，不在缓存中时分配页帧；`fill` 时从磁盘读入原有内容
+    ///
+    /// 读入持有后备文件的锁与根目录锁，不持有 `inner`；
+    /// `may_block` 为假时不睡眠，需要等待锁时返回 `None`
+    fn page(&self, index: usize, fill: bool, may_block: bool) -> Option<Arc<FrameTracker>> {
+        if let Some(frame) = self.cached(index) {
+            return Some(frame);
+        }
+        let frame = match frame_alloc() {
+            Some(frame) => frame,
+            None if may_block && relieve_memory_pressure() => frame_alloc()?,
+            None => return None,
+        };
+        if !fill || index * PAGE_SIZE >= self.inner.lock().disk_size {
+            return Some(self.insert(index, frame));
+        }
+        let mut backing = self.lock_backing(may_block)?;
+        // 等待期间别的任务可能已经读入了这一页，或者截断了文件
+        if let Some(frame) = self.cached(index) {
+            return Some(frame);
+        }
+        let root = lock_fs(may_block)?;
+        self.read_page(&mut backing, &root, index, &frame)?;
+        drop(root);
+        Some(self.insert(index, frame))
+    }
+
+    fn read(&self, offset: usize, buf: &mut [u8], may_block: bool) -> usize {
+        let size = self.size();
+        if offset >= size {
+            return 0;
+        }
+        let end = size.min(offset + buf.len());
+        let mut pos = offset;
+        while pos < end {
+            let page_offset = pos % PAGE_SIZE;
+            let n = (PAGE_SIZE - page_offset).min(end - pos);
+            let Some(frame) = self.page(pos / PAGE_SIZE, true, may_block) else {
+                break;
+            };
+            buf[pos - offset..pos - offset + n]
+                .copy_from_slice(&frame.ppn.get_bytes_array()[page_offset..page_offset + n]);
+            pos += n;
+        }
+        pos - offset
+    }
+
+    /// 从 `offset` 处读取，返回读取的字节数；内存不足或磁盘出错时提前返回
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.read(offset, buf, true)
+    }
+
+    /// 与 `read_at` 相同但不睡眠，需要等待锁时提前返回；供持有进程锁的路径使用
+    pub fn try_read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.read(offset, buf, false)
+    }
+
+    /// 从 `offset` 处写入，返回写入的字节数；写到文件末尾之后时延长文件
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let end = offset + buf.len();
+        let mut pos = offset;
+        while pos < end {
+            let index = pos / PAGE_SIZE;
+            let page_offset = pos % PAGE_SIZE;
+            let n = (PAGE_SIZE - page_offset).min(end - pos);
+            // 整页覆盖时不必先从磁盘读入
+            let fill = n < PAGE_SIZE;
+            let Some(frame) = self.page(index, fill, true) else {
+                break;
+            };
+            frame.ppn.get_bytes_array()[page_offset..page_offset + n]
+                .copy_from_slice(&buf[pos - offset..pos - offset + n]);
+            pos += n;
+            // 复制完成之后才标记脏页，并发的写回不会漏掉这次写入
+            let mut inner = self.inner.lock();
+            if let Some(page) = inner.pages.get_mut(&index) {
+                page.dirty = true;
+            }
+            inner.size = inner.size.max(pos);
+        }
+        pos - offset
+    }
+
+    /// 取得第 `index` 页的页帧用于映射；`writable` 表示共享可写映射
+    ///
+    /// 在持有进程锁时调用，不睡眠：页不在缓存中且需要等待锁时返回 `None`
+    pub fn map_page(&self, index: usize, writable: bool) -> Option<Arc<FrameTracker>> {
+        let frame = self.page(index, true, false)?;
+        if writable {
+            if let Some(page) = self.inner.lock().pages.get_mut(&index) {
+                page.mapped_writable = true;
+                page.dirty = true;
+            }
+        }
+        Some(frame)
+    }
+
+    /// 读入 `[offset, offset + len)` 中文件范围内的页，之后在持有进程锁时映射它们
+    pub fn prefetch(&self, offset: usize, len: usize) {
+        let end = self.size().min(offset.saturating_add(len));
+        let mut pos = offset / PAGE_SIZE * PAGE_SIZE;
+        while pos < end {
+            if self.page(pos / PAGE_SIZE, true, true).is_none() {
+                break;
+            }
+            pos += PAGE_SIZE;
+        }
+    }
+
+    /// 把文件截断为空并丢弃所有缓存页
+    pub fn truncate(&self) -> Result<(), ()> {
+        let mut backing = self.backing.lock();
+        {
+            let mut inner = self.inner.lock();
+            inner.pages.clear();
+            inner.size = 0;
+            inner.disk_size = 0;
+            if inner.detached {
+                return Ok(());
+            }
+        }
+        let root = root_dir();
+        let file = open_backing(&mut backing, &root, &self.path)?;
+        file.seek(SeekFrom::Start(0)).map_err(|_| ())?;
+        file.truncate().map_err(|_| ())?;
+        file.flush().map_err(|_| ())
+    }
+
+    /// 写回脏页：在 `inner` 中取下脏页并清除脏标志，在不持有 `inner` 时写盘，
+    /// 失败时重新标记；写回期间的写入会再次标记脏页。脱离磁盘的缓存不写回
+    fn writeback(&self, may_block: bool) -> Result<(), ()> {
+        let mut backing = self.lock_backing(may_block).ok_or(())?;
+        let (size, mut disk_size, dirty) = {
+            let mut inner = self.inner.lock();
+            if inner.detached {
+                return Ok(());
+            }
+            let mut dirty = Vec::new();
+            for (&index, page) in inner.pages.iter_mut() {
+                if !page.dirty {
+                    continue;
+                }
+                if page.mapped_writable && !page.is_mapped() {
+                    page.mapped_writable = false;
+                }
+                page.dirty = page.mapped_writable;
+                dirty.push((index, page.frame.clone()));
+            }
+            (inner.size, inner.disk_size, dirty)
+        };
+        if dirty.is_empty() {
+            return Ok(());
+        }
+        let result = match lock_fs(may_block) {
+            Some(root) => open_backing(&mut backing, &root, &self.path)
+                .and_then(|file| write_pages(file, size, &mut disk_size, &dirty)),
+            None => Err(()),
+        };
+        let mut inner = self.inner.lock();
+        inner.disk_size = disk_size;
+        if result.is_err() {
+            inner.mark_dirty(&dirty);
+        }
+        result
+    }
+
+    /// 写回本文件的脏页
+    pub fn sync(&self) -> Result<(), ()> {
+        self.writeback(true)
+    }
+
+    /// 以可写方式打开的文件的最后一个描述符关闭时调用，按 `fsync_on_close` 策略写回；
+    /// `may_block` 为假时不睡眠，锁被占用时脏页留待之后写回
+    pub fn close(&self, may_block: bool) {
+        let policy = *CLOSE_POLICY;
+        if policy == ClosePolicy::Never {
+            return;
+        }
+        if self.writeback(may_block).is_err() {
+            if may_block {
+                log::warn!("[page_cache] failed to write back {}", self.path);
+            } else {
+                log::debug!("[page_cache] write back of {} deferred", self.path);
+            }
+        }
+        if policy == ClosePolicy::Full {
+            let _io = may_block.then(BlockingIo::enter);
+            crate::fs::block_cache_sync_all();
+        }
+    }
+}
+
+impl Drop for PageCache {
+    /// 后备文件对象在根目录锁下销毁
+    fn drop(&mut self) {
+        if let Some(file) = self.backing.get_mut().take() {
+            drop_fat_object(FatType::File(file));
+        }
+    }
+}
@@ -1,9 +1,11 @@
 use super::UserStat;
+use crate::fs::OpenFlags;
 use crate::mm::UserBuffer;
 use crate::sync::UPIntrFreeCell;
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use core::any::Any;
+use ring_buffer::RingBuffer;
 use crate::fs::file::BLK_SIZE;
 use crate::task::suspend_current_and_run_next;
 
@@ -39,28 +41,16 @@ impl Pipe {
 
 const RING_BUFFER_SIZE: usize = 32;
 
-#[derive(Copy, Clone, PartialEq)]
-enum RingBufferStatus {
-    Full,
-    Empty,
-    Normal,
-}
-
+/// 管道缓冲区：字节环形缓冲区加上写端的弱引用，用来判断写端是否全部关闭
 pub struct PipeRingBuffer {
-    arr: [u8; RING_BUFFER_SIZE],
-    head: usize,
-    tail: usize,
-    status: RingBufferStatus,
+    ring: RingBuffer<RING_BUFFER_SIZE>,
     write_end: Option<Weak<Pipe>>,
 }
 
 impl PipeRingBuffer {
     pub fn new() -> Self {
         Self {
-            arr: [0; RING_BUFFER_SIZE],
-            head: 0,
-            tail: 0,
-            status: RingBufferStatus::Empty,
+            ring: RingBuffer::new(),
             write_end: None,
         }
     }
@@ -68,37 +58,16 @@ impl PipeRingBuffer {
         self.write_end = Some(Arc::downgrade(write_end));
     }
     pub fn write_byte(&mut self, byte: u8) {
-        self.status = RingBufferStatus::Normal;
-        self.arr[self.tail] = byte;
-        self.tail = (self.tail + 1) % RING_BUFFER_SIZE;
-        if self.tail == self.head {
-            self.status = RingBufferStatus::Full;
-        }
+        self.ring.write_byte(byte);
     }
     pub fn read_byte(&mut self) -> u8 {
-        self.status = RingBufferStatus::Normal;
-        let c = self.arr[self.head];
-        self.head = (self.head + 1) % RING_BUFFER_SIZE;
-        if self.head == self.tail {
-            self.status = RingBufferStatus::Empty;
-        }
-        c
+        self.ring.read_byte()
     }
     pub fn available_read(&self) -> usize {
-        if self.status == RingBufferStatus::Empty {
-            0
-        } else if self.tail > self.head {
-            self.tail - self.head
-        } else {
-            self.tail + RING_BUFFER_SIZE - self.head
-        }
+        self.ring.available_read()
     }
     pub fn available_write(&self) -> usize {
-        if self.status == RingBufferStatus::Full {
-            0
-        } else {
-            RING_BUFFER_SIZE - self.available_read()
-        }
+        self.ring.available_write()
     }
     pub fn all_write_ends_closed(&self) -> bool {
         self.write_end.as_ref().unwrap().upgrade().is_none()
@@ -249,6 +218,84 @@ impl super::File for Pipe {
     }
 
     fn as_any(&self) -> &dyn Any {
-        todo!()
+        self
+    }
+
+    fn status_flags(&self) -> OpenFlags {
+        if *self.nonblocking.exclusive_access() {
+            OpenFlags::NONBLOCK
+        } else {
+            OpenFlags::empty()
+        }
+    }
+
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.set_nonblocking(flags.contains(OpenFlags::NONBLOCK));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{make_pipe, PipeRingBuffer, RING_BUFFER_SIZE};
+
+    #[test_case]
+    fn new_buffer_is_empty() {
+        let buffer = PipeRingBuffer::new();
+        assert_eq!(buffer.available_read(), 0);
+        assert_eq!(buffer.available_write(), RING_BUFFER_SIZE);
+    }
+
+    #[test_case]
+    fn bytes_come_out_in_order() {
+        let mut buffer = PipeRingBuffer::new();
+        for byte in b"pipe" {
+            buffer.write_byte(*byte);
+        }
+        assert_eq!(buffer.available_read(), 4);
+        assert_eq!(buffer.available_write(), RING_BUFFER_SIZE - 4);
+        let out: [u8; 4] = core::array::from_fn(|_| buffer.read_byte());
+        assert_eq!(&out, b"pipe");
+        assert_eq!(buffer.available_read(), 0);
+    }
+
+    #[test_case]
+    fn fill_and_drain() {
+        let mut buffer = PipeRingBuffer::new();
+        for i in 0..RING_BUFFER_SIZE {
+            buffer.write_byte(i as u8);
+        }
+        assert_eq!(buffer.available_read(), RING_BUFFER_SIZE);
+        assert_eq!(buffer.available_write(), 0);
+        for i in 0..RING_BUFFER_SIZE {
+            assert_eq!(buffer.read_byte(), i as u8);
+        }
+        assert_eq!(buffer.available_read(), 0);
+        assert_eq!(buffer.available_write(), RING_BUFFER_SIZE);
+    }
+
+    #[test_case]
+    fn wraps_around() {
+        let mut buffer = PipeRingBuffer::new();
+        for round in 0..3 * RING_BUFFER_SIZE {
+            buffer.write_byte(round as u8);
+            buffer.write_byte(!(round as u8));
+            assert_eq!(buffer.available_read(), 2);
+            assert_eq!(buffer.read_byte(), round as u8);
+            assert_eq!(buffer.read_byte(), !(round as u8));
+        }
+        // 写满时头尾重合，仍能区分满与空
+        for _ in 0..RING_BUFFER_SIZE {
+            buffer.write_byte(0);
+        }
+        assert_eq!(buffer.available_read(), RING_BUFFER_SIZE);
+        assert_eq!(buffer.available_write(), 0);
+    }
+
+    #[test_case]
+    fn write_end_closed_after_drop() {
+        let (read_end, write_end) = make_pipe();
+        assert!(!read_end.buffer.exclusive_access().all_write_ends_closed());
+        drop(write_end);
+        assert!(read_end.buffer.exclusive_access().all_write_ends_closed());
     }
 }
@@ -0,0 +1,129 @@
+//! # 内核状态文件
+//!
+//! ## Overview
+//! `/proc` 下的只读文件，内容在打开时生成，之后的读取看到的是打开时的快照：
+//! - `/proc/block_cache`：块缓存统计，每行一个 `名字 数值`
+//!
+//! ## Assumptions
+//! - 路径已经是规范化的绝对路径（`resolve_path` 的结果）
+//!
+//! ## Behavior
+//! - 文件不可写，`read_at` 按快照中的偏移读取
+//! - 不在目录中列出，也不能以 `O_DIRECTORY` 打开 `/proc`
+
+use super::block_cache::block_cache_stats;
+use super::file::{UserStat, BLK_SIZE, S_IFREG};
+use super::File;
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+pub struct ProcFile {
+    path: &'static str,
+    data: Vec<u8>,
+    pos: UPIntrFreeCell<usize>,
+}
+
+/// 打开 `/proc` 下的文件，不是内核状态文件时返回 `None`
+pub fn open_proc(path: &str) -> Option<Arc<ProcFile>> {
+    let (path, data) = match path {
+        "/proc/block_cache" => ("/proc/block_cache", block_cache_text()),
+        _ => return None,
+    };
+    Some(Arc::new(ProcFile {
+        path,
+        data: data.into_bytes(),
+        pos: unsafe { UPIntrFreeCell::new(0) },
+    }))
+}
+
+fn block_cache_text() -> String {
+    let stats = block_cache_stats();
+    format!(
+        "cached {}\ncapacity {}\nhits {}\nmisses {}\nread_ahead {}\nevictions {}\nwritebacks {}\noverflows {}\n",
+        stats.cached,
+        stats.capacity,
+        stats.hits,
+        stats.misses,
+        stats.read_ahead,
+        stats.evictions,
+        stats.writebacks,
+        stats.overflows
+    )
+}
+
+impl ProcFile {
+    fn copy_from(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let start = offset.min(self.data.len());
+        let n = buf.len().min(self.data.len() - start);
+        buf[..n].copy_from_slice(&self.data[start..start + n]);
+        n
+    }
+}
+
+impl File for ProcFile {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        let mut pos = self.pos.exclusive_access();
+        let mut total = 0;
+        for slice in buf.buffers.iter_mut() {
+            let n = self.copy_from(*pos, slice);
+            *pos += n;
+            total += n;
+            if n < slice.len() {
+                break;
+            }
+        }
+        total
+    }
+    fn write(&self, _buf: UserBuffer) -> usize {
+        0
+    }
+    fn get_stat(&self) -> UserStat {
+        UserStat {
+            st_dev: 0,
+            st_ino: 0,
+            st_mode: S_IFREG | 0o444,
+            st_nlink: 1,
+            st_uid: 0,
+            st_gid: 0,
+            st_rdev: 0,
+            __pad: 0,
+            st_size: self.data.len() as i64,
+            st_blksize: BLK_SIZE,
+            __pad2: 0,
+            st_blocks: 0,
+            st_atime_sec: 0,
+            st_atime_nsec: 0,
+            st_mtime_sec: 0,
+            st_mtime_nsec: 0,
+            st_ctime_sec: 0,
+            st_ctime_nsec: 0,
+            __unused: [0; 2],
+        }
+    }
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn get_path(&self) -> String {
+        String::from(self.path)
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize, isize> {
+        Ok(self.copy_from(offset, buf))
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-1)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
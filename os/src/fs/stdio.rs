@@ -1,6 +1,6 @@
+use super::tty::TTY;
 use super::File;
 use crate::fs::file::{Stat, UserStat};
-use crate::hal::console_getchar;
 use crate::mm::UserBuffer;
 use alloc::string::String;
 use core::any::Any;
@@ -15,20 +15,14 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-
-        // 根据 sbi 接口规定，若无输入则返回 usize::MAX
-        let ch = loop {
-            let c = console_getchar();
-            if c != usize::MAX {
-                break c;
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        let data = TTY.read(user_buf.len());
+        for (dst, byte) in user_buf.into_iter().zip(data.iter()) {
+            unsafe {
+                *dst = *byte;
             }
-        };
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch as u8);
         }
-        1
+        data.len()
     }
     fn write(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot write to stdin!");
@@ -56,6 +50,9 @@ impl File for Stdin {
     fn as_any(&self) -> &dyn Any {
         self
     }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        TTY.ioctl(cmd, arg)
+    }
 }
 
 impl File for Stdout {
@@ -70,7 +67,7 @@ impl File for Stdout {
     }
     fn write(&self, user_buf: UserBuffer) -> usize {
         for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
+            TTY.write(buffer);
         }
         user_buf.len()
     }
@@ -97,4 +94,7 @@ impl File for Stdout {
     fn as_any(&self) -> &dyn Any {
         self
     }
+    fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        TTY.ioctl(cmd, arg)
+    }
 }
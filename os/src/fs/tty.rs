@@ -0,0 +1,510 @@
+//! # 终端（TTY）行规程
+//!
+//! ## Overview
+//! 控制台串口之上的终端层，`Stdin` / `Stdout` 的读写和 `ioctl` 都转发到全局的 `TTY`：
+//! - 输入：串口中断把收到的字节交给 `Tty::receive`，按 termios 做输入转换、回显和行编辑
+//! - 输出：按 `OPOST` / `ONLCR` 做输出转换后写到控制台
+//! - 规范模式（`ICANON`）：按行提交，支持 erase（`^?`）、werase（`^W`）、kill（`^U`）和 EOF（`^D`）
+//! - 非规范模式：字节直接可读，`VMIN` 决定读者至少要等到多少字节
+//! - `ISIG`：`^C`、`^\`、`^Z` 向前台进程组发送 SIGINT、SIGQUIT、SIGTSTP
+//!
+//! ## Assumptions
+//! - 系统中只有一个终端，即控制台串口
+//! - 没有会话的概念，任何进程都可以设置前台进程组
+//! - `VTIME` 不支持，非规范模式下只看 `VMIN`
+//!
+//! ## Safety
+//! - 终端状态由 `UPIntrFreeCell` 保护，读者在临界区内完成入队和阻塞，
+//!   不会丢失在阻塞前到达的中断
+//!
+//! ## Invariants
+//! - `ready` 中的每一项都是一个完整的行，空行表示 EOF
+//! - 缓冲的输入总量不超过 `INPUT_LIMIT`，超出的字节被丢弃
+//!
+//! ## Behavior
+//! - 有输入到达或产生信号时唤醒全部读者，由读者自行重新检查
+//! - 读者被唤醒后若进程有待处理的致命信号则返回 0，随后在返回用户态前被终止
+//! - 尚未设置前台进程组时，`^C` 等只清空输入、不发送信号
+
+use crate::drivers::serial;
+use crate::hal::{console_flush, console_putchar};
+use crate::mm::{copy_to_user, get_from_user};
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{EFAULT, EINVAL, ENOTTY, ESRCH};
+use crate::task::{
+    block_current_task_interruptible, check_signals_of_current, current_task, current_user_token,
+    process_group_exists, schedule, signal_process_group, suspend_current_and_run_next,
+    wake_interruptible, SignalFlags, TaskContext, TaskControlBlock,
+};
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+/// ioctl 命令
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TCSETSW: usize = 0x5403;
+pub const TCSETSF: usize = 0x5404;
+pub const TIOCGPGRP: usize = 0x540F;
+pub const TIOCSPGRP: usize = 0x5410;
+pub const TIOCGWINSZ: usize = 0x5413;
+pub const TIOCSWINSZ: usize = 0x5414;
+
+/// `c_iflag`
+pub const INLCR: u32 = 0o100;
+pub const IGNCR: u32 = 0o200;
+pub const ICRNL: u32 = 0o400;
+pub const IXON: u32 = 0o2000;
+
+/// `c_oflag`
+pub const OPOST: u32 = 0o1;
+pub const ONLCR: u32 = 0o4;
+
+/// `c_lflag`
+pub const ISIG: u32 = 0o1;
+pub const ICANON: u32 = 0o2;
+pub const ECHO: u32 = 0o10;
+pub const ECHOE: u32 = 0o20;
+pub const ECHOK: u32 = 0o40;
+pub const ECHONL: u32 = 0o100;
+pub const NOFLSH: u32 = 0o200;
+pub const ECHOCTL: u32 = 0o1000;
+pub const ECHOKE: u32 = 0o4000;
+pub const IEXTEN: u32 = 0o100000;
+
+/// `c_cc` 下标
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSUSP: usize = 10;
+pub const VEOL: usize = 11;
+pub const VWERASE: usize = 14;
+pub const NCCS: usize = 19;
+
+/// 缓冲的输入字节数上限
+const INPUT_LIMIT: usize = 4096;
+
+/// 内核的 `struct termios`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Termios {
+    pub c_iflag: u32,
+    pub c_oflag: u32,
+    pub c_cflag: u32,
+    pub c_lflag: u32,
+    pub c_line: u8,
+    pub c_cc: [u8; NCCS],
+}
+
+impl Default for Termios {
+    /// 与 Linux 新打开的终端相同：规范模式、回显、产生信号，输出时 NL 转为 CR NL
+    fn default() -> Self {
+        let mut c_cc = [0u8; NCCS];
+        c_cc[VINTR] = 0x03;
+        c_cc[VQUIT] = 0x1c;
+        c_cc[VERASE] = 0x7f;
+        c_cc[VKILL] = 0x15;
+        c_cc[VEOF] = 0x04;
+        c_cc[VTIME] = 0;
+        c_cc[VMIN] = 1;
+        c_cc[VSUSP] = 0x1a;
+        c_cc[VWERASE] = 0x17;
+        Self {
+            c_iflag: ICRNL | IXON,
+            c_oflag: OPOST | ONLCR,
+            // B38400 | CS8 | CREAD | HUPCL
+            c_cflag: 0o2277,
+            c_lflag: ISIG | ICANON | ECHO | ECHOE | ECHOK | ECHOCTL | ECHOKE | IEXTEN,
+            c_line: 0,
+            c_cc,
+        }
+    }
+}
+
+/// `struct winsize`
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct WinSize {
+    pub ws_row: u16,
+    pub ws_col: u16,
+    pub ws_xpixel: u16,
+    pub ws_ypixel: u16,
+}
+
+impl Default for WinSize {
+    /// 串口无法得知对端窗口大小，默认 24 行 80 列
+    fn default() -> Self {
+        Self {
+            ws_row: 24,
+            ws_col: 80,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+}
+
+struct TtyInner {
+    termios: Termios,
+    winsize: WinSize,
+    /// 规范模式下正在编辑的行
+    line: Vec<u8>,
+    /// 规范模式下已提交的行
+    ready: VecDeque<Vec<u8>>,
+    /// 非规范模式下的输入
+    raw: VecDeque<u8>,
+    /// 前台进程组
+    fg_pgrp: Option<usize>,
+    /// 等待输入的任务
+    readers: VecDeque<Arc<TaskControlBlock>>,
+}
+
+pub struct Tty {
+    inner: UPIntrFreeCell<TtyInner>,
+}
+
+lazy_static! {
+    pub static ref TTY: Tty = Tty::new();
+}
+
+/// 读者一次检查的结果
+enum ReadState {
+    Done(Vec<u8>),
+    Blocked(*mut TaskContext),
+    Empty,
+}
+
+impl Tty {
+    fn new() -> Self {
+        Self {
+            inner: unsafe {
+                UPIntrFreeCell::new(TtyInner {
+                    termios: Termios::default(),
+                    winsize: WinSize::default(),
+                    line: Vec::new(),
+                    ready: VecDeque::new(),
+                    raw: VecDeque::new(),
+                    fg_pgrp: None,
+                    readers: VecDeque::new(),
+                })
+            },
+        }
+    }
+
+    /// 处理串口收到的一个字节
+    pub fn receive(&self, byte: u8) {
+        let mut inner = self.inner.exclusive_access();
+        inner.receive(byte);
+        inner.wake_readers();
+    }
+
+    /// 读取至多 `len` 字节，没有可读数据时阻塞
+    pub fn read(&self, len: usize) -> Vec<u8> {
+        loop {
+            if check_signals_of_current().is_some() {
+                return Vec::new();
+            }
+            if !serial::rx_interrupt() {
+                serial::poll();
+            }
+            let state = self.inner.exclusive_session(|inner| {
+                if let Some(data) = inner.take(len) {
+                    return ReadState::Done(data);
+                }
+                if !serial::rx_interrupt() {
+                    return ReadState::Empty;
+                }
+                inner.readers.push_back(current_task().unwrap());
+                ReadState::Blocked(block_current_task_interruptible())
+            });
+            match state {
+                ReadState::Done(data) => return data,
+                ReadState::Blocked(task_cx_ptr) => {
+                    schedule(task_cx_ptr);
+                    // 被信号唤醒时自己仍在读者队列中
+                    let task = current_task().unwrap();
+                    self.inner
+                        .exclusive_access()
+                        .readers
+                        .retain(|t| !Arc::ptr_eq(t, &task));
+                }
+                ReadState::Empty => suspend_current_and_run_next(),
+            }
+        }
+    }
+
+    /// 按输出标志写出数据
+    pub fn write(&self, data: &[u8]) {
+        let oflag = self.inner.exclusive_access().termios.c_oflag;
+        for &byte in data {
+            output(oflag, byte);
+        }
+        console_flush();
+    }
+
+    /// 终端的 ioctl，`arg` 为用户态指针
+    pub fn ioctl(&self, cmd: usize, arg: usize) -> isize {
+        if !matches!(
+            cmd,
+            TCGETS | TCSETS | TCSETSW | TCSETSF | TIOCGPGRP | TIOCSPGRP | TIOCGWINSZ | TIOCSWINSZ
+        ) {
+            return -ENOTTY;
+        }
+        if arg == 0 {
+            return -EFAULT;
+        }
+        let token = current_user_token();
+        match cmd {
+            TCGETS => {
+                let termios = self.inner.exclusive_access().termios;
+                copy_out(token, &termios, arg as *mut Termios)
+            }
+            TCSETS | TCSETSW | TCSETSF => {
+                // 输出是同步写出的，TCSETSW 无需等待
+                let termios = get_from_user(token, arg as *const Termios);
+                let mut inner = self.inner.exclusive_access();
+                if cmd == TCSETSF {
+                    inner.flush_input();
+                }
+                inner.set_termios(termios);
+                inner.wake_readers();
+                0
+            }
+            TIOCGWINSZ => {
+                let winsize = self.inner.exclusive_access().winsize;
+                copy_out(token, &winsize, arg as *mut WinSize)
+            }
+            TIOCSWINSZ => {
+                self.inner.exclusive_access().winsize =
+                    get_from_user(token, arg as *const WinSize);
+                0
+            }
+            TIOCGPGRP => {
+                // 没有前台进程组时与 Linux 一样返回 0
+                let pgrp = self.inner.exclusive_access().fg_pgrp.unwrap_or(0);
+                copy_out(token, &(pgrp as i32), arg as *mut i32)
+            }
+            TIOCSPGRP => {
+                let pgrp = get_from_user(token, arg as *const i32);
+                if pgrp < 0 {
+                    return -EINVAL;
+                }
+                if !process_group_exists(pgrp as usize) {
+                    return -ESRCH;
+                }
+                self.inner.exclusive_access().fg_pgrp = Some(pgrp as usize);
+                0
+            }
+            _ => -ENOTTY,
+        }
+    }
+}
+
+impl TtyInner {
+    fn lflag(&self, flag: u32) -> bool {
+        self.termios.c_lflag & flag != 0
+    }
+
+    fn iflag(&self, flag: u32) -> bool {
+        self.termios.c_iflag & flag != 0
+    }
+
+    fn buffered(&self) -> usize {
+        self.line.len() + self.raw.len() + self.ready.iter().map(|l| l.len()).sum::<usize>()
+    }
+
+    fn receive(&mut self, byte: u8) {
+        let byte = match byte {
+            b'\r' if self.iflag(IGNCR) => return,
+            b'\r' if self.iflag(ICRNL) => b'\n',
+            b'\n' if self.iflag(INLCR) => b'\r',
+            _ => byte,
+        };
+        let cc = self.termios.c_cc;
+        if self.lflag(ISIG) {
+            let signal = match byte {
+                _ if byte == cc[VINTR] => Some(SignalFlags::SIGINT),
+                _ if byte == cc[VQUIT] => Some(SignalFlags::SIGQUIT),
+                _ if byte == cc[VSUSP] => Some(SignalFlags::SIGTSTP),
+                _ => None,
+            };
+            if let Some(signal) = signal {
+                self.interrupt(byte, signal);
+                return;
+            }
+        }
+        if self.buffered() >= INPUT_LIMIT {
+            return;
+        }
+        if !self.lflag(ICANON) {
+            self.raw.push_back(byte);
+            self.echo(byte);
+            return;
+        }
+        match byte {
+            _ if byte == cc[VERASE] => {
+                self.erase();
+            }
+            _ if byte == cc[VWERASE] && self.lflag(IEXTEN) => {
+                while self.line.last().map_or(false, |c| c.is_ascii_whitespace()) {
+                    self.erase();
+                }
+                while self.line.last().map_or(false, |c| !c.is_ascii_whitespace()) {
+                    self.erase();
+                }
+            }
+            _ if byte == cc[VKILL] => self.kill(),
+            _ if byte == cc[VEOF] => {
+                // ^D 提交当前行但不包含自身，空行即 EOF
+                let line = core::mem::take(&mut self.line);
+                self.ready.push_back(line);
+            }
+            _ if byte == b'\n' || (byte == cc[VEOL] && byte != 0) => {
+                if self.lflag(ECHO) || (byte == b'\n' && self.lflag(ECHONL)) {
+                    output(self.termios.c_oflag, byte);
+                }
+                self.line.push(byte);
+                let line = core::mem::take(&mut self.line);
+                self.ready.push_back(line);
+            }
+            _ => {
+                self.line.push(byte);
+                self.echo(byte);
+            }
+        }
+    }
+
+    /// 回显一个普通输入字节，`ECHOCTL` 时控制字符显示为 `^X`
+    fn echo(&self, byte: u8) {
+        if !self.lflag(ECHO) {
+            return;
+        }
+        if self.lflag(ECHOCTL) && is_ctrl(byte) {
+            console_putchar(b'^' as usize);
+            console_putchar((byte ^ 0x40) as usize);
+        } else {
+            output(self.termios.c_oflag, byte);
+        }
+    }
+
+    /// 删除正在编辑的行的最后一个字节
+    fn erase(&mut self) {
+        let byte = match self.line.pop() {
+            Some(byte) => byte,
+            None => return,
+        };
+        if !self.lflag(ECHO) {
+            return;
+        }
+        if self.lflag(ECHOE) {
+            let width = if self.lflag(ECHOCTL) && is_ctrl(byte) { 2 } else { 1 };
+            for _ in 0..width {
+                for c in b"\x08 \x08" {
+                    console_putchar(*c as usize);
+                }
+            }
+        } else {
+            self.echo(self.termios.c_cc[VERASE]);
+        }
+    }
+
+    /// 删除正在编辑的整行
+    fn kill(&mut self) {
+        if self.lflag(ECHO) && self.lflag(ECHOKE) {
+            while !self.line.is_empty() {
+                self.erase();
+            }
+            return;
+        }
+        self.line.clear();
+        if self.lflag(ECHO) {
+            self.echo(self.termios.c_cc[VKILL]);
+            if self.lflag(ECHOK) {
+                output(self.termios.c_oflag, b'\n');
+            }
+        }
+    }
+
+    /// 处理信号字符：回显、清空输入并通知前台进程组
+    fn interrupt(&mut self, byte: u8, signal: SignalFlags) {
+        self.echo(byte);
+        if !self.lflag(NOFLSH) {
+            self.flush_input();
+        }
+        if let Some(pgrp) = self.fg_pgrp {
+            signal_process_group(pgrp, signal);
+        }
+    }
+
+    fn flush_input(&mut self) {
+        self.line.clear();
+        self.ready.clear();
+        self.raw.clear();
+    }
+
+    /// 切换模式时把已有输入搬到新模式的缓冲区中
+    fn set_termios(&mut self, termios: Termios) {
+        let was_canonical = self.lflag(ICANON);
+        self.termios = termios;
+        match (was_canonical, self.lflag(ICANON)) {
+            (true, false) => {
+                for line in self.ready.drain(..) {
+                    self.raw.extend(line);
+                }
+                self.raw.extend(self.line.drain(..));
+            }
+            (false, true) => self.line.extend(self.raw.drain(..)),
+            _ => {}
+        }
+    }
+
+    /// 取出至多 `len` 字节，数据不足以满足读者时返回 `None`
+    fn take(&mut self, len: usize) -> Option<Vec<u8>> {
+        if self.lflag(ICANON) {
+            let line = self.ready.front_mut()?;
+            let n = len.min(line.len());
+            let data: Vec<u8> = line.drain(..n).collect();
+            // 读空的行（包括表示 EOF 的空行）出队
+            if line.is_empty() {
+                self.ready.pop_front();
+            }
+            return Some(data);
+        }
+        let vmin = self.termios.c_cc[VMIN] as usize;
+        if self.raw.len() < vmin.min(len) {
+            return None;
+        }
+        let n = len.min(self.raw.len());
+        Some(self.raw.drain(..n).collect())
+    }
+
+    fn wake_readers(&mut self) {
+        for task in self.readers.drain(..) {
+            wake_interruptible(task);
+        }
+    }
+}
+
+/// 控制字符（不含制表符与换行）
+fn is_ctrl(byte: u8) -> bool {
+    (byte < 0x20 && byte != b'\t' && byte != b'\n') || byte == 0x7f
+}
+
+/// 按输出标志写出一个字节
+fn output(oflag: u32, byte: u8) {
+    if byte == b'\n' && oflag & OPOST != 0 && oflag & ONLCR != 0 {
+        console_putchar(b'\r' as usize);
+    }
+    console_putchar(byte as usize);
+}
+
+fn copy_out<T: 'static + Copy>(token: usize, value: &T, dst: *mut T) -> isize {
+    match copy_to_user(token, value as *const T, dst) {
+        Ok(()) => 0,
+        Err(errno) => errno,
+    }
+}
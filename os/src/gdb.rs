@@ -0,0 +1,315 @@
+//! # GDB 桩
+//!
+//! ## Overview
+//! 内核中的 GDB 远程串行协议桩，协议部分见 `gdbstub` crate，体系结构部分见 `hal::gdb`。
+//! - 连接：命令行参数 `gdb` 或 `gdb=uart` 使用 `MACHINE.debug_uart`（第二个 NS16550A），
+//!   `gdb=virtio` 使用 virtio-console；单独的 `gdb` 在没有第二个串口时也改用 virtio-console
+//! - 进入桩的时机：
+//!   - 内核 panic：打印栈回溯之后停下，GDB 断开后照常关机
+//!   - 在控制台上按下 `MAGIC_KEY`（`Ctrl-G`），或 GDB 连着时按下 `Ctrl-C`：
+//!     在下一次时钟或外部中断时停下
+//!   - 执行到 GDB 设置的断点，或单步的临时断点
+//! - 现场：内核态陷阱停下时为该陷阱保存的寄存器，用户态陷阱停下时为当前任务的 `TrapContext`；
+//!   内存经停下时的页表访问
+//!
+//! ## Assumptions
+//! - 单核；停下期间 `GDB` 被独占借用，中断保持屏蔽
+//! - `gdb` 参数可以写在命令行或启动盘的配置文件中（LoongArch 上只有后者），
+//!   因此读取配置文件之前的 panic 不会进入桩
+//!
+//! ## Behavior
+//! - 没有 `gdb` 参数时桩不启用，`MAGIC_KEY` 照常交给终端，断点异常照旧处理
+//! - 断点地址按设置时的页表解释，在用户态停下时设置的断点只对当前进程有意义
+//! - GDB 发出 `k` 时以 `128 + SIGKILL` 关机
+
+use crate::cmdline;
+use crate::drivers::serial::ns16550a::FIFO_SIZE;
+use crate::drivers::{Ns16550a, VIRTIO_CONSOLE};
+use crate::hal::gdb::{
+    capture_registers, kernel_addr, kernel_token, sync_icache, write_byte, GdbArch,
+};
+use crate::hal::{shutdown, PageTableImpl, MACHINE, PAGE_SIZE};
+use crate::mm::PageTable;
+use crate::sync::UPIntrFreeCell;
+use core::fmt;
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embedded_hal::serial::nb::{Read, Write};
+use gdbstub::{Arch, Connection, GdbStub, Registers, Resume, Target, SIGABRT, SIGINT, SIGTRAP};
+
+/// 控制台上请求进入桩的按键，`Ctrl-G`
+pub const MAGIC_KEY: u8 = 0x07;
+
+/// GDB 发出 `k` 时的退出状态
+const KILL_EXIT_CODE: i32 = 128 + 9;
+
+#[derive(Clone, Copy)]
+enum Port {
+    /// 第二个 NS16550A 的基址
+    Uart(usize),
+    Virtio,
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Port::Uart(base) => write!(f, "uart {:#x}", base),
+            Port::Virtio => write!(f, "virtio-console"),
+        }
+    }
+}
+
+/// 与 GDB 的连接
+struct Link {
+    port: Option<Port>,
+    /// virtio-console 一次交来的输入，`VirtIOConsole::read` 会丢弃放不下的部分
+    rx: [u8; PAGE_SIZE],
+    rx_pos: usize,
+    rx_len: usize,
+}
+
+impl Connection for Link {
+    fn read_byte(&mut self) -> Option<u8> {
+        match self.port? {
+            Port::Uart(base) => Ns16550a::new(base).read().ok(),
+            Port::Virtio => {
+                if self.rx_pos == self.rx_len {
+                    self.rx_len = VIRTIO_CONSOLE.as_ref()?.read(&mut self.rx);
+                    self.rx_pos = 0;
+                }
+                let byte = *self.rx[..self.rx_len].get(self.rx_pos)?;
+                self.rx_pos += 1;
+                Some(byte)
+            }
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        match self.port {
+            Some(Port::Uart(base)) => {
+                let mut uart = Ns16550a::new(base);
+                for chunk in data.chunks(FIFO_SIZE) {
+                    while !uart.tx_empty() {
+                        spin_loop();
+                    }
+                    for &byte in chunk {
+                        let _ = uart.write(byte);
+                    }
+                }
+            }
+            Some(Port::Virtio) => {
+                if let Some(console) = VIRTIO_CONSOLE.as_ref() {
+                    console.write(data);
+                }
+            }
+            None => {}
+        }
+    }
+}
+
+struct Gdb {
+    stub: GdbStub<GdbArch>,
+    link: Link,
+}
+
+/// 收发缓冲区较大，放在静态区而不是很小的内核栈上
+static GDB: UPIntrFreeCell<Gdb> = unsafe {
+    UPIntrFreeCell::new(Gdb {
+        stub: GdbStub::new(),
+        link: Link {
+            port: None,
+            rx: [0; PAGE_SIZE],
+            rx_pos: 0,
+            rx_len: 0,
+        },
+    })
+};
+
+/// 桩是否启用，中断路径上据此跳过借用 `GDB`
+static ENABLED: AtomicBool = AtomicBool::new(false);
+/// 控制台上按下了 `MAGIC_KEY`
+static BREAK_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 停下的内核或用户现场
+struct KernelTarget<'a> {
+    regs: &'a mut dyn Registers,
+    page_table: PageTableImpl,
+}
+
+impl KernelTarget<'_> {
+    fn kernel_addr(&self, addr: usize, offset: usize) -> Option<usize> {
+        kernel_addr(&self.page_table, addr.checked_add(offset)?)
+    }
+}
+
+impl Target for KernelTarget<'_> {
+    fn read_register(&self, n: usize) -> Option<usize> {
+        self.regs.read(n)
+    }
+
+    fn write_register(&mut self, n: usize, value: usize) -> bool {
+        self.regs.write(n, value)
+    }
+
+    fn read_memory(&self, addr: usize, buf: &mut [u8]) -> usize {
+        for (i, byte) in buf.iter_mut().enumerate() {
+            let Some(kaddr) = self.kernel_addr(addr, i) else {
+                return i;
+            };
+            *byte = unsafe { (kaddr as *const u8).read_volatile() };
+        }
+        buf.len()
+    }
+
+    fn write_memory(&mut self, addr: usize, data: &[u8]) -> bool {
+        // 先确认全部可写，避免只写入半条断点指令
+        if (0..data.len()).any(|i| self.kernel_addr(addr, i).is_none()) {
+            return false;
+        }
+        for (i, &byte) in data.iter().enumerate() {
+            let kaddr = self.kernel_addr(addr, i).unwrap();
+            unsafe { write_byte(kaddr, byte) };
+        }
+        sync_icache();
+        true
+    }
+}
+
+/// 按命令行选择连接，在读取启动盘上的配置文件之后调用
+pub fn init() {
+    let Some(choice) = cmdline::get("gdb") else {
+        return;
+    };
+    let uart = MACHINE.debug_uart.map(|uart| Port::Uart(uart.base));
+    let virtio = VIRTIO_CONSOLE.is_some().then_some(Port::Virtio);
+    let port = match choice.as_str() {
+        "" => uart.or(virtio),
+        "uart" => uart,
+        "virtio" => virtio,
+        _ => {
+            println!("[kernel] gdb: unknown port `{}`", choice);
+            return;
+        }
+    };
+    let Some(port) = port else {
+        println!("[kernel] gdb: no port available, stub disabled");
+        return;
+    };
+    if let Port::Uart(base) = port {
+        Ns16550a::new(base).enable_fifo();
+    }
+    GDB.exclusive_access().link.port = Some(port);
+    ENABLED.store(true, Ordering::Relaxed);
+    println!("[kernel] gdb stub on {}, press Ctrl-G to break in", port);
+}
+
+/// 与 GDB 交互直到它让目标继续运行
+fn enter(regs: &mut dyn Registers, token: usize, signal: u8) {
+    let mut gdb = GDB.exclusive_access();
+    let Gdb { stub, link } = &mut *gdb;
+    let mut target = KernelTarget {
+        regs,
+        page_table: PageTableImpl::from_token(token),
+    };
+    match stub.stop(link, &mut target, signal) {
+        Resume::Continue => {}
+        Resume::Detach => println!("[kernel] gdb detached"),
+        Resume::Kill => {
+            println!("[kernel] killed by gdb");
+            shutdown(KILL_EXIT_CODE);
+        }
+    }
+}
+
+/// 控制台收到 `byte`，是 `MAGIC_KEY` 且桩已启用时记下请求并返回 `true`，该字节不再交给终端
+pub fn magic_key(byte: u8) -> bool {
+    if byte != MAGIC_KEY || !ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    BREAK_REQUESTED.store(true, Ordering::Relaxed);
+    true
+}
+
+/// 时钟与外部中断处理的末尾调用：有停下的请求时以 `SIGINT` 停在现场 `regs`
+///
+/// `token` 是访问内存所用的页表
+pub fn check_break(regs: &mut dyn Registers, token: usize) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if !BREAK_REQUESTED.swap(false, Ordering::Relaxed) {
+        let mut gdb = GDB.exclusive_access();
+        let Gdb { stub, link } = &mut *gdb;
+        if !stub.is_attached() || !stub.poll_interrupt(link) {
+            return;
+        }
+    }
+    enter(regs, token, SIGINT);
+}
+
+/// 断点异常：是本桩放置的断点时以 `SIGTRAP` 停下并返回 `true`，否则交给调用者处理
+pub fn handle_breakpoint(regs: &mut dyn Registers, token: usize) -> bool {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return false;
+    }
+    let Some(pc) = regs.read(GdbArch::PC) else {
+        return false;
+    };
+    if !GDB.exclusive_access().stub.has_breakpoint(pc) {
+        return false;
+    }
+    enter(regs, token, SIGTRAP);
+    true
+}
+
+/// 内核 panic：以 `SIGABRT` 停在调用处，直到 GDB 断开或结束目标
+///
+/// ## Behavior
+/// - panic 发生在桩内部时 `GDB` 已被借用，直接返回
+/// - panic 无法继续执行，GDB 要求继续时立刻再次报告 `SIGABRT`
+pub fn on_panic() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let Some(mut gdb) = GDB.try_exclusive_access() else {
+        return;
+    };
+    let Gdb { stub, link } = &mut *gdb;
+    println!("[kernel] waiting for gdb on {}", link.port.unwrap());
+    let mut regs = capture_registers();
+    let mut target = KernelTarget {
+        regs: &mut regs,
+        page_table: PageTableImpl::from_token(kernel_token()),
+    };
+    while stub.stop(link, &mut target, SIGABRT) == Resume::Continue {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KernelTarget;
+    use crate::hal::gdb::{capture_registers, kernel_token, GdbArch};
+    use crate::hal::PageTableImpl;
+    use crate::mm::PageTable;
+    use gdbstub::{Arch, Target};
+
+    static mut PATCHED: [u8; 4] = [1, 2, 3, 4];
+
+    #[test_case]
+    fn target_accesses_kernel_memory_and_registers() {
+        let mut regs = capture_registers();
+        let mut target = KernelTarget {
+            regs: &mut regs,
+            page_table: PageTableImpl::from_token(kernel_token()),
+        };
+        let addr = unsafe { core::ptr::addr_of!(PATCHED) } as usize;
+        let mut buf = [0; 4];
+        assert_eq!(target.read_memory(addr, &mut buf), 4);
+        assert_eq!(buf, [1, 2, 3, 4]);
+        assert!(target.write_memory(addr + 1, &[5, 6]));
+        assert_eq!(unsafe { PATCHED }, [1, 5, 6, 4]);
+
+        assert_eq!(target.read_register(0), Some(0));
+        assert!(target.write_register(GdbArch::PC, 0x1234));
+        assert_eq!(target.read_register(GdbArch::PC), Some(0x1234));
+    }
+}
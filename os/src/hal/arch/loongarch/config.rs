@@ -46,6 +46,14 @@ pub const TRAMPOLINE: usize = VA_SPACE_SIZE - PAGE_SIZE + 1;
 pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE;
 /// 用户栈的基地址，根据预留的大小计算得出
 pub const UserStackBase: usize = TRAP_CONTEXT_BASE - USER_STACK_Totol_SIZE;
+/// 位置无关可执行文件（ET_DYN）的装载基址，堆紧随其后
+pub const ELF_DYN_BASE: usize = 0x1000_0000;
+/// 动态链接器的装载基址，远离程序与堆
+pub const ELF_INTERP_BASE: usize = 0x20_0000_0000;
+/// ELF 头中的机器类型 EM_LOONGARCH
+pub const ELF_MACHINE: u16 = 258;
+/// AT_HWCAP：CPUCFG、LAM、UAL 与 FPU（与 Linux 的 HWCAP_LOONGARCH_* 相同）
+pub const HWCAP: usize = 0b1111;
 // /// ========================
 // /// 内存与系统资源相关常量
 // /// ========================
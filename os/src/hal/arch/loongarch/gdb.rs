@@ -0,0 +1,118 @@
+//! # GDB 桩的体系结构部分（LoongArch）
+//!
+//! ## Overview
+//! - `GdbArch`：寄存器编号为 `r0`-`r31`、`orig_a0`（32 号）、`pc`（33 号）与 `badv`（34 号）
+//! - 内核态陷阱的现场是 `GeneralRegs`，用户态陷阱的现场是 `TrapContext`，两者都按此编号读写
+//! - `capture_registers`：panic 时没有陷阱现场，取调用处的 `pc`/`ra`/`sp`/`fp` 等构造一份
+//! - `kernel_addr` / `write_byte` / `sync_icache`：经页表访问被调试的内存
+//!
+//! ## Assumptions
+//! - 内核经直接映射窗口访问 `HIGH_BASE_EIGHT` 以上的地址
+//! - 与 `PhysPageNum::get_bytes_array` 一样，翻译得到的物理地址可以直接读写
+//!
+//! ## Behavior
+//! - `badv` 只读；`GeneralRegs` 中没有 `orig_a0`，读取时报告为不可用
+//! - 内核态陷阱返回时 `sp` 取自 `CSR.SAVE`，修改 `sp` 无效
+
+use super::trap::context::{GeneralRegs, TrapContext};
+use crate::hal::{PageTableImpl, HIGH_BASE_EIGHT, PAGE_SIZE_BITS};
+use crate::mm::{PageTable, VirtAddr};
+use core::arch::asm;
+use gdbstub::Registers;
+use loongArch64::register::{badv, pgdl};
+
+pub type GdbArch = gdbstub::LoongArch64;
+
+impl Registers for GeneralRegs {
+    fn read(&self, n: usize) -> Option<usize> {
+        match n {
+            0..=31 => Some(self[n]),
+            33 => Some(self.pc),
+            34 => Some(badv::read().vaddr()),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, n: usize, value: usize) -> bool {
+        match n {
+            1..=31 => self[n] = value,
+            33 => self.pc = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Registers for TrapContext {
+    fn read(&self, n: usize) -> Option<usize> {
+        match n {
+            32 => Some(self.origin_a0),
+            _ => self.gp.read(n),
+        }
+    }
+
+    fn write(&mut self, n: usize, value: usize) -> bool {
+        match n {
+            32 => {
+                self.origin_a0 = value;
+                true
+            }
+            _ => self.gp.write(n, value),
+        }
+    }
+}
+
+/// 调用处的寄存器，`pc` 为调用处的地址，只有 `ra`、`tp`、`sp`、`fp` 有意义
+#[inline(always)]
+pub fn capture_registers() -> GeneralRegs {
+    let mut regs = GeneralRegs::default();
+    unsafe {
+        asm!(
+            "pcaddi {pc}, 0",
+            "move {ra}, $ra",
+            "move {tp}, $tp",
+            "move {sp}, $sp",
+            "move {fp}, $fp",
+            pc = out(reg) regs.pc,
+            ra = out(reg) regs.ra,
+            tp = out(reg) regs.tp,
+            sp = out(reg) regs.sp,
+            fp = out(reg) regs.fp,
+        );
+    }
+    regs
+}
+
+/// 内核态停下时低半地址空间使用的页表，即 `PGDL` 指向的根页表
+pub fn kernel_token() -> usize {
+    pgdl::read().base() >> PAGE_SIZE_BITS
+}
+
+/// 被调试地址 `addr` 在内核中可以直接读取的地址
+///
+/// ## Behavior
+/// - 直接映射窗口中的地址原样返回，其余地址经 `page_table` 翻译为物理地址
+pub fn kernel_addr(page_table: &PageTableImpl, addr: usize) -> Option<usize> {
+    if addr >= HIGH_BASE_EIGHT {
+        return Some(addr);
+    }
+    let va = VirtAddr::from(addr);
+    let pte = page_table.translate(va.floor())?;
+    if !pte.is_valid() {
+        return None;
+    }
+    Some(usize::from(pte.ppn()) << PAGE_SIZE_BITS | va.page_offset())
+}
+
+/// 向 `kernel_addr` 得到的地址写入一个字节
+///
+/// ## Safety
+/// - `addr` 必须是 `kernel_addr` 返回的地址
+pub unsafe fn write_byte(addr: usize, byte: u8) {
+    (addr as *mut u8).write_volatile(byte);
+}
+
+/// 让取指看到 `write_byte` 写入的指令
+pub fn sync_icache() {
+    unsafe { asm!("ibar 0") };
+}
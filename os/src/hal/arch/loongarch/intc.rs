@@ -0,0 +1,129 @@
+//! LoongArch 外部中断控制器（EIOINTC + PCH-PIC）
+//!
+//! # Overview
+//! QEMU `virt` 机器上外设中断的路径为：
+//! 设备 → PCH-PIC（桥片中断控制器，MMIO）→ EIOINTC（扩展 IO 中断，IOCSR）→ CPU `HWI0`。
+//! PCH-PIC 的第 n 号输入接到 EIOINTC 的第 n 号向量，因此中断号在两级之间保持一致。
+//! - `init`：打开扩展 IO 中断，把所有向量路由到 0 号核的 `HWI0`，屏蔽全部 PCH-PIC 输入
+//! - `enable_irq`：在两级控制器上同时使能中断号
+//! - `handle_external`：从 EIOINTC 状态寄存器中取出待处理中断，交给 `drivers::handle_irq`
+//!
+//! # Assumptions
+//! - 内核只在 0 号核上运行
+//! - 外设中断均为电平触发，设备驱动负责清除设备侧的中断
+//!
+//! # Safety
+//! - IOCSR 与 MMIO 寄存器访问都依赖 QEMU `virt` 的固定地址
+//!
+//! # Invariants
+//! - EIOINTC 状态位在分发前写 1 清除，避免同一中断被重复分发
+
+use crate::hal::HIGH_BASE_EIGHT;
+use core::arch::asm;
+use core::ptr::{read_volatile, write_volatile};
+use loongArch64::register::ecfg::{self, LineBasedInterrupt};
+
+/// PCH-PIC 寄存器基址
+const PCH_PIC_BASE: usize = 0x1000_0000 + HIGH_BASE_EIGHT;
+const PCH_PIC_INT_MASK: usize = 0x20;
+const PCH_PIC_HTMSI_EN: usize = 0x40;
+const PCH_PIC_EDGE: usize = 0x60;
+const PCH_PIC_ROUTE_ENTRY: usize = 0x100;
+const PCH_PIC_HTMSI_VECTOR: usize = 0x200;
+const PCH_PIC_POLARITY: usize = 0x3e0;
+const PCH_PIC_IRQS: usize = 64;
+
+/// 打开扩展 IO 中断的 IOCSR 寄存器与位
+const IOCSR_MISC_FUNC: usize = 0x420;
+const IOCSR_MISC_FUNC_EXT_IOI_EN: u64 = 1 << 48;
+
+const EIOINTC_IPMAP: usize = 0x14c0;
+const EIOINTC_ENABLE: usize = 0x1600;
+const EIOINTC_BOUNCE: usize = 0x1680;
+const EIOINTC_ISR: usize = 0x1800;
+const EIOINTC_ROUTE: usize = 0x1c00;
+const EIOINTC_VECTORS: usize = 256;
+
+fn iocsr_read_d(reg: usize) -> u64 {
+    let value: u64;
+    unsafe { asm!("iocsrrd.d {}, {}", out(reg) value, in(reg) reg) };
+    value
+}
+
+fn iocsr_write_d(reg: usize, value: u64) {
+    unsafe { asm!("iocsrwr.d {}, {}", in(reg) value, in(reg) reg) };
+}
+
+fn iocsr_read_w(reg: usize) -> u32 {
+    let value: u32;
+    unsafe { asm!("iocsrrd.w {}, {}", out(reg) value, in(reg) reg) };
+    value
+}
+
+fn iocsr_write_w(reg: usize, value: u32) {
+    unsafe { asm!("iocsrwr.w {}, {}", in(reg) value, in(reg) reg) };
+}
+
+fn pch_pic_read(offset: usize) -> u64 {
+    unsafe { read_volatile((PCH_PIC_BASE + offset) as *const u64) }
+}
+
+fn pch_pic_write(offset: usize, value: u64) {
+    unsafe { write_volatile((PCH_PIC_BASE + offset) as *mut u64, value) }
+}
+
+pub fn init() {
+    // 打开扩展 IO 中断模式
+    iocsr_write_d(
+        IOCSR_MISC_FUNC,
+        iocsr_read_d(IOCSR_MISC_FUNC) | IOCSR_MISC_FUNC_EXT_IOI_EN,
+    );
+    // 每 32 个向量一组，全部映射到 HWI0
+    iocsr_write_d(EIOINTC_IPMAP, 0x0101_0101_0101_0101);
+    // 每个向量一个字节，全部路由到 0 号核
+    for vector in (0..EIOINTC_VECTORS).step_by(4) {
+        iocsr_write_w(EIOINTC_ROUTE + vector, 0x0101_0101);
+    }
+    for group in 0..EIOINTC_VECTORS / 32 {
+        iocsr_write_w(EIOINTC_ENABLE + group * 4, 0);
+        iocsr_write_w(EIOINTC_BOUNCE + group * 4, 0);
+    }
+
+    // PCH-PIC：全部屏蔽，电平触发、高电平有效，第 n 号输入送往向量 n
+    pch_pic_write(PCH_PIC_INT_MASK, u64::MAX);
+    pch_pic_write(PCH_PIC_HTMSI_EN, 0);
+    pch_pic_write(PCH_PIC_EDGE, 0);
+    pch_pic_write(PCH_PIC_POLARITY, 0);
+    for irq in 0..PCH_PIC_IRQS {
+        unsafe {
+            write_volatile((PCH_PIC_BASE + PCH_PIC_ROUTE_ENTRY + irq) as *mut u8, 1);
+            write_volatile(
+                (PCH_PIC_BASE + PCH_PIC_HTMSI_VECTOR + irq) as *mut u8,
+                irq as u8,
+            );
+        }
+    }
+
+    ecfg::set_lie(LineBasedInterrupt::TIMER | LineBasedInterrupt::HWI0);
+}
+
+/// 使能中断号 `irq`
+pub fn enable_irq(irq: usize) {
+    let enable = EIOINTC_ENABLE + (irq / 32) * 4;
+    iocsr_write_w(enable, iocsr_read_w(enable) | 1 << (irq % 32));
+    pch_pic_write(PCH_PIC_INT_MASK, pch_pic_read(PCH_PIC_INT_MASK) & !(1 << irq));
+}
+
+/// 处理一次 `HWI0` 中断
+pub fn handle_external() {
+    for group in 0..EIOINTC_VECTORS / 64 {
+        let status = EIOINTC_ISR + group * 8;
+        let mut pending = iocsr_read_d(status);
+        while pending != 0 {
+            let bit = pending.trailing_zeros() as usize;
+            pending &= !(1 << bit);
+            iocsr_write_d(status, 1 << bit);
+            crate::drivers::handle_irq(group * 64 + bit);
+        }
+    }
+}
@@ -1,5 +1,7 @@
 mod boot;
 pub mod config;
+pub mod gdb;
+pub mod intc;
 pub mod kernel_stack;
 mod laflex;
 mod merrera;
@@ -104,6 +106,23 @@ pub fn machine_init() {
     println!("[machine_init] MMAP_BASE: {:#x}", MMAP_BASE);
 
     trap::enable_timer_interrupt();
+    // 必须在打开时钟中断之后：两者共用 ecfg.LIE
+    intc::init();
+}
+
+/// 打开中断并等待下一个中断到来，返回前重新关闭中断
+pub fn wait_for_interrupt() {
+    crmd::set_ie(true);
+    unsafe { core::arch::asm!("idle 0") };
+    crmd::set_ie(false);
+}
+
+/// 调用者的帧指针（`$fp`），栈回溯的起点
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe { core::arch::asm!("move {}, $fp", out(reg) fp) };
+    fp
 }
 
 pub type PageTableEntryImpl = laflex::LAFlexPageTableEntry;
@@ -25,11 +25,30 @@ pub fn console_flush() {
     unsafe { while UART.flush().is_err() {} }
 }
 
-pub fn shutdown() -> ! {
-    {
-        unsafe {
-            (0x100E_001C as *mut u8).write_volatile(0x34);
-        }
+/// LoongArch 从一开始就直接使用串口，无需切换
+pub fn console_init() {}
+
+/// 输出是同步的，不使用发送中断
+pub fn console_enable_tx_interrupt() {}
+
+pub fn console_transmit() {}
+
+/// 向 GED 的睡眠控制寄存器写入 S5 关机
+///
+/// GED 的关机不带状态，QEMU 总是以 0 退出；这里把 `code` 打印出来，
+/// 由运行脚本从输出中取得退出状态
+pub fn shutdown(code: i32) -> ! {
+    println!("run shutdown, status {}", code);
+    unsafe {
+        (0x100E_001C as *mut u8).write_volatile(0x34);
+    }
+    loop {}
+}
+
+/// 向 GED 的复位寄存器写入复位值
+pub fn reboot() -> ! {
+    unsafe {
+        (0x100E_001E as *mut u8).write_volatile(0x42);
     }
     loop {}
 }
@@ -32,6 +32,10 @@ impl IntrMaskingInfo {
         self.nested_level += 1;
     }
 
+    pub fn in_critical_section(&self) -> bool {
+        self.nested_level > 0
+    }
+
     pub fn exit(&mut self) {
         self.nested_level -= 1;
 
@@ -1,4 +1,4 @@
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 use loongArch64::register::prmd;
 use loongArch64::register::prmd::Prmd;
 
@@ -101,6 +101,29 @@ impl Debug for GeneralRegs {
     }
 }
 
+impl fmt::Display for GeneralRegs {
+    /// 先打印 `pc`，其后每行四个寄存器，不打印恒为零的 `r0`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const NAMES: [&str; 32] = [
+            "pc", "ra", "tp", "sp", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "t0", "t1",
+            "t2", "t3", "t4", "t5", "t6", "t7", "t8", "r21", "fp", "s0", "s1", "s2", "s3", "s4",
+            "s5", "s6", "s7", "s8",
+        ];
+        writeln!(f, "{:>3}: {:#018x}", NAMES[0], self.pc)?;
+        for (i, name) in NAMES.iter().enumerate().skip(1) {
+            let sep = if i + 1 == NAMES.len() {
+                ""
+            } else if i % 4 == 0 {
+                "\n"
+            } else {
+                "  "
+            };
+            write!(f, "{:>3}: {:#018x}{}", name, self[i], sep)?;
+        }
+        Ok(())
+    }
+}
+
 /// FP registers
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
@@ -144,6 +167,14 @@ pub struct TrapContext {
 //     }
 // }
 
+impl fmt::Display for TrapContext {
+    /// 只打印通用寄存器与 `origin_a0`，`Prmd` 没有提供可读的格式
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "origin_a0: {:#x}", self.origin_a0)?;
+        write!(f, "{}", self.gp)
+    }
+}
+
 impl TrapContext {
     pub fn set_sp(&mut self, sp: usize) {
         self.gp.sp = sp;
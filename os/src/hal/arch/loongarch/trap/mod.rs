@@ -1,13 +1,17 @@
 pub mod context;
 mod mem_access;
 
+use super::intc;
 use super::merrera;
+use crate::backtrace::dump_kernel_trap;
+use crate::gdb;
+use crate::hal::arch::loongarch::gdb::kernel_token;
 use crate::hal::arch::loongarch::timer::TICKS_PER_SEC;
 use crate::hal::get_clock_freq;
 use context::GeneralRegs;
 use core::arch::{asm, global_asm};
 use loongArch64::register::ecfg::LineBasedInterrupt;
-use loongArch64::register::estat::{Exception, Trap};
+use loongArch64::register::estat::{Exception, Interrupt, Trap};
 use loongArch64::register::{badi, badv, ecfg, eentry, era, estat, pgdh, tcfg};
 use mem_access::Instruction;
 
@@ -144,6 +148,15 @@ pub extern "C" fn trap_from_kernel(gr: &mut GeneralRegs) {
     let sub_code = estat::read().esubcode();
 
     match cause {
+        // 外部中断：由 EIOINTC 分发给对应设备
+        Trap::Interrupt(Interrupt::HWI0) => {
+            intc::handle_external();
+            gdb::check_break(gr, kernel_token());
+            return;
+        }
+        Trap::Exception(Exception::Breakpoint) if gdb::handle_breakpoint(gr, kernel_token()) => {
+            return;
+        }
         // npucore 中添加了 TLBReFill 异常处理, 这里先留空
         Trap::Exception(Exception::AddressNotAligned) => {
             let pc = gr.pc;
@@ -191,6 +204,7 @@ pub extern "C" fn trap_from_kernel(gr: &mut GeneralRegs) {
                 break;
             }
             if gr.pc == pc {
+                dump_kernel_trap(&*gr, gr.pc, gr.fp);
                 panic!(
                     "Failed to execute the command. Bad Instruction: {}, PC:{}",
                     unsafe { *(gr.pc as *const u32) },
@@ -202,6 +216,7 @@ pub extern "C" fn trap_from_kernel(gr: &mut GeneralRegs) {
         }
         _ => {}
     }
+    dump_kernel_trap(&*gr, gr.pc, gr.fp);
     panic!(
         "a trap {:?} from kernel! bad addr = {:#x}, bad instruction = {:#x}, pc:{:#x}, (subcode:{}), PGDH: {:?}, PGDL: {:?}, {}",
         cause,
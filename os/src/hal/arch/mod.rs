@@ -11,6 +11,8 @@
 //!     - `config`：页表、堆、栈、内存边界等常量
 //!     - `kernel_stack`：内核栈分配和管理接口
 //!     - `sbi`：控制台、关机等系统调用接口
+//!     - `console`：RISC-V 上从 SBI 切换到串口的控制台
+//!     - `gdb`：GDB 桩所需的寄存器编号、断点写入与地址翻译
 //!     - `switch`：任务上下文切换函数
 //!     - `sync`：中断屏蔽信息
 //!     - `timer`：时钟和定时器接口
@@ -39,14 +41,24 @@ pub use riscv::{
     bootstrap_init,
     // 配置常量
     config::{
-        UserStackBase, BLOCK_SZ, KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END, PAGE_SIZE,
-        PAGE_SIZE_BITS, TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE,
+        UserStackBase, BLOCK_SZ, ELF_DYN_BASE, ELF_INTERP_BASE, ELF_MACHINE, HWCAP,
+        KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END, PAGE_SIZE, PAGE_SIZE_BITS, TRAMPOLINE,
+        TRAP_CONTEXT_BASE, USER_STACK_SIZE,
     },
     // 内核栈管理
     kernel_stack::{kstack_alloc, trap_cx_bottom_from_tid, ustack_bottom_from_tid, KernelStack},
+    // 控制台
+    console::{
+        console_enable_tx_interrupt, console_flush, console_getchar, console_init,
+        console_putchar, console_transmit,
+    },
     machine_init,
+    // GDB 桩
+    gdb,
+    // 外部中断控制器
+    plic::enable_irq,
     // SBI 系统调用
-    sbi::{console_flush, console_getchar, console_putchar, shutdown},
+    sbi::{reboot, shutdown},
     // 任务上下文切换
     switch::__switch,
     // 中断屏蔽管理
@@ -55,6 +67,8 @@ pub use riscv::{
     timer::{get_clock_freq, get_time},
     // Trap 相关
     trap::{context::TrapContext, trap_handler, trap_return},
+    frame_pointer,
+    wait_for_interrupt,
     // 页表类型别名
     PageTableEntryImpl,
     PageTableImpl,
@@ -69,21 +83,30 @@ pub use loongarch::{
     bootstrap_init,
     // 配置常量
     config::{
-        UserStackBase, HIGH_BASE_EIGHT, KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END,
-        MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN, MEMORY_SIZE, PAGE_SIZE, PAGE_SIZE_BITS, PALEN,
-        TRAMPOLINE, TRAP_CONTEXT_BASE, USER_STACK_SIZE, VA_MASK, VPN_SEG_MASK,
+        UserStackBase, ELF_DYN_BASE, ELF_INTERP_BASE, ELF_MACHINE, HIGH_BASE_EIGHT, HWCAP,
+        KERNEL_HEAP_SIZE, KERNEL_STACK_SIZE, MEMORY_END, MEMORY_HIGH_BASE, MEMORY_HIGH_BASE_VPN,
+        MEMORY_SIZE, PAGE_SIZE, PAGE_SIZE_BITS, PALEN, TRAMPOLINE, TRAP_CONTEXT_BASE,
+        USER_STACK_SIZE, VA_MASK, VPN_SEG_MASK,
     },
     // 内核栈管理
     kernel_stack::{kstack_alloc, KernelStack},
+    // GDB 桩
+    gdb,
+    intc::enable_irq,
     machine_init,
     // SBI 系统调用
-    sbi::{console_flush, console_getchar, console_putchar, shutdown},
+    sbi::{
+        console_enable_tx_interrupt, console_flush, console_getchar, console_init,
+        console_putchar, console_transmit, reboot, shutdown,
+    },
     // 中断屏蔽管理
     sync::INTR_MASKING_INFO,
     // 时钟与定时器
     timer::{get_clock_freq, get_time},
     // Trap 相关
     trap::{context::TrapContext, trap_handler, trap_return},
+    frame_pointer,
+    wait_for_interrupt,
     // 页表类型别名
     PageTableEntryImpl,
     PageTableImpl,
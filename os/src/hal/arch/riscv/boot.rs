@@ -5,8 +5,10 @@
 //!
 //! 主要功能包括：
 //! 1. 设置栈指针 `sp`。
-//! 2. 调用 Rust 层的主函数 `rust_main`。
-//! 3. 定义 `.bss` 段的栈空间。
+//! 2. 保存 SBI 传入的启动核编号（`a0`），供 PLIC 选择中断上下文；
+//!    以及设备树地址（`a1`），供 `hal::machine` 探测硬件布局。
+//! 3. 调用 Rust 层的主函数 `rust_main`。
+//! 4. 定义 `.bss` 段的栈空间。
 //!
 //! 注意：这是裸机或操作系统内核开发中的启动代码，不依赖标准库。
 
@@ -18,8 +20,22 @@ global_asm!(
     .globl _start
 _start:
     la sp, boot_stack_top
+    la t0, boot_hart_id
+    sd a0, 0(t0)
+    la t0, boot_dtb
+    sd a1, 0(t0)
     call rust_main
 
+    # 放在 .data 段，避免被 clear_bss 清零
+    .section .data
+    .globl boot_hart_id
+    .align 3
+boot_hart_id:
+    .dword 0
+    .globl boot_dtb
+boot_dtb:
+    .dword 0
+
     .section .bss.stack
     .globl boot_stack
 boot_stack:
@@ -28,3 +44,21 @@ boot_stack:
 boot_stack_top:
 "#
 );
+
+extern "C" {
+    static boot_hart_id: usize;
+    static boot_dtb: usize;
+}
+
+/// 启动核的编号
+///
+/// # Overview
+/// - OpenSBI 在多核时随机选择启动核，编号不一定为 0
+pub fn hart_id() -> usize {
+    unsafe { boot_hart_id }
+}
+
+/// 固件传入的设备树物理地址，没有时为 0
+pub fn dtb_addr() -> usize {
+    unsafe { boot_dtb }
+}
@@ -39,3 +39,18 @@ pub const MEMORY_END: usize = 0x8800_0000; // 约 2.2 GB
 /// 常用于文件系统或磁盘块管理
 pub const BLOCK_SZ: usize = 512;
 pub const UserStackBase: usize = TRAP_CONTEXT_BASE - 8 * (PAGE_SIZE + USER_STACK_SIZE);
+
+/// 位置无关可执行文件（ET_DYN）的装载基址，堆紧随其后
+pub const ELF_DYN_BASE: usize = 0x1000_0000;
+/// 动态链接器的装载基址，远离程序与堆
+pub const ELF_INTERP_BASE: usize = 0x20_0000_0000;
+/// ELF 头中的机器类型 EM_RISCV
+pub const ELF_MACHINE: u16 = 243;
+/// AT_HWCAP：每个 ISA 扩展字母占一位，QEMU virt 为 RV64IMAFDC
+pub const HWCAP: usize = ISA_I | ISA_M | ISA_A | ISA_F | ISA_D | ISA_C;
+const ISA_A: usize = 1 << (b'a' - b'a');
+const ISA_C: usize = 1 << (b'c' - b'a');
+const ISA_D: usize = 1 << (b'd' - b'a');
+const ISA_F: usize = 1 << (b'f' - b'a');
+const ISA_I: usize = 1 << (b'i' - b'a');
+const ISA_M: usize = 1 << (b'm' - b'a');
@@ -0,0 +1,221 @@
+//! 控制台模块（RISC-V）
+//! # Overview
+//! 内核启动时页表尚未建立，控制台经由 SBI 输出；`console_init` 在页表建立后
+//! 切换到设备树给出的 NS16550A（没有时为 `UART_BASE`），不再依赖 SBI 控制台：
+//! - SBI 阶段：`bootstrap_init` 时探测固件，优先使用 legacy 控制台扩展，
+//!   新版 OpenSBI 去掉 legacy 扩展后改用 DBCN 扩展
+//! - 串口阶段：输出先写入环形缓冲区，`console_flush` 或缓冲区满时送往串口
+//! - 串口中断接入后（`console_enable_tx_interrupt`），`console_flush` 只填满发送 FIFO，
+//!   剩余数据由发送 FIFO 空中断（`console_transmit`）继续发送
+//!
+//! # Assumptions
+//! - 单核运行，OpenSBI 已经完成串口的波特率等配置
+//! - 串口已经在内核页表中映射（`MACHINE.mmio()`）
+//!
+//! # Safety
+//! - 环形缓冲区是全局可变状态，所有访问都在 `INTR_MASKING_INFO` 屏蔽中断后进行
+//!
+//! # Invariants
+//! - 字节按写入顺序输出
+//! - 缓冲区满时同步发送，不丢弃任何输出
+//!
+//! # Behavior
+//! - 关机（`shutdown`）和 panic 前调用 `console_drain` 把缓冲区发送完毕
+//! - 没有可用的 SBI 控制台时，切换到串口前的输出被丢弃
+
+use super::sbi::{
+    dbcn_read, dbcn_write_byte, legacy_console_getchar, legacy_console_putchar, probe_extension,
+    SBI_EXT_DBCN,
+};
+use super::sync::INTR_MASKING_INFO;
+use crate::drivers::serial::ns16550a::FIFO_SIZE;
+use crate::drivers::Ns16550a;
+use crate::hal::platform::UART_BASE;
+use crate::hal::MACHINE;
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use embedded_hal::serial::nb::{Read, Write};
+
+/// 控制台后端
+const BACKEND_SBI_LEGACY: u8 = 0;
+const BACKEND_SBI_DBCN: u8 = 1;
+const BACKEND_UART: u8 = 2;
+const BACKEND_NONE: u8 = 3;
+
+/// legacy 控制台扩展的扩展号即 `console_putchar` 的功能号
+const SBI_EXT_LEGACY_CONSOLE_PUTCHAR: usize = 1;
+
+/// 发送缓冲区大小
+const TX_BUFFER_SIZE: usize = 4096;
+
+static BACKEND: AtomicU8 = AtomicU8::new(BACKEND_SBI_LEGACY);
+static TX_INTERRUPT: AtomicBool = AtomicBool::new(false);
+/// 串口寄存器基址，`console_init` 时确定
+static UART: AtomicUsize = AtomicUsize::new(UART_BASE);
+
+fn uart() -> Ns16550a {
+    Ns16550a::new(UART.load(Ordering::Relaxed))
+}
+
+/// 发送环形缓冲区
+struct TxBuffer {
+    buf: [u8; TX_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+}
+
+static mut TX_BUFFER: TxBuffer = TxBuffer {
+    buf: [0; TX_BUFFER_SIZE],
+    head: 0,
+    len: 0,
+};
+
+impl TxBuffer {
+    fn push(&mut self, byte: u8) {
+        self.buf[(self.head + self.len) % TX_BUFFER_SIZE] = byte;
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.buf[self.head];
+        self.head = (self.head + 1) % TX_BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+
+    /// 在发送 FIFO 空时写入一批数据，返回缓冲区是否已经发完
+    fn transmit(&mut self, uart: &mut Ns16550a) -> bool {
+        while self.len > 0 && uart.tx_empty() {
+            for _ in 0..FIFO_SIZE {
+                match self.pop() {
+                    Some(byte) => {
+                        let _ = uart.write(byte);
+                    }
+                    None => break,
+                }
+            }
+        }
+        self.len == 0
+    }
+
+    /// 忙等直到缓冲区全部发出
+    fn drain(&mut self, uart: &mut Ns16550a) {
+        while !self.transmit(uart) {}
+    }
+}
+
+/// 在屏蔽中断的情况下访问发送缓冲区
+fn with_tx_buffer<V>(f: impl FnOnce(&mut TxBuffer, &mut Ns16550a) -> V) -> V {
+    INTR_MASKING_INFO.get_mut().enter();
+    let mut uart = uart();
+    let ret = f(unsafe { &mut *core::ptr::addr_of_mut!(TX_BUFFER) }, &mut uart);
+    INTR_MASKING_INFO.get_mut().exit();
+    ret
+}
+
+/// 探测可用的 SBI 控制台，由 `bootstrap_init` 调用
+pub fn console_probe() {
+    let backend = if probe_extension(SBI_EXT_LEGACY_CONSOLE_PUTCHAR) {
+        BACKEND_SBI_LEGACY
+    } else if probe_extension(SBI_EXT_DBCN) {
+        BACKEND_SBI_DBCN
+    } else {
+        BACKEND_NONE
+    };
+    BACKEND.store(backend, Ordering::Relaxed);
+}
+
+/// 页表建立后切换到串口
+pub fn console_init() {
+    if let Some(device) = MACHINE.uart {
+        UART.store(device.base, Ordering::Relaxed);
+    }
+    uart().enable_fifo();
+    BACKEND.store(BACKEND_UART, Ordering::Relaxed);
+}
+
+/// 串口中断接入后改为中断驱动的发送
+pub fn console_enable_tx_interrupt() {
+    TX_INTERRUPT.store(true, Ordering::Relaxed);
+}
+
+/// 控制台输出一个字符
+///
+/// # Overview
+/// - 串口后端下只写入缓冲区，需要调用 `console_flush` 才会送出
+pub fn console_putchar(c: usize) {
+    match BACKEND.load(Ordering::Relaxed) {
+        BACKEND_UART => with_tx_buffer(|tx, uart| {
+            if tx.len == TX_BUFFER_SIZE {
+                tx.drain(uart);
+            }
+            tx.push(c as u8);
+        }),
+        BACKEND_SBI_LEGACY => legacy_console_putchar(c),
+        BACKEND_SBI_DBCN => dbcn_write_byte(c as u8),
+        _ => {}
+    }
+}
+
+/// 控制台读取一个字符
+///
+/// # Returns
+/// - 读取的字符，若无输入返回 `usize::MAX`
+pub fn console_getchar() -> usize {
+    match BACKEND.load(Ordering::Relaxed) {
+        BACKEND_UART => match uart().read() {
+            Ok(byte) => byte as usize,
+            Err(_) => usize::MAX,
+        },
+        BACKEND_SBI_LEGACY => legacy_console_getchar(),
+        BACKEND_SBI_DBCN => {
+            let mut byte = [0u8; 1];
+            if dbcn_read(&mut byte) == 1 {
+                byte[0] as usize
+            } else {
+                usize::MAX
+            }
+        }
+        _ => usize::MAX,
+    }
+}
+
+/// 送出缓冲区中的数据
+///
+/// # Overview
+/// - 中断驱动时只填满发送 FIFO 并打开发送中断，不忙等
+/// - 否则忙等直到全部发出
+pub fn console_flush() {
+    if BACKEND.load(Ordering::Relaxed) != BACKEND_UART {
+        return;
+    }
+    with_tx_buffer(|tx, uart| {
+        if TX_INTERRUPT.load(Ordering::Relaxed) {
+            let done = tx.transmit(uart);
+            uart.set_tx_interrupt(!done);
+        } else {
+            tx.drain(uart);
+        }
+    });
+}
+
+/// 串口中断中继续发送，缓冲区发完后关闭发送中断
+pub fn console_transmit() {
+    if BACKEND.load(Ordering::Relaxed) != BACKEND_UART {
+        return;
+    }
+    with_tx_buffer(|tx, uart| {
+        let done = tx.transmit(uart);
+        uart.set_tx_interrupt(!done);
+    });
+}
+
+/// 忙等直到缓冲区全部发出，用于关机和 panic 等不能再等中断的场合
+pub fn console_drain() {
+    if BACKEND.load(Ordering::Relaxed) != BACKEND_UART {
+        return;
+    }
+    with_tx_buffer(|tx, uart| tx.drain(uart));
+}
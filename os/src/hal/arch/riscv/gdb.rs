@@ -0,0 +1,119 @@
+//! # GDB 桩的体系结构部分（RISC-V）
+//!
+//! ## Overview
+//! - `GdbArch`：寄存器编号为 `x0`-`x31` 与 `pc`（32 号，即 `sepc`）
+//! - `TrapContext` 按此编号读写，内核态陷阱与用户态陷阱的现场都是它
+//! - `capture_registers`：panic 时没有陷阱现场，取调用处的 `pc`/`ra`/`sp`/`fp` 等构造一份
+//! - `kernel_addr` / `write_byte` / `sync_icache`：经页表访问被调试的内存
+//!
+//! ## Assumptions
+//! - 物理内存在内核页表中恒等映射，翻译得到的物理地址可以直接读取
+//!
+//! ## Safety
+//! - 内核代码段映射为只读可执行，`write_byte` 在关闭分页的一瞬间按物理地址写入，
+//!   期间不访问栈，且屏蔽中断
+//!
+//! ## Behavior
+//! - 内核态陷阱的现场不保存 `tp`，恢复时也不从现场恢复 `sp`，修改这两个寄存器无效
+
+use super::trap::context::TrapContext;
+use crate::hal::PageTableImpl;
+use crate::mm::{PageTable, VirtAddr};
+use core::arch::asm;
+use gdbstub::Registers;
+use riscv::register::{satp, sstatus};
+
+pub type GdbArch = gdbstub::RiscV64;
+
+impl Registers for TrapContext {
+    fn read(&self, n: usize) -> Option<usize> {
+        let regs = unsafe { &*(&self.general_regs as *const _ as *const [usize; 32]) };
+        match n {
+            0 => Some(0),
+            1..=31 => Some(regs[n]),
+            32 => Some(self.sepc),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, n: usize, value: usize) -> bool {
+        let regs = unsafe { &mut *(&mut self.general_regs as *mut _ as *mut [usize; 32]) };
+        match n {
+            1..=31 => regs[n] = value,
+            32 => self.sepc = value,
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// 调用处的寄存器，`sepc` 为调用处的地址，只有 `ra`、`sp`、`gp`、`tp`、`s0` 有意义
+#[inline(always)]
+pub fn capture_registers() -> TrapContext {
+    let mut cx = TrapContext {
+        general_regs: Default::default(),
+        sstatus: sstatus::read(),
+        sepc: 0,
+        kernel_satp: 0,
+        kernel_sp: 0,
+        trap_handler: 0,
+    };
+    let regs = &mut cx.general_regs;
+    unsafe {
+        asm!(
+            "auipc {pc}, 0",
+            "mv {ra}, ra",
+            "mv {sp}, sp",
+            "mv {gp}, gp",
+            "mv {tp}, tp",
+            "mv {fp}, s0",
+            pc = out(reg) cx.sepc,
+            ra = out(reg) regs.ra,
+            sp = out(reg) regs.sp,
+            gp = out(reg) regs.gp,
+            tp = out(reg) regs.tp,
+            fp = out(reg) regs.s0,
+        );
+    }
+    cx
+}
+
+/// 内核态停下时使用的页表，即当前的 `satp`
+pub fn kernel_token() -> usize {
+    satp::read().bits()
+}
+
+/// 被调试地址 `addr` 在内核中可以直接读取的地址，即经 `page_table` 翻译得到的物理地址
+pub fn kernel_addr(page_table: &PageTableImpl, addr: usize) -> Option<usize> {
+    let va = VirtAddr::from(addr);
+    let pte = page_table.translate(va.floor())?;
+    if !pte.is_valid() {
+        return None;
+    }
+    Some(usize::from(pte.ppn()) << crate::hal::PAGE_SIZE_BITS | va.page_offset())
+}
+
+/// 向 `kernel_addr` 得到的地址写入一个字节，不受页表权限的限制
+///
+/// ## Safety
+/// - `addr` 必须是物理内存中的地址
+pub unsafe fn write_byte(addr: usize, byte: u8) {
+    asm!(
+        "csrrci {sstatus}, sstatus, 2",
+        "csrrw {satp}, satp, zero",
+        "sfence.vma",
+        "sb {byte}, 0({addr})",
+        "csrw satp, {satp}",
+        "sfence.vma",
+        "csrw sstatus, {sstatus}",
+        sstatus = out(reg) _,
+        satp = out(reg) _,
+        addr = in(reg) addr,
+        byte = in(reg) byte,
+    );
+}
+
+/// 让取指看到 `write_byte` 写入的指令
+pub fn sync_icache() {
+    unsafe { asm!("fence.i") };
+}
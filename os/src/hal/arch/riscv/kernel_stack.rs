@@ -30,7 +30,7 @@ use crate::hal::{
 };
 use crate::mm::{MapPermission, VirtAddr, KERNEL_SPACE};
 use crate::sync::UPIntrFreeCell;
-use alloc::vec::Vec;
+use id_alloc::RecycleAllocator;
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -42,18 +42,6 @@ lazy_static! {
         unsafe { UPIntrFreeCell::new(RecycleAllocator::new()) };
 }
 
-/// 回收式内核栈分配器
-///
-/// # Fields
-/// - `current`：当前已分配的最大栈 ID
-/// - `recycled`：回收的栈 ID，等待复用
-struct RecycleAllocator {
-    /// 当前分配的最大栈 ID
-    current: usize,
-    /// 回收的栈 ID，可以重新分配
-    recycled: Vec<usize>,
-}
-
 /// 分配一个新的内核栈并映射到内核空间
 ///
 /// # Returns
@@ -90,43 +78,6 @@ fn kernel_stack_position(kstack_id: usize) -> (usize, usize) {
     (bottom, top)
 }
 
-impl RecycleAllocator {
-    /// 创建一个新的回收式栈分配器
-    fn new() -> Self {
-        RecycleAllocator {
-            current: 0,
-            recycled: Vec::new(),
-        }
-    }
-
-    /// 分配一个栈 ID
-    ///
-    /// 优先使用回收的 ID，如果没有回收的则使用新的 ID
-    fn alloc(&mut self) -> usize {
-        if let Some(id) = self.recycled.pop() {
-            id
-        } else {
-            self.current += 1;
-            self.current - 1
-        }
-    }
-
-    /// 回收一个栈 ID
-    ///
-    /// # Panics
-    /// - `id >= current` 时会 panic
-    /// - `id` 已经被回收过时会 panic
-    fn dealloc(&mut self, id: usize) {
-        assert!(id < self.current);
-        assert!(
-            !self.recycled.iter().any(|i| *i == id),
-            "id {} has been deallocated!",
-            id
-        );
-        self.recycled.push(id);
-    }
-}
-
 /// 内核栈句柄
 ///
 /// # Fields
@@ -4,11 +4,13 @@
 //! 包含任务如中断初始化、时钟中断配置，以及类型别名定义以统一页表接口。
 //!
 //! # Design
-//! - `bootstrap_init()`：在 kernel 启动阶段根据架构特点进行初始化。RISC-V 架构不需特殊处理，函数为空。
+//! - `bootstrap_init()`：在 kernel 启动阶段根据架构特点进行初始化，目前只探测 SBI 控制台。
 //! - `machine_init()`：初始化机器相关部分，设置中断处理函数和定时器中断。
 //! - 通过 `trap::init()` 初始化中断向量。
 //! - 通过 `trap::enable_timer_interrupt()` 启用时钟中断。
 //! - 通过 `set_next_trigger()` 设置下一次定时器触发。
+//! - 通过 `plic::init()` 初始化外部中断控制器。
+//! - `wait_for_interrupt()`：空闲时打开中断并等待，供调度循环在没有就绪任务时使用。
 //! - 提供类型别名 `PageTableImpl` 和 `PageTableEntryImpl`，统一上层内核页表接口。
 //!
 //! # Assumptions
@@ -18,7 +20,7 @@
 //!
 //! # Safety
 //! - 中断初始化和定时器触发涉及硬件寄存器操作，必须在允许上下文调用。
-//! - bootstrap_init() 为空函数，不产生副作用。
+//! - bootstrap_init() 只进行 SBI 调用，不修改内核状态以外的硬件配置。
 //!
 //! # Invariants
 //! - 初始化完成后，内核能够正确接收时钟中断。
@@ -28,7 +30,10 @@ use crate::hal::arch::riscv::timer::set_next_trigger;
 
 pub mod boot;
 pub mod config;
+pub mod console;
+pub mod gdb;
 pub mod kernel_stack;
+pub mod plic;
 pub mod sbi;
 pub mod sv39;
 pub mod switch;
@@ -39,8 +44,10 @@ pub mod trap;
 /// 内核启动阶段架构相关初始化
 ///
 /// # Overview
-/// - RISC-V 架构无需特殊处理，故为空函数
-pub fn bootstrap_init() {}
+/// - 探测固件提供的控制台扩展，页表建立前的输出都经由 SBI
+pub fn bootstrap_init() {
+    console::console_probe();
+}
 
 /// 初始化机器相关部分
 ///
@@ -48,10 +55,33 @@ pub fn bootstrap_init() {}
 /// - 初始化中断处理函数
 /// - 启用时钟中断
 /// - 设置下一次定时器触发
+/// - 初始化 PLIC，打开外部中断
 pub fn machine_init() {
     trap::init();
     trap::enable_timer_interrupt();
     set_next_trigger();
+    plic::init();
+}
+
+/// 打开中断并等待下一个中断到来
+///
+/// # Overview
+/// - 调度循环没有就绪任务时调用，被阻塞的任务只能由中断唤醒
+/// - 返回前重新关闭中断
+pub fn wait_for_interrupt() {
+    unsafe {
+        riscv::register::sstatus::set_sie();
+        riscv::asm::wfi();
+        riscv::register::sstatus::clear_sie();
+    }
+}
+
+/// 调用者的帧指针（`s0`），栈回溯的起点
+#[inline(always)]
+pub fn frame_pointer() -> usize {
+    let fp: usize;
+    unsafe { core::arch::asm!("mv {}, s0", out(reg) fp) };
+    fp
 }
 
 /// 页表实现类型别名
@@ -0,0 +1,69 @@
+//! PLIC 外部中断控制器
+//!
+//! # Overview
+//! QEMU `virt` 机器上的外设中断都经过 PLIC 汇总后以 S 态外部中断送达。
+//! 本模块只使用启动核的 S 态上下文：
+//! - `init`：阈值设为 0 并打开 `sie.SEIE`
+//! - `enable_irq`：设置中断源优先级并在启动核上使能
+//! - `handle_external`：claim 中断号，交给 `drivers::handle_irq` 分发，最后 complete
+//!
+//! # Assumptions
+//! - 内核只在启动核上运行
+//! - 中断号与设备的对应关系由驱动层维护
+//!
+//! # Safety
+//! - 寄存器访问均为 volatile，地址来自 `MACHINE.plic`，没有时为平台常量 `PLIC_BASE`
+//!
+//! # Invariants
+//! - 每次 claim 到的中断必须 complete，否则该中断源不会再次触发
+
+use super::boot::hart_id;
+use crate::hal::platform::PLIC_BASE;
+use crate::hal::MACHINE;
+use core::ptr::{read_volatile, write_volatile};
+use riscv::register::sie;
+
+const PRIORITY: usize = 0x0;
+const ENABLE: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const THRESHOLD: usize = 0x0;
+const CLAIM: usize = 0x4;
+
+/// 启动核 S 态对应的 PLIC 上下文编号
+fn context() -> usize {
+    hart_id() * 2 + 1
+}
+
+fn reg(offset: usize) -> *mut u32 {
+    let base = MACHINE.plic.map_or(PLIC_BASE, |plic| plic.base);
+    (base + offset) as *mut u32
+}
+
+pub fn init() {
+    unsafe {
+        write_volatile(reg(CONTEXT + context() * CONTEXT_STRIDE + THRESHOLD), 0);
+        sie::set_sext();
+    }
+}
+
+/// 使能中断源 `irq`
+pub fn enable_irq(irq: usize) {
+    let enable = reg(ENABLE + context() * ENABLE_STRIDE + (irq / 32) * 4);
+    unsafe {
+        write_volatile(reg(PRIORITY + irq * 4), 1);
+        write_volatile(enable, read_volatile(enable) | 1 << (irq % 32));
+    }
+}
+
+/// 处理一次 S 态外部中断
+pub fn handle_external() {
+    let claim = reg(CONTEXT + context() * CONTEXT_STRIDE + CLAIM);
+    let irq = unsafe { read_volatile(claim) } as usize;
+    if irq == 0 {
+        return;
+    }
+    crate::drivers::handle_irq(irq);
+    unsafe { write_volatile(claim, irq as u32) };
+}
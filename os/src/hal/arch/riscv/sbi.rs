@@ -2,11 +2,15 @@
 //! # Overview
 //! 本模块提供对 RISC-V SBI（Supervisor Binary Interface）的封装，用于内核和平台交互。
 //! 包含定时器设置、控制台输入输出、IPI（Inter-Processor Interrupt）、页表同步和系统关机等功能。
+//! 关机时带上退出状态：QEMU 上直接写测试设备（`sifive,test0`），QEMU 以该状态退出；
+//! 测试设备尚未就绪或不存在时经 SRST 扩展传递“是否失败”。
+//! 控制台既可以走 legacy 扩展，也可以走 SBI v2.0 的 DBCN（Debug Console）扩展，
+//! 由 `console` 模块在启动时探测后选用。
 //!
 //! # Design
 //! - 所有 SBI 调用通过 `ecall` 指令触发陷入 S 模式执行。
 //! - `sbi_call` 函数是通用封装，将函数号和参数传递给 SBI。
-//! - 上层函数（如 `set_timer`、`console_putchar`）直接调用 `sbi_call`，简化内核接口。
+//! - 上层函数（如 `set_timer`、`legacy_console_putchar`）直接调用 `sbi_call`，简化内核接口。
 //!
 //! # Assumptions
 //! - 内核运行在 S 模式下，并且底层固件或 SBI 实现可响应这些调用。
@@ -24,6 +28,7 @@
 #![allow(unused)]
 
 use core::arch::asm;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// SBI (Supervisor Binary Interface) 系统调用常量
 const SBI_SET_TIMER: usize = 0;
@@ -36,6 +41,31 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_REMOTE_SFENCE_VMA_ASID: usize = 7;
 const SBI_SHUTDOWN: usize = 8;
 
+/// Base 扩展及其功能号
+const SBI_EXT_BASE: usize = 0x10;
+const SBI_BASE_GET_SPEC_VERSION: usize = 0;
+const SBI_BASE_PROBE_EXTENSION: usize = 3;
+
+/// DBCN 扩展（"DBCN"）及其功能号
+pub const SBI_EXT_DBCN: usize = 0x4442_434E;
+const SBI_DBCN_CONSOLE_READ: usize = 1;
+const SBI_DBCN_CONSOLE_WRITE_BYTE: usize = 2;
+
+/// SRST 扩展（"SRST"）及其复位类型
+const SBI_EXT_SRST: usize = 0x5352_5354;
+const SBI_SRST_RESET: usize = 0;
+const SBI_SRST_TYPE_SHUTDOWN: usize = 0;
+const SBI_SRST_TYPE_COLD_REBOOT: usize = 1;
+const SBI_SRST_REASON_NONE: usize = 0;
+const SBI_SRST_REASON_SYSTEM_FAILURE: usize = 1;
+
+/// QEMU 测试设备的命令：成功退出，或在高 16 位带上状态失败退出
+const FINISHER_PASS: u32 = 0x5555;
+const FINISHER_FAIL: u32 = 0x3333;
+
+/// 测试设备基址，0 表示还不能使用
+static TEST_FINISHER: AtomicUsize = AtomicUsize::new(0);
+
 /// 通用 SBI 调用封装函数
 ///
 /// # Fields
@@ -64,6 +94,39 @@ fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// 带功能号的 SBI 调用（SBI v0.2 之后的调用约定）
+///
+/// # Returns
+/// - `(error, value)`，`error` 为 0 表示成功
+#[inline(always)]
+fn sbi_call_ext(eid: usize, fid: usize, arg0: usize, arg1: usize, arg2: usize) -> (isize, usize) {
+    let (error, value);
+    unsafe {
+        asm!(
+        "ecall",
+        inlateout("x10") arg0 => error,
+        inlateout("x11") arg1 => value,
+        in("x12") arg2,
+        in("x16") fid,
+        in("x17") eid,
+        );
+    }
+    (error, value)
+}
+
+/// 固件是否实现了扩展 `eid`
+///
+/// # Overview
+/// - 只实现 SBI v0.1 的固件没有 Base 扩展，此时只认为 legacy 扩展存在
+pub fn probe_extension(eid: usize) -> bool {
+    let (error, _) = sbi_call_ext(SBI_EXT_BASE, SBI_BASE_GET_SPEC_VERSION, 0, 0, 0);
+    if error != 0 {
+        return eid < SBI_EXT_BASE;
+    }
+    let (error, value) = sbi_call_ext(SBI_EXT_BASE, SBI_BASE_PROBE_EXTENSION, eid, 0, 0);
+    error == 0 && value != 0
+}
+
 /// 设置定时器
 ///
 /// # Arguments
@@ -74,39 +137,108 @@ pub fn set_timer(timer: usize) {
     sbi_call(SBI_SET_TIMER, timer, 0, 0);
 }
 
-/// 控制台输出一个字符
+/// legacy 扩展：控制台输出一个字符
 ///
 /// # Arguments
 /// - `c`：要输出的字符（ASCII 值）
 ///
 /// 调用 SBI 的 `SBI_CONSOLE_PUTCHAR` 功能，输出到串口或终端。
-pub fn console_putchar(c: usize) {
+pub fn legacy_console_putchar(c: usize) {
     sbi_call(SBI_CONSOLE_PUTCHAR, c, 0, 0);
 }
 
-/// 控制台读取一个字符
+/// legacy 扩展：控制台读取一个字符
 ///
 /// # Returns
-/// - 读取的字符（ASCII 值），若无输入返回负值或 0（取决于 SBI 实现）
+/// - 读取的字符（ASCII 值），若无输入返回 `usize::MAX`
 ///
 /// 调用 SBI 的 `SBI_CONSOLE_GETCHAR` 功能。
-pub fn console_getchar() -> usize {
+pub fn legacy_console_getchar() -> usize {
     sbi_call(SBI_CONSOLE_GETCHAR, 0, 0, 0)
 }
 
-/// 刷新控制台缓冲区
+/// DBCN 扩展：输出一个字节，固件保证字节被送出后才返回
+pub fn dbcn_write_byte(byte: u8) {
+    sbi_call_ext(SBI_EXT_DBCN, SBI_DBCN_CONSOLE_WRITE_BYTE, byte as usize, 0, 0);
+}
+
+/// DBCN 扩展：读取至多 `buf.len()` 个字节，返回读到的字节数
 ///
-/// 当前实现为空函数，SBI 不提供显式刷新接口。
-pub fn console_flush() {}
+/// # Safety
+/// - 固件按物理地址写入 `buf`，内核镜像是恒等映射的，虚拟地址即物理地址
+pub fn dbcn_read(buf: &mut [u8]) -> usize {
+    let (error, value) = sbi_call_ext(
+        SBI_EXT_DBCN,
+        SBI_DBCN_CONSOLE_READ,
+        buf.len(),
+        buf.as_mut_ptr() as usize,
+        0,
+    );
+    if error == 0 {
+        value
+    } else {
+        0
+    }
+}
 
-/// 关机系统
+/// 记录测试设备的基址
 ///
-/// 调用 SBI 的 `SBI_SHUTDOWN` 功能，尝试关机或重置系统。
+/// 设备在页表建立前以物理地址访问，之后经 `MACHINE.mmio()` 恒等映射，地址不变
+pub fn set_test_finisher(base: usize) {
+    TEST_FINISHER.store(base, Ordering::Relaxed);
+}
+
+/// 关机系统，`code` 为退出状态，0 表示成功
+///
+/// 依次尝试测试设备、SRST 扩展与 legacy `SBI_SHUTDOWN`：
+/// - 测试设备把状态的低 8 位交给 QEMU，低 8 位为 0 的非零状态按 1 处理
+/// - SRST 只能区分成功与失败，失败以“系统故障”为原因关机
+/// - legacy 关机不带状态
 ///
 /// # Panics
 /// - 如果关机失败，会触发 panic。
-pub fn shutdown() -> ! {
-    println!("run shutdown");
+pub fn shutdown(code: i32) -> ! {
+    println!("run shutdown, status {}", code);
+    super::console::console_drain();
+    let status = match code & 0xff {
+        0 if code != 0 => 1,
+        status => status as u32,
+    };
+    let finisher = TEST_FINISHER.load(Ordering::Relaxed);
+    if finisher != 0 {
+        let command = if status == 0 {
+            FINISHER_PASS
+        } else {
+            (status << 16) | FINISHER_FAIL
+        };
+        unsafe { (finisher as *mut u32).write_volatile(command) };
+    }
+    if probe_extension(SBI_EXT_SRST) {
+        let reason = if status == 0 {
+            SBI_SRST_REASON_NONE
+        } else {
+            SBI_SRST_REASON_SYSTEM_FAILURE
+        };
+        sbi_call_ext(
+            SBI_EXT_SRST,
+            SBI_SRST_RESET,
+            SBI_SRST_TYPE_SHUTDOWN,
+            reason,
+            0,
+        );
+    }
     sbi_call(SBI_SHUTDOWN, 0, 0, 0);
     panic!("It should shutdown!");
 }
+
+/// 重启系统
+///
+/// 通过 SRST 扩展冷重启；固件不支持时退化为关机。
+pub fn reboot() -> ! {
+    println!("run reboot");
+    super::console::console_drain();
+    if probe_extension(SBI_EXT_SRST) {
+        sbi_call_ext(SBI_EXT_SRST, SBI_SRST_RESET, SBI_SRST_TYPE_COLD_REBOOT, 0, 0);
+    }
+    shutdown(0)
+}
@@ -84,4 +84,9 @@ impl IntrMaskingInfo {
             }
         }
     }
+
+    /// 当前是否处于 `UPIntrFreeCell` 的临界区内
+    pub fn in_critical_section(&self) -> bool {
+        self.nested_level > 0
+    }
 }
@@ -10,7 +10,7 @@
 //! - 提供获取系统时钟频率接口 `get_clock_freq()`
 //!
 //! # Assumptions
-//! - `CLOCK_FREQ` 为 CPU 时钟频率，单位 Hz
+//! - 时钟频率取自设备树，没有设备树时使用平台常量 `CLOCK_FREQ`，单位 Hz
 //! - SBI `set_timer` 能正确触发定时器中断
 //! - 定时器中断处理函数能够及时响应触发
 //!
@@ -24,7 +24,7 @@
 //! - `get_time()` 返回单调递增时间戳
 
 use super::sbi::set_timer;
-use crate::hal::CLOCK_FREQ;
+use crate::hal::{CLOCK_FREQ, MACHINE};
 use riscv::register::time;
 
 /// 每秒的定时器 tick 数
@@ -42,15 +42,15 @@ pub fn get_time() -> usize {
 ///
 /// # Behavior
 /// - 通过 SBI `set_timer` 设置下一次触发时间
-/// - 触发时间 = 当前时间 + CLOCK_FREQ / TICKS_PER_SEC
+/// - 触发时间 = 当前时间 + 时钟频率 / TICKS_PER_SEC
 pub fn set_next_trigger() {
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
+    set_timer(get_time() + get_clock_freq() / TICKS_PER_SEC);
 }
 
 /// 获取系统时钟频率
 ///
 /// # Returns
-/// - 系统时钟频率（Hz）
+/// - 设备树 `/cpus/timebase-frequency` 给出的频率（Hz），没有时为 `CLOCK_FREQ`
 pub fn get_clock_freq() -> usize {
-    CLOCK_FREQ
+    MACHINE.timebase_freq.unwrap_or(CLOCK_FREQ)
 }
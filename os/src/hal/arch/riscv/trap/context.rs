@@ -7,6 +7,7 @@
 //! - 定义异常上下文（`TrapContext`）结构，保存完整 CPU 状态。
 //! - 提供初始化函数 `app_init_context` 用于创建用户任务上下文。
 //! - 支持设置用户栈指针 (`set_sp`)。
+//! - `Display` 按 ABI 名称打印寄存器现场，用于 panic 与进程异常退出时的诊断。
 //!
 //! # Design
 //! - 在发生 trap（异常或中断）时保存用户任务状态，便于异常返回。
@@ -28,6 +29,7 @@
 //! - `TrapContext.kernel_sp`：内核栈顶地址，用于 trap 处理。
 //! - `TrapContext.trap_handler`：内核异常/中断处理函数入口地址。
 
+use core::fmt;
 use riscv::register::sstatus::{read, Sstatus, SPP};
 
 /// 通用寄存器（General Purpose Registers）
@@ -73,6 +75,56 @@ pub struct GeneralRegs {
     pub t6: usize,  // 31
 }
 
+impl fmt::Display for GeneralRegs {
+    /// 每行四个寄存器，不打印恒为零的 `x0`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let regs = [
+            ("ra", self.ra),
+            ("sp", self.sp),
+            ("gp", self.gp),
+            ("tp", self.tp),
+            ("t0", self.t0),
+            ("t1", self.t1),
+            ("t2", self.t2),
+            ("s0", self.s0),
+            ("s1", self.s1),
+            ("a0", self.a0),
+            ("a1", self.a1),
+            ("a2", self.a2),
+            ("a3", self.a3),
+            ("a4", self.a4),
+            ("a5", self.a5),
+            ("a6", self.a6),
+            ("a7", self.a7),
+            ("s2", self.s2),
+            ("s3", self.s3),
+            ("s4", self.s4),
+            ("s5", self.s5),
+            ("s6", self.s6),
+            ("s7", self.s7),
+            ("s8", self.s8),
+            ("s9", self.s9),
+            ("s10", self.s10),
+            ("s11", self.s11),
+            ("t3", self.t3),
+            ("t4", self.t4),
+            ("t5", self.t5),
+            ("t6", self.t6),
+        ];
+        for (i, (name, value)) in regs.iter().enumerate() {
+            let sep = if i + 1 == regs.len() {
+                ""
+            } else if i % 4 == 3 {
+                "\n"
+            } else {
+                "  "
+            };
+            write!(f, "{:>3}: {:#018x}{}", name, value, sep)?;
+        }
+        Ok(())
+    }
+}
+
 // TODO: 因为实现浮点寄存器需要修改整个汇编代码，所以暂时注释掉
 //
 // #[repr(C)]
@@ -115,6 +167,24 @@ pub struct TrapContext {
     pub trap_handler: usize,
 }
 
+impl fmt::Display for TrapContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let spp = match self.sstatus.spp() {
+            SPP::User => 'U',
+            SPP::Supervisor => 'S',
+        };
+        writeln!(
+            f,
+            "sepc: {:#018x}  sstatus: SPP={} SIE={} SPIE={}",
+            self.sepc,
+            spp,
+            self.sstatus.sie() as u8,
+            self.sstatus.spie() as u8
+        )?;
+        write!(f, "{}", self.general_regs)
+    }
+}
+
 impl TrapContext {
     /// 设置用户态栈指针
     pub fn set_sp(&mut self, sp: usize) {
@@ -5,7 +5,10 @@
 //! - 用户态系统调用（Syscall）的分发
 //! - 用户态异常（如缺页、非法指令）的捕捉与处理
 //! - 时钟中断（Timer Interrupt）的调度
+//! - 外部中断（External Interrupt）经 PLIC 分发给设备驱动
 //! - 内核态陷阱（Kernel Trap）的保护性处理
+//! - 进程因 SIGSEGV 终止或遇到无法处理的陷阱时，打印寄存器现场与栈回溯
+//! - 断点异常与中断处理的末尾交给 GDB 桩（`crate::gdb`），由它决定是否停下
 //!
 //! # Overview
 //! - `trap_handler`: 用户态进入内核态后的统一 C 入口。
@@ -25,6 +28,9 @@
 
 pub mod context;
 
+use crate::backtrace::{dump_kernel_trap, dump_user_trap};
+use crate::gdb;
+use crate::hal::arch::riscv::gdb::kernel_token;
 use crate::hal::TRAMPOLINE;
 use crate::syscall::syscall;
 use crate::task::{
@@ -37,7 +43,9 @@ use riscv::register::mtvec::TrapMode;
 use riscv::register::scause::{Exception, Interrupt, Trap};
 use riscv::register::{scause, sepc, sie, sscratch, sstatus, stval, stvec};
 
+use crate::hal::arch::riscv::plic;
 use crate::hal::arch::riscv::timer::set_next_trigger;
+use crate::fs::block_cache_flush_expired;
 use crate::timer::check_timer;
 pub use context::TrapContext;
 
@@ -70,25 +78,29 @@ fn set_kernel_trap_entry() {
 
 /// 处理来自内核态的陷阱。
 ///
-/// 目前内核态仅预期处理外部中断和时钟中断。
+/// 目前内核态仅预期处理外部中断、时钟中断和 GDB 桩放置的断点。
 /// 如果发生页错误或非法指令，将触发 panic。
 #[no_mangle]
-pub fn trap_from_kernel(_trap_cx: &TrapContext) {
+pub fn trap_from_kernel(trap_cx: &mut TrapContext) {
     let scause = scause::read();
     let stval = stval::read();
     match scause.cause() {
         Trap::Interrupt(Interrupt::SupervisorExternal) => {
-            // crate::board::irq_handler();
-            // 外部中断处理逻辑（待实现）
-            todo!()
+            // 外部中断：由 PLIC 分发给对应设备
+            plic::handle_external();
+            gdb::check_break(trap_cx, kernel_token());
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             // 时钟中断：更新下次触发时间，但不立即触发调度
             set_next_trigger();
             check_timer();
+            gdb::check_break(trap_cx, kernel_token());
             // do not schedule now
         }
+        Trap::Exception(Exception::Breakpoint)
+            if gdb::handle_breakpoint(trap_cx, kernel_token()) => {}
         _ => {
+            dump_kernel_trap(&*trap_cx, trap_cx.sepc, trap_cx.general_regs.s0);
             panic!(
                 "Unsupported trap from kernel: {:?},sepc = {:#x}, stval = {:#x}!",
                 scause.cause(),
@@ -185,13 +197,27 @@ pub fn trap_handler() -> ! {
         Trap::Exception(Exception::IllegalInstruction) => {
             current_add_signal(SignalFlags::SIGILL);
         }
+        // 断点：不是 GDB 桩放置的断点时交给进程
+        Trap::Exception(Exception::Breakpoint) => {
+            if !gdb::handle_breakpoint(current_trap_cx(), current_user_token()) {
+                current_add_signal(SignalFlags::SIGTRAP);
+            }
+        }
         // 时钟中断
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             set_next_trigger();
             check_timer();
+            // 来自用户态，当前任务不持有内核锁，可以在此写回过期的脏块
+            block_cache_flush_expired();
+            gdb::check_break(current_trap_cx(), current_user_token());
             suspend_current_and_run_next();
         }
+        Trap::Interrupt(Interrupt::SupervisorExternal) => {
+            plic::handle_external();
+            gdb::check_break(current_trap_cx(), current_user_token());
+        }
         _ => {
+            dump_current_user_trap();
             panic!(
                 "Unsupported trap from user: {:?}, stval = {:#x}!",
                 scause.cause(),
@@ -207,11 +233,21 @@ pub fn trap_handler() -> ! {
     // 检查并处理信号，如进程因异常需要退出
     if let Some((errno, msg)) = check_signals_of_current() {
         println!("[kernel] {}", msg);
+        if SignalFlags::from_signum(-errno as usize) == Ok(SignalFlags::SIGSEGV) {
+            dump_current_user_trap();
+        }
         exit_current_and_run_next(errno);
     }
     trap_return();
 }
 
+/// 打印当前任务的用户态现场与用户栈回溯
+fn dump_current_user_trap() {
+    let cx: &TrapContext = current_trap_cx();
+    let regs = &cx.general_regs;
+    dump_user_trap(cx, current_user_token(), cx.sepc, regs.s0, regs.sp);
+}
+
 /// 返回用户态。
 ///
 /// 该函数完成最后的环境切换：
@@ -0,0 +1,257 @@
+//! 扁平设备树（FDT）解析
+//!
+//! # Overview
+//! 固件把设备树的物理地址交给内核（RISC-V 上为 `a1`）。
+//! 本模块只做只读遍历，不分配内存，可以在堆和页表建立之前使用：
+//! - `Fdt::from_addr`：检查头部并定位结构块与字符串块
+//! - `Fdt::nodes`：按深度优先顺序遍历所有节点
+//! - `Node::props`：遍历节点自身的属性（不含子节点的属性）
+//!
+//! # Assumptions
+//! - 设备树格式版本不低于 17
+//! - 设备树所在内存在解析期间不会被改写
+//!
+//! # Safety
+//! - `from_addr` 只检查魔数，之后按头部给出的长度访问内存，调用者需保证地址有效
+//!
+//! # Invariants
+//! - 结构块中的所有 token 都按 4 字节对齐
+//!
+//! # Behavior
+//! - 遇到无法识别的 token 时停止遍历，而不是 panic
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+const FDT_BEGIN_NODE: u32 = 1;
+const FDT_END_NODE: u32 = 2;
+const FDT_PROP: u32 = 3;
+const FDT_NOP: u32 = 4;
+
+/// 设备树
+#[derive(Clone, Copy)]
+pub struct Fdt {
+    structs: &'static [u8],
+    strings: &'static [u8],
+}
+
+/// 设备树节点
+#[derive(Clone, Copy)]
+pub struct Node {
+    /// 节点名，例如 `memory@80000000`，根节点为空串
+    pub name: &'static str,
+    /// 根节点深度为 0
+    pub depth: usize,
+    fdt: Fdt,
+    /// 第一个属性在结构块中的偏移
+    props: usize,
+}
+
+/// 节点属性
+#[derive(Clone, Copy)]
+pub struct Prop {
+    pub name: &'static str,
+    pub value: &'static [u8],
+}
+
+fn be32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let word = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([word[0], word[1], word[2], word[3]]))
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// 读取从 `offset` 开始以 0 结尾的字符串
+fn cstr(bytes: &'static [u8], offset: usize) -> Option<&'static str> {
+    let rest = bytes.get(offset..)?;
+    let len = rest.iter().position(|&b| b == 0)?;
+    core::str::from_utf8(&rest[..len]).ok()
+}
+
+impl Fdt {
+    /// 解析 `addr` 处的设备树
+    ///
+    /// # Safety
+    /// `addr` 必须指向可读的内存
+    pub unsafe fn from_addr(addr: usize) -> Option<Self> {
+        if addr == 0 || addr % 4 != 0 {
+            return None;
+        }
+        let header = core::slice::from_raw_parts(addr as *const u8, 40);
+        if be32(header, 0)? != FDT_MAGIC {
+            return None;
+        }
+        let total = be32(header, 4)? as usize;
+        let data = core::slice::from_raw_parts(addr as *const u8, total);
+        let off_struct = be32(header, 8)? as usize;
+        let off_strings = be32(header, 12)? as usize;
+        let size_strings = be32(header, 32)? as usize;
+        let size_struct = be32(header, 36)? as usize;
+        Some(Self {
+            structs: data.get(off_struct..off_struct + size_struct)?,
+            strings: data.get(off_strings..off_strings + size_strings)?,
+        })
+    }
+
+    /// 深度优先遍历所有节点
+    pub fn nodes(&self) -> Nodes {
+        Nodes {
+            fdt: *self,
+            offset: 0,
+            depth: 0,
+        }
+    }
+
+    /// 按完整路径（如 `/chosen`、`/cpus`）查找节点
+    pub fn find_node(&self, path: &str) -> Option<Node> {
+        let mut components = path.split('/').filter(|c| !c.is_empty());
+        let mut want = components.next();
+        let mut matched = 0;
+        for node in self.nodes() {
+            let want_name = match want {
+                Some(name) => name,
+                None => return None,
+            };
+            if node.depth == 0 {
+                continue;
+            }
+            if node.depth == matched + 1 && node.name == want_name {
+                matched += 1;
+                want = components.next();
+                if want.is_none() {
+                    return Some(node);
+                }
+            } else if node.depth <= matched {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+/// 节点迭代器
+pub struct Nodes {
+    fdt: Fdt,
+    offset: usize,
+    /// 下一个 BEGIN_NODE 的深度
+    depth: usize,
+}
+
+impl Iterator for Nodes {
+    type Item = Node;
+
+    fn next(&mut self) -> Option<Node> {
+        let structs = self.fdt.structs;
+        loop {
+            let token = be32(structs, self.offset)?;
+            self.offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name = cstr(structs, self.offset)?;
+                    self.offset = align4(self.offset + name.len() + 1);
+                    let node = Node {
+                        name,
+                        depth: self.depth,
+                        fdt: self.fdt,
+                        props: self.offset,
+                    };
+                    self.depth += 1;
+                    return Some(node);
+                }
+                FDT_END_NODE => self.depth = self.depth.checked_sub(1)?,
+                FDT_PROP => {
+                    let len = be32(structs, self.offset)? as usize;
+                    self.offset = align4(self.offset + 8 + len);
+                }
+                FDT_NOP => {}
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl Node {
+    /// 遍历节点自身的属性
+    pub fn props(&self) -> Props {
+        Props {
+            fdt: self.fdt,
+            offset: self.props,
+        }
+    }
+
+    pub fn prop(&self, name: &str) -> Option<Prop> {
+        self.props().find(|prop| prop.name == name)
+    }
+
+    /// 不含单元地址的节点名，例如 `memory@80000000` 为 `memory`
+    pub fn base_name(&self) -> &'static str {
+        self.name.split('@').next().unwrap_or(self.name)
+    }
+
+    /// `compatible` 属性中是否含有 `compat`
+    pub fn is_compatible(&self, compat: &str) -> bool {
+        self.prop("compatible")
+            .map_or(false, |prop| prop.strings().any(|s| s == compat))
+    }
+}
+
+/// 属性迭代器
+pub struct Props {
+    fdt: Fdt,
+    offset: usize,
+}
+
+impl Iterator for Props {
+    type Item = Prop;
+
+    fn next(&mut self) -> Option<Prop> {
+        let structs = self.fdt.structs;
+        loop {
+            match be32(structs, self.offset)? {
+                FDT_PROP => {
+                    let len = be32(structs, self.offset + 4)? as usize;
+                    let name_offset = be32(structs, self.offset + 8)? as usize;
+                    let start = self.offset + 12;
+                    let value = structs.get(start..start + len)?;
+                    self.offset = align4(start + len);
+                    return Some(Prop {
+                        name: cstr(self.fdt.strings, name_offset)?,
+                        value,
+                    });
+                }
+                FDT_NOP => self.offset += 4,
+                // 属性都在子节点之前，遇到其它 token 即结束
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl Prop {
+    /// 第 `index` 个 32 位单元
+    pub fn u32_at(&self, index: usize) -> Option<u32> {
+        be32(self.value, index * 4)
+    }
+
+    /// 从第 `index` 个单元起读取 `cells` 个单元组成的数
+    pub fn cells_at(&self, index: usize, cells: usize) -> Option<usize> {
+        (0..cells).try_fold(0usize, |acc, i| {
+            Some((acc << 32) | self.u32_at(index + i)? as usize)
+        })
+    }
+
+    /// 以 0 分隔的字符串列表
+    pub fn strings(&self) -> impl Iterator<Item = &'static str> {
+        let value: &'static [u8] = self.value;
+        value
+            .split(|&b| b == 0)
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| core::str::from_utf8(s).ok())
+    }
+
+    /// 单个字符串
+    pub fn as_str(&self) -> Option<&'static str> {
+        self.strings().next()
+    }
+}
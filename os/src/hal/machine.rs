@@ -0,0 +1,482 @@
+//! 机器描述
+//!
+//! # Overview
+//! 启动时确定的硬件布局，取代各平台模块中的硬编码常量：
+//! - RISC-V：解析 OpenSBI 通过 `a1` 传入的设备树，得到内存范围、时基频率、CPU 数量、
+//!   PLIC、串口（第二个串口留给 GDB 桩）、QEMU 测试设备、virtio-mmio 槽位、PCIe 主桥、
+//!   `/chosen/bootargs` 以及 initrd 的位置
+//! - LoongArch，或 RISC-V 上没有设备树时：使用 `platform` 中的常量；
+//!   LoongArch 的启动镜像固定放在 `DISK_IMAGE_BASE` 起到内存末尾的区间，
+//!   开头是 cpio 归档或磁盘镜像时才作为 initrd 预留
+//!
+//! `MACHINE` 在 `rust_main` 清零 `.bss` 之后、页帧分配器接管内存之前第一次访问时生成，此后只读。
+//!
+//! # Assumptions
+//! - 根节点与 `/soc` 使用相同的 `#address-cells` / `#size-cells`（QEMU `virt` 上均为 2）
+//! - 物理内存从一个 `memory` 节点描述，内核只使用第一段
+//! - PCIe 主桥的 32 位 MMIO 窗口中 CPU 地址与总线地址相同，INTx 按标准方式轮转
+//!
+//! # Safety
+//! - 设备树位于物理内存中，会被页帧分配器回收；需要的信息在解析时全部拷贝出来
+//! - LoongArch 上 `DISK_IMAGE_BASE` 处的内存在生成 `MACHINE` 时已可直接读取
+//!
+//! # Invariants
+//! - 中断号为 0 表示设备没有接入中断控制器（PLIC 的 0 号中断保留不用）
+//!
+//! # Behavior
+//! - 超出容量的 virtio 槽位和 MMIO 区域被忽略
+//! - `bootargs` 超过 `BOOTARGS_MAX` 时被截断
+//! - RISC-V 上 PCIe 只映射前 `PCI_MAX_BUSES` 条总线的配置空间与 MMIO 窗口的前 `PCI_MEM_MAP_MAX` 字节
+
+use crate::hal::fdt::Fdt;
+use crate::hal::{MEMORY_END, PAGE_SIZE};
+use lazy_static::lazy_static;
+
+/// 最多记录的 virtio-mmio 槽位数
+pub const MAX_VIRTIO: usize = 8;
+/// 最多记录的 MMIO 区域数
+pub const MAX_MMIO: usize = 16;
+/// 内核命令行的最大长度
+pub const BOOTARGS_MAX: usize = 512;
+
+/// RISC-V 上扫描并映射配置空间的总线数
+pub const PCI_MAX_BUSES: usize = 16;
+/// RISC-V 上映射并用于分配 BAR 的 MMIO 窗口大小
+pub const PCI_MEM_MAP_MAX: usize = 0x100_0000;
+
+/// 一个 MMIO 设备
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MmioDevice {
+    pub base: usize,
+    pub size: usize,
+    /// 中断号，0 表示没有
+    pub irq: usize,
+}
+
+/// ECAM 方式访问配置空间的 PCIe 主桥，地址均为物理地址
+#[derive(Clone, Copy, Debug)]
+pub struct PciHost {
+    /// 配置空间，每条总线 1 MiB
+    pub ecam: MmioDevice,
+    /// 第一条总线的总线号
+    pub bus_start: usize,
+    /// 分配 BAR 用的 32 位 MMIO 窗口 `(base, size)`
+    pub mem: (usize, usize),
+    /// 0 号插槽 INTA 的中断号
+    pub irq_base: usize,
+}
+
+pub struct MachineInfo {
+    /// 物理内存起止地址
+    pub memory_start: usize,
+    pub memory_end: usize,
+    /// 时基频率（Hz），`None` 表示使用平台常量
+    pub timebase_freq: Option<usize>,
+    pub cpus: usize,
+    pub plic: Option<MmioDevice>,
+    pub uart: Option<MmioDevice>,
+    /// 第二个 NS16550A，供 GDB 桩使用
+    pub debug_uart: Option<MmioDevice>,
+    /// QEMU 的 `sifive,test0` 设备，关机时经它把退出状态交给 QEMU
+    pub test_finisher: Option<MmioDevice>,
+    pub pci: Option<PciHost>,
+    virtio: [MmioDevice; MAX_VIRTIO],
+    virtio_count: usize,
+    mmio: [(usize, usize); MAX_MMIO],
+    mmio_count: usize,
+    bootargs: [u8; BOOTARGS_MAX],
+    bootargs_len: usize,
+    /// 引导程序加载到内存中的启动镜像 `[start, end)`（cpio 或磁盘镜像）
+    pub initrd: Option<(usize, usize)>,
+    /// 是否来自设备树
+    pub from_fdt: bool,
+}
+
+lazy_static! {
+    pub static ref MACHINE: MachineInfo = MachineInfo::probe();
+}
+
+impl MachineInfo {
+    fn empty() -> Self {
+        Self {
+            memory_start: 0,
+            memory_end: MEMORY_END,
+            timebase_freq: None,
+            cpus: 1,
+            plic: None,
+            uart: None,
+            debug_uart: None,
+            test_finisher: None,
+            pci: None,
+            virtio: [MmioDevice::default(); MAX_VIRTIO],
+            virtio_count: 0,
+            mmio: [(0, 0); MAX_MMIO],
+            mmio_count: 0,
+            bootargs: [0; BOOTARGS_MAX],
+            bootargs_len: 0,
+            initrd: None,
+            from_fdt: false,
+        }
+    }
+
+    #[cfg(feature = "riscv")]
+    fn probe() -> Self {
+        let dtb = crate::hal::arch::riscv::boot::dtb_addr();
+        let info = match unsafe { Fdt::from_addr(dtb) } {
+            Some(fdt) => Self::from_fdt(&fdt),
+            None => Self::from_platform(),
+        };
+        // 测试设备在内核页表中恒等映射，从此关机即可带上退出状态
+        if let Some(test) = info.test_finisher {
+            crate::hal::arch::riscv::sbi::set_test_finisher(test.base);
+        }
+        info
+    }
+
+    #[cfg(feature = "loongarch")]
+    fn probe() -> Self {
+        Self::from_platform()
+    }
+
+    /// 由平台常量构造
+    fn from_platform() -> Self {
+        let mut info = Self::empty();
+        for &(base, size) in crate::hal::platform::MMIO {
+            info.push_mmio(base, size);
+        }
+        #[cfg(feature = "board_rvqemu")]
+        {
+            use crate::hal::platform::{PLIC_BASE, UART_BASE, UART_IRQ};
+            info.memory_start = 0x8000_0000;
+            info.plic = Some(MmioDevice {
+                base: PLIC_BASE,
+                size: 0x40_0000,
+                irq: 0,
+            });
+            info.uart = Some(MmioDevice {
+                base: UART_BASE,
+                size: 0x100,
+                irq: UART_IRQ,
+            });
+            use crate::hal::platform::TEST_BASE;
+            info.test_finisher = Some(MmioDevice {
+                base: TEST_BASE,
+                size: 0x1000,
+                irq: 0,
+            });
+            use crate::hal::platform::{
+                PCI_ECAM_BASE, PCI_ECAM_SIZE, PCI_IRQ_BASE, PCI_MEM_BASE, PCI_MEM_SIZE,
+            };
+            info.set_pci(PciHost {
+                ecam: MmioDevice {
+                    base: PCI_ECAM_BASE,
+                    size: PCI_ECAM_SIZE,
+                    irq: 0,
+                },
+                bus_start: 0,
+                mem: (PCI_MEM_BASE, PCI_MEM_SIZE),
+                irq_base: PCI_IRQ_BASE,
+            });
+            // virtio-mmio 第 n 个槽位的中断号为 n + 1
+            for slot in 0..MAX_VIRTIO {
+                info.push_virtio(MmioDevice {
+                    base: 0x1000_1000 + slot * 0x1000,
+                    size: 0x1000,
+                    irq: slot + 1,
+                });
+            }
+        }
+        #[cfg(feature = "loongarch")]
+        {
+            use crate::hal::platform::{BLOCK_SZ, DISK_IMAGE_BASE, MEM_SIZE, MEM_START};
+            // 引导程序没有放入镜像时，这段内存照常交给页帧分配器
+            let head =
+                unsafe { core::slice::from_raw_parts(DISK_IMAGE_BASE as *const u8, BLOCK_SZ) };
+            if crate::fs::is_boot_image(head) {
+                info.initrd = Some((DISK_IMAGE_BASE, MEM_START + MEM_SIZE));
+            }
+        }
+        #[cfg(feature = "board_laqemu")]
+        {
+            use crate::hal::platform::{
+                PCI_ECAM_BASE, PCI_ECAM_SIZE, PCI_IRQ_BASE, PCI_MEM_BASE, PCI_MEM_SIZE,
+            };
+            use crate::hal::platform::{UART_BASE, UART_IRQ};
+            // LoongArch 经直接映射窗口访问设备，不需要登记 MMIO 区域
+            info.pci = Some(PciHost {
+                ecam: MmioDevice {
+                    base: PCI_ECAM_BASE,
+                    size: PCI_ECAM_SIZE,
+                    irq: 0,
+                },
+                bus_start: 0,
+                mem: (PCI_MEM_BASE, PCI_MEM_SIZE),
+                irq_base: PCI_IRQ_BASE,
+            });
+            info.uart = Some(MmioDevice {
+                base: UART_BASE,
+                size: 0x100,
+                irq: UART_IRQ,
+            });
+            // 较旧的 QEMU 只有一个串口，第二个串口的位置可能什么都没有
+            let debug_uart = crate::hal::platform::DEBUG_UART_BASE;
+            if crate::drivers::Ns16550a::new(debug_uart).probe() {
+                info.debug_uart = Some(MmioDevice {
+                    base: debug_uart,
+                    size: 0x100,
+                    irq: 0,
+                });
+            }
+        }
+        #[cfg(feature = "board_2k1000")]
+        {
+            info.uart = Some(MmioDevice {
+                base: crate::hal::platform::UART_BASE,
+                size: 0x100,
+                irq: 0,
+            });
+            info.debug_uart = Some(MmioDevice {
+                base: crate::hal::platform::DEBUG_UART_BASE,
+                size: 0x100,
+                irq: 0,
+            });
+        }
+        info
+    }
+
+    /// 由设备树构造
+    fn from_fdt(fdt: &Fdt) -> Self {
+        let mut info = Self::empty();
+        info.from_fdt = true;
+        info.cpus = 0;
+        let root = fdt.nodes().next();
+        let cell = |name: &str, default: usize| {
+            root.and_then(|root| root.prop(name))
+                .and_then(|prop| prop.u32_at(0))
+                .map_or(default, |value| value as usize)
+        };
+        let address_cells = cell("#address-cells", 2);
+        let size_cells = cell("#size-cells", 1);
+        let reg = |node: &crate::hal::fdt::Node| {
+            let prop = node.prop("reg")?;
+            Some((
+                prop.cells_at(0, address_cells)?,
+                prop.cells_at(address_cells, size_cells)?,
+            ))
+        };
+        let irq = |node: &crate::hal::fdt::Node| {
+            node.prop("interrupts")
+                .and_then(|prop| prop.u32_at(0))
+                .map_or(0, |irq| irq as usize)
+        };
+
+        for node in fdt.nodes() {
+            let name = node.base_name();
+            if name == "memory" && info.memory_start == 0 {
+                if let Some((base, size)) = reg(&node) {
+                    info.memory_start = base;
+                    info.memory_end = base + size;
+                }
+            } else if name == "cpus" {
+                info.timebase_freq = node
+                    .prop("timebase-frequency")
+                    .and_then(|prop| prop.u32_at(0))
+                    .map(|freq| freq as usize);
+            } else if name == "cpu" && node.depth == 2 {
+                info.cpus += 1;
+            } else if node.is_compatible("virtio,mmio") {
+                if let Some((base, size)) = reg(&node) {
+                    info.push_virtio(MmioDevice {
+                        base,
+                        size,
+                        irq: irq(&node),
+                    });
+                }
+            } else if node.is_compatible("riscv,plic0") || node.is_compatible("sifive,plic-1.0.0") {
+                if let Some((base, size)) = reg(&node) {
+                    info.plic = Some(MmioDevice { base, size, irq: 0 });
+                    info.push_mmio(base, size);
+                }
+            } else if node.is_compatible("pci-host-ecam-generic") && info.pci.is_none() {
+                if let Some(host) = Self::pci_host(&node, reg(&node), address_cells) {
+                    info.set_pci(host);
+                }
+            } else if node.is_compatible("ns16550a") && info.debug_uart.is_none() {
+                if let Some((base, size)) = reg(&node) {
+                    let uart = MmioDevice {
+                        base,
+                        size,
+                        irq: irq(&node),
+                    };
+                    if info.uart.is_none() {
+                        info.uart = Some(uart);
+                    } else {
+                        info.debug_uart = Some(uart);
+                    }
+                    info.push_mmio(base, size);
+                }
+            } else if node.is_compatible("sifive,test0") && info.test_finisher.is_none() {
+                if let Some((base, size)) = reg(&node) {
+                    info.test_finisher = Some(MmioDevice { base, size, irq: 0 });
+                    info.push_mmio(base, size);
+                }
+            }
+        }
+        info.cpus = info.cpus.max(1);
+
+        // virtio 槽位按地址排序，与命令行中 `virtio-mmio-bus.N` 的编号一致
+        info.virtio[..info.virtio_count].sort_unstable_by_key(|slot| slot.base);
+        for i in 0..info.virtio_count {
+            let slot = info.virtio[i];
+            info.push_mmio(slot.base, slot.size);
+        }
+
+        let chosen = fdt.find_node("/chosen");
+        // initrd 的起止地址可能是 32 位或 64 位
+        let chosen_addr = |name: &str| {
+            let prop = chosen?.prop(name)?;
+            prop.cells_at(0, prop.value.len() / 4)
+        };
+        if let (Some(start), Some(end)) = (
+            chosen_addr("linux,initrd-start"),
+            chosen_addr("linux,initrd-end"),
+        ) {
+            if start < end {
+                info.initrd = Some((start, end));
+            }
+        }
+        if let Some(bootargs) = chosen
+            .and_then(|chosen| chosen.prop("bootargs"))
+            .and_then(|prop| prop.as_str())
+        {
+            let len = bootargs.len().min(BOOTARGS_MAX);
+            info.bootargs[..len].copy_from_slice(&bootargs.as_bytes()[..len]);
+            info.bootargs_len = len;
+        }
+        info
+    }
+
+    /// 解析 `pci-host-ecam-generic` 节点
+    ///
+    /// `ranges` 每项为 3 个单元的总线地址、`address_cells` 个单元的 CPU 地址与 2 个单元的大小，
+    /// 总线地址首个单元的第 24～25 位为空间类型；`interrupt-map` 每项为
+    /// 3 个单元的设备地址、中断引脚、中断控制器句柄与 1 个单元的中断号
+    fn pci_host(
+        node: &crate::hal::fdt::Node,
+        reg: Option<(usize, usize)>,
+        address_cells: usize,
+    ) -> Option<PciHost> {
+        let (base, size) = reg?;
+        let bus_start = node
+            .prop("bus-range")
+            .and_then(|prop| prop.u32_at(0))
+            .map_or(0, |bus| bus as usize);
+        let ranges = node.prop("ranges")?;
+        let entry_cells = 3 + address_cells + 2;
+        let mut mem = None;
+        for i in 0..ranges.value.len() / 4 / entry_cells {
+            let at = i * entry_cells;
+            // 0b10 为 32 位 MMIO 空间
+            if (ranges.u32_at(at)? >> 24) & 0b11 == 0b10 {
+                mem = Some((
+                    ranges.cells_at(at + 3, address_cells)?,
+                    ranges.cells_at(at + 3 + address_cells, 2)?,
+                ));
+                break;
+            }
+        }
+        // 由第一项反推 0 号插槽 INTA 的中断号：插槽 s 的引脚 p 接到 base + (s + p - 1) % 4
+        let irq_base = node.prop("interrupt-map").and_then(|map| {
+            let slot = (map.u32_at(0)? as usize >> 11) & 0x1f;
+            let pin = map.u32_at(3)? as usize;
+            let irq = map.u32_at(5)? as usize;
+            irq.checked_sub((slot + pin.checked_sub(1)?) % 4)
+        });
+        Some(PciHost {
+            ecam: MmioDevice { base, size, irq: 0 },
+            bus_start,
+            mem: mem?,
+            irq_base: irq_base.unwrap_or(0),
+        })
+    }
+
+    /// 记录 PCIe 主桥并登记需要映射的配置空间与 MMIO 窗口
+    fn set_pci(&mut self, host: PciHost) {
+        self.push_mmio(host.ecam.base, host.ecam.size.min(PCI_MAX_BUSES << 20));
+        self.push_mmio(host.mem.0, host.mem.1.min(PCI_MEM_MAP_MAX));
+        self.pci = Some(host);
+    }
+
+    fn push_virtio(&mut self, device: MmioDevice) {
+        if self.virtio_count < MAX_VIRTIO {
+            self.virtio[self.virtio_count] = device;
+            self.virtio_count += 1;
+        }
+    }
+
+    /// 记录需要映射的 MMIO 区域，按页对齐
+    fn push_mmio(&mut self, base: usize, size: usize) {
+        if self.mmio_count < MAX_MMIO {
+            let start = base & !(PAGE_SIZE - 1);
+            let end = (base + size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
+            self.mmio[self.mmio_count] = (start, end - start);
+            self.mmio_count += 1;
+        }
+    }
+
+    /// virtio-mmio 槽位
+    pub fn virtio_mmio(&self) -> &[MmioDevice] {
+        &self.virtio[..self.virtio_count]
+    }
+
+    /// 内核需要映射的 MMIO 区域 `(base, size)`
+    pub fn mmio(&self) -> &[(usize, usize)] {
+        &self.mmio[..self.mmio_count]
+    }
+
+    /// 内核命令行，没有时为空串
+    pub fn bootargs(&self) -> &str {
+        core::str::from_utf8(&self.bootargs[..self.bootargs_len]).unwrap_or("")
+    }
+
+    /// 打印探测结果
+    pub fn print(&self) {
+        println!(
+            "[kernel] machine ({}): memory {:#x}..{:#x}, {} cpu(s)",
+            if self.from_fdt { "fdt" } else { "built-in" },
+            self.memory_start,
+            self.memory_end,
+            self.cpus
+        );
+        if let Some(freq) = self.timebase_freq {
+            println!("[kernel] timebase frequency: {} Hz", freq);
+        }
+        if let Some(uart) = self.uart {
+            println!("[kernel] uart: {:#x}, irq {}", uart.base, uart.irq);
+        }
+        if let Some(uart) = self.debug_uart {
+            println!("[kernel] debug uart: {:#x}", uart.base);
+        }
+        if let Some(plic) = self.plic {
+            println!("[kernel] plic: {:#x}", plic.base);
+        }
+        if let Some(test) = self.test_finisher {
+            println!("[kernel] test finisher: {:#x}", test.base);
+        }
+        println!("[kernel] virtio-mmio slots: {}", self.virtio_count);
+        if let Some(pci) = self.pci {
+            println!(
+                "[kernel] pci: ecam {:#x}, mmio window {:#x}..{:#x}",
+                pci.ecam.base,
+                pci.mem.0,
+                pci.mem.0 + pci.mem.1
+            );
+        }
+        if let Some((start, end)) = self.initrd {
+            println!("[kernel] initrd: {:#x}..{:#x}", start, end);
+        }
+        if !self.bootargs().is_empty() {
+            println!("[kernel] bootargs: {}", self.bootargs());
+        }
+    }
+}
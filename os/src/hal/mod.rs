@@ -3,6 +3,7 @@
 //! # Overview
 //! - **内存布局**：定义了物理内存终点 `MEMORY_END`、页大小 `PAGE_SIZE` 以及内核/用户栈大小。
 //! - **地址空间**：定义了 `TRAMPOLINE`（跳板页）和 `TRAP_CONTEXT_BASE` 等关键虚拟地址。
+//! - **硬件布局**：`MACHINE` 描述启动时探测到的内存范围、外设与内核命令行（RISC-V 上来自设备树）。
 //! - **硬件交互**：导出串口输入输出 (`console`)、时钟管理和关机等原语。
 //! - **进程切换**：导出上下文切换函数 `__switch` 和中断上下文结构 `TrapContext`。
 //! # Design
@@ -14,6 +15,12 @@
 pub mod arch;
 // 导入具体平台相关的模块（如 qemu, real_board 等）
 mod platform;
+// 设备树解析与启动时探测到的硬件布局
+pub mod fdt;
+mod machine;
+
+// --- 硬件布局 ---
+pub use machine::{MachineInfo, MmioDevice, PciHost, MACHINE, PCI_MAX_BUSES, PCI_MEM_MAP_MAX}; // 内存范围、外设地址与内核命令行
 
 // --- 进程与上下文切换 ---
 pub use arch::__switch; // 核心函数：实现 CPU 寄存器上下文的切换
@@ -24,6 +31,9 @@ pub use arch::TrapContext; // 中断上下文结构体（保存通用寄存器
 // --- 中断与陷阱处理 ---
 pub use arch::INTR_MASKING_INFO; // 中断屏蔽相关信息（用于处理中断嵌套或优先级）
 pub use arch::{bootstrap_init, machine_init}; // 系统的早期初始化和硬件初始化
+pub use arch::{enable_irq, wait_for_interrupt}; // 外部中断使能与空闲等待
+pub use arch::frame_pointer; // 当前帧指针，用于栈回溯
+pub use arch::gdb; // GDB 桩的体系结构部分：寄存器现场、断点写入与地址翻译
 pub use arch::{trap_handler, trap_return}; // 中断处理入口函数及返回函数
 
 // --- 内存管理相关 ---
@@ -40,13 +50,18 @@ pub use arch::{
 // --- 地址空间布局常量 ---
 pub use arch::{
     UserStackBase,     // 用户栈基地址
+    ELF_DYN_BASE,      // 位置无关可执行文件（ET_DYN）的装载基址
+    ELF_INTERP_BASE,   // 动态链接器的装载基址
+    ELF_MACHINE,       // 可执行文件 ELF 头中应有的机器类型
+    HWCAP,             // 通过 AT_HWCAP 告知用户程序的硬件能力
     TRAMPOLINE,        // 跳板页地址（用于用户态/内核态转换代码的映射）
     TRAP_CONTEXT_BASE, // 中断上下文在虚拟地址空间中的基地址
     USER_STACK_SIZE,   // 用户栈大小
 };
 
 // --- 控制台与系统操作 ---
-pub use arch::{console_flush, console_getchar, console_putchar, shutdown}; // 串口输入输出及关机
+pub use arch::{console_flush, console_getchar, console_putchar, reboot, shutdown}; // 串口输入输出、关机及重启
+pub use arch::{console_enable_tx_interrupt, console_init, console_transmit}; // 控制台切换到串口与中断驱动发送
 pub use arch::{get_clock_freq, get_time}; // 获取时钟频率和当前时间戳
 
 // --- 进程地址计算助手 ---
@@ -68,11 +83,17 @@ pub use arch::{
 // --- 针对特定板卡：LoongArch QEMU ---
 #[cfg(feature = "board_laqemu")]
 pub use platform::{MEM_SIZE, MMIO}; // 内存大小和内存映射 I/O 地址
+#[cfg(feature = "board_laqemu")]
+pub use platform::UART_BASE; // 串口基址，中断号见 `MACHINE.uart`
 
 // --- 针对特定板卡：RISC-V QEMU ---
 #[cfg(feature = "board_rvqemu")]
 pub use platform::{CLOCK_FREQ, MMIO}; // 时钟频率和内存映射 I/O 地址
+#[cfg(feature = "board_rvqemu")]
+pub use platform::UART_BASE; // 设备树缺失时使用的串口基址
 
 // --- 针对特定板卡：龙芯 2K1000 开发板 ---
 #[cfg(feature = "board_2k1000")]
 pub use platform::{MEM_SIZE, MMIO};
+#[cfg(feature = "board_2k1000")]
+pub use platform::UART_BASE; // 开发板上尚未接入中断控制器，串口只能轮询
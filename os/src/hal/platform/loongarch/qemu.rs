@@ -11,7 +11,18 @@ pub const MMIO: &[(usize, usize)] = &[
 // pub const BLOCK_SZ: usize = 2048;
 pub const BLOCK_SZ: usize = 4096;
 pub const UART_BASE: usize = 0x1FE0_01E0 + HIGH_BASE_EIGHT;
+/// 第二个串口，供 GDB 桩轮询使用（较新的 QEMU 在 `UART_BASE` 之后依次放置多个串口）
+pub const DEBUG_UART_BASE: usize = 0x1FE0_02E0 + HIGH_BASE_EIGHT;
+/// 串口在 PCH-PIC 上的中断号
+pub const UART_IRQ: usize = 2;
 pub const ACPI_BASE: usize = 0x100E_0000 + HIGH_BASE_EIGHT;
+/// PCIe 配置空间（ECAM）与 32 位 MMIO 窗口的物理地址
+pub const PCI_ECAM_BASE: usize = 0x2000_0000;
+pub const PCI_ECAM_SIZE: usize = 0x0800_0000;
+pub const PCI_MEM_BASE: usize = 0x4000_0000;
+pub const PCI_MEM_SIZE: usize = 0x4000_0000;
+/// INTA 在 PCH-PIC 上的中断号，INTB～INTD 依次加 1
+pub const PCI_IRQ_BASE: usize = 16;
 pub const MEM_START: usize = 0x0000_0000_8000_0000;
 pub const MEM_SIZE: usize = 0x3000_0000;
 pub const DISK_IMAGE_BASE: usize = 0x1800_0000 + MEM_START;
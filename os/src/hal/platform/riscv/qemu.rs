@@ -34,10 +34,31 @@ pub const CLOCK_FREQ: usize = 12500000;
 /// - 前者为起始地址，后者为区域大小（字节）
 pub const MMIO: &[(usize, usize)] = &[
     // 前者为地址，后者为大小
+    // QEMU 测试设备（`sifive,test0`），写入后 QEMU 以给定状态退出
+    (0x10_0000, 0x1000),
     // `UARTO` 串口设备 `mmio` 地址，用于打印日志
     (0x1000_0000, 0x1000),
-    // `VirtIO` 虚拟磁盘设备 `mmio`地址，用于读写文件
-    (0x1000_1000, 0x1000),
+    // `VirtIO` 设备 `mmio` 地址，共 8 个槽位：虚拟磁盘在槽位 0，网卡在槽位 1
+    (0x1000_1000, 0x8000),
     // `PLIC` 中断控制设备 `mmio`地址，用于处理外部事件
     (0xC00_0000, 0x40_0000),
 ];
+
+/// `PLIC` 中断控制器基址
+pub const PLIC_BASE: usize = 0xC00_0000;
+
+/// QEMU 测试设备基址
+pub const TEST_BASE: usize = 0x10_0000;
+
+/// `UART0` 寄存器基址
+pub const UART_BASE: usize = 0x1000_0000;
+/// `UART0` 在 `PLIC` 上的中断号
+pub const UART_IRQ: usize = 10;
+
+/// PCIe 配置空间（ECAM）与 32 位 MMIO 窗口，设备树缺失时使用
+pub const PCI_ECAM_BASE: usize = 0x3000_0000;
+pub const PCI_ECAM_SIZE: usize = 0x1000_0000;
+pub const PCI_MEM_BASE: usize = 0x4000_0000;
+pub const PCI_MEM_SIZE: usize = 0x4000_0000;
+/// INTA 在 `PLIC` 上的中断号，INTB～INTD 依次加 1
+pub const PCI_IRQ_BASE: usize = 32;
@@ -0,0 +1,48 @@
+//! # 内核单元测试
+//!
+//! ## Overview
+//! 以 `ktest` 特性并带 `--test` 编译内核（`make test`）时启用 `custom_test_frameworks`：
+//! 编译器收集各模块 `#[cfg(test)] mod tests` 中的 `#[test_case]` 函数，
+//! `rust_main` 在内存管理初始化之后调用 `test_main`，由 `run_tests` 逐个运行。
+//!
+//! ## Assumptions
+//! - 测例运行时堆、页帧分配器与内核页表已经就绪
+//! - 还没有任何任务，设备与文件系统也未初始化，测例不能阻塞或访问当前进程
+//!
+//! ## Behavior
+//! - 每个测例打印名字与结果，全部通过后以成功状态关机
+//! - 测例 panic 时由 panic 处理函数调用 `fail`，以失败状态关机，其余测例不再运行
+//! - RISC-V 上退出状态经 QEMU 测试设备交给 QEMU，失败时 QEMU 以非零状态退出；
+//!   LoongArch 的关机寄存器不带状态，只能根据输出判断
+
+use crate::hal::shutdown;
+use crate::power::PANIC_EXIT_CODE;
+
+/// 可运行的测例，打印自身名字与结果
+pub trait Testable {
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    fn run(&self) {
+        print!("test {} ... ", core::any::type_name::<T>());
+        self();
+        println!("ok");
+    }
+}
+
+/// 测试框架入口，由编译器生成的 `test_main` 调用
+pub fn run_tests(tests: &[&dyn Testable]) {
+    println!("\n[ktest] running {} tests", tests.len());
+    for test in tests {
+        test.run();
+    }
+    println!("[ktest] test result: ok. {} passed", tests.len());
+    shutdown(0)
+}
+
+/// 测例 panic 后调用，以失败状态关机
+pub fn fail() -> ! {
+    println!("[ktest] test result: FAILED");
+    shutdown(PANIC_EXIT_CODE)
+}
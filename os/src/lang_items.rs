@@ -1,6 +1,6 @@
+use crate::backtrace::backtrace;
 use crate::hal::shutdown;
-use crate::task::current_kstack_top;
-use core::arch::asm;
+use crate::power::PANIC_EXIT_CODE;
 use core::panic::PanicInfo;
 
 #[panic_handler]
@@ -18,26 +18,10 @@ fn panic(info: &PanicInfo) -> ! {
         println!("[kernel] Message: {}", msg);
     }
     backtrace();
-    shutdown()
-}
-
-fn backtrace() {
-    let mut fp: usize;
-    let stop = current_kstack_top();
-    unsafe {
-        asm!("mv {}, s0", out(reg) fp);
-    }
-    println!("\n----START BACKTRACE----");
-    for i in 0..10 {
-        if fp == stop {
-            break;
-        }
-        unsafe {
-            println!("#{}:ra={:#x}", i, *((fp - 8) as *const usize));
-            fp = *((fp - 16) as *const usize);
-        }
-    }
-    println!("----END OF BACKTRACE----");
+    crate::gdb::on_panic();
+    #[cfg(test)]
+    crate::ktest::fail();
+    shutdown(PANIC_EXIT_CODE)
 }
 
 #[macro_export]
@@ -3,14 +3,19 @@
 #![feature(panic_info_message)]
 #![feature(alloc_error_handler)]
 #![feature(asm_const)]
+#![cfg_attr(feature = "ktest", feature(custom_test_frameworks))]
+#![cfg_attr(feature = "ktest", test_runner(crate::ktest::run_tests))]
+#![cfg_attr(feature = "ktest", reexport_test_harness_main = "test_main")]
 
 extern crate alloc;
 extern crate core;
 
-use crate::hal::shutdown;
+use crate::power::{kernel_shutdown, PowerAction};
 
 #[macro_use]
 pub mod console;
+mod backtrace;
+mod cmdline;
 mod hal;
 mod lang_items;
 mod task;
@@ -33,7 +38,12 @@ fn clear_bss() {
 
 mod drivers;
 mod fs;
+mod gdb;
+#[cfg(all(feature = "ktest", test))]
+mod ktest;
 mod mm;
+mod net;
+mod power;
 mod sync;
 mod syscall;
 
@@ -43,14 +53,30 @@ pub fn rust_main() -> ! {
     clear_bss();
     console::init();
     println!("Welcome to RustOS!");
+    // 在页帧分配器回收设备树所在内存之前探测硬件布局
+    lazy_static::initialize(&hal::MACHINE);
     mm::init();
+    #[cfg(all(feature = "ktest", test))]
+    test_main();
+    hal::console_init();
     println!("Memory management initialized.");
+    cmdline::init();
+    if !cmdline::quiet() {
+        hal::MACHINE.print();
+    }
     hal::machine_init();
+    drivers::init();
     println!("machine init completed.");
-    fs::list_apps();
+    fs::load_initrd();
+    cmdline::load_config();
+    gdb::init();
+    if !cmdline::quiet() {
+        fs::list_apps();
+    }
     println!("File system initialized.");
+    drivers::net::init();
     task::add_initproc();
     println!("Initialization complete.");
     task::run_tasks();
-    shutdown();
+    kernel_shutdown(PowerAction::PowerOff(0));
 }
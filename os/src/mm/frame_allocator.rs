@@ -12,22 +12,29 @@
 //! - 当前实现为基于栈（Stack）的页帧分配器
 //! - 支持顺序分配与回收页帧
 //! - 使用 recycled 列表复用已释放页帧
+//! - 页帧耗尽时回收页缓存中干净且未被映射的页后重试
 //!
 //! # Safety
 //! - 本模块包含全局可变状态
 //! - 所有访问必须通过 `UPIntrFreeCell` 串行化
 //! - 调用方必须保证在正确的初始化顺序下使用
 //!
+//! # Reserved Range
+//! - 启动镜像（`MACHINE.initrd`）所在的页帧在初始化时被预留，不参与分配
+//! - 镜像解包后可以调用 `frame_release_reserved` 把这些页帧交还给分配器
+//!
 //! # Invariants
 //! - 已分配的页帧不会被重复分配
 //! - 被回收的页帧只能回收一次
 //! - `FrameTracker` 生命周期与页帧占用严格绑定
 
 use super::{PhysAddr, PhysPageNum};
-use crate::hal::MEMORY_END;
+use crate::fs::page_cache_reclaim;
+use crate::hal::MACHINE;
 use crate::sync::UPIntrFreeCell;
 use alloc::vec::Vec;
 use core::fmt::{self, Debug, Formatter};
+use id_alloc::StackAllocator;
 use lazy_static::*;
 
 lazy_static! {
@@ -47,7 +54,8 @@ lazy_static! {
 ///
 /// 页帧管理范围：
 /// - 起始地址：内核镜像结束地址（`ekernel`）
-/// - 结束地址：系统物理内存上限（`MEMORY_END`）
+/// - 结束地址：启动时探测到的物理内存上限（`MACHINE.memory_end`）
+/// - 启动镜像所在的区间被预留
 ///
 /// SAFETY:
 /// - `ekernel` 由链接脚本提供，地址有效
@@ -57,21 +65,36 @@ pub fn init_frame_allocator() {
     extern "C" {
         fn ekernel();
     }
-    FRAME_ALLOCATOR.exclusive_access().init(
+    let mut allocator = FRAME_ALLOCATOR.exclusive_access();
+    allocator.init(
         PhysAddr::from(ekernel as *const () as usize).ceil(),
-        PhysAddr::from(MEMORY_END).floor(),
+        PhysAddr::from(MACHINE.memory_end).floor(),
     );
+    if let Some((start, end)) = MACHINE.initrd {
+        allocator.reserve(PhysAddr::from(start).floor(), PhysAddr::from(end).ceil());
+    }
+}
+
+/// 把预留的启动镜像页帧交还给分配器，镜像内容此后不再可用
+pub fn frame_release_reserved() {
+    FRAME_ALLOCATOR.exclusive_access().release_reserved();
 }
 
 /// 分配一个物理页帧。
 ///
 /// 成功时返回一个 `FrameTracker`，
 /// 其生命周期与页帧占用绑定。
+/// 页帧耗尽时先回收页缓存再重试一次。
 pub fn frame_alloc() -> Option<FrameTracker> {
-    FRAME_ALLOCATOR
-        .exclusive_access()
-        .alloc()
-        .map(FrameTracker::new)
+    let ppn = FRAME_ALLOCATOR.exclusive_access().alloc();
+    ppn.or_else(|| {
+        // 回收时会释放页帧，不能持有分配器
+        if page_cache_reclaim() == 0 {
+            return None;
+        }
+        FRAME_ALLOCATOR.exclusive_access().alloc()
+    })
+    .map(FrameTracker::new)
 }
 
 /// 一次性分配多个连续页帧。
@@ -144,76 +167,138 @@ trait FrameAllocator {
     fn dealloc(&mut self, ppn: PhysPageNum);
 }
 
-/// 基于栈的页帧分配器实现。
+/// 基于栈的页帧分配器实现，以页帧号为编号包装 `id_alloc::StackAllocator`。
 ///
 /// 分配策略：
 /// - 顺序分配未使用页帧
 /// - 回收的页帧放入 recycled 栈中复用
-pub struct StackFrameAllocator {
-    /// 当前尚未分配的起始页帧号
-    current: usize,
-    /// 可分配页帧的上界（不包含）
-    end: usize,
-    /// 已回收、可再次分配的页帧号
-    recycled: Vec<usize>,
-}
+pub struct StackFrameAllocator(StackAllocator);
 
 impl StackFrameAllocator {
     /// 初始化页帧分配区间。
     ///
     /// `[l, r)` 区间内的页帧将被纳入管理。
     pub fn init(&mut self, l: PhysPageNum, r: PhysPageNum) {
-        self.current = l.0;
-        self.end = r.0;
+        self.0.init(l.0, r.0);
+    }
+
+    /// 预留 `[l, r)`，只取与尚未分配部分的交集
+    pub fn reserve(&mut self, l: PhysPageNum, r: PhysPageNum) {
+        self.0.reserve(l.0, r.0);
+    }
+
+    /// 撤销预留：还未越过预留区间时直接取消，否则把区间内的页帧放入回收栈
+    pub fn release_reserved(&mut self) {
+        self.0.release_reserved();
     }
 }
 impl FrameAllocator for StackFrameAllocator {
     /// 创建一个新的栈式页帧分配器。
     fn new() -> Self {
-        Self {
-            current: 0,
-            end: 0,
-            recycled: Vec::new(),
-        }
+        Self(StackAllocator::new())
     }
 
     /// 分配一个页帧。
     fn alloc(&mut self) -> Option<PhysPageNum> {
-        if let Some(ppn) = self.recycled.pop() {
-            Some(ppn.into())
-        } else if self.current == self.end {
-            None
-        } else {
-            self.current += 1;
-            Some((self.current - 1).into())
-        }
+        self.0.alloc().map(PhysPageNum::from)
     }
 
     /// 分配多个连续页帧。
     fn alloc_more(&mut self, pages: usize) -> Option<Vec<PhysPageNum>> {
-        if self.current + pages >= self.end {
-            None
-        } else {
-            self.current += pages;
-            let arr: Vec<usize> = (1..pages + 1).collect();
-            let v = arr.iter().map(|x| (self.current - x).into()).collect();
-            Some(v)
-        }
+        self.0
+            .alloc_more(pages)
+            .map(|ppns| ppns.into_iter().map(PhysPageNum::from).collect())
     }
 
     /// 回收一个页帧。
     ///
     /// 会进行合法性检查，防止重复回收或非法回收。
     fn dealloc(&mut self, ppn: PhysPageNum) {
-        let ppn = ppn.0;
-        // 合法性检查
-        if ppn >= self.current || self.recycled.iter().any(|&v| v == ppn) {
-            panic!("Frame ppn={:#x} has not been allocated!", ppn);
-        }
-        // 回收页帧
-        self.recycled.push(ppn);
+        self.0.dealloc(ppn.0);
     }
 }
 
 /// 当前使用的页帧分配器实现。
 type FrameAllocatorImpl = StackFrameAllocator;
+
+#[cfg(test)]
+mod tests {
+    use super::{FrameAllocator, StackFrameAllocator};
+    use crate::mm::PhysPageNum;
+
+    fn with_range(l: usize, r: usize) -> StackFrameAllocator {
+        let mut allocator = StackFrameAllocator::new();
+        allocator.init(PhysPageNum(l), PhysPageNum(r));
+        allocator
+    }
+
+    #[test_case]
+    fn alloc_until_exhausted() {
+        let mut allocator = with_range(10, 13);
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(10)));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(11)));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(12)));
+        assert_eq!(allocator.alloc(), None);
+    }
+
+    #[test_case]
+    fn dealloc_then_reuse() {
+        let mut allocator = with_range(10, 20);
+        let a = allocator.alloc().unwrap();
+        let b = allocator.alloc().unwrap();
+        allocator.dealloc(a);
+        allocator.dealloc(b);
+        assert_eq!(allocator.alloc(), Some(b));
+        assert_eq!(allocator.alloc(), Some(a));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(12)));
+    }
+
+    #[test_case]
+    fn alloc_more_is_contiguous() {
+        let mut allocator = with_range(10, 20);
+        let mut frames = allocator.alloc_more(3).unwrap();
+        frames.sort();
+        assert_eq!(frames, [PhysPageNum(10), PhysPageNum(11), PhysPageNum(12)]);
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(13)));
+        assert!(allocator.alloc_more(10).is_none());
+    }
+
+    #[test_case]
+    fn reserved_range_is_skipped() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(PhysPageNum(12), PhysPageNum(15));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(10)));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(11)));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(15)));
+        // 跨越预留区间起点的连续分配从区间终点开始
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(PhysPageNum(12), PhysPageNum(15));
+        let mut frames = allocator.alloc_more(3).unwrap();
+        frames.sort();
+        assert_eq!(frames, [PhysPageNum(15), PhysPageNum(16), PhysPageNum(17)]);
+    }
+
+    #[test_case]
+    fn release_reserved_returns_frames() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(PhysPageNum(12), PhysPageNum(15));
+        for _ in 0..3 {
+            allocator.alloc();
+        }
+        allocator.release_reserved();
+        let mut frames: [usize; 3] = core::array::from_fn(|_| allocator.alloc().unwrap().0);
+        frames.sort();
+        assert_eq!(frames, [12, 13, 14]);
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(16)));
+    }
+
+    #[test_case]
+    fn release_reserved_before_reaching_it() {
+        let mut allocator = with_range(10, 20);
+        allocator.reserve(PhysPageNum(12), PhysPageNum(15));
+        allocator.alloc();
+        allocator.release_reserved();
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(11)));
+        assert_eq!(allocator.alloc(), Some(PhysPageNum(12)));
+    }
+}
@@ -17,23 +17,35 @@
 //! # Safety / Invariants
 //! - 内核空间 `KERNEL_SPACE` 只初始化一次
 //! - 所有映射、解除映射操作需保证单核独占访问（使用 UPIntrFreeCell）
-//! - ELF 加载区域假设合法且与用户栈、trap_context 不冲突
+//! - ELF 加载区域假设合法且与用户栈、trap_context 不冲突；
+//!   相邻两个 `PT_LOAD` 段不共用同一页
+//! - 位置无关程序装载在 `ELF_DYN_BASE`，动态链接器装载在 `ELF_INTERP_BASE`
 //! - Framed 类型映射的页帧在 `MapArea` 内部追踪，确保不会泄漏
-
-use crate::fs::File;
-use crate::hal::{PageTableEntryImpl, PageTableImpl, MEMORY_END, MMIO, PAGE_SIZE, TRAMPOLINE};
+//! - 文件映射与 ELF 只读段可以直接映射页缓存中的页帧，这类区域标记为共享，
+//!   fork 时与父进程共用页帧而不复制
+
+use crate::fs::inode::OSInode;
+use crate::fs::{open_boot_file, File, OpenFlags, PageCache};
+use crate::hal::{
+    PageTableEntryImpl, PageTableImpl, UserStackBase, ELF_DYN_BASE, ELF_INTERP_BASE, ELF_MACHINE,
+    MACHINE, PAGE_SIZE, TRAMPOLINE,
+};
 use crate::mm::address::{align_up, VPNRange};
 use crate::mm::{
     frame_alloc, FrameTracker, PageTable, PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum,
 };
 use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{ENOENT, ENOEXEC};
 use alloc::collections::BTreeMap;
+use alloc::string::String;
 use alloc::sync::Arc;
-use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use lazy_static::lazy_static;
 use log::info;
+use vma::PageRange;
+use xmas_elf::header;
+use xmas_elf::program::Type;
 
 // 内核段符号，由链接脚本提供
 extern "C" {
@@ -127,10 +139,23 @@ impl<T: PageTable> MemorySet<T> {
     pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
         map_area.map(&mut self.page_table);
         if let Some(data) = data {
-            map_area.copy_data(&self.page_table, data);
+            map_area.copy_data(&self.page_table, 0, data);
         }
         self.areas.push(map_area);
     }
+
+    /// 将 MapArea 插入 MemorySet，并把 `data` 拷贝到首页内偏移 `offset` 处
+    fn push_at(&mut self, mut map_area: MapArea, offset: usize, data: &[u8]) {
+        map_area.map(&mut self.page_table);
+        map_area.copy_data(&self.page_table, offset, data);
+        self.areas.push(map_area);
+    }
+
+    /// 将 MapArea 插入 MemorySet，按顺序映射到给定的（共享）页帧
+    pub fn push_shared(&mut self, mut map_area: MapArea, frames: Vec<Arc<FrameTracker>>) {
+        map_area.map_frames(&mut self.page_table, frames);
+        self.areas.push(map_area);
+    }
     /// 映射 trampoline，不归 areas 管理
     fn map_trampoline(&mut self) {
         self.page_table.map(
@@ -245,41 +270,52 @@ impl<T: PageTable> MemorySet<T> {
             }
         }
 
-        let perm = MapPermission::from_bits(prot as u8)
-            .unwrap_or(MapPermission::R | MapPermission::W | MapPermission::U);
+        // PROT_READ / PROT_WRITE / PROT_EXEC 依次对应 R / W / X
+        let perm = MapPermission::from_bits_truncate((prot as u8) << 1) | MapPermission::U;
+        let flags = MapFlags::from_bits_truncate(flags);
+        let pages = end_vpn.0 - start_vpn.0;
+
+        let cache = file_arc
+            .as_ref()
+            .and_then(|file| file.as_any().downcast_ref::<OSInode>())
+            .and_then(|inode| inode.page_cache().cloned());
+        if let Some(cache) = cache {
+            if off % PAGE_SIZE != 0 {
+                return Err(-1);
+            }
+            let first = off / PAGE_SIZE;
+            let writable = perm.contains(MapPermission::W);
+            if flags.contains(MapFlags::MAP_SHARED) || !writable {
+                // 共享映射与只读私有映射直接映射页缓存中的页
+                let shared_writable = flags.contains(MapFlags::MAP_SHARED) && writable;
+                let frames = (0..pages)
+                    .map(|i| cache.map_page(first + i, shared_writable))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(-1isize)?;
+                self.push_shared(MapArea::new(start_va, end_va, MapType::Framed, perm), frames);
+            } else {
+                // 可写私有映射复制一份页缓存中的内容
+                self.insert_framed_area(start_va, end_va, perm);
+                let mut vpn = start_vpn;
+                for i in 0..pages {
+                    let page = self.page_table.translate(vpn).unwrap().ppn().get_bytes_array();
+                    cache.try_read_at(off + i * PAGE_SIZE, page);
+                    vpn.step();
+                }
+            }
+            return Ok(start_va.into());
+        }
 
-        MapArea::new(start_va, end_va, MapType::Framed, perm);
         //建立映射，并将数据初始化为零
         self.insert_framed_area(start_va, end_va, perm);
-
-        if file_arc.is_some() {
-            let file = file_arc.as_deref().ok_or(-1isize)?;
-            let file_stat = file.get_stat();
-            let file_len = file_stat.st_size as usize;
-            let copy_len = core::cmp::min(len, file_len);
-
-            let mut buf = vec![0u8; copy_len];
-            file.read_at(0, &mut buf)
-                .expect("[MemorySet.mmap]pelease add true fd");
-
-            let mut offset = off;
+        if let Some(file) = file_arc {
+            // 没有页缓存的文件逐页读入
             let mut vpn = start_vpn;
-
-            while offset < copy_len {
-                let page = self
-                    .page_table
-                    .translate(vpn)
-                    .unwrap()
-                    .ppn()
-                    .get_bytes_array();
-
-                let end = core::cmp::min(offset + PAGE_SIZE, copy_len);
-                let src = &buf[offset..end];
-                let dst = &mut page[..src.len()];
-
-                dst.copy_from_slice(src);
-
-                offset += PAGE_SIZE;
+            for i in 0..pages {
+                let page = self.page_table.translate(vpn).unwrap().ppn().get_bytes_array();
+                if file.read_at(off + i * PAGE_SIZE, page).is_err() {
+                    break;
+                }
                 vpn.step();
             }
         }
@@ -339,7 +375,7 @@ impl<T: PageTable> MemorySet<T> {
         memory_set.push(
             MapArea::new(
                 (ekernel as usize).into(),
-                MEMORY_END.into(),
+                MACHINE.memory_end.into(),
                 MapType::Identical,
                 MapPermission::R | MapPermission::W,
             ),
@@ -347,7 +383,7 @@ impl<T: PageTable> MemorySet<T> {
         );
 
         // 映射 MMIO 外设
-        for pair in MMIO {
+        for pair in MACHINE.mmio() {
             memory_set.push(
                 MapArea::new(
                     (*pair).0.into(),
@@ -361,46 +397,48 @@ impl<T: PageTable> MemorySet<T> {
         memory_set
     }
 
-    /// 从 ELF 数据构建用户空间 MemorySet
-    /// 返回 (MemorySet, user_stack_base, entry_point)
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize) {
+    /// 从 ELF 数据构建用户空间 MemorySet，返回地址空间与进程启动所需的 [`ElfInfo`]
+    ///
+    /// - `ET_DYN`（PIE）程序装载到 [`ELF_DYN_BASE`]，`ET_EXEC` 按链接地址装载
+    /// - 带有 `PT_INTERP` 的程序同时装载动态链接器到 [`ELF_INTERP_BASE`]，进程从链接器入口开始执行
+    /// - 给出 ELF 文件的页缓存 `cache` 时，只读且没有 bss 的段直接映射页缓存中的页
+    ///
+    /// ELF 头或段不合法（类别、字节序、机器类型与当前架构不符等）时返回 `ENOEXEC`，
+    /// 找不到动态链接器时返回 `ENOENT`
+    pub fn from_elf(
+        elf_data: &[u8],
+        cache: Option<&PageCache>,
+    ) -> Result<(Self, ElfInfo), isize> {
+        let elf = parse_elf(elf_data)?;
+        let bias = load_bias(&elf, ELF_DYN_BASE);
         let mut memory_set = Self::new_bare();
         // map trampoline
         memory_set.map_trampoline();
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
-        let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
-        let ph_count = elf_header.pt2.ph_count();
-        let mut max_end_vpn = VirtPageNum(0);
-        // 映射每一个段
-        for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
-                let mut map_perm = MapPermission::U;
-                let ph_flags = ph.flags();
-                if ph_flags.is_read() {
-                    map_perm |= MapPermission::R;
-                }
-                if ph_flags.is_write() {
-                    map_perm |= MapPermission::W;
-                }
-                if ph_flags.is_execute() {
-                    map_perm |= MapPermission::X;
-                }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                // 选择最大的作为结束虚拟页号
-                max_end_vpn = max_end_vpn.max(map_area.vpn_range.get_end());
-                // 插入映射，并拷贝数据，初始化数据区为 0
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
-            }
+        let (max_end_vpn, phdr) = memory_set.map_elf(&elf, bias, cache)?;
+        let entry = bias + elf.header.pt2.entry_point() as usize;
+        let mut info = ElfInfo {
+            entry,
+            program_entry: entry,
+            phdr,
+            phent: elf.header.pt2.ph_entry_size() as usize,
+            phnum: elf.header.pt2.ph_count() as usize,
+            interp_base: 0,
+        };
+
+        if let Some(interp) = elf_interpreter(&elf)? {
+            let inode = open_boot_file(&interp, OpenFlags::RDONLY).ok_or(ENOENT)?;
+            let interp_data = inode.read_all();
+            let interp_elf = parse_elf(&interp_data)?;
+            let interp_bias = load_bias(&interp_elf, ELF_INTERP_BASE);
+            let interp_cache = inode.page_cache().map(|cache| cache.as_ref());
+            memory_set.map_elf(&interp_elf, interp_bias, interp_cache)?;
+            info.interp_base = interp_bias;
+            info.entry = interp_bias + interp_elf.header.pt2.entry_point() as usize;
+            info!("[exec] interpreter {} at {:#x}", interp, interp_bias);
         }
+
+        // 堆紧跟在程序自身的最后一个段之后，与动态链接器无关
         let max_end_va: VirtAddr = max_end_vpn.into();
         let heap_start = align_up(max_end_va.into(), PAGE_SIZE);
 
@@ -408,7 +446,85 @@ impl<T: PageTable> MemorySet<T> {
         memory_set.heap_start = heap_start;
         memory_set.brk = heap_start;
 
-        (memory_set, elf.header.pt2.entry_point() as usize)
+        Ok((memory_set, info))
+    }
+
+    /// 把 `elf` 的每个 `PT_LOAD` 段偏移 `bias` 后映射到地址空间
+    ///
+    /// 返回最高段的结束页号，以及程序头表在用户空间中的地址（`AT_PHDR`）：
+    /// 优先使用 `PT_PHDR`，否则由覆盖 `e_phoff` 的 `PT_LOAD` 段推算
+    fn map_elf(
+        &mut self,
+        elf: &xmas_elf::ElfFile,
+        bias: usize,
+        cache: Option<&PageCache>,
+    ) -> Result<(VirtPageNum, usize), isize> {
+        let ph_offset = elf.header.pt2.ph_offset() as usize;
+        let mut max_end_vpn = VirtPageNum(0);
+        let mut phdr = None;
+        // 映射每一个段
+        for ph in elf.program_iter() {
+            match ph.get_type() {
+                Ok(Type::Phdr) => phdr = Some(bias + ph.virtual_addr() as usize),
+                Ok(Type::Load) => {}
+                _ => continue,
+            }
+            let offset = ph.offset() as usize;
+            let file_size = ph.file_size() as usize;
+            if offset + file_size > elf.input.len() || ph.file_size() > ph.mem_size() {
+                return Err(ENOEXEC);
+            }
+            if phdr.is_none() && (offset..offset + file_size).contains(&ph_offset) {
+                phdr = Some(bias + ph.virtual_addr() as usize + ph_offset - offset);
+            }
+            // 段必须落在用户栈之下，且不能与已经映射的段重叠，否则建立映射时会出错
+            let start = bias.checked_add(ph.virtual_addr() as usize).ok_or(ENOEXEC)?;
+            let end = start.checked_add(ph.mem_size() as usize).ok_or(ENOEXEC)?;
+            if end > UserStackBase {
+                return Err(ENOEXEC);
+            }
+            let start_va: VirtAddr = start.into();
+            let end_va: VirtAddr = end.into();
+            let (start_vpn, end_vpn) = (start_va.floor(), end_va.ceil());
+            if self.areas.iter().any(|area| {
+                start_vpn < area.vpn_range.get_end() && area.vpn_range.get_start() < end_vpn
+            }) {
+                return Err(ENOEXEC);
+            }
+            let mut map_perm = MapPermission::U;
+            let ph_flags = ph.flags();
+            if ph_flags.is_read() {
+                map_perm |= MapPermission::R;
+            }
+            if ph_flags.is_write() {
+                map_perm |= MapPermission::W;
+            }
+            if ph_flags.is_execute() {
+                map_perm |= MapPermission::X;
+            }
+            let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+            // 选择最大的作为结束虚拟页号
+            max_end_vpn = max_end_vpn.max(map_area.vpn_range.get_end());
+            let shareable = !map_perm.contains(MapPermission::W)
+                && ph.mem_size() == ph.file_size()
+                && offset % PAGE_SIZE == start_va.page_offset();
+            let pages = map_area.vpn_range.get_end().0 - map_area.vpn_range.get_start().0;
+            let frames = cache.filter(|_| shareable).and_then(|cache| {
+                (0..pages)
+                    .map(|i| cache.map_page(offset / PAGE_SIZE + i, false))
+                    .collect::<Option<Vec<_>>>()
+            });
+            match frames {
+                Some(frames) => self.push_shared(map_area, frames),
+                // 插入映射，并拷贝数据，初始化数据区为 0；段可能不从页首开始
+                None => self.push_at(
+                    map_area,
+                    start_va.page_offset(),
+                    &elf.input[offset..offset + file_size],
+                ),
+            }
+        }
+        Ok((max_end_vpn, phdr.unwrap_or(0)))
     }
 
     /// 从已存在的用户空间 MemorySet 克隆新的 MemorySet
@@ -420,6 +536,16 @@ impl<T: PageTable> MemorySet<T> {
         // 复制用户空间的每个映射区域
         for area in user_space.areas.iter() {
             let new_area = MapArea::from_another(area);
+            if area.shared {
+                // 页缓存中的页与父进程共用
+                let frames = area
+                    .vpn_range
+                    .into_iter()
+                    .map(|vpn| area.data_frames[&vpn].clone())
+                    .collect();
+                memory_set.push_shared(new_area, frames);
+                continue;
+            }
             memory_set.push(new_area, None);
 
             // 复制用户数据页内容
@@ -446,43 +572,12 @@ impl<T: PageTable> MemorySet<T> {
     /// 从堆顶开始找到一块连续可用虚拟地址，并将堆顶向后移动（len/PAGE_SIZE）向下取整
     /// len: 需要的字节数
     pub fn find_free_area(&mut self, len: usize) -> Result<usize, isize> {
-        // 1. 对齐到页
-        let len = (len + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
-
-        // 2. 从 堆顶 开始搜索
-        let mut addr = self.brk;
-
-        loop {
-            let start_vpn = VirtAddr::from(addr).floor();
-            let end_vpn = VirtAddr::from(addr + len).ceil();
-
-            // 检查是否与已有 VMA 冲突
-            let mut conflict = false;
-            for area in self.areas.iter() {
-                if area.check_overlapping(start_vpn, end_vpn).is_some() {
-                    conflict = true;
-                    break;
-                }
-            }
-
-            if !conflict {
-                // 找到空闲区，更新 brk
-                self.brk = addr + len;
-                return Ok(addr);
-            }
-
-            // 冲突的话跳到上一个 VMA 结束后继续
-            let mut next_addr = addr + PAGE_SIZE;
-            for area in self.areas.iter() {
-                let area_start: usize = VirtAddr::from(area.vpn_range.get_start()).into();
-                let area_end: usize = VirtAddr::from(area.vpn_range.get_end()).into();
-                if area_start <= addr && addr < area_end {
-                    next_addr = area_end;
-                    break;
-                }
-            }
-            addr = next_addr;
-        }
+        // 从堆顶开始搜索，冲突时跳到所在 VMA 的结束处继续
+        let areas = self.areas.iter().map(MapArea::page_range);
+        let addr = vma::find_free_area(areas, self.brk, len, PAGE_SIZE);
+        // 找到空闲区，更新 brk
+        self.brk = addr + align_up(len, PAGE_SIZE);
+        Ok(addr)
     }
 
     /// 回收数据页（清空 areas）
@@ -501,6 +596,8 @@ impl<T: PageTable> MemorySet<T> {
 /// `map_type`：映射类型
 ///
 /// `map_perm`：映射权限
+///
+/// `shared`：页帧由页缓存等外部共享
 pub struct MapArea {
     /// 虚拟页号范围
     vpn_range: VPNRange,
@@ -508,7 +605,7 @@ pub struct MapArea {
     ///
     /// 键：虚拟页号
     /// 值：对应的物理页帧追踪器
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     /// 映射类型
     ///
     /// `Identical`：虚拟页号与物理页号相同映射
@@ -519,6 +616,8 @@ pub struct MapArea {
     ///
     /// `MapPermission` 位标志，表示读(R)/写(W)/执行(X)/用户权限(U)
     map_perm: MapPermission,
+    /// 页帧来自页缓存，fork 时共享而不复制
+    shared: bool,
 }
 
 impl MapArea {
@@ -536,6 +635,7 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            shared: false,
         }
     }
 
@@ -546,32 +646,24 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type: another.map_type,
             map_perm: another.map_perm,
+            shared: false,
         }
     }
 
+    /// 虚拟页号范围
+    fn page_range(&self) -> PageRange {
+        PageRange::new(self.vpn_range.get_start().0, self.vpn_range.get_end().0)
+    }
+
     ///求虚拟地址的交集
     pub fn check_overlapping(
         &self,
         start_vpn: VirtPageNum,
         end_vpn: VirtPageNum,
     ) -> Option<(VirtPageNum, VirtPageNum)> {
-        let area_start_vpn: VirtPageNum = self.vpn_range.get_start();
-        let area_end_vpn: VirtPageNum = self.vpn_range.get_end();
-        if end_vpn < area_start_vpn || start_vpn > area_end_vpn {
-            None
-        } else {
-            let overlap_start = if start_vpn > area_start_vpn {
-                start_vpn
-            } else {
-                area_start_vpn
-            };
-            let overlap_end = if end_vpn < area_end_vpn {
-                end_vpn
-            } else {
-                area_end_vpn
-            };
-            Some((overlap_start, overlap_end))
-        }
+        self.page_range()
+            .check_overlapping(PageRange::new(start_vpn.0, end_vpn.0))
+            .map(|overlap| (VirtPageNum(overlap.start), VirtPageNum(overlap.end)))
     }
 
     ///将MaoAera分成三块
@@ -580,30 +672,17 @@ impl MapArea {
         start_vpn: VirtPageNum,
         end_vpn: VirtPageNum,
     ) -> Option<(MapArea, MapArea)> {
-        let area_start = self.vpn_range.get_start();
-        let area_end = self.vpn_range.get_end();
-        let start_va = VirtAddr::from(start_vpn);
-        let end_va = VirtAddr::from(end_vpn);
-        let area_end_va = VirtAddr::from(area_end);
-        let _ = VirtAddr::from(area_start);
         // 必须是严格的中间拆分
-        if !(area_start < start_vpn && start_vpn < end_vpn && end_vpn < area_end) {
-            return None;
-        }
-
-        // 1. 构造 middle: [start, end)
-        let mut middle = MapArea::new(start_va, end_va, self.map_type, self.map_perm);
+        let (left, mid, right) = self.page_range().split_three(start_vpn.0, end_vpn.0)?;
 
-        // middle 继承 frame / lazy 状态
-        middle.data_frames = self.data_frames.clone();
+        // 1. 构造 middle: [start, end)，继承 frame / lazy 状态
+        let middle = self.with_range(mid);
 
         // 2. 构造 right: [end, area_end)
-        let mut right = MapArea::new(end_va, area_end_va, self.map_type, self.map_perm);
-
-        right.data_frames = self.data_frames.clone();
+        let right = self.with_range(right);
 
         // 3. 修改 self 为 left: [area_start, start)
-        self.vpn_range = VPNRange::new(area_start, start_vpn);
+        self.set_range(left);
 
         Some((middle, right))
     }
@@ -613,22 +692,15 @@ impl MapArea {
         page_table: &mut T,
         new_end: VirtAddr,
     ) -> Result<(), ()> {
-        let new_end_vpn = new_end.floor();
-        let old_end_vpn = self.vpn_range.get_end();
-        let start_vpn = self.vpn_range.get_start();
-
-        if !(start_vpn < new_end_vpn && new_end_vpn < old_end_vpn) {
-            return Err(());
-        }
-
+        let mut range = self.page_range();
         // unmap [new_end, old_end)
-        for vpn in new_end_vpn.0..old_end_vpn.0 {
-            let vpn = VirtPageNum(vpn);
-            let _ = page_table.unmap(vpn); // 已经 unmapped 也无所谓
+        let removed = range.shrink_to(new_end.floor().0).ok_or(())?;
+        for vpn in removed.start..removed.end {
+            let _ = page_table.unmap(VirtPageNum(vpn)); // 已经 unmapped 也无所谓
         }
 
         // 更新区域
-        self.vpn_range = VPNRange::new(start_vpn, new_end_vpn);
+        self.set_range(range);
         Ok(())
     }
     ///将MapAera分成后一块
@@ -637,25 +709,31 @@ impl MapArea {
         page_table: &mut T,
         new_start: VirtAddr,
     ) -> Result<(), ()> {
-        let new_start_vpn = new_start.floor();
-        let old_start_vpn = self.vpn_range.get_start();
-        let old_end_vpn = self.vpn_range.get_end();
-
-        if !(old_start_vpn < new_start_vpn && new_start_vpn < old_end_vpn) {
-            return Err(());
-        }
-
+        let mut range = self.page_range();
         // unmap [old_start, new_start)
-        for vpn in old_start_vpn.0..new_start_vpn.0 {
-            let vpn = VirtPageNum(vpn);
-            let _ = page_table.unmap(vpn);
+        let removed = range.rshrink_to(new_start.floor().0).ok_or(())?;
+        for vpn in removed.start..removed.end {
+            let _ = page_table.unmap(VirtPageNum(vpn));
         }
 
         // 更新区域
-        self.vpn_range = VPNRange::new(new_start_vpn, old_end_vpn);
+        self.set_range(range);
         Ok(())
     }
 
+    /// 复制映射属性与页帧追踪表，覆盖 `range`
+    fn with_range(&self, range: PageRange) -> MapArea {
+        let mut area = MapArea::from_another(self);
+        area.set_range(range);
+        area.shared = self.shared;
+        area.data_frames = self.data_frames.clone();
+        area
+    }
+
+    fn set_range(&mut self, range: PageRange) {
+        self.vpn_range = VPNRange::new(VirtPageNum(range.start), VirtPageNum(range.end));
+    }
+
     /// 映射单个虚拟页
     ///
     /// 自动处理不同映射类型
@@ -668,7 +746,7 @@ impl MapArea {
             MapType::Framed => {
                 let frame = frame_alloc().unwrap();
                 ppn = frame.ppn;
-                self.data_frames.insert(vpn, frame);
+                self.data_frames.insert(vpn, Arc::new(frame));
             }
             MapType::Linear(pn_offset) => {
                 // check for sv39
@@ -695,6 +773,21 @@ impl MapArea {
         }
     }
 
+    /// 按顺序把给定页帧映射到整个 MapArea，页帧数与页数一致
+    pub fn map_frames<T: PageTable>(
+        &mut self,
+        page_table: &mut T,
+        frames: Vec<Arc<FrameTracker>>,
+    ) {
+        assert_eq!(self.map_type, MapType::Framed);
+        let pte_flags = MapPermission::from_bits(self.map_perm.bits()).unwrap();
+        for (vpn, frame) in self.vpn_range.into_iter().zip(frames) {
+            page_table.map(vpn, frame.ppn, pte_flags);
+            self.data_frames.insert(vpn, frame);
+        }
+        self.shared = true;
+    }
+
     /// 解除整个 MapArea 映射
     pub fn unmap<T: PageTable>(&mut self, page_table: &mut T) {
         for vpn in self.vpn_range {
@@ -704,29 +797,105 @@ impl MapArea {
 
     /// 将数据拷贝到映射的页帧
     ///
-    /// 假设所有帧已清零
-    pub fn copy_data<T: PageTable>(&mut self, page_table: &T, data: &[u8]) {
+    /// 假设所有帧已清零，`offset` 为数据在首页内的起始偏移
+    pub fn copy_data<T: PageTable>(&mut self, page_table: &T, offset: usize, data: &[u8]) {
         assert_eq!(self.map_type, MapType::Framed);
+        assert!(offset < PAGE_SIZE);
         let mut start: usize = 0;
+        let mut page_offset = offset;
         let mut current_vpn = self.vpn_range.get_start();
         let len = data.len();
-        loop {
-            let src = &data[start..len.min(start + PAGE_SIZE)];
+        while start < len {
+            let src = &data[start..len.min(start + PAGE_SIZE - page_offset)];
             let dst = &mut page_table
                 .translate(current_vpn)
                 .unwrap()
                 .ppn()
-                .get_bytes_array()[..src.len()];
+                .get_bytes_array()[page_offset..page_offset + src.len()];
             dst.copy_from_slice(src);
-            start += PAGE_SIZE;
-            if start >= len {
-                break;
-            }
+            start += src.len();
+            page_offset = 0;
             current_vpn.step();
         }
     }
 }
 
+/// ELF 装载结果，exec 据此设置入口并构造辅助向量
+#[derive(Clone, Copy, Debug)]
+pub struct ElfInfo {
+    /// 进程开始执行的地址：有动态链接器时为链接器入口
+    pub entry: usize,
+    /// 程序自身的入口（`AT_ENTRY`）
+    pub program_entry: usize,
+    /// 程序头表在用户空间中的地址（`AT_PHDR`），无法确定时为 0
+    pub phdr: usize,
+    /// 程序头表项大小（`AT_PHENT`）
+    pub phent: usize,
+    /// 程序头表项个数（`AT_PHNUM`）
+    pub phnum: usize,
+    /// 动态链接器的装载基址（`AT_BASE`），静态程序为 0
+    pub interp_base: usize,
+}
+
+/// ELF 头的长度（64 位）
+const ELF64_HEADER_SIZE: usize = 64;
+/// 程序头表项的长度（64 位）
+const ELF64_PHDR_SIZE: usize = 56;
+
+/// 解析并检查 ELF 头，确保后续遍历程序头不会越界
+///
+/// 只接受与当前架构相同的 64 位小端 `ET_EXEC` 或 `ET_DYN` 文件，其他情况返回 `ENOEXEC`
+fn parse_elf(elf_data: &[u8]) -> Result<xmas_elf::ElfFile<'_>, isize> {
+    if elf_data.len() < ELF64_HEADER_SIZE || elf_data[..4] != [0x7f, 0x45, 0x4c, 0x46] {
+        return Err(ENOEXEC);
+    }
+    let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| ENOEXEC)?;
+    let pt1 = elf.header.pt1;
+    if pt1.class() != header::Class::SixtyFour || pt1.data() != header::Data::LittleEndian {
+        return Err(ENOEXEC);
+    }
+    // e_machine 位于偏移 18，直接比较数值，不依赖 xmas_elf 是否认识该架构
+    if u16::from_le_bytes([elf_data[18], elf_data[19]]) != ELF_MACHINE {
+        return Err(ENOEXEC);
+    }
+    match elf.header.pt2.type_().as_type() {
+        header::Type::Executable | header::Type::SharedObject => {}
+        _ => return Err(ENOEXEC),
+    }
+    let ph_offset = elf.header.pt2.ph_offset() as usize;
+    let ph_size = elf.header.pt2.ph_entry_size() as usize;
+    let ph_count = elf.header.pt2.ph_count() as usize;
+    let ph_end = ph_offset.checked_add(ph_size * ph_count).ok_or(ENOEXEC)?;
+    if ph_size != ELF64_PHDR_SIZE || ph_count == 0 || ph_end > elf_data.len() {
+        return Err(ENOEXEC);
+    }
+    Ok(elf)
+}
+
+/// 装载偏移：`ET_DYN` 装载到 `dyn_base`，`ET_EXEC` 按链接地址装载
+fn load_bias(elf: &xmas_elf::ElfFile, dyn_base: usize) -> usize {
+    match elf.header.pt2.type_().as_type() {
+        header::Type::SharedObject => dyn_base,
+        _ => 0,
+    }
+}
+
+/// 读取 `PT_INTERP` 给出的动态链接器路径，路径不是合法字符串时返回 `ENOEXEC`
+fn elf_interpreter(elf: &xmas_elf::ElfFile) -> Result<Option<String>, isize> {
+    let Some(ph) = elf.program_iter().find(|ph| ph.get_type() == Ok(Type::Interp)) else {
+        return Ok(None);
+    };
+    let start = ph.offset() as usize;
+    let end = start + ph.file_size() as usize;
+    let bytes = elf.input.get(start..end).ok_or(ENOEXEC)?;
+    // 路径以 NUL 结尾
+    let bytes = bytes.split(|&b| b == 0).next().unwrap_or(&[]);
+    match core::str::from_utf8(bytes) {
+        Ok(path) if !path.is_empty() => Ok(Some(String::from(path))),
+        _ => Err(ENOEXEC),
+    }
+}
+
 /// 页映射类型
 ///
 /// `Identical`：虚拟页号与物理页号相同映射
@@ -772,3 +941,66 @@ bitflags! {
         const MAP_FIXED   = 0x10;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{MapArea, MapPermission, MapType};
+    use crate::hal::PAGE_SIZE;
+    use crate::mm::{VirtAddr, VirtPageNum};
+
+    /// 覆盖第 `start` 到第 `end` 页（不含）的未映射区域
+    fn area(start: usize, end: usize) -> MapArea {
+        MapArea::new(
+            VirtAddr::from(start * PAGE_SIZE),
+            VirtAddr::from(end * PAGE_SIZE),
+            MapType::Framed,
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        )
+    }
+
+    fn range(area: &MapArea) -> (usize, usize) {
+        (area.vpn_range.get_start().0, area.vpn_range.get_end().0)
+    }
+
+    #[test_case]
+    fn into_three_splits_in_the_middle() {
+        let mut left = area(0x10, 0x20);
+        let (middle, right) = left
+            .into_three(VirtPageNum(0x14), VirtPageNum(0x18))
+            .unwrap();
+        assert_eq!(range(&left), (0x10, 0x14));
+        assert_eq!(range(&middle), (0x14, 0x18));
+        assert_eq!(range(&right), (0x18, 0x20));
+        assert_eq!(middle.map_perm, left.map_perm);
+        assert_eq!(right.map_perm, left.map_perm);
+    }
+
+    #[test_case]
+    fn into_three_rejects_boundaries() {
+        let mut area = area(0x10, 0x20);
+        assert!(area
+            .into_three(VirtPageNum(0x10), VirtPageNum(0x18))
+            .is_none());
+        assert!(area
+            .into_three(VirtPageNum(0x14), VirtPageNum(0x20))
+            .is_none());
+        assert!(area
+            .into_three(VirtPageNum(0x18), VirtPageNum(0x14))
+            .is_none());
+        assert!(area
+            .into_three(VirtPageNum(0x08), VirtPageNum(0x28))
+            .is_none());
+        assert_eq!(range(&area), (0x10, 0x20));
+    }
+
+    #[test_case]
+    fn into_three_single_page_middle() {
+        let mut left = area(0x10, 0x13);
+        let (middle, right) = left
+            .into_three(VirtPageNum(0x11), VirtPageNum(0x12))
+            .unwrap();
+        assert_eq!(range(&left), (0x10, 0x11));
+        assert_eq!(range(&middle), (0x11, 0x12));
+        assert_eq!(range(&right), (0x12, 0x13));
+    }
+}
@@ -12,9 +12,13 @@ pub fn init() {
     KERNEL_SPACE.exclusive_access().activate();
 }
 
-pub use crate::mm::memory_set::{kernel_token, MapFlags, MapPermission, MemorySet, KERNEL_SPACE};
+pub use crate::mm::memory_set::{
+    kernel_token, ElfInfo, MapFlags, MapPermission, MemorySet, KERNEL_SPACE,
+};
 pub use address::{PhysAddr, PhysPageNum, StepByOne, VirtAddr, VirtPageNum};
-pub use frame_allocator::{frame_alloc, frame_alloc_more, frame_dealloc, FrameTracker};
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_more, frame_dealloc, frame_release_reserved, FrameTracker,
+};
 pub use pagetable::{
     copy_to_user, get_from_user, translated_byte_buffer, translated_ref, translated_refmut,
     translated_str, PageTable, UserBuffer,
@@ -0,0 +1,173 @@
+//! # IPv4 回环
+//!
+//! ## Overview
+//! 内核只有一个回环接口 `lo`（127.0.0.1/8），本模块负责：
+//! - IPv4 地址（`InetAddr`）与路由：只有 `127.0.0.0/8` 可达
+//! - 端口表（`PortTable`）：TCP、UDP 各一张，管理 bind 与临时端口分配
+//!
+//! 回环上的数据不经过 IP 分组的封装与解析，
+//! 由 `tcp` / `udp` 直接放进对端套接字的接收队列。
+//!
+//! ## Assumptions
+//! - 没有真实网卡，也不支持 IPv6
+//!
+//! ## Safety
+//! - 端口表只保存 `Weak` 引用，不延长套接字的生命周期
+//!
+//! ## Invariants
+//! - 同一端口上，除了经 SO_REUSEADDR 共享的绑定，存活的绑定之间 IP 不相同，
+//!   且至多一个是 `INADDR_ANY`
+//!
+//! ## Behavior
+//! - 绑定端口 0 时从临时端口区间中分配，临时端口不与其他绑定共享
+//! - 显式绑定时，由协议判断已有的哪些绑定可以共享端口（SO_REUSEADDR）
+//! - 套接字销毁后，其端口在下一次访问端口表时被回收
+
+use crate::syscall::errno::{EADDRINUSE, EADDRNOTAVAIL, ENETUNREACH};
+use alloc::collections::BTreeMap;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+
+/// 通配地址 0.0.0.0
+pub const INADDR_ANY: [u8; 4] = [0, 0, 0, 0];
+/// 回环地址 127.0.0.1
+pub const INADDR_LOOPBACK: [u8; 4] = [127, 0, 0, 1];
+
+/// 临时端口区间
+const EPHEMERAL_PORT_START: u16 = 49152;
+const EPHEMERAL_PORT_END: u16 = 65535;
+
+/// IPv4 地址与端口
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct InetAddr {
+    pub ip: [u8; 4],
+    pub port: u16,
+}
+
+impl InetAddr {
+    pub const fn new(ip: [u8; 4], port: u16) -> Self {
+        Self { ip, port }
+    }
+
+    pub fn is_any(&self) -> bool {
+        self.ip == INADDR_ANY
+    }
+
+    pub fn is_loopback(&self) -> bool {
+        self.ip[0] == 127
+    }
+}
+
+/// 检查地址能否用于 bind：只能是通配地址或回环地址
+pub fn check_local(addr: &InetAddr) -> Result<(), isize> {
+    if addr.is_any() || addr.is_loopback() {
+        Ok(())
+    } else {
+        Err(-EADDRNOTAVAIL)
+    }
+}
+
+/// 路由：返回发往 `dest` 时实际使用的目的地址
+///
+/// 与 Linux 一样，目的地址 0.0.0.0 被当作本机
+pub fn route(dest: &InetAddr) -> Result<InetAddr, isize> {
+    if dest.is_any() {
+        Ok(InetAddr::new(INADDR_LOOPBACK, dest.port))
+    } else if dest.is_loopback() {
+        Ok(*dest)
+    } else {
+        Err(-ENETUNREACH)
+    }
+}
+
+/// 端口表
+pub struct PortTable<T> {
+    bindings: BTreeMap<u16, Vec<(InetAddr, Weak<T>)>>,
+    next_ephemeral: u16,
+}
+
+impl<T> PortTable<T> {
+    pub fn new() -> Self {
+        Self {
+            bindings: BTreeMap::new(),
+            next_ephemeral: EPHEMERAL_PORT_START,
+        }
+    }
+
+    /// 端口上 `ip` 是否与存活的绑定冲突，`shareable` 返回真的绑定不算冲突
+    pub fn conflicts(&mut self, ip: [u8; 4], port: u16, shareable: impl Fn(&T) -> bool) -> bool {
+        let Some(list) = self.bindings.get_mut(&port) else {
+            return false;
+        };
+        list.retain(|(_, weak)| weak.strong_count() > 0);
+        list.iter().any(|(addr, weak)| {
+            (addr.ip == ip || addr.is_any() || ip == INADDR_ANY)
+                && weak.upgrade().map_or(false, |socket| !shareable(&socket))
+        })
+    }
+
+    /// 绑定地址，端口为 0 时自动分配；返回实际绑定的地址
+    ///
+    /// 显式指定端口时，`shareable` 返回真的已有绑定可以与新绑定共享端口
+    pub fn bind(
+        &mut self,
+        addr: InetAddr,
+        socket: Weak<T>,
+        shareable: impl Fn(&T) -> bool,
+    ) -> Result<InetAddr, isize> {
+        check_local(&addr)?;
+        let port = if addr.port != 0 {
+            if self.conflicts(addr.ip, addr.port, shareable) {
+                return Err(-EADDRINUSE);
+            }
+            addr.port
+        } else {
+            let span = (EPHEMERAL_PORT_END - EPHEMERAL_PORT_START) as usize + 1;
+            let mut found = None;
+            for _ in 0..span {
+                let port = self.next_ephemeral;
+                self.next_ephemeral = if port == EPHEMERAL_PORT_END {
+                    EPHEMERAL_PORT_START
+                } else {
+                    port + 1
+                };
+                if !self.conflicts(addr.ip, port, |_| false) {
+                    found = Some(port);
+                    break;
+                }
+            }
+            found.ok_or(-EADDRINUSE)?
+        };
+        let bound = InetAddr::new(addr.ip, port);
+        self.bindings
+            .entry(port)
+            .or_insert_with(Vec::new)
+            .push((bound, socket));
+        Ok(bound)
+    }
+
+    /// 查找接收 `dest` 且 `accept` 返回真的套接字
+    ///
+    /// 精确匹配优先于通配地址；共享端口时后绑定的优先
+    pub fn lookup(&self, dest: &InetAddr, accept: impl Fn(&T) -> bool) -> Option<Arc<T>> {
+        let list = self.bindings.get(&dest.port)?;
+        let find = |matches: &dyn Fn(&InetAddr) -> bool| {
+            list.iter()
+                .rev()
+                .filter(|(addr, _)| matches(addr))
+                .filter_map(|(_, weak)| weak.upgrade())
+                .find(|socket| accept(socket))
+        };
+        find(&|addr| addr.ip == dest.ip).or_else(|| find(&|addr| addr.is_any()))
+    }
+
+    /// 回收端口上已经销毁的套接字
+    pub fn release(&mut self, port: u16) {
+        if let Some(list) = self.bindings.get_mut(&port) {
+            list.retain(|(_, weak)| weak.strong_count() > 0);
+            if list.is_empty() {
+                self.bindings.remove(&port);
+            }
+        }
+    }
+}
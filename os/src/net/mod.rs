@@ -0,0 +1,470 @@
+//! # 套接字模块
+//!
+//! ## Overview
+//! 本模块提供与协议族无关的套接字抽象：
+//! - `Socket` trait：bind / listen / accept / connect / send / recv / shutdown
+//! - `SockAddr`：内核内部的套接字地址，负责与用户态 `sockaddr` 互相转换
+//! - `SocketOptions`：setsockopt / getsockopt 保存的选项
+//! - 各协议族的实现位于子模块中（`unix`、`inet`、`tcp`、`udp`）
+//!
+//! 套接字本身也是 `File`，放在进程的 `fd_table` 中，
+//! 系统调用层通过 `File::as_socket` 取得套接字接口。
+//!
+//! ## Assumptions
+//! - 单处理器环境，套接字内部状态由 `UPIntrFreeCell` 保护
+//! - 阻塞操作与管道相同，采用让出 CPU 后重新检查的方式等待
+//!
+//! ## Safety
+//! - 访问用户态地址前必须检查指针非空，并通过页表转换
+//!
+//! ## Invariants
+//! - `SockAddr` 中的文件系统路径均为绝对路径
+//!
+//! ## Behavior
+//! - 等待期间进程收到信号则返回 `EINTR`
+
+pub mod inet;
+pub mod tcp;
+pub mod udp;
+pub mod unix;
+
+use crate::fs::file::{BLK_SIZE, S_IFSOCK};
+use crate::fs::{resolve_path, File, UserStat};
+use crate::mm::{translated_byte_buffer, UserBuffer};
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{EAFNOSUPPORT, EFAULT, EINTR, EINVAL, ENOPROTOOPT};
+use crate::task::{current_process, suspend_current_and_run_next};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub use inet::InetAddr;
+pub use tcp::TcpSocket;
+pub use udp::UdpSocket;
+pub use unix::{UnixAddr, UnixSocket};
+
+/// 协议族
+pub const AF_UNIX: u16 = 1;
+pub const AF_INET: u16 = 2;
+
+/// 协议号
+pub const IPPROTO_IP: i32 = 0;
+pub const IPPROTO_TCP: i32 = 6;
+pub const IPPROTO_UDP: i32 = 17;
+
+/// 套接字类型
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+pub const SOCK_SEQPACKET: usize = 5;
+/// `socket` 的 type 参数中可以附带的标志
+pub const SOCK_NONBLOCK: usize = 0o4000;
+pub const SOCK_CLOEXEC: usize = 0o2000000;
+pub const SOCK_TYPE_MASK: usize = 0xf;
+
+/// `shutdown` 的 how 参数
+pub const SHUT_RD: usize = 0;
+pub const SHUT_WR: usize = 1;
+pub const SHUT_RDWR: usize = 2;
+
+/// send / recv 标志
+pub const MSG_PEEK: usize = 0x2;
+pub const MSG_CTRUNC: usize = 0x8;
+pub const MSG_TRUNC: usize = 0x20;
+pub const MSG_DONTWAIT: usize = 0x40;
+pub const MSG_NOSIGNAL: usize = 0x4000;
+pub const MSG_CMSG_CLOEXEC: usize = 0x4000_0000;
+
+/// 辅助数据
+pub const SOL_SOCKET: i32 = 1;
+pub const SCM_RIGHTS: i32 = 1;
+
+/// SOL_SOCKET 层的选项
+pub const SO_REUSEADDR: i32 = 2;
+pub const SO_TYPE: i32 = 3;
+pub const SO_ERROR: i32 = 4;
+pub const SO_DONTROUTE: i32 = 5;
+pub const SO_BROADCAST: i32 = 6;
+pub const SO_SNDBUF: i32 = 7;
+pub const SO_RCVBUF: i32 = 8;
+pub const SO_KEEPALIVE: i32 = 9;
+pub const SO_LINGER: i32 = 13;
+pub const SO_REUSEPORT: i32 = 15;
+pub const SO_PASSCRED: i32 = 16;
+pub const SO_RCVTIMEO: i32 = 20;
+pub const SO_SNDTIMEO: i32 = 21;
+pub const SO_ACCEPTCONN: i32 = 30;
+pub const SO_PROTOCOL: i32 = 38;
+pub const SO_DOMAIN: i32 = 39;
+/// IPPROTO_IP 层的选项
+pub const IP_TOS: i32 = 1;
+pub const IP_TTL: i32 = 2;
+/// IPPROTO_TCP 层的选项
+pub const TCP_NODELAY: i32 = 1;
+pub const TCP_MAXSEG: i32 = 2;
+pub const TCP_KEEPIDLE: i32 = 4;
+pub const TCP_KEEPINTVL: i32 = 5;
+pub const TCP_KEEPCNT: i32 = 6;
+
+/// 收发缓冲区的默认大小与上限
+pub const SOCK_BUF_DEFAULT: usize = 212992;
+pub const SOCK_BUF_MAX: usize = 4 * 1024 * 1024;
+
+/// 套接字类型（不含标志位）
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SocketType {
+    Stream,
+    Datagram,
+    SeqPacket,
+}
+
+impl SocketType {
+    pub fn raw(&self) -> usize {
+        match self {
+            SocketType::Stream => SOCK_STREAM,
+            SocketType::Datagram => SOCK_DGRAM,
+            SocketType::SeqPacket => SOCK_SEQPACKET,
+        }
+    }
+
+    pub fn from_raw(ty: usize) -> Option<Self> {
+        match ty & SOCK_TYPE_MASK {
+            SOCK_STREAM => Some(SocketType::Stream),
+            SOCK_DGRAM => Some(SocketType::Datagram),
+            SOCK_SEQPACKET => Some(SocketType::SeqPacket),
+            _ => None,
+        }
+    }
+}
+
+/// 内核内部的套接字地址
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SockAddr {
+    Unix(UnixAddr),
+    Inet(InetAddr),
+}
+
+impl SockAddr {
+    /// 转换成用户态 `sockaddr` 的字节表示
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SockAddr::Unix(addr) => {
+                let mut bytes = Vec::from(AF_UNIX.to_ne_bytes());
+                match addr {
+                    UnixAddr::Unnamed => {}
+                    UnixAddr::Path(path) => {
+                        bytes.extend_from_slice(path.as_bytes());
+                        bytes.push(0);
+                    }
+                    UnixAddr::Abstract(name) => {
+                        bytes.push(0);
+                        bytes.extend_from_slice(name);
+                    }
+                }
+                bytes
+            }
+            SockAddr::Inet(addr) => {
+                let mut bytes = Vec::from(AF_INET.to_ne_bytes());
+                bytes.extend_from_slice(&addr.port.to_be_bytes());
+                bytes.extend_from_slice(&addr.ip);
+                bytes.extend_from_slice(&[0; 8]);
+                bytes
+            }
+        }
+    }
+}
+
+/// 一次接收得到的数据
+pub struct RecvMsg {
+    /// 实际拷贝给用户的数据
+    pub data: Vec<u8>,
+    /// 消息原本的长度（数据报被截断时大于 `data.len()`）
+    pub full_len: usize,
+    /// 随数据一起传递的文件（SCM_RIGHTS）
+    ///
+    /// 消息出队后文件只由这里持有，没有装入 `fd_table` 就被丢弃时即被关闭
+    pub fds: Vec<Arc<dyn File + Send + Sync>>,
+    /// 发送方地址
+    pub from: Option<SockAddr>,
+}
+
+/// 套接字接口
+pub trait Socket: Send + Sync {
+    fn bind(&self, addr: SockAddr) -> Result<(), isize>;
+    fn listen(&self, backlog: usize) -> Result<(), isize>;
+    /// 返回新连接对应的套接字与对端地址
+    fn accept(&self) -> Result<(Arc<dyn File + Send + Sync>, SockAddr), isize>;
+    fn connect(&self, addr: SockAddr) -> Result<(), isize>;
+    /// `dest` 为 `None` 时发往已连接的对端
+    fn send(
+        &self,
+        data: &[u8],
+        fds: Vec<Arc<dyn File + Send + Sync>>,
+        dest: Option<SockAddr>,
+        flags: usize,
+    ) -> Result<usize, isize>;
+    fn recv(&self, len: usize, flags: usize) -> Result<RecvMsg, isize>;
+    fn shutdown(&self, how: usize) -> Result<(), isize>;
+    fn local_addr(&self) -> Result<SockAddr, isize>;
+    fn peer_addr(&self) -> Result<SockAddr, isize>;
+
+    fn domain(&self) -> u16;
+    fn socket_type(&self) -> SocketType;
+    fn protocol(&self) -> i32 {
+        0
+    }
+    fn is_listening(&self) -> bool {
+        false
+    }
+    fn options(&self) -> &UPIntrFreeCell<SocketOptions>;
+
+    /// 取出并清除挂起的异步错误（SO_ERROR），返回正的错误码
+    ///
+    /// 回环上只有非阻塞 connect 会产生异步错误
+    fn take_error(&self) -> isize {
+        0
+    }
+
+    fn setsockopt(&self, level: i32, name: i32, value: &[u8]) -> Result<(), isize> {
+        self.options().exclusive_access().set(level, name, value)
+    }
+
+    fn getsockopt(&self, level: i32, name: i32) -> Result<Vec<u8>, isize> {
+        let int = |value: i32| value.to_ne_bytes().to_vec();
+        match (level, name) {
+            (SOL_SOCKET, SO_TYPE) => Ok(int(self.socket_type().raw() as i32)),
+            (SOL_SOCKET, SO_DOMAIN) => Ok(int(self.domain() as i32)),
+            (SOL_SOCKET, SO_PROTOCOL) => Ok(int(self.protocol())),
+            (SOL_SOCKET, SO_ACCEPTCONN) => Ok(int(self.is_listening() as i32)),
+            (SOL_SOCKET, SO_ERROR) => Ok(int(self.take_error() as i32)),
+            _ => self.options().exclusive_access().get(level, name),
+        }
+    }
+}
+
+/// setsockopt 保存下来的选项
+///
+/// 只记录内核认识的选项，未知选项返回 `ENOPROTOOPT`；
+/// 大部分选项在回环上没有实际效果，只保证读回的值与写入的一致
+#[derive(Clone)]
+pub struct SocketOptions {
+    values: BTreeMap<(i32, i32), Vec<u8>>,
+}
+
+impl SocketOptions {
+    pub fn new() -> Self {
+        Self {
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// 认识的选项及其默认值
+    fn default_value(level: i32, name: i32) -> Option<Vec<u8>> {
+        let int = |value: i32| Some(value.to_ne_bytes().to_vec());
+        match (level, name) {
+            (SOL_SOCKET, SO_SNDBUF | SO_RCVBUF) => int(SOCK_BUF_DEFAULT as i32),
+            (
+                SOL_SOCKET,
+                SO_REUSEADDR | SO_DONTROUTE | SO_BROADCAST | SO_KEEPALIVE | SO_REUSEPORT
+                | SO_PASSCRED,
+            ) => int(0),
+            // struct linger
+            (SOL_SOCKET, SO_LINGER) => Some(vec![0; 8]),
+            // struct timeval
+            (SOL_SOCKET, SO_RCVTIMEO | SO_SNDTIMEO) => Some(vec![0; 16]),
+            (IPPROTO_IP, IP_TOS) => int(0),
+            (IPPROTO_IP, IP_TTL) => int(64),
+            (IPPROTO_TCP, TCP_NODELAY) => int(0),
+            (IPPROTO_TCP, TCP_MAXSEG) => int(65483),
+            (IPPROTO_TCP, TCP_KEEPIDLE) => int(7200),
+            (IPPROTO_TCP, TCP_KEEPINTVL) => int(75),
+            (IPPROTO_TCP, TCP_KEEPCNT) => int(9),
+            _ => None,
+        }
+    }
+
+    pub fn set(&mut self, level: i32, name: i32, value: &[u8]) -> Result<(), isize> {
+        let default = Self::default_value(level, name).ok_or(-ENOPROTOOPT)?;
+        if value.len() < default.len().min(4) {
+            return Err(-EINVAL);
+        }
+        let mut stored = value[..default.len().min(value.len())].to_vec();
+        if level == SOL_SOCKET && (name == SO_SNDBUF || name == SO_RCVBUF) {
+            // 与 Linux 一样，内核记录的是用户请求值的两倍
+            let size = i32::from_ne_bytes([stored[0], stored[1], stored[2], stored[3]]).max(0);
+            let size = (size as usize * 2).clamp(2048, SOCK_BUF_MAX) as i32;
+            stored = size.to_ne_bytes().to_vec();
+        }
+        self.values.insert((level, name), stored);
+        Ok(())
+    }
+
+    pub fn get(&self, level: i32, name: i32) -> Result<Vec<u8>, isize> {
+        match self.values.get(&(level, name)) {
+            Some(value) => Ok(value.clone()),
+            None => Self::default_value(level, name).ok_or(-ENOPROTOOPT),
+        }
+    }
+
+    /// 读取整数选项，未知选项视为 0
+    pub fn get_int(&self, level: i32, name: i32) -> i32 {
+        match self.get(level, name) {
+            Ok(value) if value.len() >= 4 => {
+                i32::from_ne_bytes([value[0], value[1], value[2], value[3]])
+            }
+            _ => 0,
+        }
+    }
+
+    /// 接收缓冲区大小
+    pub fn rcvbuf(&self) -> usize {
+        self.get_int(SOL_SOCKET, SO_RCVBUF) as usize
+    }
+
+    /// 是否设置了 SO_REUSEADDR
+    pub fn reuse_addr(&self) -> bool {
+        self.get_int(SOL_SOCKET, SO_REUSEADDR) != 0
+    }
+}
+
+/// 让出 CPU 等待条件满足，期间收到信号则返回 `EINTR`
+pub(crate) fn wait_interruptible() -> Result<(), isize> {
+    suspend_current_and_run_next();
+    if current_process().inner_exclusive_access().signals.is_empty() {
+        Ok(())
+    } else {
+        Err(-EINTR)
+    }
+}
+
+/// 从用户态拷贝 `len` 字节
+pub(crate) fn copy_in(token: usize, ptr: *const u8, len: usize) -> Result<Vec<u8>, isize> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    if ptr.is_null() {
+        return Err(-EFAULT);
+    }
+    let mut bytes = vec![0u8; len];
+    UserBuffer::new(translated_byte_buffer(token, ptr, len)).read(None, &mut bytes);
+    Ok(bytes)
+}
+
+/// 向用户态拷贝数据，返回实际写入的字节数
+pub(crate) fn copy_out(token: usize, ptr: *mut u8, len: usize, src: &[u8]) -> Result<usize, isize> {
+    let len = len.min(src.len());
+    if len == 0 {
+        return Ok(0);
+    }
+    if ptr.is_null() {
+        return Err(-EFAULT);
+    }
+    Ok(UserBuffer::new(translated_byte_buffer(token, ptr, len)).write_buffer(None, &src[..len]))
+}
+
+/// 解析用户态 `sockaddr`
+pub fn read_sockaddr(token: usize, addr: *const u8, addrlen: usize) -> Result<SockAddr, isize> {
+    if addrlen < 2 {
+        return Err(-EINVAL);
+    }
+    let bytes = copy_in(token, addr, addrlen)?;
+    let family = u16::from_ne_bytes([bytes[0], bytes[1]]);
+    match family {
+        AF_UNIX => {
+            let path = &bytes[2..bytes.len().min(2 + unix::UNIX_PATH_MAX)];
+            if path.is_empty() {
+                Ok(SockAddr::Unix(UnixAddr::Unnamed))
+            } else if path[0] == 0 {
+                Ok(SockAddr::Unix(UnixAddr::Abstract(path[1..].to_vec())))
+            } else {
+                let end = path.iter().position(|&c| c == 0).unwrap_or(path.len());
+                let path = core::str::from_utf8(&path[..end]).map_err(|_| -EINVAL)?;
+                let cwd = current_process().inner_exclusive_access().cwd.clone();
+                Ok(SockAddr::Unix(UnixAddr::Path(resolve_path(path, &cwd))))
+            }
+        }
+        AF_INET => {
+            if addrlen < 16 {
+                return Err(-EINVAL);
+            }
+            Ok(SockAddr::Inet(InetAddr {
+                ip: [bytes[4], bytes[5], bytes[6], bytes[7]],
+                port: u16::from_be_bytes([bytes[2], bytes[3]]),
+            }))
+        }
+        _ => Err(-EAFNOSUPPORT),
+    }
+}
+
+/// 将地址写回用户态 `sockaddr`，`addrlen` 为值-结果参数
+pub fn write_sockaddr(
+    token: usize,
+    sockaddr: &SockAddr,
+    addr: *mut u8,
+    addrlen: *mut u32,
+) -> Result<(), isize> {
+    if addr.is_null() {
+        return Ok(());
+    }
+    if addrlen.is_null() {
+        return Err(-EFAULT);
+    }
+    let bytes = sockaddr.to_bytes();
+    let len_bytes = copy_in(token, addrlen as *const u8, 4)?;
+    let len = u32::from_ne_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]);
+    copy_out(token, addr, len as usize, &bytes)?;
+    copy_out(token, addrlen as *mut u8, 4, &(bytes.len() as u32).to_ne_bytes())?;
+    Ok(())
+}
+
+/// 套接字的 stat 信息
+pub(crate) fn socket_stat() -> UserStat {
+    UserStat {
+        st_dev: 0,
+        st_ino: 0,
+        st_mode: S_IFSOCK | 0o777,
+        st_nlink: 1,
+        st_uid: 0,
+        st_gid: 0,
+        st_rdev: 0,
+        __pad: 0,
+        st_size: 0,
+        st_blksize: BLK_SIZE,
+        __pad2: 0,
+        st_blocks: 0,
+        st_atime_sec: 0,
+        st_atime_nsec: 0,
+        st_mtime_sec: 0,
+        st_mtime_nsec: 0,
+        st_ctime_sec: 0,
+        st_ctime_nsec: 0,
+        __unused: [0; 2],
+    }
+}
+
+/// 套接字在 `get_path` 中显示的名字
+pub(crate) fn socket_path() -> String {
+    String::from("socket")
+}
+
+/// 从套接字读到用户缓冲区，返回读到的字节数或负的错误码
+///
+/// `sys_read` 直接返回错误码；`File::read` 没有错误通道，出错时按读到 0 字节处理
+pub(crate) fn socket_read(socket: &dyn Socket, mut buf: UserBuffer) -> Result<usize, isize> {
+    let msg = socket.recv(buf.len(), 0)?;
+    // 与 recvfrom 相同，read 关闭随消息传来的文件
+    drop(msg.fds);
+    Ok(buf.write_buffer(None, &msg.data))
+}
+
+/// 把用户缓冲区写入套接字，返回写入的字节数或负的错误码
+///
+/// `sys_write` 直接返回错误码；`File::write` 出错时按写入 0 字节处理
+pub(crate) fn socket_write(socket: &dyn Socket, buf: UserBuffer) -> Result<usize, isize> {
+    let mut data = Vec::with_capacity(buf.len());
+    for slice in buf.buffers.iter() {
+        data.extend_from_slice(slice);
+    }
+    socket.send(&data, Vec::new(), None, 0)
+}
@@ -0,0 +1,581 @@
+//! # 回环 TCP
+//!
+//! ## Overview
+//! `AF_INET` + `SOCK_STREAM` 套接字。连接只可能发生在本机内部，
+//! 因此不实现三次握手、重传与拥塞控制：
+//! - `connect` 直接在监听套接字的队列中创建服务端套接字，两端互相以 `Weak` 引用
+//! - 发送即把字节追加到对端的接收缓冲区，缓冲区大小由对端的 SO_RCVBUF 决定
+//!
+//! ## Assumptions
+//! - 所有地址都经过 `inet::route` 检查，只会是回环地址
+//!
+//! ## Safety
+//! - 同一时刻只借用一个套接字的内部状态
+//!
+//! ## Invariants
+//! - 只有主动 bind 或 connect 时自动绑定的套接字出现在端口表中，
+//!   accept 得到的套接字与监听套接字共享端口
+//!
+//! ## Behavior
+//! - 对端关闭后，读完剩余数据返回 EOF，写返回 `EPIPE`
+//! - 连接到没有监听者的端口返回 `ECONNREFUSED`
+//! - 监听队列已满时，阻塞的 `connect` 等待空位；非阻塞的返回 `EINPROGRESS`，
+//!   之后再次 `connect`、收发或读取 SO_ERROR 时重新尝试入队，
+//!   监听套接字在此之前关闭则连接以 `ECONNREFUSED` 失败
+//! - 双方都设置了 SO_REUSEADDR 且已有的套接字不在监听时，可以绑定同一端口；
+//!   同一端口上至多一个套接字监听。没有 TIME_WAIT，套接字关闭后端口立即可用
+
+use super::inet::{route, InetAddr, PortTable, INADDR_ANY, INADDR_LOOPBACK};
+use super::{
+    socket_path, socket_read, socket_stat, socket_write, wait_interruptible, RecvMsg, SockAddr,
+    Socket, SocketOptions, SocketType, AF_INET, IPPROTO_TCP, MSG_DONTWAIT, MSG_PEEK, SHUT_RD,
+    SHUT_RDWR, SHUT_WR,
+};
+use crate::fs::{File, OpenFlags, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{
+    EADDRINUSE, EAFNOSUPPORT, EAGAIN, EALREADY, ECONNREFUSED, EINPROGRESS, EINVAL, EISCONN,
+    ENOTCONN, EPIPE, ESPIPE,
+};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::lazy_static;
+
+lazy_static! {
+    static ref TCP_PORTS: UPIntrFreeCell<PortTable<TcpSocket>> =
+        unsafe { UPIntrFreeCell::new(PortTable::new()) };
+}
+
+enum TcpState {
+    Closed,
+    /// 监听队列已满，等待排入这个监听套接字
+    Connecting(Weak<TcpSocket>),
+    Listening {
+        backlog: usize,
+        pending: VecDeque<Arc<TcpSocket>>,
+    },
+    Established(Weak<TcpSocket>),
+}
+
+struct TcpInner {
+    nonblocking: bool,
+    /// 本端地址；未绑定时为 `None`
+    local: Option<InetAddr>,
+    /// 是否占用了端口表中的表项
+    owns_port: bool,
+    /// 对端地址，连接建立后有效
+    remote: Option<InetAddr>,
+    state: TcpState,
+    rx: VecDeque<u8>,
+    shut_rd: bool,
+    shut_wr: bool,
+    peer_shut_wr: bool,
+}
+
+pub struct TcpSocket {
+    me: Weak<TcpSocket>,
+    opts: UPIntrFreeCell<SocketOptions>,
+    inner: UPIntrFreeCell<TcpInner>,
+}
+
+impl TcpSocket {
+    pub fn new() -> Arc<Self> {
+        Self::with_options(SocketOptions::new())
+    }
+
+    fn with_options(opts: SocketOptions) -> Arc<Self> {
+        Arc::new_cyclic(|me| Self {
+            me: me.clone(),
+            opts: unsafe { UPIntrFreeCell::new(opts) },
+            inner: unsafe {
+                UPIntrFreeCell::new(TcpInner {
+                    nonblocking: false,
+                    local: None,
+                    owns_port: false,
+                    remote: None,
+                    state: TcpState::Closed,
+                    rx: VecDeque::new(),
+                    shut_rd: false,
+                    shut_wr: false,
+                    peer_shut_wr: false,
+                })
+            },
+        })
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.inner.exclusive_access().nonblocking = nonblocking;
+    }
+
+    fn would_block(&self, flags: usize) -> bool {
+        flags & MSG_DONTWAIT != 0 || self.inner.exclusive_access().nonblocking
+    }
+
+    fn peer(&self) -> Option<Arc<TcpSocket>> {
+        match &self.inner.exclusive_access().state {
+            TcpState::Established(peer) => peer.upgrade(),
+            _ => None,
+        }
+    }
+
+    fn is_established(&self) -> bool {
+        matches!(self.inner.exclusive_access().state, TcpState::Established(_))
+    }
+
+    /// 在端口表中登记本套接字
+    fn bind_to(&self, addr: InetAddr) -> Result<InetAddr, isize> {
+        if self.inner.exclusive_access().local.is_some() {
+            return Err(-EINVAL);
+        }
+        let reuse = self.opts.exclusive_access().reuse_addr();
+        let bound = TCP_PORTS.exclusive_access().bind(addr, self.me.clone(), |other| {
+            reuse && other.opts.exclusive_access().reuse_addr() && !other.is_listening()
+        })?;
+        let mut inner = self.inner.exclusive_access();
+        inner.local = Some(bound);
+        inner.owns_port = true;
+        Ok(bound)
+    }
+
+    /// 尝试排入监听队列，返回连接是否已经建立
+    ///
+    /// 监听套接字已关闭或不再监听时连接失败，回到 `Closed`
+    fn poll_connect(&self) -> Result<bool, isize> {
+        let listener = match &self.inner.exclusive_access().state {
+            TcpState::Established(_) => return Ok(true),
+            TcpState::Connecting(listener) => listener.upgrade(),
+            _ => return Err(-ENOTCONN),
+        };
+        let refuse = || {
+            let mut inner = self.inner.exclusive_access();
+            inner.state = TcpState::Closed;
+            inner.remote = None;
+            Err(-ECONNREFUSED)
+        };
+        let Some(listener) = listener else {
+            return refuse();
+        };
+        let listener_opts = listener.opts.exclusive_access().clone();
+        let mut listener_inner = listener.inner.exclusive_access();
+        let TcpState::Listening { backlog, pending } = &mut listener_inner.state else {
+            return refuse();
+        };
+        if pending.len() >= *backlog {
+            return Ok(false);
+        }
+        let (local, dest) = {
+            let inner = self.inner.exclusive_access();
+            (inner.local.unwrap(), inner.remote.unwrap())
+        };
+        // 服务端套接字继承监听套接字的选项
+        let server = TcpSocket::with_options(listener_opts);
+        {
+            let mut server_inner = server.inner.exclusive_access();
+            server_inner.local = Some(dest);
+            server_inner.remote = Some(local);
+            server_inner.state = TcpState::Established(self.me.clone());
+        }
+        pending.push_back(server.clone());
+        drop(listener_inner);
+        self.inner.exclusive_access().state = TcpState::Established(Arc::downgrade(&server));
+        Ok(true)
+    }
+
+    /// 等待连接建立，不能阻塞时返回 `EAGAIN`
+    fn wait_established(&self, flags: usize) -> Result<(), isize> {
+        loop {
+            if self.poll_connect()? {
+                return Ok(());
+            }
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    /// 接收缓冲区剩余空间
+    fn rx_space(&self) -> usize {
+        let capacity = self.opts.exclusive_access().rcvbuf();
+        capacity.saturating_sub(self.inner.exclusive_access().rx.len())
+    }
+}
+
+impl Socket for TcpSocket {
+    fn bind(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Inet(addr) = addr else {
+            return Err(-EAFNOSUPPORT);
+        };
+        self.bind_to(addr).map(|_| ())
+    }
+
+    fn listen(&self, backlog: usize) -> Result<(), isize> {
+        if self.inner.exclusive_access().local.is_none() {
+            self.bind_to(InetAddr::new(INADDR_ANY, 0))?;
+        }
+        let backlog = backlog.clamp(1, 4096);
+        let local = self.inner.exclusive_access().local.unwrap();
+        // 经 SO_REUSEADDR 共享端口的套接字中只能有一个监听
+        let shared_listener = TCP_PORTS
+            .exclusive_access()
+            .conflicts(local.ip, local.port, |other| {
+                core::ptr::eq(other, self) || !other.is_listening()
+            });
+        let mut inner = self.inner.exclusive_access();
+        match &mut inner.state {
+            TcpState::Closed if shared_listener => return Err(-EADDRINUSE),
+            TcpState::Closed => {}
+            TcpState::Listening { backlog: old, .. } => {
+                *old = backlog;
+                return Ok(());
+            }
+            TcpState::Connecting(_) | TcpState::Established(_) => return Err(-EINVAL),
+        }
+        inner.state = TcpState::Listening {
+            backlog,
+            pending: VecDeque::new(),
+        };
+        Ok(())
+    }
+
+    fn accept(&self) -> Result<(Arc<dyn File + Send + Sync>, SockAddr), isize> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            let TcpState::Listening { pending, .. } = &mut inner.state else {
+                return Err(-EINVAL);
+            };
+            if let Some(socket) = pending.pop_front() {
+                drop(inner);
+                let remote = socket.inner.exclusive_access().remote.unwrap();
+                return Ok((socket, SockAddr::Inet(remote)));
+            }
+            let nonblocking = inner.nonblocking;
+            drop(inner);
+            if nonblocking {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    fn connect(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Inet(addr) = addr else {
+            return Err(-EAFNOSUPPORT);
+        };
+        let dest = route(&addr)?;
+        let connecting = match self.inner.exclusive_access().state {
+            TcpState::Closed => false,
+            TcpState::Connecting(_) => true,
+            TcpState::Listening { .. } => return Err(-EINVAL),
+            TcpState::Established(_) => return Err(-EISCONN),
+        };
+        if connecting {
+            return if self.poll_connect()? {
+                Ok(())
+            } else {
+                Err(-EALREADY)
+            };
+        }
+        let listener = TCP_PORTS
+            .exclusive_access()
+            .lookup(&dest, |socket| socket.is_listening())
+            .ok_or(-ECONNREFUSED)?;
+        // 自动绑定到回环地址上的临时端口
+        let local = match self.inner.exclusive_access().local {
+            Some(local) if !local.is_any() => Some(local),
+            Some(local) => Some(InetAddr::new(INADDR_LOOPBACK, local.port)),
+            None => None,
+        };
+        let local = match local {
+            Some(local) => local,
+            None => self.bind_to(InetAddr::new(INADDR_LOOPBACK, 0))?,
+        };
+        {
+            let mut inner = self.inner.exclusive_access();
+            inner.local = Some(local);
+            inner.remote = Some(dest);
+            inner.state = TcpState::Connecting(Arc::downgrade(&listener));
+        }
+        drop(listener);
+        // 被信号打断时连接仍在进行，与非阻塞时一样留待之后完成
+        match self.wait_established(0) {
+            Err(err) if err == -EAGAIN => Err(-EINPROGRESS),
+            result => result,
+        }
+    }
+
+    fn send(
+        &self,
+        data: &[u8],
+        _fds: Vec<Arc<dyn File + Send + Sync>>,
+        dest: Option<SockAddr>,
+        flags: usize,
+    ) -> Result<usize, isize> {
+        self.wait_established(flags)?;
+        if dest.is_some() {
+            return Err(-EISCONN);
+        }
+        if self.inner.exclusive_access().shut_wr {
+            return Err(-EPIPE);
+        }
+        let mut sent = 0;
+        while sent < data.len() {
+            let peer = self.peer().ok_or(-EPIPE)?;
+            if peer.inner.exclusive_access().shut_rd {
+                return Err(-EPIPE);
+            }
+            let space = peer.rx_space();
+            if space > 0 {
+                let len = space.min(data.len() - sent);
+                peer.inner
+                    .exclusive_access()
+                    .rx
+                    .extend(data[sent..sent + len].iter());
+                sent += len;
+                continue;
+            }
+            drop(peer);
+            if self.would_block(flags) {
+                return if sent > 0 { Ok(sent) } else { Err(-EAGAIN) };
+            }
+            if let Err(err) = wait_interruptible() {
+                return if sent > 0 { Ok(sent) } else { Err(err) };
+            }
+        }
+        Ok(sent)
+    }
+
+    fn recv(&self, len: usize, flags: usize) -> Result<RecvMsg, isize> {
+        self.wait_established(flags)?;
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if !inner.rx.is_empty() && len > 0 {
+                let take = inner.rx.len().min(len);
+                let data: Vec<u8> = if flags & MSG_PEEK != 0 {
+                    inner.rx.iter().take(take).copied().collect()
+                } else {
+                    inner.rx.drain(..take).collect()
+                };
+                return Ok(RecvMsg {
+                    full_len: data.len(),
+                    data,
+                    fds: Vec::new(),
+                    from: inner.remote.map(SockAddr::Inet),
+                });
+            }
+            let eof = inner.shut_rd || inner.peer_shut_wr;
+            drop(inner);
+            if len == 0 || eof || self.peer().is_none() {
+                return Ok(RecvMsg {
+                    data: Vec::new(),
+                    full_len: 0,
+                    fds: Vec::new(),
+                    from: None,
+                });
+            }
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    fn shutdown(&self, how: usize) -> Result<(), isize> {
+        if how > SHUT_RDWR {
+            return Err(-EINVAL);
+        }
+        if !self.is_established() {
+            return Err(-ENOTCONN);
+        }
+        let mut inner = self.inner.exclusive_access();
+        if how == SHUT_RD || how == SHUT_RDWR {
+            inner.shut_rd = true;
+        }
+        if how == SHUT_WR || how == SHUT_RDWR {
+            inner.shut_wr = true;
+        }
+        drop(inner);
+        if how != SHUT_RD {
+            if let Some(peer) = self.peer() {
+                peer.inner.exclusive_access().peer_shut_wr = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn local_addr(&self) -> Result<SockAddr, isize> {
+        let local = self.inner.exclusive_access().local;
+        Ok(SockAddr::Inet(local.unwrap_or(InetAddr::new(INADDR_ANY, 0))))
+    }
+
+    fn peer_addr(&self) -> Result<SockAddr, isize> {
+        let inner = self.inner.exclusive_access();
+        match (&inner.state, inner.remote) {
+            (TcpState::Established(_), Some(remote)) => Ok(SockAddr::Inet(remote)),
+            _ => Err(-ENOTCONN),
+        }
+    }
+
+    fn domain(&self) -> u16 {
+        AF_INET
+    }
+
+    fn socket_type(&self) -> SocketType {
+        SocketType::Stream
+    }
+
+    fn protocol(&self) -> i32 {
+        IPPROTO_TCP
+    }
+
+    fn is_listening(&self) -> bool {
+        matches!(self.inner.exclusive_access().state, TcpState::Listening { .. })
+    }
+
+    fn options(&self) -> &UPIntrFreeCell<SocketOptions> {
+        &self.opts
+    }
+
+    /// 读取 SO_ERROR 时推进尚未完成的 connect，失败的原因只报告一次
+    fn take_error(&self) -> isize {
+        if !matches!(self.inner.exclusive_access().state, TcpState::Connecting(_)) {
+            return 0;
+        }
+        match self.poll_connect() {
+            Err(err) => -err,
+            Ok(_) => 0,
+        }
+    }
+}
+
+impl Drop for TcpSocket {
+    fn drop(&mut self) {
+        let inner = self.inner.exclusive_access();
+        if let (true, Some(local)) = (inner.owns_port, inner.local) {
+            drop(inner);
+            TCP_PORTS.exclusive_access().release(local.port);
+        }
+    }
+}
+
+impl File for TcpSocket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        socket_read(self, buf).unwrap_or(0)
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        socket_write(self, buf).unwrap_or(0)
+    }
+    fn get_stat(&self) -> UserStat {
+        socket_stat()
+    }
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn get_path(&self) -> String {
+        socket_path()
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn status_flags(&self) -> OpenFlags {
+        if self.inner.exclusive_access().nonblocking {
+            OpenFlags::NONBLOCK
+        } else {
+            OpenFlags::empty()
+        }
+    }
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.set_nonblocking(flags.contains(OpenFlags::NONBLOCK));
+    }
+    fn as_socket(&self) -> Option<&dyn Socket> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TcpSocket;
+    use crate::net::inet::{InetAddr, INADDR_LOOPBACK};
+    use crate::net::{SockAddr, Socket, SOL_SOCKET, SO_ERROR, SO_REUSEADDR};
+    use crate::syscall::errno::{EADDRINUSE, EALREADY, ECONNREFUSED, EINPROGRESS};
+    use alloc::vec::Vec;
+
+    fn loopback(port: u16) -> SockAddr {
+        SockAddr::Inet(InetAddr::new(INADDR_LOOPBACK, port))
+    }
+
+    fn so_error(socket: &TcpSocket) -> Vec<u8> {
+        socket.getsockopt(SOL_SOCKET, SO_ERROR).unwrap()
+    }
+
+    #[test_case]
+    fn nonblocking_connect_completes_through_so_error() {
+        let listener = TcpSocket::new();
+        listener.bind(loopback(7001)).unwrap();
+        listener.listen(1).unwrap();
+        let first = TcpSocket::new();
+        first.connect(loopback(7001)).unwrap();
+
+        // 监听队列已满
+        let second = TcpSocket::new();
+        second.set_nonblocking(true);
+        assert_eq!(second.connect(loopback(7001)), Err(-EINPROGRESS));
+        assert_eq!(second.connect(loopback(7001)), Err(-EALREADY));
+        assert_eq!(so_error(&second), 0i32.to_ne_bytes());
+        assert!(second.peer_addr().is_err());
+
+        // 队列腾出空位后，读取 SO_ERROR 完成连接
+        listener.accept().unwrap();
+        assert_eq!(so_error(&second), 0i32.to_ne_bytes());
+        assert!(second.peer_addr().is_ok());
+
+        // 监听套接字关闭，连接失败只报告一次
+        let third = TcpSocket::new();
+        third.set_nonblocking(true);
+        assert_eq!(third.connect(loopback(7001)), Err(-EINPROGRESS));
+        drop(listener);
+        assert_eq!(so_error(&third), (ECONNREFUSED as i32).to_ne_bytes());
+        assert_eq!(so_error(&third), 0i32.to_ne_bytes());
+    }
+
+    #[test_case]
+    fn reuseaddr_shares_ports_but_not_listeners() {
+        let reusing = || {
+            let socket = TcpSocket::new();
+            socket
+                .setsockopt(SOL_SOCKET, SO_REUSEADDR, &1i32.to_ne_bytes())
+                .unwrap();
+            socket
+        };
+        let plain = TcpSocket::new();
+        plain.bind(loopback(7002)).unwrap();
+        assert_eq!(reusing().bind(loopback(7002)), Err(-EADDRINUSE));
+
+        let a = reusing();
+        a.bind(loopback(7003)).unwrap();
+        let b = reusing();
+        b.bind(loopback(7003)).unwrap();
+        a.listen(1).unwrap();
+        assert_eq!(b.listen(1), Err(-EADDRINUSE));
+        assert_eq!(reusing().bind(loopback(7003)), Err(-EADDRINUSE));
+        // 没有 TIME_WAIT，监听套接字关闭后端口立即可以再次绑定
+        drop(a);
+        reusing().bind(loopback(7003)).unwrap();
+    }
+}
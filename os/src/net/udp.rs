@@ -0,0 +1,322 @@
+//! # 回环 UDP
+//!
+//! ## Overview
+//! `AF_INET` + `SOCK_DGRAM` 套接字。发送时按目的端口在端口表中找到接收方，
+//! 把数据报连同发送方地址放进接收方的队列。
+//!
+//! ## Assumptions
+//! - 所有地址都经过 `inet::route` 检查，只会是回环地址
+//!
+//! ## Safety
+//! - 同一时刻只借用一个套接字的内部状态
+//!
+//! ## Invariants
+//! - 接收队列中的字节数不超过接收方的 SO_RCVBUF
+//!
+//! ## Behavior
+//! - 与真实网络一样，没有接收者或接收队列已满时数据报被静默丢弃
+//! - 未绑定的套接字在第一次发送或 connect 时自动绑定临时端口
+//! - 双方都设置了 SO_REUSEADDR 时可以绑定同一端口，数据报交给最后绑定的套接字
+
+use super::inet::{route, InetAddr, PortTable, INADDR_ANY, INADDR_LOOPBACK};
+use super::{
+    socket_path, socket_read, socket_stat, socket_write, wait_interruptible, RecvMsg, SockAddr,
+    Socket, SocketOptions, SocketType, AF_INET, IPPROTO_UDP, MSG_DONTWAIT, MSG_PEEK, SHUT_RD,
+    SHUT_RDWR, SHUT_WR,
+};
+use crate::fs::{File, OpenFlags, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{
+    EAFNOSUPPORT, EAGAIN, EDESTADDRREQ, EINVAL, EMSGSIZE, ENOTCONN, EOPNOTSUPP, EPIPE, ESPIPE,
+};
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::lazy_static;
+
+/// IPv4 上 UDP 载荷的最大长度
+const UDP_MAX_PAYLOAD: usize = 65507;
+
+lazy_static! {
+    static ref UDP_PORTS: UPIntrFreeCell<PortTable<UdpSocket>> =
+        unsafe { UPIntrFreeCell::new(PortTable::new()) };
+}
+
+struct UdpInner {
+    nonblocking: bool,
+    local: Option<InetAddr>,
+    /// connect 设置的默认对端
+    remote: Option<InetAddr>,
+    rx: VecDeque<(Vec<u8>, InetAddr)>,
+    rx_bytes: usize,
+    shut_rd: bool,
+    shut_wr: bool,
+}
+
+pub struct UdpSocket {
+    me: Weak<UdpSocket>,
+    opts: UPIntrFreeCell<SocketOptions>,
+    inner: UPIntrFreeCell<UdpInner>,
+}
+
+impl UdpSocket {
+    pub fn new() -> Arc<Self> {
+        Arc::new_cyclic(|me| Self {
+            me: me.clone(),
+            opts: unsafe { UPIntrFreeCell::new(SocketOptions::new()) },
+            inner: unsafe {
+                UPIntrFreeCell::new(UdpInner {
+                    nonblocking: false,
+                    local: None,
+                    remote: None,
+                    rx: VecDeque::new(),
+                    rx_bytes: 0,
+                    shut_rd: false,
+                    shut_wr: false,
+                })
+            },
+        })
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.inner.exclusive_access().nonblocking = nonblocking;
+    }
+
+    fn would_block(&self, flags: usize) -> bool {
+        flags & MSG_DONTWAIT != 0 || self.inner.exclusive_access().nonblocking
+    }
+
+    fn bind_to(&self, addr: InetAddr) -> Result<InetAddr, isize> {
+        if self.inner.exclusive_access().local.is_some() {
+            return Err(-EINVAL);
+        }
+        let reuse = self.opts.exclusive_access().reuse_addr();
+        let bound = UDP_PORTS.exclusive_access().bind(addr, self.me.clone(), |other| {
+            reuse && other.opts.exclusive_access().reuse_addr()
+        })?;
+        self.inner.exclusive_access().local = Some(bound);
+        Ok(bound)
+    }
+
+    /// 本端地址，未绑定时自动绑定
+    fn local_or_autobind(&self) -> Result<InetAddr, isize> {
+        let local = self.inner.exclusive_access().local;
+        match local {
+            Some(local) => Ok(local),
+            None => self.bind_to(InetAddr::new(INADDR_ANY, 0)),
+        }
+    }
+
+    /// 投递一个数据报；返回是否被接收
+    fn deliver(&self, data: &[u8], from: InetAddr) -> bool {
+        let capacity = self.opts.exclusive_access().rcvbuf();
+        let mut inner = self.inner.exclusive_access();
+        if inner.shut_rd || inner.rx_bytes + data.len() > capacity {
+            return false;
+        }
+        // 已 connect 的套接字只接收来自对端的数据报
+        if inner.remote.map_or(false, |remote| remote != from) {
+            return false;
+        }
+        inner.rx_bytes += data.len();
+        inner.rx.push_back((data.to_vec(), from));
+        true
+    }
+}
+
+impl Socket for UdpSocket {
+    fn bind(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Inet(addr) = addr else {
+            return Err(-EAFNOSUPPORT);
+        };
+        self.bind_to(addr).map(|_| ())
+    }
+
+    fn listen(&self, _backlog: usize) -> Result<(), isize> {
+        Err(-EOPNOTSUPP)
+    }
+
+    fn accept(&self) -> Result<(Arc<dyn File + Send + Sync>, SockAddr), isize> {
+        Err(-EOPNOTSUPP)
+    }
+
+    fn connect(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Inet(addr) = addr else {
+            return Err(-EAFNOSUPPORT);
+        };
+        let dest = route(&addr)?;
+        self.local_or_autobind()?;
+        self.inner.exclusive_access().remote = Some(dest);
+        Ok(())
+    }
+
+    fn send(
+        &self,
+        data: &[u8],
+        _fds: Vec<Arc<dyn File + Send + Sync>>,
+        dest: Option<SockAddr>,
+        _flags: usize,
+    ) -> Result<usize, isize> {
+        if self.inner.exclusive_access().shut_wr {
+            return Err(-EPIPE);
+        }
+        if data.len() > UDP_MAX_PAYLOAD {
+            return Err(-EMSGSIZE);
+        }
+        let dest = match dest {
+            Some(SockAddr::Inet(addr)) => route(&addr)?,
+            Some(_) => return Err(-EAFNOSUPPORT),
+            None => self
+                .inner
+                .exclusive_access()
+                .remote
+                .ok_or(-EDESTADDRREQ)?,
+        };
+        let local = self.local_or_autobind()?;
+        // 通配地址绑定的套接字以回环地址作为源地址
+        let from = if local.is_any() {
+            InetAddr::new(INADDR_LOOPBACK, local.port)
+        } else {
+            local
+        };
+        let target = UDP_PORTS.exclusive_access().lookup(&dest, |_| true);
+        if let Some(target) = target {
+            target.deliver(data, from);
+        }
+        Ok(data.len())
+    }
+
+    fn recv(&self, len: usize, flags: usize) -> Result<RecvMsg, isize> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if let Some((data, from)) = inner.rx.front() {
+                let take = data.len().min(len);
+                let result = RecvMsg {
+                    data: data[..take].to_vec(),
+                    full_len: data.len(),
+                    fds: Vec::new(),
+                    from: Some(SockAddr::Inet(*from)),
+                };
+                if flags & MSG_PEEK == 0 {
+                    let (data, _) = inner.rx.pop_front().unwrap();
+                    inner.rx_bytes -= data.len();
+                }
+                return Ok(result);
+            }
+            if inner.shut_rd {
+                return Ok(RecvMsg {
+                    data: Vec::new(),
+                    full_len: 0,
+                    fds: Vec::new(),
+                    from: None,
+                });
+            }
+            drop(inner);
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    fn shutdown(&self, how: usize) -> Result<(), isize> {
+        if how > SHUT_RDWR {
+            return Err(-EINVAL);
+        }
+        let mut inner = self.inner.exclusive_access();
+        if inner.remote.is_none() {
+            return Err(-ENOTCONN);
+        }
+        if how == SHUT_RD || how == SHUT_RDWR {
+            inner.shut_rd = true;
+        }
+        if how == SHUT_WR || how == SHUT_RDWR {
+            inner.shut_wr = true;
+        }
+        Ok(())
+    }
+
+    fn local_addr(&self) -> Result<SockAddr, isize> {
+        let local = self.inner.exclusive_access().local;
+        Ok(SockAddr::Inet(local.unwrap_or(InetAddr::new(INADDR_ANY, 0))))
+    }
+
+    fn peer_addr(&self) -> Result<SockAddr, isize> {
+        let remote = self.inner.exclusive_access().remote;
+        remote.map(SockAddr::Inet).ok_or(-ENOTCONN)
+    }
+
+    fn domain(&self) -> u16 {
+        AF_INET
+    }
+
+    fn socket_type(&self) -> SocketType {
+        SocketType::Datagram
+    }
+
+    fn protocol(&self) -> i32 {
+        IPPROTO_UDP
+    }
+
+    fn options(&self) -> &UPIntrFreeCell<SocketOptions> {
+        &self.opts
+    }
+}
+
+impl Drop for UdpSocket {
+    fn drop(&mut self) {
+        let local = self.inner.exclusive_access().local;
+        if let Some(local) = local {
+            UDP_PORTS.exclusive_access().release(local.port);
+        }
+    }
+}
+
+impl File for UdpSocket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        socket_read(self, buf).unwrap_or(0)
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        socket_write(self, buf).unwrap_or(0)
+    }
+    fn get_stat(&self) -> UserStat {
+        socket_stat()
+    }
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn get_path(&self) -> String {
+        socket_path()
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn status_flags(&self) -> OpenFlags {
+        if self.inner.exclusive_access().nonblocking {
+            OpenFlags::NONBLOCK
+        } else {
+            OpenFlags::empty()
+        }
+    }
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.set_nonblocking(flags.contains(OpenFlags::NONBLOCK));
+    }
+    fn as_socket(&self) -> Option<&dyn Socket> {
+        Some(self)
+    }
+}
@@ -0,0 +1,718 @@
+//! # Unix 域套接字
+//!
+//! ## Overview
+//! 实现 `AF_UNIX` 的 `SOCK_STREAM`、`SOCK_DGRAM` 与 `SOCK_SEQPACKET`：
+//! - 每个套接字拥有自己的接收队列，发送方直接把消息放入对端的接收队列
+//! - 地址既可以是文件系统路径，也可以是抽象名字（以 `\0` 开头）
+//! - 支持通过 SCM_RIGHTS 在进程间传递文件描述符
+//!
+//! ## Assumptions
+//! - FAT32 无法表示套接字文件，绑定到路径时在该路径创建一个空的普通文件，
+//!   真正的套接字记录在全局名字表中
+//!
+//! ## Safety
+//! - 连接双方互相只持有 `Weak` 引用，任一端关闭后对端可以感知
+//! - 同一时刻只借用一个套接字的内部状态，避免 `UPIntrFreeCell` 重复借用
+//!
+//! ## Invariants
+//! - 名字表中的每个名字最多对应一个存活的套接字
+//! - 接收队列中的字节数不超过 `UNIX_BUF_SIZE`（数据报不超过 `UNIX_DGRAM_QLEN` 个）
+//!
+//! ## Behavior
+//! - 流式连接在 `connect` 时即创建服务端套接字并放入监听队列，`accept` 只是取出
+//! - 对端关闭后，读到 EOF，写返回 `EPIPE`
+//! - 携带文件描述符的消息不会与之前的数据合并成一次读取
+//! - 文件随所在消息的第一次（非 MSG_PEEK）读取一起出队；经 `read` / `recvfrom`
+//!   读取时没有地方存放，这些文件被关闭，之后的 `recvmsg` 也不会再收到
+
+use super::{
+    socket_path, socket_read, socket_stat, socket_write, wait_interruptible, RecvMsg, SockAddr,
+    Socket, SocketOptions, SocketType, AF_UNIX, MSG_DONTWAIT, MSG_PEEK, SHUT_RD, SHUT_RDWR,
+    SHUT_WR,
+};
+use crate::fs::{open_file, File, OpenFlags, UserStat};
+use crate::mm::UserBuffer;
+use crate::sync::UPIntrFreeCell;
+use crate::syscall::errno::{
+    EADDRINUSE, EAGAIN, ECONNREFUSED, EINVAL, EISCONN, EMSGSIZE, ENOENT, ENOTCONN, EOPNOTSUPP,
+    EPIPE, EPROTOTYPE, ESPIPE,
+};
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use lazy_static::lazy_static;
+
+/// `sockaddr_un.sun_path` 的长度
+pub const UNIX_PATH_MAX: usize = 108;
+/// 接收缓冲区大小
+const UNIX_BUF_SIZE: usize = 64 * 1024;
+/// 数据报接收队列长度
+const UNIX_DGRAM_QLEN: usize = 64;
+
+/// Unix 域套接字地址
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UnixAddr {
+    /// 未绑定
+    Unnamed,
+    /// 文件系统路径（绝对路径）
+    Path(String),
+    /// 抽象名字，不含开头的 `\0`
+    Abstract(Vec<u8>),
+}
+
+lazy_static! {
+    /// 已绑定的名字
+    static ref UNIX_NAMES: UPIntrFreeCell<BTreeMap<UnixAddr, Weak<UnixSocket>>> =
+        unsafe { UPIntrFreeCell::new(BTreeMap::new()) };
+    /// 自动绑定时使用的名字序号
+    static ref AUTOBIND_ID: UPIntrFreeCell<usize> = unsafe { UPIntrFreeCell::new(0) };
+}
+
+/// 按名字查找存活的套接字
+fn lookup(addr: &UnixAddr) -> Result<Arc<UnixSocket>, isize> {
+    let socket = UNIX_NAMES
+        .exclusive_access()
+        .get(addr)
+        .and_then(|weak| weak.upgrade());
+    match (socket, addr) {
+        // 路径被 unlink 之后就无法再连接
+        (Some(socket), UnixAddr::Path(path)) if open_file(path, OpenFlags::RDONLY).is_none() => {
+            drop(socket);
+            Err(-ENOENT)
+        }
+        (Some(socket), _) => Ok(socket),
+        (None, UnixAddr::Path(_)) => Err(-ENOENT),
+        (None, _) => Err(-ECONNREFUSED),
+    }
+}
+
+/// 队列中的一条消息
+struct UnixMessage {
+    data: Vec<u8>,
+    /// 流式套接字中已经被读走的字节数
+    pos: usize,
+    fds: Vec<Arc<dyn File + Send + Sync>>,
+    from: UnixAddr,
+}
+
+enum UnixState {
+    Unconnected,
+    Listening {
+        backlog: usize,
+        pending: VecDeque<Arc<UnixSocket>>,
+    },
+    Connected(Weak<UnixSocket>),
+}
+
+struct UnixSocketInner {
+    nonblocking: bool,
+    local: UnixAddr,
+    state: UnixState,
+    rx: VecDeque<UnixMessage>,
+    rx_bytes: usize,
+    /// 本端不再接收
+    shut_rd: bool,
+    /// 本端不再发送
+    shut_wr: bool,
+    /// 对端不再发送，读空后返回 EOF
+    peer_shut_wr: bool,
+}
+
+pub struct UnixSocket {
+    ty: SocketType,
+    me: Weak<UnixSocket>,
+    opts: UPIntrFreeCell<SocketOptions>,
+    inner: UPIntrFreeCell<UnixSocketInner>,
+}
+
+impl UnixSocket {
+    pub fn new(ty: SocketType) -> Arc<Self> {
+        Arc::new_cyclic(|me| Self {
+            ty,
+            me: me.clone(),
+            opts: unsafe { UPIntrFreeCell::new(SocketOptions::new()) },
+            inner: unsafe {
+                UPIntrFreeCell::new(UnixSocketInner {
+                    nonblocking: false,
+                    local: UnixAddr::Unnamed,
+                    state: UnixState::Unconnected,
+                    rx: VecDeque::new(),
+                    rx_bytes: 0,
+                    shut_rd: false,
+                    shut_wr: false,
+                    peer_shut_wr: false,
+                })
+            },
+        })
+    }
+
+    /// 创建一对互相连接的匿名套接字（socketpair）
+    pub fn new_pair(ty: SocketType) -> (Arc<Self>, Arc<Self>) {
+        let a = Self::new(ty);
+        let b = Self::new(ty);
+        a.inner.exclusive_access().state = UnixState::Connected(Arc::downgrade(&b));
+        b.inner.exclusive_access().state = UnixState::Connected(Arc::downgrade(&a));
+        (a, b)
+    }
+
+    pub fn set_nonblocking(&self, nonblocking: bool) {
+        self.inner.exclusive_access().nonblocking = nonblocking;
+    }
+
+    fn would_block(&self, flags: usize) -> bool {
+        flags & MSG_DONTWAIT != 0 || self.inner.exclusive_access().nonblocking
+    }
+
+    fn is_stream(&self) -> bool {
+        self.ty != SocketType::Datagram
+    }
+
+    /// 已连接的对端；对端已关闭时返回 `None`
+    fn peer(&self) -> Option<Arc<UnixSocket>> {
+        match &self.inner.exclusive_access().state {
+            UnixState::Connected(peer) => peer.upgrade(),
+            _ => None,
+        }
+    }
+
+    fn is_connected(&self) -> bool {
+        matches!(self.inner.exclusive_access().state, UnixState::Connected(_))
+    }
+
+    /// 将本套接字登记到名字表
+    fn register(&self, addr: UnixAddr) -> Result<(), isize> {
+        if self.inner.exclusive_access().local != UnixAddr::Unnamed {
+            return Err(-EINVAL);
+        }
+        // 打开文件可能睡眠，在取得名字表之前创建套接字文件
+        if let UnixAddr::Path(path) = &addr {
+            if open_file(path, OpenFlags::RDONLY).is_some() {
+                return Err(-EADDRINUSE);
+            }
+            open_file(path, OpenFlags::CREATE | OpenFlags::WRONLY).ok_or(-ENOENT)?;
+        }
+        let mut names = UNIX_NAMES.exclusive_access();
+        if !matches!(addr, UnixAddr::Path(_))
+            && names.get(&addr).map_or(false, |weak| weak.strong_count() > 0)
+        {
+            return Err(-EADDRINUSE);
+        }
+        // 路径被 unlink 后留下的旧记录会在这里被覆盖
+        names.insert(addr.clone(), self.me.clone());
+        drop(names);
+        self.inner.exclusive_access().local = addr;
+        Ok(())
+    }
+
+    /// 未绑定时自动绑定一个抽象名字
+    fn autobind(&self) -> Result<(), isize> {
+        if self.inner.exclusive_access().local != UnixAddr::Unnamed {
+            return Ok(());
+        }
+        loop {
+            let id = {
+                let mut next = AUTOBIND_ID.exclusive_access();
+                *next = (*next + 1) & 0xfffff;
+                *next
+            };
+            match self.register(UnixAddr::Abstract(format!("{:05x}", id).into_bytes())) {
+                Err(err) if err == -EADDRINUSE => continue,
+                result => return result,
+            }
+        }
+    }
+
+    /// 把消息放入本套接字的接收队列
+    fn enqueue(&self, msg: UnixMessage) {
+        let mut inner = self.inner.exclusive_access();
+        inner.rx_bytes += msg.data.len();
+        inner.rx.push_back(msg);
+    }
+
+    /// 接收队列的剩余空间
+    fn rx_space(&self) -> usize {
+        let inner = self.inner.exclusive_access();
+        if !self.is_stream() && inner.rx.len() >= UNIX_DGRAM_QLEN {
+            return 0;
+        }
+        UNIX_BUF_SIZE - inner.rx_bytes.min(UNIX_BUF_SIZE)
+    }
+
+    fn send_stream(
+        &self,
+        data: &[u8],
+        mut fds: Vec<Arc<dyn File + Send + Sync>>,
+        flags: usize,
+    ) -> Result<usize, isize> {
+        if !self.is_connected() {
+            return Err(-ENOTCONN);
+        }
+        if self.ty == SocketType::Stream && data.is_empty() && fds.is_empty() {
+            return Ok(0);
+        }
+        if self.ty == SocketType::SeqPacket && data.len() > UNIX_BUF_SIZE {
+            return Err(-EMSGSIZE);
+        }
+        let local = self.inner.exclusive_access().local.clone();
+        let mut sent = 0;
+        loop {
+            let peer = self.peer().ok_or(-EPIPE)?;
+            if peer.inner.exclusive_access().shut_rd {
+                return Err(-EPIPE);
+            }
+            let space = peer.rx_space();
+            let enough = match self.ty {
+                // 有序分组必须整条放入
+                SocketType::SeqPacket => space >= data.len(),
+                _ => space > 0 || data.is_empty(),
+            };
+            if enough {
+                let len = space.min(data.len() - sent);
+                peer.enqueue(UnixMessage {
+                    data: data[sent..sent + len].to_vec(),
+                    pos: 0,
+                    fds: core::mem::take(&mut fds),
+                    from: local.clone(),
+                });
+                sent += len;
+                if sent == data.len() {
+                    return Ok(sent);
+                }
+                continue;
+            }
+            drop(peer);
+            if self.would_block(flags) {
+                return if sent > 0 { Ok(sent) } else { Err(-EAGAIN) };
+            }
+            if let Err(err) = wait_interruptible() {
+                return if sent > 0 { Ok(sent) } else { Err(err) };
+            }
+        }
+    }
+
+    fn send_dgram(
+        &self,
+        data: &[u8],
+        fds: Vec<Arc<dyn File + Send + Sync>>,
+        dest: Option<UnixAddr>,
+        flags: usize,
+    ) -> Result<usize, isize> {
+        if data.len() > UNIX_BUF_SIZE {
+            return Err(-EMSGSIZE);
+        }
+        let target = match dest {
+            Some(addr) => lookup(&addr)?,
+            None if self.is_connected() => self.peer().ok_or(-ECONNREFUSED)?,
+            None => return Err(-ENOTCONN),
+        };
+        if target.ty != self.ty {
+            return Err(-EPROTOTYPE);
+        }
+        let local = self.inner.exclusive_access().local.clone();
+        loop {
+            if target.inner.exclusive_access().shut_rd {
+                return Err(-ECONNREFUSED);
+            }
+            if target.rx_space() >= data.len() {
+                target.enqueue(UnixMessage {
+                    data: data.to_vec(),
+                    pos: 0,
+                    fds,
+                    from: local,
+                });
+                return Ok(data.len());
+            }
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    /// 从流中读取最多 `len` 字节
+    fn recv_stream(&self, len: usize, flags: usize) -> Result<RecvMsg, isize> {
+        if !self.is_connected() {
+            return Err(-ENOTCONN);
+        }
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if !inner.rx.is_empty() && len > 0 {
+                let mut data = Vec::new();
+                let mut fds = Vec::new();
+                let mut from = None;
+                let mut consumed = 0;
+                for msg in inner.rx.iter() {
+                    if data.len() == len || (!msg.fds.is_empty() && !data.is_empty()) {
+                        break;
+                    }
+                    let take = (msg.data.len() - msg.pos).min(len - data.len());
+                    data.extend_from_slice(&msg.data[msg.pos..msg.pos + take]);
+                    fds.extend(msg.fds.iter().cloned());
+                    from.get_or_insert_with(|| SockAddr::Unix(msg.from.clone()));
+                    consumed += 1;
+                    // 有序分组每次只读一条
+                    if self.ty == SocketType::SeqPacket {
+                        break;
+                    }
+                }
+                let full_len = match self.ty {
+                    SocketType::SeqPacket => inner.rx[0].data.len(),
+                    _ => data.len(),
+                };
+                if flags & MSG_PEEK == 0 {
+                    let mut left = data.len();
+                    for _ in 0..consumed {
+                        let msg = inner.rx.front_mut().unwrap();
+                        let take = (msg.data.len() - msg.pos).min(left);
+                        msg.pos += take;
+                        msg.fds.clear();
+                        left -= take;
+                        if msg.pos == msg.data.len() || self.ty == SocketType::SeqPacket {
+                            let msg = inner.rx.pop_front().unwrap();
+                            inner.rx_bytes -= msg.data.len();
+                        }
+                    }
+                }
+                return Ok(RecvMsg {
+                    data,
+                    full_len,
+                    fds,
+                    from,
+                });
+            }
+            let eof = inner.shut_rd || inner.peer_shut_wr;
+            drop(inner);
+            if len == 0 || eof || self.peer().is_none() {
+                return Ok(RecvMsg {
+                    data: Vec::new(),
+                    full_len: 0,
+                    fds: Vec::new(),
+                    from: None,
+                });
+            }
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    /// 读取一个数据报，超过 `len` 的部分被丢弃
+    fn recv_dgram(&self, len: usize, flags: usize) -> Result<RecvMsg, isize> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            if let Some(msg) = inner.rx.front() {
+                let take = msg.data.len().min(len);
+                let result = RecvMsg {
+                    data: msg.data[..take].to_vec(),
+                    full_len: msg.data.len(),
+                    fds: msg.fds.clone(),
+                    from: Some(SockAddr::Unix(msg.from.clone())),
+                };
+                if flags & MSG_PEEK == 0 {
+                    let msg = inner.rx.pop_front().unwrap();
+                    inner.rx_bytes -= msg.data.len();
+                }
+                return Ok(result);
+            }
+            if inner.shut_rd {
+                return Ok(RecvMsg {
+                    data: Vec::new(),
+                    full_len: 0,
+                    fds: Vec::new(),
+                    from: None,
+                });
+            }
+            drop(inner);
+            if self.would_block(flags) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+}
+
+impl Socket for UnixSocket {
+    fn bind(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Unix(addr) = addr else {
+            return Err(-EINVAL);
+        };
+        match addr {
+            UnixAddr::Unnamed => self.autobind(),
+            addr => self.register(addr),
+        }
+    }
+
+    fn listen(&self, backlog: usize) -> Result<(), isize> {
+        if !self.is_stream() {
+            return Err(-EOPNOTSUPP);
+        }
+        self.autobind()?;
+        let backlog = backlog.clamp(1, 128);
+        let mut inner = self.inner.exclusive_access();
+        match &mut inner.state {
+            UnixState::Unconnected => {}
+            UnixState::Listening { backlog: old, .. } => {
+                *old = backlog;
+                return Ok(());
+            }
+            UnixState::Connected(_) => return Err(-EINVAL),
+        }
+        inner.state = UnixState::Listening {
+            backlog,
+            pending: VecDeque::new(),
+        };
+        Ok(())
+    }
+
+    fn accept(&self) -> Result<(Arc<dyn File + Send + Sync>, SockAddr), isize> {
+        loop {
+            let mut inner = self.inner.exclusive_access();
+            let UnixState::Listening { pending, .. } = &mut inner.state else {
+                return Err(-EINVAL);
+            };
+            if let Some(socket) = pending.pop_front() {
+                drop(inner);
+                let peer = socket
+                    .peer()
+                    .map_or(UnixAddr::Unnamed, |peer| {
+                        peer.inner.exclusive_access().local.clone()
+                    });
+                return Ok((socket, SockAddr::Unix(peer)));
+            }
+            let nonblocking = inner.nonblocking;
+            drop(inner);
+            if nonblocking {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    fn connect(&self, addr: SockAddr) -> Result<(), isize> {
+        let SockAddr::Unix(addr) = addr else {
+            return Err(-EINVAL);
+        };
+        let target = lookup(&addr)?;
+        if target.ty != self.ty {
+            return Err(-EPROTOTYPE);
+        }
+        if !self.is_stream() {
+            // 数据报套接字只是记录默认的发送目标
+            self.inner.exclusive_access().state = UnixState::Connected(Arc::downgrade(&target));
+            return Ok(());
+        }
+        match self.inner.exclusive_access().state {
+            UnixState::Unconnected => {}
+            UnixState::Listening { .. } => return Err(-EINVAL),
+            UnixState::Connected(_) => return Err(-EISCONN),
+        }
+        loop {
+            let mut target_inner = target.inner.exclusive_access();
+            let target_addr = target_inner.local.clone();
+            let UnixState::Listening { backlog, pending } = &mut target_inner.state else {
+                return Err(-ECONNREFUSED);
+            };
+            if pending.len() < *backlog {
+                // 服务端套接字在连接时创建，继承监听套接字的地址
+                let server = UnixSocket::new(self.ty);
+                {
+                    let mut server_inner = server.inner.exclusive_access();
+                    server_inner.local = target_addr;
+                    server_inner.state = UnixState::Connected(self.me.clone());
+                }
+                pending.push_back(server.clone());
+                drop(target_inner);
+                self.inner.exclusive_access().state =
+                    UnixState::Connected(Arc::downgrade(&server));
+                return Ok(());
+            }
+            drop(target_inner);
+            if self.would_block(0) {
+                return Err(-EAGAIN);
+            }
+            wait_interruptible()?;
+        }
+    }
+
+    fn send(
+        &self,
+        data: &[u8],
+        fds: Vec<Arc<dyn File + Send + Sync>>,
+        dest: Option<SockAddr>,
+        flags: usize,
+    ) -> Result<usize, isize> {
+        if self.inner.exclusive_access().shut_wr {
+            return Err(-EPIPE);
+        }
+        if self.is_stream() {
+            if dest.is_some() && self.is_connected() {
+                return Err(-EISCONN);
+            }
+            self.send_stream(data, fds, flags)
+        } else {
+            let dest = match dest {
+                Some(SockAddr::Unix(addr)) => Some(addr),
+                Some(_) => return Err(-EINVAL),
+                None => None,
+            };
+            self.send_dgram(data, fds, dest, flags)
+        }
+    }
+
+    fn recv(&self, len: usize, flags: usize) -> Result<RecvMsg, isize> {
+        if self.is_stream() {
+            self.recv_stream(len, flags)
+        } else {
+            self.recv_dgram(len, flags)
+        }
+    }
+
+    fn shutdown(&self, how: usize) -> Result<(), isize> {
+        if how > SHUT_RDWR {
+            return Err(-EINVAL);
+        }
+        if self.is_stream() && !self.is_connected() {
+            return Err(-ENOTCONN);
+        }
+        let mut inner = self.inner.exclusive_access();
+        if how == SHUT_RD || how == SHUT_RDWR {
+            inner.shut_rd = true;
+        }
+        if how == SHUT_WR || how == SHUT_RDWR {
+            inner.shut_wr = true;
+        }
+        drop(inner);
+        if how != SHUT_RD {
+            if let Some(peer) = self.peer() {
+                peer.inner.exclusive_access().peer_shut_wr = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn local_addr(&self) -> Result<SockAddr, isize> {
+        Ok(SockAddr::Unix(self.inner.exclusive_access().local.clone()))
+    }
+
+    fn peer_addr(&self) -> Result<SockAddr, isize> {
+        let peer = self.peer().ok_or(-ENOTCONN)?;
+        let addr = peer.inner.exclusive_access().local.clone();
+        Ok(SockAddr::Unix(addr))
+    }
+
+    fn domain(&self) -> u16 {
+        AF_UNIX
+    }
+
+    fn socket_type(&self) -> SocketType {
+        self.ty
+    }
+
+    fn is_listening(&self) -> bool {
+        matches!(self.inner.exclusive_access().state, UnixState::Listening { .. })
+    }
+
+    fn options(&self) -> &UPIntrFreeCell<SocketOptions> {
+        &self.opts
+    }
+}
+
+impl Drop for UnixSocket {
+    fn drop(&mut self) {
+        let local = self.inner.exclusive_access().local.clone();
+        if local != UnixAddr::Unnamed {
+            let mut names = UNIX_NAMES.exclusive_access();
+            if names.get(&local).map_or(false, |weak| weak.strong_count() == 0) {
+                names.remove(&local);
+            }
+        }
+    }
+}
+
+impl File for UnixSocket {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    fn read(&self, buf: UserBuffer) -> usize {
+        socket_read(self, buf).unwrap_or(0)
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        socket_write(self, buf).unwrap_or(0)
+    }
+    fn get_stat(&self) -> UserStat {
+        socket_stat()
+    }
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn get_path(&self) -> String {
+        socket_path()
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> Result<usize, isize> {
+        Err(-ESPIPE)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn status_flags(&self) -> OpenFlags {
+        if self.inner.exclusive_access().nonblocking {
+            OpenFlags::NONBLOCK
+        } else {
+            OpenFlags::empty()
+        }
+    }
+    fn set_status_flags(&self, flags: OpenFlags) {
+        self.set_nonblocking(flags.contains(OpenFlags::NONBLOCK));
+    }
+    fn as_socket(&self) -> Option<&dyn Socket> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnixSocket;
+    use crate::fs::{make_pipe, File};
+    use crate::net::{Socket, SocketType, MSG_PEEK};
+    use alloc::sync::Arc;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test_case]
+    fn passed_files_are_closed_when_read_without_recvmsg() {
+        let (a, b) = UnixSocket::new_pair(SocketType::Stream);
+        let (read_end, _write_end) = make_pipe();
+        let passed: Arc<dyn File + Send + Sync> = read_end.clone();
+        a.send(b"fd", vec![passed], None, 0).unwrap();
+        assert_eq!(Arc::strong_count(&read_end), 2);
+
+        // MSG_PEEK 不取走文件
+        let peeked = b.recv(16, MSG_PEEK).unwrap();
+        assert_eq!(peeked.fds.len(), 1);
+        drop(peeked);
+        assert_eq!(Arc::strong_count(&read_end), 2);
+
+        // recvfrom / read 拿到消息后丢弃其中的文件
+        let msg = b.recv(16, 0).unwrap();
+        assert_eq!(msg.data, b"fd");
+        assert_eq!(msg.fds.len(), 1);
+        drop(msg.fds);
+        assert_eq!(Arc::strong_count(&read_end), 1);
+
+        // 之后的读取不会再收到这些文件
+        a.send(b"x", Vec::new(), None, 0).unwrap();
+        let msg = b.recv(16, 0).unwrap();
+        assert_eq!(msg.data, b"x");
+        assert!(msg.fds.is_empty());
+    }
+}
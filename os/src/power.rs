@@ -0,0 +1,40 @@
+//! # 关机与重启
+//!
+//! ## Overview
+//! 内核有序关机的唯一出口：先卸载文件系统，再交给 `hal` 关机或重启。
+//! - init 进程退出时以它的退出码关机，调度循环结束时以 0 关机
+//! - `reboot` 系统调用按命令关机或重启
+//!
+//! ## Assumptions
+//! - 调用时不持有文件系统、页缓存或块缓存的锁
+//!
+//! ## Behavior
+//! - 卸载写回页缓存中的脏页、清除 FAT 卷的脏标志并刷新块缓存，
+//!   下次启动时可以看到关机前写入的全部内容
+//! - 内核恐慌时不经过这里，直接以 `PANIC_EXIT_CODE` 关机，避免在不一致的状态下写盘
+//! - 退出状态经 `hal::shutdown` 交给模拟器，见各平台的实现
+
+use crate::fs::{block_cache_stats, unmount_root};
+use crate::hal::{reboot, shutdown};
+
+/// 内核恐慌时的退出状态，与被 `SIGABRT` 终止的进程在 shell 中的状态相同
+pub const PANIC_EXIT_CODE: i32 = 128 + 6;
+
+/// 关机之后的动作
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PowerAction {
+    /// 关机，带上退出状态，0 表示成功
+    PowerOff(i32),
+    Reboot,
+}
+
+/// 卸载文件系统后关机或重启
+pub fn kernel_shutdown(action: PowerAction) -> ! {
+    println!("[kernel] unmounting filesystems ...");
+    unmount_root();
+    log::info!("[kernel] block cache: {}", block_cache_stats());
+    match action {
+        PowerAction::PowerOff(code) => shutdown(code),
+        PowerAction::Reboot => reboot(),
+    }
+}
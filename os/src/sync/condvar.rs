@@ -83,6 +83,17 @@ impl Condvar {
         }
     }
 
+    /// 把 `task` 从等待队列中移除
+    ///
+    /// 等待者不是经 `signal` 醒来、或不再等待时调用，
+    /// 避免之后的 `signal` 唤醒一个并未阻塞在这里的任务
+    pub fn remove(&self, task: &Arc<TaskControlBlock>) {
+        self.inner
+            .exclusive_access()
+            .wait_queue
+            .retain(|t| !Arc::ptr_eq(t, task));
+    }
+
     /// 在条件变量上等待，但 **不立即触发调度**
     ///
     /// ## Overview
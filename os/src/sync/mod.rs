@@ -6,7 +6,7 @@
 //! （如任务调度、系统调用、文件系统等）使用的同步设施。
 //!
 //! 模块内部按功能拆分为多个子模块：
-//! - `mutex`：互斥锁抽象及其具体实现（自旋 / 阻塞）
+//! - `mutex`：互斥锁抽象及其具体实现（自旋 / 阻塞），以及内核使用的可睡眠互斥锁
 //! - `semaphore`：计数型信号量
 //! - `condvar`：条件变量
 //! - `up`：单处理器环境下的内部可变性与中断屏蔽封装
@@ -44,7 +44,7 @@ mod up;
 pub use condvar::Condvar;
 
 /// 互斥锁抽象与实现
-pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use mutex::{Mutex, MutexBlocking, MutexSpin, SleepMutex, SleepMutexGuard};
 
 /// 计数型信号量
 pub use semaphore::Semaphore;
@@ -9,6 +9,9 @@
 //! 二者都实现了统一的 `Mutex` trait，以便在系统调用层和同步原语层
 //! 以 **多态方式** 使用不同类型的互斥锁。
 //!
+//! 内核子系统自身使用 `SleepMutex<T>`：它与 `MutexBlocking` 的等待方式相同，
+//! 但保护数据并以 RAII 守卫解锁，持有守卫期间可以睡眠（如等待磁盘请求完成）。
+//!
 //! ## Assumptions
 //! - 系统运行在单处理器环境下
 //! - 任务切换只能发生在显式调度点或中断返回时
@@ -34,6 +37,7 @@
 //!   - 若存在等待任务，唤醒其中一个
 //!   - 否则释放互斥锁
 
+use crate::hal::INTR_MASKING_INFO;
 use crate::sync::UPIntrFreeCell;
 use crate::task::{
     block_current_and_run_next, current_task, suspend_current_and_run_next, wakeup_task,
@@ -41,6 +45,8 @@ use crate::task::{
 };
 use alloc::collections::VecDeque;
 use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
 
 /// 互斥锁统一抽象接口
 ///
@@ -192,3 +198,130 @@ impl Mutex for MutexBlocking {
         }
     }
 }
+
+/// 可以在持有期间睡眠的互斥锁，保护数据 `T`
+///
+/// ## Overview
+/// - 锁被占用时，当前任务加入等待队列并阻塞；解锁时锁直接交给队首任务，记在 `handoff` 中
+/// - 醒来的任务只有锁交给了自己（或锁已空闲）才返回，否则继续睡眠
+/// - `lock` 返回 RAII 守卫，守卫销毁时解锁
+///
+/// ## Assumptions
+/// - `lock` 只在任务上下文中、不处于 `UPIntrFreeCell` 临界区（也不持有自旋锁）时调用；
+///   不能睡眠的路径只能使用 `try_lock`
+/// - 还没有任务时只有一条执行流，锁不会被争用
+pub struct SleepMutex<T> {
+    state: UPIntrFreeCell<SleepMutexState>,
+    data: UnsafeCell<T>,
+}
+
+struct SleepMutexState {
+    locked: bool,
+    /// 解锁时锁交给的等待者，它醒来后取走锁
+    handoff: Option<Arc<TaskControlBlock>>,
+    wait_queue: VecDeque<Arc<TaskControlBlock>>,
+}
+
+// 理由同 `UPIntrFreeCell`：单处理器下同一时间只有持有守卫的任务访问数据
+unsafe impl<T> Sync for SleepMutex<T> {}
+unsafe impl<T> Send for SleepMutex<T> {}
+
+/// `SleepMutex` 的守卫
+pub struct SleepMutexGuard<'a, T> {
+    mutex: &'a SleepMutex<T>,
+}
+
+impl<T> SleepMutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            state: unsafe {
+                UPIntrFreeCell::new(SleepMutexState {
+                    locked: false,
+                    handoff: None,
+                    wait_queue: VecDeque::new(),
+                })
+            },
+            data: UnsafeCell::new(value),
+        }
+    }
+
+    /// 获取锁，被占用时睡眠等待
+    pub fn lock(&self) -> SleepMutexGuard<'_, T> {
+        let task = current_task();
+        loop {
+            let mut state = self.state.exclusive_access();
+            let acquired = if !state.locked {
+                state.locked = true;
+                true
+            } else if matches!(
+                (&state.handoff, &task),
+                (Some(handoff), Some(task)) if Arc::ptr_eq(handoff, task)
+            ) {
+                state.handoff = None;
+                true
+            } else {
+                false
+            };
+            if acquired {
+                // 不是经解锁者交接醒来时自己可能仍在队列中
+                if let Some(task) = &task {
+                    state.wait_queue.retain(|t| !Arc::ptr_eq(t, task));
+                }
+                return SleepMutexGuard { mutex: self };
+            }
+            let task = task
+                .as_ref()
+                .expect("SleepMutex contended before the first task");
+            if !state.wait_queue.iter().any(|t| Arc::ptr_eq(t, task)) {
+                state.wait_queue.push_back(task.clone());
+            }
+            drop(state);
+            debug_assert!(
+                !INTR_MASKING_INFO.get_mut().in_critical_section(),
+                "SleepMutex::lock would sleep inside a critical section"
+            );
+            block_current_and_run_next();
+        }
+    }
+
+    /// 独占数据时（如销毁前）不经过锁直接访问
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// 不睡眠地尝试获取锁
+    pub fn try_lock(&self) -> Option<SleepMutexGuard<'_, T>> {
+        let mut state = self.state.exclusive_access();
+        if state.locked {
+            return None;
+        }
+        state.locked = true;
+        Some(SleepMutexGuard { mutex: self })
+    }
+}
+
+impl<T> Deref for SleepMutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for SleepMutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for SleepMutexGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.mutex.state.exclusive_access();
+        if let Some(waking_task) = state.wait_queue.pop_front() {
+            // 锁直接交给队首任务，其他任务醒来时看到锁不属于自己会继续睡眠
+            state.handoff = Some(waking_task.clone());
+            wakeup_task(waking_task);
+        } else {
+            state.locked = false;
+        }
+    }
+}
@@ -29,7 +29,7 @@
 //! ## Behavior
 //! - 所有 `exclusive_access` 调用都会返回独占可变访问
 //! - 使用 RAII 保证中断屏蔽与恢复成对出现
-//! - 借用冲突将直接 panic（`RefCell` 语义）
+//! - 借用冲突将直接 panic（`RefCell` 语义），`try_exclusive_access` 除外
 
 use crate::hal::INTR_MASKING_INFO;
 use core::cell::{RefCell, RefMut, UnsafeCell};
@@ -106,11 +106,11 @@ unsafe impl<T> Send for UPIntrFreeCell<T> {}
 pub struct UPIntrRefMut<'a, T>(Option<RefMut<'a, T>>);
 
 impl<T> UPIntrFreeCell<T> {
-    /// 创建一个新的 `UPIntrFreeCell`
+    /// 创建一个新的 `UPIntrFreeCell`，可以直接初始化 `static`
     ///
     /// ## Safety
     /// - 使用者需保证仅在 UP 环境下使用
-    pub unsafe fn new(value: T) -> Self {
+    pub const unsafe fn new(value: T) -> Self {
         Self {
             inner: RefCell::new(value),
         }
@@ -127,6 +127,22 @@ impl<T> UPIntrFreeCell<T> {
         UPIntrRefMut(Some(self.inner.borrow_mut()))
     }
 
+    /// 尝试获取内部数据的独占访问权
+    ///
+    /// ## Behavior
+    /// - 借用冲突时恢复中断屏蔽状态并返回 `None`，不会 panic
+    /// - 供 panic 处理等不能再次 panic 的路径使用
+    pub fn try_exclusive_access(&self) -> Option<UPIntrRefMut<'_, T>> {
+        INTR_MASKING_INFO.get_mut().enter();
+        match self.inner.try_borrow_mut() {
+            Ok(inner) => Some(UPIntrRefMut(Some(inner))),
+            Err(_) => {
+                INTR_MASKING_INFO.get_mut().exit();
+                None
+            }
+        }
+    }
+
     /// 在独占访问会话中执行闭包
     ///
     /// ## Behavior
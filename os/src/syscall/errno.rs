@@ -0,0 +1,103 @@
+//! Linux 错误码
+//!
+//! 系统调用失败时返回对应错误码的相反数，例如 `-EBADF`。
+//! 数值与 Linux 通用 ABI（asm-generic/errno-base.h、errno.h）保持一致。
+
+#![allow(unused)]
+
+/// 操作不被允许
+pub const EPERM: isize = 1;
+/// 文件或目录不存在
+pub const ENOENT: isize = 2;
+/// 进程不存在
+pub const ESRCH: isize = 3;
+/// 系统调用被信号中断
+pub const EINTR: isize = 4;
+/// I/O 错误
+pub const EIO: isize = 5;
+/// 参数列表过长
+pub const E2BIG: isize = 7;
+/// 可执行文件格式错误
+pub const ENOEXEC: isize = 8;
+/// 错误的文件描述符
+pub const EBADF: isize = 9;
+/// 没有子进程
+pub const ECHILD: isize = 10;
+/// 资源暂时不可用
+pub const EAGAIN: isize = 11;
+/// 内存不足
+pub const ENOMEM: isize = 12;
+/// 权限不足
+pub const EACCES: isize = 13;
+/// 错误的地址
+pub const EFAULT: isize = 14;
+/// 设备或资源忙
+pub const EBUSY: isize = 16;
+/// 文件已存在
+pub const EEXIST: isize = 17;
+/// 没有这个设备
+pub const ENODEV: isize = 19;
+/// 不是目录
+pub const ENOTDIR: isize = 20;
+/// 是目录
+pub const EISDIR: isize = 21;
+/// 无效参数
+pub const EINVAL: isize = 22;
+/// 打开的文件过多
+pub const EMFILE: isize = 24;
+/// 不适用于该设备的 ioctl
+pub const ENOTTY: isize = 25;
+/// 非法 seek
+pub const ESPIPE: isize = 29;
+/// 管道破裂
+pub const EPIPE: isize = 32;
+/// 结果超出范围
+pub const ERANGE: isize = 34;
+/// 会产生死锁
+pub const EDEADLK: isize = 35;
+/// 文件名过长
+pub const ENAMETOOLONG: isize = 36;
+/// 没有可用的记录锁
+pub const ENOLCK: isize = 37;
+/// 系统调用未实现
+pub const ENOSYS: isize = 38;
+/// 符号链接层数过多
+pub const ELOOP: isize = 40;
+/// 不是套接字
+pub const ENOTSOCK: isize = 88;
+/// 需要目标地址
+pub const EDESTADDRREQ: isize = 89;
+/// 消息过长
+pub const EMSGSIZE: isize = 90;
+/// 套接字协议类型错误
+pub const EPROTOTYPE: isize = 91;
+/// 不支持的协议选项
+pub const ENOPROTOOPT: isize = 92;
+/// 不支持的协议
+pub const EPROTONOSUPPORT: isize = 93;
+/// 不支持的操作
+pub const EOPNOTSUPP: isize = 95;
+/// 不支持的地址族
+pub const EAFNOSUPPORT: isize = 97;
+/// 地址已被占用
+pub const EADDRINUSE: isize = 98;
+/// 无法分配请求的地址
+pub const EADDRNOTAVAIL: isize = 99;
+/// 网络不可达
+pub const ENETUNREACH: isize = 101;
+/// 连接被对端重置
+pub const ECONNRESET: isize = 104;
+/// 缓冲区空间不足
+pub const ENOBUFS: isize = 105;
+/// 套接字已连接
+pub const EISCONN: isize = 106;
+/// 套接字未连接
+pub const ENOTCONN: isize = 107;
+/// 连接超时
+pub const ETIMEDOUT: isize = 110;
+/// 连接被拒绝
+pub const ECONNREFUSED: isize = 111;
+/// 操作已在进行中
+pub const EALREADY: isize = 114;
+/// 操作正在进行
+pub const EINPROGRESS: isize = 115;
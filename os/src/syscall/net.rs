@@ -0,0 +1,427 @@
+//! 套接字相关系统调用
+//!
+//! 套接字作为 `File` 存放在 `fd_table` 中，这里负责用户态参数的解析，
+//! 具体协议的行为由 `crate::net` 中各套接字类型实现。
+
+use crate::fs::{File, OpenFlags};
+use crate::mm::{copy_to_user, get_from_user};
+use crate::net::{
+    copy_in, copy_out, read_sockaddr, write_sockaddr, SocketType, TcpSocket, UdpSocket,
+    UnixSocket, AF_INET, AF_UNIX, IPPROTO_TCP, IPPROTO_UDP, MSG_CMSG_CLOEXEC, MSG_CTRUNC,
+    MSG_TRUNC, SCM_RIGHTS, SOCK_CLOEXEC, SOCK_NONBLOCK, SOCK_TYPE_MASK, SOL_SOCKET,
+};
+use crate::syscall::errno::{
+    EAFNOSUPPORT, EBADF, EFAULT, EINVAL, ENOTSOCK, EOPNOTSUPP, EPROTONOSUPPORT,
+};
+use crate::task::{current_process, current_user_token};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// 用户态 `struct iovec`
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct IoVec {
+    base: usize,
+    len: usize,
+}
+
+/// 用户态 `struct msghdr`
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct MsgHdr {
+    msg_name: usize,
+    msg_namelen: u32,
+    msg_iov: usize,
+    msg_iovlen: usize,
+    msg_control: usize,
+    msg_controllen: usize,
+    msg_flags: i32,
+}
+
+/// 用户态 `struct cmsghdr`
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct CmsgHdr {
+    cmsg_len: usize,
+    cmsg_level: i32,
+    cmsg_type: i32,
+}
+
+const CMSG_HDR_LEN: usize = core::mem::size_of::<CmsgHdr>();
+
+/// 辅助数据按 usize 对齐
+fn cmsg_align(len: usize) -> usize {
+    (len + core::mem::size_of::<usize>() - 1) & !(core::mem::size_of::<usize>() - 1)
+}
+
+/// 取出 fd 对应的套接字文件
+fn socket_file(fd: usize) -> Result<Arc<dyn File + Send + Sync>, isize> {
+    let process = current_process();
+    let inner = process.inner_exclusive_access();
+    let file = match inner.fd_table.get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(-EBADF),
+    };
+    if file.as_socket().is_none() {
+        return Err(-ENOTSOCK);
+    }
+    Ok(file)
+}
+
+/// 将文件放入 fd_table，返回新的文件描述符
+fn install_fd(file: Arc<dyn File + Send + Sync>, cloexec: bool) -> usize {
+    let process = current_process();
+    let mut inner = process.inner_exclusive_access();
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(file);
+    inner.set_cloexec(fd, cloexec);
+    fd
+}
+
+/// 按协议族与类型创建套接字
+fn new_socket(domain: usize, ty: usize, protocol: usize) -> Result<Arc<dyn File + Send + Sync>, isize> {
+    let socket_type = SocketType::from_raw(ty).ok_or(-EINVAL)?;
+    if ty & !(SOCK_TYPE_MASK | SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return Err(-EINVAL);
+    }
+    let protocol = protocol as i32;
+    let socket: Arc<dyn File + Send + Sync> = match (domain as u16, socket_type) {
+        (AF_UNIX, _) if protocol == 0 => UnixSocket::new(socket_type),
+        (AF_INET, SocketType::Stream) if protocol == 0 || protocol == IPPROTO_TCP => {
+            TcpSocket::new()
+        }
+        (AF_INET, SocketType::Datagram) if protocol == 0 || protocol == IPPROTO_UDP => {
+            UdpSocket::new()
+        }
+        (AF_UNIX | AF_INET, _) => return Err(-EPROTONOSUPPORT),
+        _ => return Err(-EAFNOSUPPORT),
+    };
+    if ty & SOCK_NONBLOCK != 0 {
+        socket.set_status_flags(OpenFlags::NONBLOCK);
+    }
+    Ok(socket)
+}
+
+fn into_isize(result: Result<usize, isize>) -> isize {
+    match result {
+        Ok(value) => value as isize,
+        Err(err) => err,
+    }
+}
+
+pub fn sys_socket(domain: usize, ty: usize, protocol: usize) -> isize {
+    let socket = match new_socket(domain, ty, protocol) {
+        Ok(socket) => socket,
+        Err(err) => return err,
+    };
+    install_fd(socket, ty & SOCK_CLOEXEC != 0) as isize
+}
+
+pub fn sys_socketpair(domain: usize, ty: usize, protocol: usize, sv: *mut i32) -> isize {
+    if let Err(err) = new_socket(domain, ty, protocol) {
+        return err;
+    }
+    // 只有 Unix 域支持 socketpair
+    if domain != AF_UNIX as usize {
+        return -EOPNOTSUPP;
+    }
+    if sv.is_null() {
+        return -EFAULT;
+    }
+    let (a, b) = UnixSocket::new_pair(SocketType::from_raw(ty).unwrap());
+    a.set_nonblocking(ty & SOCK_NONBLOCK != 0);
+    b.set_nonblocking(ty & SOCK_NONBLOCK != 0);
+    let cloexec = ty & SOCK_CLOEXEC != 0;
+    let fds = [install_fd(a, cloexec) as i32, install_fd(b, cloexec) as i32];
+    match copy_to_user(current_user_token(), &fds as *const [i32; 2], sv as *mut [i32; 2]) {
+        Ok(()) => 0,
+        Err(err) => err,
+    }
+}
+
+pub fn sys_bind(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let addr = read_sockaddr(current_user_token(), addr, addrlen)?;
+        file.as_socket().unwrap().bind(addr)
+    });
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| file.as_socket().unwrap().listen(backlog));
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_accept4(fd: usize, addr: *mut u8, addrlen: *mut u32, flags: usize) -> isize {
+    if flags & !(SOCK_NONBLOCK | SOCK_CLOEXEC) != 0 {
+        return -EINVAL;
+    }
+    let result = socket_file(fd).and_then(|file| {
+        let (conn, peer) = file.as_socket().unwrap().accept()?;
+        if flags & SOCK_NONBLOCK != 0 {
+            conn.set_status_flags(OpenFlags::NONBLOCK);
+        }
+        write_sockaddr(current_user_token(), &peer, addr, addrlen)?;
+        Ok(install_fd(conn, flags & SOCK_CLOEXEC != 0))
+    });
+    into_isize(result)
+}
+
+pub fn sys_connect(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let addr = read_sockaddr(current_user_token(), addr, addrlen)?;
+        file.as_socket().unwrap().connect(addr)
+    });
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_getsockname(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let local = file.as_socket().unwrap().local_addr()?;
+        write_sockaddr(current_user_token(), &local, addr, addrlen)
+    });
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_getpeername(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let peer = file.as_socket().unwrap().peer_addr()?;
+        write_sockaddr(current_user_token(), &peer, addr, addrlen)
+    });
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_sendto(
+    fd: usize,
+    buf: *const u8,
+    len: usize,
+    flags: usize,
+    addr: *const u8,
+    addrlen: usize,
+) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let token = current_user_token();
+        let data = copy_in(token, buf, len)?;
+        let dest = if addr.is_null() {
+            None
+        } else {
+            Some(read_sockaddr(token, addr, addrlen)?)
+        };
+        file.as_socket().unwrap().send(&data, Vec::new(), dest, flags)
+    });
+    into_isize(result)
+}
+
+/// 接收数据，可选地取得发送方地址
+///
+/// recvfrom 没有辅助数据缓冲区：随消息传来的文件（SCM_RIGHTS）与 Linux 一样被关闭，
+/// 不会留给之后的 recvmsg；需要接收文件时必须使用 recvmsg
+pub fn sys_recvfrom(
+    fd: usize,
+    buf: *mut u8,
+    len: usize,
+    flags: usize,
+    addr: *mut u8,
+    addrlen: *mut u32,
+) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let token = current_user_token();
+        let msg = file.as_socket().unwrap().recv(len, flags)?;
+        // 消息已经出队，放下对传来文件的引用即关闭它们
+        drop(msg.fds);
+        copy_out(token, buf, len, &msg.data)?;
+        if let Some(from) = &msg.from {
+            write_sockaddr(token, from, addr, addrlen)?;
+        }
+        Ok(if flags & MSG_TRUNC != 0 {
+            msg.full_len
+        } else {
+            msg.data.len()
+        })
+    });
+    into_isize(result)
+}
+
+pub fn sys_setsockopt(
+    fd: usize,
+    level: usize,
+    optname: usize,
+    optval: *const u8,
+    optlen: usize,
+) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let value = copy_in(current_user_token(), optval, optlen)?;
+        file.as_socket()
+            .unwrap()
+            .setsockopt(level as i32, optname as i32, &value)
+    });
+    into_isize(result.map(|_| 0))
+}
+
+pub fn sys_getsockopt(
+    fd: usize,
+    level: usize,
+    optname: usize,
+    optval: *mut u8,
+    optlen: *mut u32,
+) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let token = current_user_token();
+        if optlen.is_null() {
+            return Err(-EFAULT);
+        }
+        let value = file
+            .as_socket()
+            .unwrap()
+            .getsockopt(level as i32, optname as i32)?;
+        let len_bytes = copy_in(token, optlen as *const u8, 4)?;
+        let len = u32::from_ne_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]);
+        let written = copy_out(token, optval, len as usize, &value)?;
+        copy_out(token, optlen as *mut u8, 4, &(written as u32).to_ne_bytes())?;
+        Ok(0)
+    });
+    into_isize(result)
+}
+
+pub fn sys_shutdown(fd: usize, how: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| file.as_socket().unwrap().shutdown(how));
+    into_isize(result.map(|_| 0))
+}
+
+/// 读取 msghdr 中的 iovec 数组
+fn read_iovecs(token: usize, hdr: &MsgHdr) -> Result<Vec<IoVec>, isize> {
+    if hdr.msg_iovlen > 0 && hdr.msg_iov == 0 {
+        return Err(-EFAULT);
+    }
+    Ok((0..hdr.msg_iovlen)
+        .map(|i| get_from_user(token, (hdr.msg_iov + i * core::mem::size_of::<IoVec>()) as *const IoVec))
+        .collect())
+}
+
+/// 解析辅助数据中的 SCM_RIGHTS，取出要传递的文件
+fn read_rights(token: usize, hdr: &MsgHdr) -> Result<Vec<Arc<dyn File + Send + Sync>>, isize> {
+    let mut files = Vec::new();
+    if hdr.msg_control == 0 {
+        return Ok(files);
+    }
+    let mut offset = 0;
+    while offset + CMSG_HDR_LEN <= hdr.msg_controllen {
+        let cmsg: CmsgHdr = get_from_user(token, (hdr.msg_control + offset) as *const CmsgHdr);
+        if cmsg.cmsg_len < CMSG_HDR_LEN || offset + cmsg.cmsg_len > hdr.msg_controllen {
+            return Err(-EINVAL);
+        }
+        if cmsg.cmsg_level == SOL_SOCKET && cmsg.cmsg_type == SCM_RIGHTS {
+            let count = (cmsg.cmsg_len - CMSG_HDR_LEN) / 4;
+            let bytes = copy_in(
+                token,
+                (hdr.msg_control + offset + CMSG_HDR_LEN) as *const u8,
+                count * 4,
+            )?;
+            let process = current_process();
+            let inner = process.inner_exclusive_access();
+            for raw in bytes.chunks_exact(4) {
+                let fd = i32::from_ne_bytes([raw[0], raw[1], raw[2], raw[3]]);
+                match inner.fd_table.get(fd as usize) {
+                    Some(Some(file)) if fd >= 0 => files.push(file.clone()),
+                    _ => return Err(-EBADF),
+                }
+            }
+        }
+        offset += cmsg_align(cmsg.cmsg_len);
+    }
+    Ok(files)
+}
+
+pub fn sys_sendmsg(fd: usize, msg: *const u8, flags: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let token = current_user_token();
+        if msg.is_null() {
+            return Err(-EFAULT);
+        }
+        let hdr: MsgHdr = get_from_user(token, msg as *const MsgHdr);
+        let dest = if hdr.msg_name == 0 || hdr.msg_namelen == 0 {
+            None
+        } else {
+            Some(read_sockaddr(token, hdr.msg_name as *const u8, hdr.msg_namelen as usize)?)
+        };
+        let mut data = Vec::new();
+        for iov in read_iovecs(token, &hdr)? {
+            data.extend(copy_in(token, iov.base as *const u8, iov.len)?);
+        }
+        let files = read_rights(token, &hdr)?;
+        file.as_socket().unwrap().send(&data, files, dest, flags)
+    });
+    into_isize(result)
+}
+
+pub fn sys_recvmsg(fd: usize, msg: *mut u8, flags: usize) -> isize {
+    let result = socket_file(fd).and_then(|file| {
+        let token = current_user_token();
+        if msg.is_null() {
+            return Err(-EFAULT);
+        }
+        let mut hdr: MsgHdr = get_from_user(token, msg as *const MsgHdr);
+        let iovecs = read_iovecs(token, &hdr)?;
+        let total: usize = iovecs.iter().map(|iov| iov.len).sum();
+        let received = file.as_socket().unwrap().recv(total, flags)?;
+
+        // 把数据分散到各个 iovec
+        let mut copied = 0;
+        for iov in iovecs.iter() {
+            if copied == received.data.len() {
+                break;
+            }
+            copied += copy_out(token, iov.base as *mut u8, iov.len, &received.data[copied..])?;
+        }
+
+        hdr.msg_flags = 0;
+        if received.full_len > received.data.len() {
+            hdr.msg_flags |= MSG_TRUNC as i32;
+        }
+        match (&received.from, hdr.msg_name) {
+            (Some(from), name) if name != 0 => {
+                let bytes = from.to_bytes();
+                copy_out(token, name as *mut u8, hdr.msg_namelen as usize, &bytes)?;
+                hdr.msg_namelen = bytes.len() as u32;
+            }
+            _ => hdr.msg_namelen = 0,
+        }
+
+        // 把传来的文件装进本进程的 fd_table，放不下的部分被丢弃
+        let mut control_len = 0;
+        if !received.fds.is_empty() {
+            let room = hdr.msg_controllen.saturating_sub(CMSG_HDR_LEN) / 4;
+            let fit = if hdr.msg_control == 0 {
+                0
+            } else {
+                room.min(received.fds.len())
+            };
+            if fit < received.fds.len() {
+                hdr.msg_flags |= MSG_CTRUNC as i32;
+            }
+            if fit > 0 {
+                let cloexec = flags & MSG_CMSG_CLOEXEC != 0;
+                let mut bytes = Vec::new();
+                let cmsg_len = CMSG_HDR_LEN + fit * 4;
+                bytes.extend_from_slice(&cmsg_len.to_ne_bytes());
+                bytes.extend_from_slice(&SOL_SOCKET.to_ne_bytes());
+                bytes.extend_from_slice(&SCM_RIGHTS.to_ne_bytes());
+                for file in received.fds.iter().take(fit) {
+                    let fd = install_fd(file.clone(), cloexec) as i32;
+                    bytes.extend_from_slice(&fd.to_ne_bytes());
+                }
+                copy_out(token, hdr.msg_control as *mut u8, bytes.len(), &bytes)?;
+                control_len = cmsg_align(cmsg_len).min(hdr.msg_controllen);
+            }
+        }
+        hdr.msg_controllen = control_len;
+        copy_to_user(token, &hdr as *const MsgHdr, msg as *mut MsgHdr)?;
+
+        Ok(if flags & MSG_TRUNC != 0 {
+            received.full_len
+        } else {
+            received.data.len()
+        })
+    });
+    into_isize(result)
+}
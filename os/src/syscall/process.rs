@@ -1,18 +1,22 @@
 #![allow(unused)]
 
-use crate::fs::{open_file, OpenFlags};
+use crate::fs::inode::OSInode;
+use crate::fs::{open_dir, open_file, OpenFlags};
+use crate::power::{kernel_shutdown, PowerAction};
 use crate::mm::{
     copy_to_user, get_from_user, translated_byte_buffer, translated_ref, translated_refmut,
     translated_str, UserBuffer,
 };
 use crate::task::{
     block_current_and_run_next, current_process, current_task, current_user_token,
-    exit_current_and_run_next, find_task_by_pid, pid2process, suspend_current_and_run_next,
-    wake_blocked, Rusage, SignalFlags, TaskStatus,
+    exit_current_and_run_next, pid2process, process_group_exists, signal_all_processes,
+    signal_process, signal_process_group, suspend_current_and_run_next, Rusage, SignalFlags,
 };
+use crate::syscall::errno::{EACCES, EINVAL, ELOOP, ENOENT, ENOEXEC, EPERM, ESRCH};
 use crate::timer::{add_timer, get_time_ms, TimeSpec, TimeVal, TimeZone, Tms};
 use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use core::mem::size_of;
 use bitflags::bitflags;
@@ -23,6 +27,40 @@ pub fn sys_exit(exit_code: i32) -> ! {
     panic!("Unreachable in sys_exit!");
 }
 
+/// reboot 的两个魔数
+const LINUX_REBOOT_MAGIC1: usize = 0xfee1_dead;
+const LINUX_REBOOT_MAGIC2: usize = 672274793;
+const LINUX_REBOOT_MAGIC2A: usize = 85072278;
+const LINUX_REBOOT_MAGIC2B: usize = 369367448;
+const LINUX_REBOOT_MAGIC2C: usize = 537993216;
+
+/// reboot 命令
+const LINUX_REBOOT_CMD_RESTART: u32 = 0x0123_4567;
+const LINUX_REBOOT_CMD_HALT: u32 = 0xcdef_0123;
+const LINUX_REBOOT_CMD_POWER_OFF: u32 = 0x4321_fedc;
+const LINUX_REBOOT_CMD_CAD_ON: u32 = 0x89ab_cdef;
+const LINUX_REBOOT_CMD_CAD_OFF: u32 = 0;
+
+/// 卸载文件系统后关机或重启，成功时不返回；HALT 与 POWER_OFF 一样关机
+pub fn sys_reboot(magic1: usize, magic2: usize, cmd: u32, _arg: usize) -> isize {
+    let magic2_ok = matches!(
+        magic2,
+        LINUX_REBOOT_MAGIC2 | LINUX_REBOOT_MAGIC2A | LINUX_REBOOT_MAGIC2B | LINUX_REBOOT_MAGIC2C
+    );
+    if magic1 != LINUX_REBOOT_MAGIC1 || !magic2_ok {
+        return -EINVAL;
+    }
+    match cmd {
+        LINUX_REBOOT_CMD_RESTART => kernel_shutdown(PowerAction::Reboot),
+        LINUX_REBOOT_CMD_HALT | LINUX_REBOOT_CMD_POWER_OFF => {
+            kernel_shutdown(PowerAction::PowerOff(0))
+        }
+        // 没有 Ctrl-Alt-Del 的概念，接受但不做任何事
+        LINUX_REBOOT_CMD_CAD_ON | LINUX_REBOOT_CMD_CAD_OFF => 0,
+        _ => -EINVAL,
+    }
+}
+
 pub fn sys_yield() -> isize {
     suspend_current_and_run_next();
     0
@@ -76,9 +114,9 @@ pub fn sys_mmap(
     off: usize,
 ) -> isize {
     let process = current_process();
-    let mut inner = process.inner_exclusive_access();
     let file = if fd >= 0 {
-        inner
+        process
+            .inner_exclusive_access()
             .fd_table
             .get(fd as usize)
             .and_then(|f| f.as_ref())
@@ -86,6 +124,15 @@ pub fn sys_mmap(
     } else {
         None
     };
+    // 持有进程锁时页缓存不能睡眠读盘，先读入要映射的页
+    if let Some(cache) = file
+        .as_ref()
+        .and_then(|f| f.as_any().downcast_ref::<OSInode>())
+        .and_then(|inode| inode.page_cache())
+    {
+        cache.prefetch(off, len);
+    }
+    let mut inner = process.inner_exclusive_access();
     // 调用 MemorySet::mmap
     match inner.memory_set.mmap(start, len, prot, flags, file, off) {
         Ok(addr) => addr as isize, // 返回映射起始虚拟地址
@@ -201,16 +248,73 @@ pub fn sys_execve(
             }
         }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
-        let all_data = app_inode.read_all();
-        let process = current_process();
-        let argv = argv_vec.len();
-        let envp = envp_vec.len();
-        process.exec(all_data.as_slice(), argv_vec);
-        0
-    } else {
-        -1
+    let (app_inode, all_data, argv_vec) = match load_executable(path, argv_vec) {
+        Ok(loaded) => loaded,
+        Err(errno) => return -errno,
+    };
+    let process = current_process();
+    let cache = app_inode.page_cache().map(|cache| cache.as_ref());
+    // 成功时返回值写入 a0，恰好是传给 libc 的空 rtld_fini
+    match process.exec(all_data.as_slice(), cache, argv_vec, envp_vec) {
+        Ok(()) => 0,
+        Err(errno) => -errno,
+    }
+}
+
+/// `#!` 脚本的最大嵌套层数，与 Linux 的 BINPRM_MAX_RECURSION 相同
+const MAX_SCRIPT_DEPTH: usize = 4;
+/// 只在文件开头这么多字节内查找 `#!` 行
+const SCRIPT_LINE_MAX: usize = 256;
+
+/// 打开要执行的文件，逐层展开 `#!` 脚本，返回最终的 ELF 文件、其内容与调整后的参数
+///
+/// - 文件不存在返回 `ENOENT`，是目录返回 `EACCES`
+/// - `#!` 行不合法返回 `ENOEXEC`，嵌套超过 [`MAX_SCRIPT_DEPTH`] 层返回 `ELOOP`
+fn load_executable(
+    mut path: String,
+    mut args: Vec<String>,
+) -> Result<(Arc<OSInode>, Vec<u8>, Vec<String>), isize> {
+    for _ in 0..=MAX_SCRIPT_DEPTH {
+        let inode = match open_file(&path, OpenFlags::RDONLY) {
+            Some(inode) => inode,
+            None if open_dir(&path).is_ok() => return Err(EACCES),
+            None => return Err(ENOENT),
+        };
+        let data = inode.read_all();
+        if !data.starts_with(b"#!") {
+            return Ok((inode, data, args));
+        }
+        let (interp, interp_arg) = parse_shebang(&data)?;
+        // 新的 argv：解释器、可选参数、脚本路径，再接原 argv[1..]
+        let mut script_args = vec![interp.clone()];
+        script_args.extend(interp_arg);
+        script_args.push(path);
+        script_args.extend(args.into_iter().skip(1));
+        args = script_args;
+        path = interp;
+    }
+    Err(ELOOP)
+}
+
+/// 解析 `#!interpreter [arg]`，解释器之后的内容整体作为一个参数
+fn parse_shebang(data: &[u8]) -> Result<(String, Option<String>), isize> {
+    let line = &data[2..data.len().min(SCRIPT_LINE_MAX)];
+    let line = match line.iter().position(|&b| b == b'\n') {
+        Some(end) => &line[..end],
+        None => line,
+    };
+    let is_blank = |c: char| c == ' ' || c == '\t' || c == '\r';
+    let line = core::str::from_utf8(line)
+        .map_err(|_| ENOEXEC)?
+        .trim_matches(is_blank);
+    let (interp, arg) = match line.find(is_blank) {
+        Some(split) => (&line[..split], line[split..].trim_matches(is_blank)),
+        None => (line, ""),
+    };
+    if interp.is_empty() {
+        return Err(ENOEXEC);
     }
+    Ok((String::from(interp), (!arg.is_empty()).then(|| String::from(arg))))
 }
 
 /// If there is not a child process whose pid is same as given, return -1.
@@ -357,36 +461,69 @@ pub fn sys_kill(pid: usize, sig: usize) -> isize {
         Ok(signal) => signal,
         Err(_) => return -1, //EINVAL,
     };
-    if pid > 0 {
-        // [Warning] in current implementation,
-        // signal will be sent to an arbitrary task with target `pid` (`tgid` more precisely).
-        // But manual also require that the target task should not mask this signal.
-        if let Some(task) = find_task_by_pid(pid) {
-            if !signal.is_empty() {
-                let mut task_inner = task.inner_exclusive_access();
-                let mut process = task.process.upgrade().unwrap();
-                let mut process_inner = process.inner_exclusive_access();
-                process_inner.add_signal(signal);
-                // wake up target process if it is sleeping
-                if task_inner.task_status == TaskStatus::Blocked {
-                    task_inner.task_status = TaskStatus::Ready;
-                    drop(task_inner);
-                    wake_blocked(task);
-                }
+    if (pid as isize) > 0 {
+        if signal_process(pid, signal) {
+            0
+        } else {
+            -ESRCH
+        }
+    } else {
+        // pid 为 0 时发往调用者所在的进程组，为 -1 时发往所有进程，小于 -1 时发往进程组 -pid
+        let count = match pid as isize {
+            0 => {
+                let pgid = current_process().inner_exclusive_access().pgid;
+                signal_process_group(pgid, signal)
             }
-            0 // SUCCESS
+            -1 => signal_all_processes(signal),
+            pgid => signal_process_group(pgid.unsigned_abs(), signal),
+        };
+        if count == 0 {
+            -ESRCH
         } else {
-            -1 // ESRCH
+            0
         }
-    } else if pid == 0 {
-        todo!()
-    } else if (pid as isize) == -1 {
-        todo!()
+    }
+}
+/// 设置进程 `pid` 的进程组，`pid` 为 0 表示当前进程，`pgid` 为 0 表示以 `pid` 为组号
+///
+/// 只能修改当前进程或其子进程；加入的进程组必须已经存在，或者组号等于目标进程的 PID
+pub fn sys_setpgid(pid: usize, pgid: isize) -> isize {
+    if pgid < 0 {
+        return -EINVAL;
+    }
+    let process = current_process();
+    let target = if pid == 0 || pid == process.getpid() {
+        process.clone()
     } else {
-        // (pid as isize) < -1
-        todo!()
+        let inner = process.inner_exclusive_access();
+        match inner.children.iter().find(|child| child.getpid() == pid) {
+            Some(child) => child.clone(),
+            None => return -ESRCH,
+        }
+    };
+    let target_pid = target.getpid();
+    let pgid = if pgid == 0 { target_pid } else { pgid as usize };
+    if pgid != target_pid && !process_group_exists(pgid) {
+        return -EPERM;
     }
+    target.inner_exclusive_access().pgid = pgid;
+    0
+}
+
+/// 获取进程 `pid` 的进程组号，`pid` 为 0 表示当前进程
+pub fn sys_getpgid(pid: usize) -> isize {
+    let process = if pid == 0 {
+        current_process()
+    } else {
+        match pid2process(pid) {
+            Some(process) => process,
+            None => return -ESRCH,
+        }
+    };
+    let pgid = process.inner_exclusive_access().pgid;
+    pgid as isize
 }
+
 pub fn sys_getppid() -> isize {
     let task = current_task().unwrap();
     let process = task.process.upgrade().unwrap();
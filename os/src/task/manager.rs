@@ -77,6 +77,7 @@ pub fn add_task(task: Arc<TaskControlBlock>) {
 pub fn wakeup_task(task: Arc<TaskControlBlock>) {
     let mut task_inner = task.inner_exclusive_access();
     task_inner.task_status = TaskStatus::Ready;
+    task_inner.interruptible = false;
     drop(task_inner);
     add_task(task);
 }
@@ -183,10 +184,14 @@ pub fn find_task_by_pid(pid: usize) -> Option<Arc<TaskControlBlock>> {
         TASK_MANAGER.exclusive_access().find_by_pid(pid)
     }
 }
-pub fn wake_blocked(task: Arc<TaskControlBlock>) {
+/// 唤醒阻塞在可打断等待上的任务，其他阻塞（磁盘请求、`SleepMutex`、定时睡眠）不受影响
+///
+/// 信号与终端输入、记录锁释放都经由这里唤醒等待者，被唤醒者自行重新检查条件
+pub fn wake_interruptible(task: Arc<TaskControlBlock>) {
     let mut task_inner = task.inner_exclusive_access();
-    if task_inner.task_status == TaskStatus::Blocked {
+    if task_inner.task_status == TaskStatus::Blocked && task_inner.interruptible {
         task_inner.task_status = TaskStatus::Ready;
+        task_inner.interruptible = false;
         drop(task_inner);
         add_task(task);
     }
@@ -30,16 +30,23 @@
 //!   - 返回任务上下文指针
 //! - `block_current_and_run_next()`：
 //!   - 阻塞当前任务并调度下一任务
+//! - `block_current_task_interruptible()` / `block_current_and_run_next_interruptible()`：
+//!   - 同上，但等待可以被信号打断（`wake_interruptible`），被唤醒者须自行重新检查条件
 //! - `exit_current_and_run_next(exit_code)`：
 //!   - 记录退出码，释放用户资源
 //!   - 如果主线程退出，处理 PCB 回收、子进程重新挂载到 `initproc`
+//!   - init 进程退出时以它的退出状态关机
 //!   - 调度下一任务
 //! - `INITPROC`：
-//!   - 通过 ELF 文件创建初始进程 PCB
+//!   - 通过命令行 `init=` 指定的 ELF 文件（默认 `initproc`）创建初始进程 PCB
 //!   - 保证系统启动后至少有一个进程存在
 //! - 信号处理：
 //!   - `check_signals_of_current()` 返回当前进程的错误信号
 //!   - `current_add_signal(signal)` 向当前进程添加信号
+//!   - `signal_process(pid, signal)` 向单个进程发送信号（`kill(pid, sig)`）
+//!   - `signal_process_group(pgid, signal)` 向整个进程组发送信号（终端的 `^C` 等）
+//!   - `signal_all_processes(signal)` 向除 init 与当前进程以外的所有进程发送信号
+//!   - 发送信号只唤醒可打断的等待，磁盘请求与 `SleepMutex` 的等待不会被提前唤醒
 
 mod context;
 mod manager;
@@ -49,21 +56,25 @@ mod processor;
 mod signal;
 mod task;
 
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 pub use context::TaskContext;
 use lazy_static::lazy_static;
 pub use manager::{
-    add_task, find_task_by_pid, pid2process, remove_from_pid2process, wake_blocked, wakeup_task,
+    add_task, find_task_by_pid, pid2process, remove_from_pid2process, wake_interruptible,
+    wakeup_task,
 };
 pub use process::Rusage;
 pub use processor::{
-    current_kstack_top, current_process, current_task, current_trap_cx, current_trap_cx_user_va,
-    current_user_token, run_tasks, schedule, take_current_task,
+    current_process, current_task, current_trap_cx, current_trap_cx_user_va, current_user_token,
+    run_tasks, schedule, take_current_task, try_current_task,
 };
 
-use crate::fs::{open_initproc, OpenFlags};
-use crate::hal::shutdown;
+use crate::fs::{open_boot_file, release_all_posix_locks, OpenFlags};
+use crate::power::{kernel_shutdown, PowerAction};
+use crate::task::manager::PID2PCB;
 use crate::task::pid::IDLE_PID;
 pub use crate::task::process::{ProcessControlBlock, ProcessControlBlockInner};
 use crate::task::task::TaskUserRes;
@@ -101,6 +112,7 @@ pub fn block_current_task() -> *mut TaskContext {
     let task = take_current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
     task_inner.task_status = TaskStatus::Blocked;
+    task_inner.interruptible = false;
     &mut task_inner.task_cx as *mut TaskContext
 }
 
@@ -110,6 +122,21 @@ pub fn block_current_and_run_next() {
     schedule(task_cx_ptr);
 }
 
+/// 阻塞当前任务，等待可以被信号打断
+pub fn block_current_task_interruptible() -> *mut TaskContext {
+    let task = take_current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    task_inner.task_status = TaskStatus::Blocked;
+    task_inner.interruptible = true;
+    &mut task_inner.task_cx as *mut TaskContext
+}
+
+/// 可打断地阻塞当前任务并调度下一任务
+pub fn block_current_and_run_next_interruptible() {
+    let task_cx_ptr = block_current_task_interruptible();
+    schedule(task_cx_ptr);
+}
+
 /// 退出当前任务并运行下一任务
 ///
 /// - 记录退出码，释放用户资源
@@ -136,13 +163,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
                 "[kernel] Idle process exit with exit_code {} ...",
                 exit_code
             );
-            if exit_code != 0 {
-                //crate::sbi::shutdown(255); //255 == -1 for err hint
-                shutdown();
-            } else {
-                //crate::sbi::shutdown(0); //0 for success hint
-                shutdown();
-            }
+            kernel_shutdown(PowerAction::PowerOff(shell_status(exit_code)));
         }
         remove_from_pid2process(pid);
         let mut process_inner = process.inner_exclusive_access();
@@ -176,6 +197,8 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         // for now to avoid deadlock/double borrow problem.
         drop(process_inner);
         recycle_res.clear();
+        // release advisory record locks held by this process
+        release_all_posix_locks(process.getpid());
 
         let mut process_inner = process.inner_exclusive_access();
         process_inner.children.clear();
@@ -183,6 +206,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
         process_inner.memory_set.recycle_data_pages();
         // drop file descriptors
         process_inner.fd_table.clear();
+        process_inner.fd_cloexec.clear();
         // Remove all tasks except for the main thread itself.
         // This is because we are still using the kstack under the TCB
         // of the main thread. This TCB, including its kstack, will be
@@ -200,9 +224,17 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 lazy_static! {
     /// 系统初始化进程 PCB
     pub static ref INITPROC: Arc<ProcessControlBlock> = {
-        let inode = open_initproc(OpenFlags::RDONLY).unwrap();  // 已仅读模式打开 initproc 文件
-        let v = inode.read_all();   // 读取 initproc 文件的全部内容到内存中
-        ProcessControlBlock::new(v.as_slice())  // 创建 initproc 进程控制块
+        // init 程序由命令行 `init=` 指定，`--` 之后的参数原样传给它
+        let path = crate::cmdline::init_path();
+        let inode = open_boot_file(&path, OpenFlags::RDONLY)
+            .unwrap_or_else(|| panic!("init program {} not found", path));
+        let v = inode.read_all();   // 读取 init 程序的全部内容到内存中
+        let mut args = vec![path];
+        args.extend(crate::cmdline::init_args());
+        let cache = inode.page_cache().map(|cache| cache.as_ref());
+        let envs = vec![String::from("PATH=/"), String::from("HOME=/")];
+        // 创建 initproc 进程控制块，同时压入 argv、envp 与辅助向量
+        ProcessControlBlock::new(v.as_slice(), cache, args, envs)
     };
 }
 
@@ -211,6 +243,17 @@ pub fn add_initproc() {
     let _initproc = INITPROC.clone(); // 提前克隆 INITPROC，确保其在后续使用中不会被释放
 }
 
+/// 把记录的退出码换算成 shell 中的退出状态
+///
+/// 正常退出记录为 `status << 8`，取出 `status`；因信号退出记录为 `-signum`，换算为 `128 + signum`
+fn shell_status(exit_code: i32) -> i32 {
+    if exit_code < 0 {
+        128 - exit_code
+    } else {
+        (exit_code >> 8) & 0xff
+    }
+}
+
 /// 检查当前进程的信号
 pub fn check_signals_of_current() -> Option<(i32, &'static str)> {
     let process = current_process();
@@ -224,3 +267,83 @@ pub fn current_add_signal(signal: SignalFlags) {
     let mut process_inner = process.inner_exclusive_access();
     process_inner.signals |= signal;
 }
+
+/// 向进程 `pid` 发送信号，进程不存在时返回 `false`
+///
+/// 与 Linux 一样，发给 init 的信号被忽略
+pub fn signal_process(pid: usize, signal: SignalFlags) -> bool {
+    let Some(process) = pid2process(pid) else {
+        return false;
+    };
+    if pid == INITPROC.getpid() {
+        return !process.inner_exclusive_access().is_zombie;
+    }
+    deliver_signal(&process, signal)
+}
+
+/// 向进程组 `pgid` 中的所有进程发送信号，返回收到信号的进程数
+///
+/// - 与 Linux 一样，init 进程不接收终端产生的信号
+/// - 与 `sys_kill` 一样唤醒这些进程中可打断的等待，使其尽快处理信号
+pub fn signal_process_group(pgid: usize, signal: SignalFlags) -> usize {
+    signal_processes(signal, |_, inner| inner.pgid == pgid)
+}
+
+/// 向除 init 与当前进程以外的所有进程发送信号，即 `kill(-1, sig)`，返回收到信号的进程数
+pub fn signal_all_processes(signal: SignalFlags) -> usize {
+    let pid = current_process().getpid();
+    signal_processes(signal, |process, _| process.getpid() != pid)
+}
+
+/// 向满足 `filter` 的存活进程（init 除外）发送信号并唤醒其中可打断的等待
+///
+/// `signal` 为空时只统计进程数，用于 `kill(pid, 0)` 检查目标是否存在
+fn signal_processes(
+    signal: SignalFlags,
+    filter: impl Fn(&ProcessControlBlock, &ProcessControlBlockInner) -> bool,
+) -> usize {
+    let initproc_pid = INITPROC.getpid();
+    let processes: Vec<Arc<ProcessControlBlock>> =
+        PID2PCB.exclusive_access().values().cloned().collect();
+    let mut count = 0;
+    for process in processes {
+        if process.getpid() == initproc_pid {
+            continue;
+        }
+        let matched = filter(&process, &process.inner_exclusive_access());
+        if matched && deliver_signal(&process, signal) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// 向存活的进程发送信号并唤醒其中可打断的等待，僵尸进程返回 `false`
+///
+/// `signal` 为空时只检查进程是否存活
+fn deliver_signal(process: &ProcessControlBlock, signal: SignalFlags) -> bool {
+    let mut inner = process.inner_exclusive_access();
+    if inner.is_zombie {
+        return false;
+    }
+    if signal.is_empty() {
+        return true;
+    }
+    inner.add_signal(signal);
+    let tasks: Vec<Arc<TaskControlBlock>> = inner.tasks.iter().flatten().cloned().collect();
+    drop(inner);
+    for task in tasks {
+        wake_interruptible(task);
+    }
+    true
+}
+
+/// 进程组 `pgid` 中是否还有存活的进程
+pub fn process_group_exists(pgid: usize) -> bool {
+    let processes: Vec<Arc<ProcessControlBlock>> =
+        PID2PCB.exclusive_access().values().cloned().collect();
+    processes.iter().any(|process| {
+        let inner = process.inner_exclusive_access();
+        inner.pgid == pgid && !inner.is_zombie
+    })
+}
@@ -30,10 +30,10 @@
 //! - `PidHandle` drop：
 //!   - 自动将 PID 回收
 //! - `RecycleAllocator`：
-//!   - 内部记录当前最大 PID 与回收池
+//!   - 内部记录当前最大 PID 与回收池，实现位于 `id_alloc`
 
 use crate::sync::UPIntrFreeCell;
-use alloc::vec::Vec;
+pub use id_alloc::RecycleAllocator;
 use lazy_static::lazy_static;
 
 lazy_static! {
@@ -73,59 +73,28 @@ impl Drop for PidHandle {
     }
 }
 
-/// 可回收的递增 PID 分配器
-///
-/// ## Fields
-/// - `current`：
-///   - 当前最大 PID（未分配的下一个 PID）
-/// - `recycled`：
-///   - 回收池，存放已经释放的 PID
-pub struct RecycleAllocator {
-    current: usize,
-    recycled: Vec<usize>,
-}
+#[cfg(test)]
+mod tests {
+    use super::RecycleAllocator;
 
-impl RecycleAllocator {
-    /// 创建新的 PID 分配器
-    ///
-    /// ## Invariants
-    /// - 初始时 `current = 0`，`recycled` 为空
-    pub fn new() -> Self {
-        RecycleAllocator {
-            current: 0,
-            recycled: Vec::new(),
-        }
+    #[test_case]
+    fn alloc_is_sequential() {
+        let mut allocator = RecycleAllocator::new();
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 2);
     }
 
-    /// 分配一个 PID
-    ///
-    /// ## Behavior
-    /// - 优先返回回收 PID
-    /// - 若无回收 PID，递增分配新 PID
-    pub fn alloc(&mut self) -> usize {
-        if let Some(id) = self.recycled.pop() {
-            id
-        } else {
-            self.current += 1;
-            self.current - 1
+    #[test_case]
+    fn recycled_ids_are_reused_last_in_first_out() {
+        let mut allocator = RecycleAllocator::new();
+        for _ in 0..4 {
+            allocator.alloc();
         }
-    }
-
-    /// 回收一个 PID
-    ///
-    /// ## Safety
-    /// - PID 必须已经分配过
-    /// - PID 不能重复回收
-    ///
-    /// ## Panics
-    /// - PID 超出 `current` 或已在回收池中存在
-    pub fn dealloc(&mut self, id: usize) {
-        assert!(id < self.current);
-        assert!(
-            !self.recycled.iter().any(|i| *i == id),
-            "id {} has been deallocated!",
-            id
-        );
-        self.recycled.push(id);
+        allocator.dealloc(1);
+        allocator.dealloc(3);
+        assert_eq!(allocator.alloc(), 3);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 4);
     }
 }
@@ -36,8 +36,9 @@
 //!   - 初始化文件描述符表（stdin/stdout/stderr）
 //! - `exec`：
 //!   - 替换进程地址空间与 trap 上下文
-//!   - 将参数压入用户栈
-//!   - 更新 trap_cx 寄存器 a0/a1
+//!   - 按 SysV ABI 在用户栈上放置 argc、argv、envp 与辅助向量
+//!   - 关闭设置了 FD_CLOEXEC 的文件描述符
+//!   - 带有 `PT_INTERP` 的程序从动态链接器入口开始执行
 //! - `fork`：
 //!   - 完全复制父进程内存空间（包括用户栈/ trap_cx）
 //!   - 复制文件描述符表
@@ -48,9 +49,9 @@
 //! - 任务访问：通过 `get_task(tid)` 获取特定线程
 
 use crate::fs::inode::OSInode;
-use crate::fs::{current_root_inode, File, Stdin, Stdout};
-use crate::hal::{trap_handler, PageTableImpl, TrapContext, UserStackBase};
-use crate::mm::{translated_refmut, MemorySet, KERNEL_SPACE};
+use crate::fs::{current_root_inode, release_posix_locks, File, PageCache, Stdin, Stdout};
+use crate::hal::{trap_handler, PageTableImpl, TrapContext, UserStackBase, HWCAP, PAGE_SIZE};
+use crate::mm::{translated_refmut, ElfInfo, MemorySet, KERNEL_SPACE};
 use crate::sync::{Condvar, Mutex, Semaphore, UPIntrFreeCell, UPIntrRefMut};
 use crate::syscall::CloneFlags;
 use crate::task::manager::{add_task, insert_into_pid2process};
@@ -58,6 +59,7 @@ use crate::task::pid::{pid_alloc, PidHandle, RecycleAllocator};
 use crate::task::signal::SignalFlags;
 use crate::task::task::TaskControlBlock;
 use crate::timer::{ITimerVal, TimeVal};
+use alloc::collections::BTreeSet;
 use alloc::string::{String, ToString};
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
@@ -85,6 +87,8 @@ pub struct ProcessControlBlockInner {
     //由于fat32每次打开都会开一个新inode，所以需要记录当前的inode是什么
     pub cwd_inode: Arc<dyn File + Send + Sync>,
     pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    /// 设置了 FD_CLOEXEC 的文件描述符，execve 时关闭
+    pub fd_cloexec: BTreeSet<usize>,
     pub signals: SignalFlags,
     pub tasks: Vec<Option<Arc<TaskControlBlock>>>,
     pub task_res_allocator: RecycleAllocator,
@@ -95,6 +99,8 @@ pub struct ProcessControlBlockInner {
     pub clock: ProcClock,
     pub timer: ITimerVal,
     pub tgid: usize,
+    /// 进程组号，fork 时继承，通过 setpgid 修改
+    pub pgid: usize,
 }
 
 impl ProcessControlBlock {
@@ -107,12 +113,21 @@ impl ProcessControlBlock {
     ///
     /// ## Parameters
     /// - `elf_data`：用户程序 ELF 文件数据
+    /// - `cache`：ELF 文件的页缓存，与 `exec` 相同
+    /// - `args` / `envs`：压入初始用户栈的 argv 与 envp
     ///
     /// ## Returns
     /// - `Arc<Self>`：新建进程 PCB
-    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+    pub fn new(
+        elf_data: &[u8],
+        cache: Option<&PageCache>,
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> Arc<Self> {
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, elf_info) =
+            MemorySet::from_elf(elf_data, cache).expect("init program is not a valid ELF");
+        let token = memory_set.token();
         // allocate a pid
         let pid_handle = pid_alloc();
         let pid = pid_handle.0;
@@ -137,6 +152,7 @@ impl ProcessControlBlock {
                         // 2 -> stderr
                         Some(Arc::new(Stdout)),
                     ],
+                    fd_cloexec: BTreeSet::new(),
                     signals: SignalFlags::empty(),
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
@@ -147,6 +163,7 @@ impl ProcessControlBlock {
                     clock: ProcClock::new(),
                     timer: ITimerVal::new(),
                     tgid,
+                    pgid: pid,
                 })
             },
         });
@@ -164,9 +181,11 @@ impl ProcessControlBlock {
         let ustack_top = task_inner.res.as_ref().unwrap().ustack_top();
         let kstack_top = task.kstack.get_top();
         drop(task_inner);
+        // 与 `exec` 一样按 SysV ABI 构造初始用户栈
+        let user_sp = init_user_stack(token, ustack_top, &args, &envs, &elf_info);
         *trap_cx = TrapContext::app_init_context(
-            entry_point,
-            ustack_top,
+            elf_info.entry,
+            user_sp,
             KERNEL_SPACE.exclusive_access().token(),
             kstack_top,
             trap_handler as usize,
@@ -184,13 +203,29 @@ impl ProcessControlBlock {
     }
 
     /// 执行新程序（仅支持单线程进程）
-    pub fn exec(self: &Arc<Self>, elf_data: &[u8], args: Vec<String>) {
+    ///
+    /// `cache` 为 ELF 文件的页缓存，只读段直接映射其中的页。
+    /// ELF 不合法或找不到动态链接器时返回错误码，此时进程保持原样
+    pub fn exec(
+        self: &Arc<Self>,
+        elf_data: &[u8],
+        cache: Option<&PageCache>,
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> Result<(), isize> {
         assert_eq!(self.inner_exclusive_access().thread_count(), 1);
-        // 通过 ELF 数据创建新的地址空间，获得新的用户栈基址和程序入口点
-        let (memory_set, entry_point) = MemorySet::from_elf(elf_data);
+        // 通过 ELF 数据创建新的地址空间，获得程序入口与辅助向量所需的信息；
+        // 失败时原地址空间保持不变
+        let (memory_set, elf_info) = MemorySet::from_elf(elf_data, cache)?;
         let new_token = memory_set.token();
         // 更新进程地址空间
         self.inner_exclusive_access().memory_set = memory_set;
+        // 关闭设置了 FD_CLOEXEC 的文件描述符
+        let closed = self.inner_exclusive_access().close_on_exec();
+        for file in closed.iter() {
+            release_posix_locks(file, self.getpid());
+        }
+        drop(closed);
 
         // 因为地址空间已经更改，需要重新为主线程分配用户资源
         let task = self.inner_exclusive_access().get_task(0);
@@ -201,42 +236,21 @@ impl ProcessControlBlock {
         // 分配用户资源（用户栈 + trap 上下文）
         task_inner.res.as_mut().unwrap().alloc_user_res();
         task_inner.trap_cx_ppn = task_inner.res.as_mut().unwrap().trap_cx_ppn();
-        // 把参数压入用户栈
-        let mut user_sp = task_inner.res.as_mut().unwrap().ustack_top();
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    new_token,
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(new_token, p as *mut u8) = *c;
-                p += 1;
-            }
-            *translated_refmut(new_token, p as *mut u8) = 0;
-        }
-        // 让 user_sp 对齐到 8 字节（k210 平台要求）
-        user_sp -= user_sp % core::mem::size_of::<usize>();
+        // 按 SysV ABI 构造初始用户栈
+        let ustack_top = task_inner.res.as_mut().unwrap().ustack_top();
+        let user_sp = init_user_stack(new_token, ustack_top, &args, &envs, &elf_info);
         // 初始化 trap 上下文
-        let mut trap_cx = TrapContext::app_init_context(
-            entry_point,
+        let trap_cx = TrapContext::app_init_context(
+            elf_info.entry,
             user_sp,
             KERNEL_SPACE.exclusive_access().token(),
             task.kstack.get_top(),
             trap_handler as usize,
         );
-        trap_cx.general_regs.a0 = args.len();
-        trap_cx.general_regs.a1 = argv_base;
+        // a0 是动态链接器传给 libc 的退出回调（rtld_fini），内核启动时为空，
+        // `app_init_context` 已将通用寄存器清零；argc、argv 由用户程序从栈上读取
         *task_inner.get_trap_cx() = trap_cx;
+        Ok(())
     }
 
     /// 分叉子进程（仅支持单线程父进程）
@@ -356,6 +370,7 @@ impl ProcessControlBlock {
                     cwd_inode: parent.cwd_inode.clone(),
                     cwd: parent.cwd.clone(),
                     fd_table: new_fd_table,
+                    fd_cloexec: parent.fd_cloexec.clone(),
                     signals: SignalFlags::empty(),
                     tasks: Vec::new(),
                     task_res_allocator: RecycleAllocator::new(),
@@ -366,6 +381,7 @@ impl ProcessControlBlock {
                     clock: ProcClock::new(),
                     timer: ITimerVal::new(),
                     tgid,
+                    pgid: parent.pgid,
                 })
             },
         });
@@ -421,14 +437,48 @@ impl ProcessControlBlockInner {
 
     /// 分配新的文件描述符
     pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
+        self.alloc_fd_from(0)
+    }
+
+    /// 分配不小于 `min` 的最小可用文件描述符
+    pub fn alloc_fd_from(&mut self, min: usize) -> usize {
+        if let Some(fd) = (min..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
             fd
         } else {
+            if self.fd_table.len() < min {
+                self.fd_table.resize(min, None);
+            }
             self.fd_table.push(None);
             self.fd_table.len() - 1
         }
     }
 
+    /// 关闭文件描述符，同时清除其 FD_CLOEXEC 标志
+    pub fn close_fd(&mut self, fd: usize) -> Option<Arc<dyn File + Send + Sync>> {
+        self.fd_cloexec.remove(&fd);
+        self.fd_table.get_mut(fd)?.take()
+    }
+
+    /// 设置或清除文件描述符的 FD_CLOEXEC 标志
+    pub fn set_cloexec(&mut self, fd: usize, cloexec: bool) {
+        if cloexec {
+            self.fd_cloexec.insert(fd);
+        } else {
+            self.fd_cloexec.remove(&fd);
+        }
+    }
+
+    /// 文件描述符是否设置了 FD_CLOEXEC
+    pub fn is_cloexec(&self, fd: usize) -> bool {
+        self.fd_cloexec.contains(&fd)
+    }
+
+    /// 关闭全部设置了 FD_CLOEXEC 的文件描述符，返回被关闭的文件
+    pub fn close_on_exec(&mut self) -> Vec<Arc<dyn File + Send + Sync>> {
+        let fds: Vec<usize> = self.fd_cloexec.iter().copied().collect();
+        fds.into_iter().filter_map(|fd| self.close_fd(fd)).collect()
+    }
+
     /// 分配新的线程 ID
     pub fn alloc_tid(&mut self) -> usize {
         self.task_res_allocator.alloc()
@@ -532,3 +582,108 @@ impl ProcClock {
         }
     }
 }
+
+/// 辅助向量的类型（`AT_*`），取值与 Linux 相同
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_BASE: usize = 7;
+const AT_FLAGS: usize = 8;
+const AT_ENTRY: usize = 9;
+const AT_UID: usize = 11;
+const AT_EUID: usize = 12;
+const AT_GID: usize = 13;
+const AT_EGID: usize = 14;
+const AT_HWCAP: usize = 16;
+const AT_CLKTCK: usize = 17;
+const AT_SECURE: usize = 23;
+const AT_RANDOM: usize = 25;
+const AT_EXECFN: usize = 31;
+
+/// `times` 等接口使用的时钟频率
+const CLOCK_TICKS_PER_SEC: usize = 100;
+
+/// 在新地址空间的用户栈上构造进程初始栈，返回栈指针
+///
+/// 从高地址到低地址依次为：字符串区（execfn、envp、argv 的字符串）、
+/// `AT_RANDOM` 指向的 16 字节随机数、辅助向量、envp 指针数组、argv 指针数组、argc。
+/// 返回的栈指针指向 argc，按 16 字节对齐
+fn init_user_stack(
+    token: usize,
+    ustack_top: usize,
+    args: &[String],
+    envs: &[String],
+    info: &ElfInfo,
+) -> usize {
+    let mut sp = ustack_top;
+    let push_bytes = |sp: &mut usize, bytes: &[u8]| {
+        *sp -= bytes.len();
+        for (i, byte) in bytes.iter().enumerate() {
+            *translated_refmut(token, (*sp + i) as *mut u8) = *byte;
+        }
+        *sp
+    };
+    let push_str = |sp: &mut usize, s: &str| {
+        push_bytes(sp, &[0]);
+        push_bytes(sp, s.as_bytes())
+    };
+
+    let execfn = push_str(&mut sp, args.first().map_or("", |arg| arg.as_str()));
+    let env_ptrs: Vec<usize> = envs.iter().map(|env| push_str(&mut sp, env)).collect();
+    let arg_ptrs: Vec<usize> = args.iter().map(|arg| push_str(&mut sp, arg)).collect();
+    let mut random = [0u8; 16];
+    if !crate::drivers::fill_random(&mut random) {
+        // 没有 virtio-rng 时退化为以时钟为种子的 xorshift，只求每次不同
+        let mut x = crate::hal::get_time() as u64 | 1;
+        for byte in random.iter_mut() {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            *byte = x as u8;
+        }
+    }
+    sp &= !0xf;
+    let random_ptr = push_bytes(&mut sp, &random);
+
+    let auxv = [
+        (AT_PHDR, info.phdr),
+        (AT_PHENT, info.phent),
+        (AT_PHNUM, info.phnum),
+        (AT_PAGESZ, PAGE_SIZE),
+        (AT_BASE, info.interp_base),
+        (AT_FLAGS, 0),
+        (AT_ENTRY, info.program_entry),
+        (AT_UID, 0),
+        (AT_EUID, 0),
+        (AT_GID, 0),
+        (AT_EGID, 0),
+        (AT_SECURE, 0),
+        (AT_RANDOM, random_ptr),
+        (AT_HWCAP, HWCAP),
+        (AT_CLKTCK, CLOCK_TICKS_PER_SEC),
+        (AT_EXECFN, execfn),
+        (AT_NULL, 0),
+    ];
+    // 指针区的字数：argc + argv + NULL + envp + NULL + auxv
+    let words = 1 + (args.len() + 1) + (envs.len() + 1) + auxv.len() * 2;
+    sp -= words * core::mem::size_of::<usize>();
+    sp &= !0xf;
+
+    let mut words = Vec::with_capacity(words);
+    words.push(args.len());
+    words.extend(arg_ptrs);
+    words.push(0);
+    words.extend(env_ptrs);
+    words.push(0);
+    for (key, value) in auxv {
+        words.push(key);
+        words.push(value);
+    }
+    for (i, word) in words.iter().enumerate() {
+        let addr = sp + i * core::mem::size_of::<usize>();
+        *translated_refmut(token, addr as *mut usize) = *word;
+    }
+    sp
+}
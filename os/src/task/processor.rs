@@ -26,7 +26,7 @@
 
 use crate::fs::inode::{OSInode, OpenFlags};
 use crate::fs::{open_dir, open_file};
-use crate::hal::{TrapContext, __switch};
+use crate::hal::{wait_for_interrupt, TrapContext, __switch};
 use crate::sync::UPIntrFreeCell;
 use crate::task::manager::fetch_task;
 use crate::task::process::ProcessControlBlock;
@@ -142,7 +142,9 @@ pub fn run_tasks() {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
         } else {
-            println!("No task to run, shutting down...");
+            // 没有就绪任务：剩下的任务都在等待中断（如磁盘请求完成）
+            drop(processor);
+            wait_for_interrupt();
         }
     }
 }
@@ -191,9 +193,11 @@ pub fn current_trap_cx_user_va() -> usize {
         .trap_cx_user_va()
 }
 
-/// 获取当前任务的内核栈顶地址。
-pub fn current_kstack_top() -> usize {
-    current_task().unwrap().kstack.get_top()
+/// 获得当前正在运行任务的 TCB 的引用，不会 panic。
+///
+/// 处理器正被借用（例如在调度循环中 panic）时返回 `None`，供 panic 处理使用。
+pub fn try_current_task() -> Option<Arc<TaskControlBlock>> {
+    PROCESSOR.try_exclusive_access()?.current()
 }
 
 /// 切换回调度循环，恢复空闲任务上下文。
@@ -38,22 +38,31 @@ bitflags! {
     /// ## Fields
     /// - `SIGINT`：
     ///   - 中断信号（通常由用户或外部事件触发）
+    /// - `SIGQUIT`：
+    ///   - 退出信号（终端上的 `^\\`）
     /// - `SIGILL`：
     ///   - 非法指令异常
+    /// - `SIGTRAP`：
+    ///   - 断点异常（不是内核 GDB 桩放置的断点）
     /// - `SIGABRT`：
     ///   - 程序异常终止
     /// - `SIGFPE`：
     ///   - 算术错误（如除零）
     /// - `SIGSEGV`：
     ///   - 段错误（非法内存访问）
+    /// - `SIGTSTP`：
+    ///   - 终端停止信号（`^Z`），尚不支持进程停止，默认被忽略
     pub struct SignalFlags: u32 {
         const SIGINT    = 1 << 1;
+        const SIGQUIT   = 1 << 2;
         const SIGILL    = 1 << 3;
+        const SIGTRAP   = 1 << 4;
         const SIGABRT   = 1 << 5;
         const SIGFPE    = 1 << 7;
         const SIGSEGV   = 1 << 10;
         const SIGALRM	= 1 << 13;
         const SIGCHLD	= 1 << 16;
+        const SIGTSTP   = 1 << 19;
         const SIGVTALRM	= 1 << 25;
         const SIGPROF	= 1 << 26;
     }
@@ -80,15 +89,21 @@ impl SignalFlags {
     /// ## Behavior
     /// - 检查顺序即信号处理优先级：
     ///     1. SIGINT
-    ///     2. SIGILL
-    ///     3. SIGABRT
-    ///     4. SIGFPE
-    ///     5. SIGSEGV
+    ///     2. SIGQUIT
+    ///     3. SIGILL
+    ///     4. SIGTRAP
+    ///     5. SIGABRT
+    ///     6. SIGFPE
+    ///     7. SIGSEGV
     pub fn check_error(&self) -> Option<(i32, &'static str)> {
         if self.contains(Self::SIGINT) {
             Some((-2, "Killed, SIGINT=2"))
+        } else if self.contains(Self::SIGQUIT) {
+            Some((-3, "Quit, SIGQUIT=3"))
         } else if self.contains(Self::SIGILL) {
             Some((-4, "Illegal Instruction, SIGILL=4"))
+        } else if self.contains(Self::SIGTRAP) {
+            Some((-5, "Trace/Breakpoint Trap, SIGTRAP=5"))
         } else if self.contains(Self::SIGABRT) {
             Some((-6, "Aborted, SIGABRT=6"))
         } else if self.contains(Self::SIGFPE) {
@@ -100,6 +100,8 @@ impl TaskControlBlock {
                     task_cx: TaskContext::goto_trap_return(kstack_top),
                     task_status: TaskStatus::Ready,
                     exit_code: None,
+                    blocking_io: false,
+                    interruptible: false,
                 })
             },
         }
@@ -118,6 +120,10 @@ pub struct TaskControlBlockInner {
     pub task_status: TaskStatus,
     /// 退出码（None 表示未退出）
     pub exit_code: Option<i32>,
+    /// 磁盘请求可以睡眠等待完成（见 `drivers::BlockingIo`）
+    pub blocking_io: bool,
+    /// 阻塞在可以被信号打断的等待上（终端读、记录锁等待）
+    pub interruptible: bool,
 }
 
 impl TaskControlBlockInner {
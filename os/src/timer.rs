@@ -273,3 +273,40 @@ impl ITimerVal {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{TimeSpec, NSEC_PER_SEC};
+
+    fn ts(tv_sec: usize, tv_nsec: usize) -> TimeSpec {
+        TimeSpec { tv_sec, tv_nsec }
+    }
+
+    #[test_case]
+    fn add_carries_nanoseconds() {
+        assert_eq!(ts(1, 500_000_000) + ts(2, 200_000_000), ts(3, 700_000_000));
+        assert_eq!(ts(1, 600_000_000) + ts(0, 700_000_000), ts(2, 300_000_000));
+        assert_eq!(ts(0, NSEC_PER_SEC - 1) + ts(0, 1), ts(1, 0));
+    }
+
+    #[test_case]
+    fn sub_borrows_and_saturates() {
+        assert_eq!(ts(3, 100_000_000) - ts(1, 200_000_000), ts(1, 900_000_000));
+        assert_eq!(ts(1, 0) - ts(1, 0), TimeSpec::new());
+        assert_eq!(ts(1, 0) - ts(2, 0), TimeSpec::new());
+    }
+
+    #[test_case]
+    fn ns_and_ms_conversions() {
+        assert_eq!(TimeSpec::from_ns(2_500_000_001), ts(2, 500_000_001));
+        assert_eq!(ts(2, 500_000_001).to_ns(), 2_500_000_001);
+        assert_eq!(ts(2, 500_999_999).to_ms(), 2_500);
+    }
+
+    #[test_case]
+    fn ordering() {
+        assert!(ts(1, 999_999_999) < ts(2, 0));
+        assert!(ts(2, 1) > ts(2, 0));
+        assert_eq!(ts(5, 5).max(ts(5, 4)), ts(5, 5));
+    }
+}
@@ -0,0 +1,112 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use core::time::Duration;
+use user::{close, ioctl, open, println, read, sleep, write, Instant, OpenFlags};
+
+/// 经 `/dev/net0` 收发原始以太网帧，检查网卡能否与 QEMU 用户态网络通信：
+/// 以 `10.0.2.15` 的身份广播 ARP 请求询问网关 `10.0.2.2`，
+/// 超时前收到网关的 ARP 应答时打印其 MAC 并返回 0。
+const DEVICE: &str = "/dev/net0";
+const SIOCGIFHWADDR: usize = 0x8927;
+/// `struct ifreq` 中硬件地址的偏移：16 字节接口名 + 2 字节地址族
+const IFREQ_HWADDR: usize = 18;
+
+const LOCAL_IP: [u8; 4] = [10, 0, 2, 15];
+const GATEWAY_IP: [u8; 4] = [10, 0, 2, 2];
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+const ETH_HEADER_LEN: usize = 14;
+const ETHERTYPE_ARP: [u8; 2] = [0x08, 0x06];
+const ARP_REQUEST: u8 = 1;
+const ARP_REPLY: u8 = 2;
+
+/// 以太网头加 ARP 报文（以太网 / IPv4）
+fn arp_request(mac: &[u8; 6]) -> [u8; 42] {
+    let mut frame = [0u8; 42];
+    frame[0..6].fill(0xff);
+    frame[6..12].copy_from_slice(mac);
+    frame[12..14].copy_from_slice(&ETHERTYPE_ARP);
+    let arp = &mut frame[ETH_HEADER_LEN..];
+    // 硬件类型 1（以太网）、协议类型 0x0800（IPv4）、地址长度 6 与 4
+    arp[0..8].copy_from_slice(&[0, 1, 0x08, 0x00, 6, 4, 0, ARP_REQUEST]);
+    arp[8..14].copy_from_slice(mac);
+    arp[14..18].copy_from_slice(&LOCAL_IP);
+    arp[24..28].copy_from_slice(&GATEWAY_IP);
+    frame
+}
+
+/// 是网关发给 `LOCAL_IP` 的 ARP 应答时返回网关的 MAC
+fn arp_reply_from_gateway(frame: &[u8]) -> Option<[u8; 6]> {
+    if frame.len() < 42 || frame[12..14] != ETHERTYPE_ARP {
+        return None;
+    }
+    let arp = &frame[ETH_HEADER_LEN..];
+    if arp[7] != ARP_REPLY || arp[14..18] != GATEWAY_IP || arp[24..28] != LOCAL_IP {
+        return None;
+    }
+    let mut mac = [0u8; 6];
+    mac.copy_from_slice(&arp[8..14]);
+    Some(mac)
+}
+
+fn format_mac(mac: &[u8; 6]) -> [u8; 17] {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+    let mut text = [b':'; 17];
+    for (i, byte) in mac.iter().enumerate() {
+        text[i * 3] = HEX[(byte >> 4) as usize];
+        text[i * 3 + 1] = HEX[(byte & 0xf) as usize];
+    }
+    text
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    let fd = match open(DEVICE, OpenFlags::RDWR | OpenFlags::NONBLOCK) {
+        Ok(fd) => fd,
+        Err(err) => {
+            println!("arping: cannot open {}: {}", DEVICE, err);
+            return 1;
+        }
+    };
+    let mut ifreq = [0u8; 40];
+    if let Err(err) = ioctl(fd, SIOCGIFHWADDR, ifreq.as_mut_ptr() as usize) {
+        println!("arping: SIOCGIFHWADDR failed: {}", err);
+        let _ = close(fd);
+        return 1;
+    }
+    let mut mac = [0u8; 6];
+    mac.copy_from_slice(&ifreq[IFREQ_HWADDR..IFREQ_HWADDR + 6]);
+
+    let request = arp_request(&mac);
+    if write(fd, &request) != Ok(request.len()) {
+        println!("arping: failed to send the ARP request");
+        let _ = close(fd);
+        return 1;
+    }
+    let start = Instant::now();
+    let mut frame = [0u8; 1514];
+    let status = loop {
+        let n = read(fd, &mut frame).unwrap_or(0);
+        if let Some(gateway) = arp_reply_from_gateway(&frame[..n]) {
+            let text = format_mac(&gateway);
+            println!(
+                "arping: reply from 10.0.2.2 [{}]",
+                core::str::from_utf8(&text).unwrap()
+            );
+            break 0;
+        }
+        if start.elapsed() >= TIMEOUT {
+            println!("arping: no reply from 10.0.2.2");
+            break 1;
+        }
+        // 没有帧可读时稍等再试
+        if n == 0 {
+            sleep(Duration::from_millis(10));
+        }
+    };
+    let _ = close(fd);
+    status
+}
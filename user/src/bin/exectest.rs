@@ -0,0 +1,80 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use user::{
+    close, exec, exit, fork, open, println, waitpid, write_all, Errno, ExitStatus, OpenFlags,
+};
+
+/// 检查 execve 的错误码与 `#!` 脚本：
+/// 不存在的文件返回 ENOENT，非 ELF 文件返回 ENOEXEC，自我引用的脚本返回 ELOOP，
+/// 以 `#!/exectest child` 开头的脚本以 `/exectest child <脚本> <参数>` 运行。
+fn create(path: &str, content: &[u8]) -> bool {
+    let fd = match open(
+        path,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    ) {
+        Ok(fd) => fd,
+        Err(err) => {
+            println!("exectest: cannot create {}: {}", path, err);
+            return false;
+        }
+    };
+    let ok = write_all(fd, content).is_ok();
+    let _ = close(fd);
+    ok
+}
+
+fn expect<T: PartialEq + core::fmt::Debug>(name: &str, got: T, want: T) -> bool {
+    if got != want {
+        println!("exectest: {}: expected {:?}, got {:?}", name, want, got);
+    }
+    got == want
+}
+
+/// 由脚本启动时的检查：argv 应为 解释器、脚本参数、脚本路径、原 argv[1..]
+fn child(argv: &[&str]) -> i32 {
+    if argv == ["/exectest", "child", "script.sh", "arg"] {
+        0
+    } else {
+        println!("exectest: unexpected script argv {:?}", argv);
+        1
+    }
+}
+
+#[no_mangle]
+fn main(_argc: usize, argv: &[&str]) -> i32 {
+    if argv.get(1) == Some(&"child") {
+        return child(argv);
+    }
+    let mut ok = expect("missing file", exec("/no_such_file", &[]), Errno::ENOENT);
+    ok &= create("notelf.bin", b"not an executable\n");
+    ok &= expect("non-ELF", exec("notelf.bin", &[]), Errno::ENOEXEC);
+    ok &= create("loop.sh", b"#!loop.sh\n");
+    ok &= expect("recursive script", exec("loop.sh", &[]), Errno::ELOOP);
+    ok &= create("script.sh", b"#! /exectest  child \n");
+
+    match fork() {
+        Ok(0) => {
+            let err = exec("script.sh", &["script.sh", "arg"]);
+            println!("exectest: exec script.sh failed: {}", err);
+            exit(1);
+        }
+        Ok(pid) => {
+            let status = waitpid(pid);
+            ok &= expect("script exit status", status, Ok(ExitStatus::from_raw(0)));
+        }
+        Err(err) => {
+            println!("exectest: fork failed: {}", err);
+            ok = false;
+        }
+    }
+
+    if ok {
+        println!("exectest: ok");
+        0
+    } else {
+        -1
+    }
+}
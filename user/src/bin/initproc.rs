@@ -3,80 +3,47 @@
 
 extern crate user;
 
-use user::{exec, fork, println, wait, yield_};
+use user::{exec, fork, println, reboot, wait, waitpid, yield_, RB_POWER_OFF};
 
+/// 运行模式由内核命令行 `--` 之后的第一个参数决定：
+/// - `tests`：只运行测例，结束后卸载文件系统并关机
+/// - `shell`：只启动 `user_shell`
+/// - 其它或缺省：先运行测例，再启动 `user_shell`
 #[no_mangle]
-fn main() -> i32 {
-    let tests = [
-        "brk\0",
-        "clone\0",
-        "dup\0",
-        "execve\0",
-        "fork\0",
-        "getcwd\0",
-        "getpid\0",
-        "gettimeofday\0",
-        "mmap\0",
-        // "mount\0",
-        "open\0",
-        // "pipe\0",
-        "times\0",  //好像有点问题？
-        "uname\0",
-        "wait\0",
-        "write\0",
-        "chdir\0",
-        "close\0",
-        "dup2\0",
-        "exit\0",
-        "fstat\0",
-        "getdents\0",
-        "getppid\0",
-        "mkdir_\0",
-        "munmap\0",
-        "openat\0",
-        "read\0",
-        "sleep\0",
-        // "umount\0",
-        // "unlink\0",
-        "waitpid\0",
-        "yield\0",
-    ];
-
-    for prog in tests {
-        let pid = fork();
-        if pid == 0 {
-            println!("Running {}", prog);
-            exec(prog, &[core::ptr::null()]);
-            panic!("exec failed");
-        } else {
-            let mut exit_code: i32 = 0;
-            wait(&mut exit_code);
-            if pid == -1 {
-                yield_();
-                continue;
-            }
-        }
+fn main(_argc: usize, argv: &[&str]) -> i32 {
+    let mode = argv.get(1).copied().unwrap_or("");
+    if mode != "shell" {
+        run_tests();
     }
-
-    if fork() == 0 {
+    if mode == "tests" {
+        reboot(RB_POWER_OFF);
+    }
+    if mode != "tests" && fork() == Ok(0) {
         println!("Exiting main...");
-        exec("user_shell\0", &[core::ptr::null::<u8>()]);
+        // 环境变量（PATH 等）由内核传给 init，再经 exec 原样传给 shell
+        let err = exec("/user_shell", &["/user_shell"]);
+        println!("[initproc] cannot exec /user_shell: {}", err);
     } else {
         loop {
-            let mut exit_code: i32 = 0;
-            let pid = wait(&mut exit_code);
-            if pid == -1 {
+            // 回收所有孤儿进程，暂时没有子进程时让出 CPU
+            if wait().is_err() {
                 yield_();
-                continue;
             }
-            /*
-            println!(
-                "[initproc] Released a zombie process, pid={}, exit_code={}",
-                pid,
-                exit_code,
-            );
-            */
         }
     }
     0
 }
+
+/// 由 `runtests` 运行 `/tests` 下的全部测例，结果写到 `/oscomp-results.json`
+fn run_tests() {
+    match fork() {
+        Ok(0) => {
+            let err = exec("/runtests", &["/runtests"]);
+            panic!("exec /runtests failed: {}", err);
+        }
+        Ok(pid) => {
+            let _ = waitpid(pid);
+        }
+        Err(err) => println!("[initproc] fork failed: {}", err),
+    }
+}
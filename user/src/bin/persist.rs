@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use user::{close, open, println, read_to_end, write_all, OpenFlags};
+
+/// 检查关机后文件内容是否保留在磁盘上：
+/// 第一次启动运行 `persist write` 后执行 `poweroff`，
+/// 第二次启动运行 `persist check`，内容与长度都一致时返回 0。
+const PATH: &str = "persist.txt";
+/// 跨越多个页且不按页对齐，覆盖页缓存末尾的部分页
+const LEN: usize = 3 * 4096 + 123;
+
+fn pattern(i: usize) -> u8 {
+    (i * 7 + i / 251) as u8
+}
+
+fn write_file() -> i32 {
+    let fd = match open(
+        PATH,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    ) {
+        Ok(fd) => fd,
+        Err(err) => {
+            println!("persist: cannot create persist.txt: {}", err);
+            return -1;
+        }
+    };
+    let mut buf = [0u8; 512];
+    let mut written = 0;
+    while written < LEN {
+        let n = buf.len().min(LEN - written);
+        for (j, byte) in buf[..n].iter_mut().enumerate() {
+            *byte = pattern(written + j);
+        }
+        if let Err(err) = write_all(fd, &buf[..n]) {
+            println!("persist: write failed at {}: {}", written, err);
+            let _ = close(fd);
+            return -1;
+        }
+        written += n;
+    }
+    let _ = close(fd);
+    println!("persist: wrote {} bytes, now run poweroff", LEN);
+    0
+}
+
+fn check_file() -> i32 {
+    let fd = match open(PATH, OpenFlags::RDONLY) {
+        Ok(fd) => fd,
+        Err(err) => {
+            println!("persist: cannot open persist.txt: {}", err);
+            return -1;
+        }
+    };
+    let data = read_to_end(fd);
+    let _ = close(fd);
+    let data = match data {
+        Ok(data) => data,
+        Err(err) => {
+            println!("persist: read failed: {}", err);
+            return -1;
+        }
+    };
+    if let Some(offset) = (0..data.len()).find(|&i| data[i] != pattern(i)) {
+        println!("persist: mismatch at offset {}", offset);
+        return -1;
+    }
+    if data.len() != LEN {
+        println!("persist: expected {} bytes, found {}", LEN, data.len());
+        return -1;
+    }
+    println!("persist: ok");
+    0
+}
+
+#[no_mangle]
+fn main(_argc: usize, argv: &[&str]) -> i32 {
+    match argv.get(1).copied() {
+        Some("write") => write_file(),
+        Some("check") => check_file(),
+        _ => {
+            println!("usage: persist write|check");
+            -1
+        }
+    }
+}
@@ -0,0 +1,14 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use user::{println, reboot, RB_POWER_OFF};
+
+/// 卸载文件系统后关机
+#[no_mangle]
+fn main() -> i32 {
+    let err = reboot(RB_POWER_OFF);
+    println!("poweroff failed: {}", err);
+    -1
+}
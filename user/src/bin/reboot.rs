@@ -0,0 +1,14 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use user::{println, reboot, RB_AUTOBOOT};
+
+/// 卸载文件系统后重启
+#[no_mangle]
+fn main() -> i32 {
+    let err = reboot(RB_AUTOBOOT);
+    println!("reboot failed: {}", err);
+    -1
+}
@@ -0,0 +1,443 @@
+#![no_std]
+#![no_main]
+
+//! oscomp 测例运行器
+//!
+//! 用法：`runtests [-t 秒] [-o 结果文件] [-l 输出日志] [-q] [测例目录]`
+//!
+//! - 在测例目录（默认 `/tests`）中找出所有 ELF 文件，按名字排序后逐个运行，
+//!   运行时的当前目录就是测例目录，测例依赖的 `text.txt`、`test_echo` 等都放在这里
+//! - 测例的 stdout / stderr 经管道读回，原样回显到控制台并追加到输出日志，
+//!   日志格式与 `run-all.sh` 的串口输出一致，可以交给 oscomp 的 `test_runner.py` 评分
+//! - 超过时限的测例被 `SIGKILL` 杀死
+//! - 根据 `========== START / END ==========` 标记、断言失败与含 fail / success 的行
+//!   判定结果，最后打印汇总表并把结果写成 JSON
+
+extern crate alloc;
+#[macro_use]
+extern crate user;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use user::time::Duration;
+use user::{
+    close, dup2, exec, exit, fcntl, fork, kill, open, pipe, read, read_dir, waitpid,
+    waitpid_nohang, write_all, yield_, ExitStatus, FileType, Instant, OpenFlags, Result, F_SETFL,
+    SIGKILL,
+};
+
+const DEFAULT_DIR: &str = "/tests";
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_RESULT_FILE: &str = "/oscomp-results.json";
+const DEFAULT_LOG_FILE: &str = "/oscomp-output.txt";
+/// 被其它测例调用的辅助程序，不单独运行
+const HELPERS: [&str; 1] = ["test_echo"];
+
+const START_MARKER: &str = "========== START ";
+const END_MARKER: &str = "========== END ";
+/// 测例库 `assert` 失败时 panic 打印的信息
+const ASSERT_FATAL: &str = "Assert Fatal";
+
+struct Options {
+    dir: String,
+    timeout: Duration,
+    result_file: String,
+    log_file: String,
+    quiet: bool,
+}
+
+fn usage() -> ! {
+    eprintln!("usage: runtests [-t secs] [-o result-file] [-l log-file] [-q] [dir]");
+    exit(2);
+}
+
+fn parse_args(argv: &[&str]) -> Options {
+    let mut opts = Options {
+        dir: DEFAULT_DIR.to_string(),
+        timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+        result_file: DEFAULT_RESULT_FILE.to_string(),
+        log_file: DEFAULT_LOG_FILE.to_string(),
+        quiet: false,
+    };
+    let mut args = argv.iter().skip(1);
+    while let Some(&arg) = args.next() {
+        match arg {
+            "-t" => match args.next().and_then(|secs| secs.parse().ok()) {
+                Some(secs) => opts.timeout = Duration::from_secs(secs),
+                None => usage(),
+            },
+            "-o" => opts.result_file = args.next().unwrap_or_else(|| usage()).to_string(),
+            "-l" => opts.log_file = args.next().unwrap_or_else(|| usage()).to_string(),
+            "-q" => opts.quiet = true,
+            _ if arg.starts_with('-') => usage(),
+            _ => opts.dir = arg.to_string(),
+        }
+    }
+    opts
+}
+
+/// 一个测例的结果
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    /// 标记完整、正常退出且没有失败行
+    Pass,
+    /// 输出了失败行或断言失败
+    Fail,
+    /// 只有 START 没有 END，或者完全没有标记
+    Incomplete,
+    /// 以非零退出码退出或被信号杀死
+    Crashed,
+    /// 超时被杀死
+    TimedOut,
+    /// 无法启动
+    NotRun,
+}
+
+impl Outcome {
+    fn label(self) -> &'static str {
+        match self {
+            Outcome::Pass => "pass",
+            Outcome::Fail => "fail",
+            Outcome::Incomplete => "incomplete",
+            Outcome::Crashed => "crashed",
+            Outcome::TimedOut => "timeout",
+            Outcome::NotRun => "not-run",
+        }
+    }
+}
+
+/// 逐行解析测例输出
+#[derive(Default)]
+struct OutputParser {
+    partial: Vec<u8>,
+    /// START 标记中的测例名，例如 `test_brk`
+    marker: Option<String>,
+    ended: bool,
+    pass_lines: usize,
+    fail_lines: usize,
+    assert_failed: bool,
+}
+
+impl OutputParser {
+    fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if byte == b'\n' {
+                let line = core::mem::take(&mut self.partial);
+                self.line(&String::from_utf8_lossy(&line));
+            } else {
+                self.partial.push(byte);
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if !self.partial.is_empty() {
+            let line = core::mem::take(&mut self.partial);
+            self.line(&String::from_utf8_lossy(&line));
+        }
+    }
+
+    fn line(&mut self, line: &str) {
+        if let Some(rest) = line.trim_start().strip_prefix(START_MARKER) {
+            self.marker = Some(rest.trim_end_matches(|c| c == '=' || c == ' ').to_string());
+            self.ended = false;
+            return;
+        }
+        if line.trim_start().starts_with(END_MARKER) {
+            self.ended = true;
+            return;
+        }
+        if line.contains(ASSERT_FATAL) {
+            self.assert_failed = true;
+        }
+        let lower = line.to_ascii_lowercase();
+        if lower.contains("fail") || lower.contains("error") {
+            self.fail_lines += 1;
+        } else if lower.contains("success") || lower.contains("pass") {
+            self.pass_lines += 1;
+        }
+    }
+}
+
+struct TestResult {
+    name: String,
+    outcome: Outcome,
+    status: Option<ExitStatus>,
+    elapsed: Duration,
+    parser: OutputParser,
+}
+
+impl TestResult {
+    fn not_run(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            outcome: Outcome::NotRun,
+            status: None,
+            elapsed: Duration::ZERO,
+            parser: OutputParser::default(),
+        }
+    }
+
+    fn judge(&mut self, timed_out: bool) {
+        let parser = &self.parser;
+        self.outcome = if timed_out {
+            Outcome::TimedOut
+        } else if parser.assert_failed || parser.fail_lines > 0 {
+            Outcome::Fail
+        } else if !self.status.map_or(false, |status| status.success()) {
+            Outcome::Crashed
+        } else if parser.marker.is_none() || !parser.ended {
+            Outcome::Incomplete
+        } else {
+            Outcome::Pass
+        };
+    }
+}
+
+/// 文件开头是否为 ELF 魔数
+fn is_elf(path: &str) -> bool {
+    let Ok(fd) = open(path, OpenFlags::RDONLY) else {
+        return false;
+    };
+    let mut magic = [0u8; 4];
+    let n = read(fd, &mut magic);
+    let _ = close(fd);
+    n == Ok(4) && magic == *b"\x7fELF"
+}
+
+/// 找出目录中的测例
+fn discover(dir: &str) -> Result<Vec<String>> {
+    let mut tests = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type != FileType::Regular
+            || entry.name.starts_with('.')
+            || HELPERS.contains(&entry.name.as_str())
+        {
+            continue;
+        }
+        if is_elf(&format!("{}/{}", dir, entry.name)) {
+            tests.push(entry.name);
+        }
+    }
+    tests.sort();
+    Ok(tests)
+}
+
+/// 运行一个测例，输出回显到控制台并追加到 `log_fd`
+fn run_test(name: &str, opts: &Options, log_fd: Option<usize>) -> TestResult {
+    let mut result = TestResult::not_run(name);
+    let Ok((read_end, write_end)) = pipe() else {
+        return result;
+    };
+    let pid = match fork() {
+        Ok(0) => {
+            let _ = close(read_end);
+            let _ = dup2(write_end, 1);
+            let _ = dup2(write_end, 2);
+            let _ = close(write_end);
+            let path = format!("./{}", name);
+            let err = exec(&path, &[&path]);
+            eprintln!("runtests: cannot execute {}: {}", name, err);
+            exit(127);
+        }
+        Ok(pid) => pid,
+        Err(_) => {
+            let _ = close(read_end);
+            let _ = close(write_end);
+            return result;
+        }
+    };
+    let _ = close(write_end);
+    // 只把读端设为非阻塞，测例写满管道时仍然会等待
+    let _ = fcntl(read_end, F_SETFL, OpenFlags::NONBLOCK.bits() as usize);
+
+    let header = format!("Testing {} :\n", name);
+    if let Some(fd) = log_fd {
+        let _ = write_all(fd, header.as_bytes());
+    }
+    let start = Instant::now();
+    let mut buf = [0u8; 256];
+    let mut timed_out = false;
+    let mut drain = |result: &mut TestResult| loop {
+        match read(read_end, &mut buf) {
+            Ok(n) if n > 0 => {
+                let bytes = &buf[..n];
+                result.parser.feed(bytes);
+                if !opts.quiet {
+                    let _ = write_all(1, bytes);
+                }
+                if let Some(fd) = log_fd {
+                    let _ = write_all(fd, bytes);
+                }
+            }
+            _ => break,
+        }
+    };
+    loop {
+        drain(&mut result);
+        match waitpid_nohang(pid as isize) {
+            Ok(Some((_, status))) => {
+                result.status = Some(status);
+                break;
+            }
+            Ok(None) => {}
+            Err(_) => break,
+        }
+        if start.elapsed() > opts.timeout {
+            timed_out = true;
+            let _ = kill(pid, SIGKILL);
+            result.status = waitpid(pid).ok();
+            break;
+        }
+        yield_();
+    }
+    // 子进程退出前写入的数据可能还留在管道里
+    drain(&mut result);
+    let _ = close(read_end);
+
+    result.elapsed = start.elapsed();
+    result.parser.finish();
+    result.judge(timed_out);
+    result
+}
+
+fn status_text(status: Option<ExitStatus>) -> String {
+    match status {
+        Some(status) => match (status.code(), status.signal()) {
+            (Some(code), _) => format!("{}", code),
+            (_, Some(signal)) => format!("sig{}", signal),
+            _ => "-".to_string(),
+        },
+        None => "-".to_string(),
+    }
+}
+
+fn print_summary(results: &[TestResult]) {
+    println!("");
+    println!(
+        "{:<16} {:<11} {:>6} {:>9}",
+        "TEST", "RESULT", "EXIT", "TIME(ms)"
+    );
+    for result in results {
+        println!(
+            "{:<16} {:<11} {:>6} {:>9}",
+            result.name,
+            result.outcome.label(),
+            status_text(result.status),
+            result.elapsed.as_millis()
+        );
+    }
+    let passed = passed(results);
+    println!("passed {}/{}", passed, results.len());
+}
+
+fn passed(results: &[TestResult]) -> usize {
+    results
+        .iter()
+        .filter(|result| result.outcome == Outcome::Pass)
+        .count()
+}
+
+/// JSON 字符串字面量
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_option<T: core::fmt::Display>(value: Option<T>) -> String {
+    value.map_or("null".to_string(), |value| format!("{}", value))
+}
+
+fn write_results(path: &str, results: &[TestResult]) -> Result<()> {
+    let fd = open(
+        path,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    )?;
+    let mut json = format!(
+        "{{\"total\":{},\"passed\":{},\"tests\":[\n",
+        results.len(),
+        passed(results)
+    );
+    for (i, result) in results.iter().enumerate() {
+        let parser = &result.parser;
+        json.push_str(&format!(
+            "{{\"name\":{},\"result\":\"{}\",\"exit_code\":{},\"signal\":{},\"elapsed_ms\":{},\
+             \"marker\":{},\"pass_lines\":{},\"fail_lines\":{}}}{}\n",
+            json_string(&result.name),
+            result.outcome.label(),
+            json_option(result.status.and_then(|status| status.code())),
+            json_option(result.status.and_then(|status| status.signal())),
+            result.elapsed.as_millis(),
+            parser
+                .marker
+                .as_deref()
+                .map_or("null".to_string(), json_string),
+            parser.pass_lines,
+            parser.fail_lines,
+            if i + 1 < results.len() { "," } else { "" }
+        ));
+        // 结果逐条写出，避免在小堆上拼出整个文件
+        write_all(fd, json.as_bytes())?;
+        json.clear();
+    }
+    json.push_str("]}\n");
+    write_all(fd, json.as_bytes())?;
+    close(fd)
+}
+
+#[no_mangle]
+fn main(_argc: usize, argv: &[&str]) -> i32 {
+    let opts = parse_args(argv);
+    let tests = match discover(&opts.dir) {
+        Ok(tests) => tests,
+        Err(err) => {
+            eprintln!("runtests: cannot read {}: {}", opts.dir, err);
+            return 1;
+        }
+    };
+    if let Err(err) = user::chdir(&opts.dir) {
+        eprintln!("runtests: cannot enter {}: {}", opts.dir, err);
+        return 1;
+    }
+    let log_fd = match open(
+        &opts.log_file,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    ) {
+        Ok(fd) => Some(fd),
+        Err(err) => {
+            eprintln!("runtests: cannot create {}: {}", opts.log_file, err);
+            None
+        }
+    };
+
+    println!("runtests: {} tests in {}", tests.len(), opts.dir);
+    let results: Vec<TestResult> = tests
+        .iter()
+        .map(|name| run_test(name, &opts, log_fd))
+        .collect();
+    if let Some(fd) = log_fd {
+        let _ = close(fd);
+    }
+
+    print_summary(&results);
+    if let Err(err) = write_results(&opts.result_file, &results) {
+        eprintln!("runtests: cannot write {}: {}", opts.result_file, err);
+    }
+    if passed(&results) == results.len() {
+        0
+    } else {
+        1
+    }
+}
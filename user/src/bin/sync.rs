@@ -0,0 +1,13 @@
+#![no_std]
+#![no_main]
+
+extern crate user;
+
+use user::sync;
+
+/// 把所有文件的脏数据写回磁盘
+#[no_mangle]
+fn main() -> i32 {
+    sync();
+    0
+}
@@ -2,181 +2,642 @@
 #![no_main]
 
 extern crate alloc;
+#[macro_use]
 extern crate user;
 
 use alloc::format;
-use alloc::string::String;
+use alloc::string::{String, ToString};
+use alloc::vec;
 use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
 
 use user::console::getchar;
-use user::{close, dup, exec, fork, open, pipe, println, print, waitpid, OpenFlags};
+use user::{
+    chdir, close, dup, dup2, environ, execve, exit, fork, getcwd, getpgid, open, pipe, setpgid,
+    tcsetpgrp, waitpid, waitpid_nohang, Errno, OpenFlags,
+};
 
+/// 交互式 shell：
+/// - 管道 `|`（任意长度）、重定向 `<`、`>`、`>>`、`2>`、`2>>`
+/// - 行尾 `&` 放到后台运行，`jobs`/`fg`/`bg` 管理作业
+/// - 单引号、双引号与反斜杠转义，`$VAR`、`${VAR}`、`$?` 展开
+/// - 内建命令 `cd`、`pwd`、`exit`、`echo`、`export`、`unset`、`jobs`、`fg`、`bg`
+/// - 不含 `/` 的命令名按 `PATH` 查找，变量全部导出给子进程
+///
+/// 内核还不能停止进程（`^Z` 被忽略），所以作业总在运行，`bg` 只用于确认
+///
+/// 内核也不支持忽略信号，shell 因此自成一个进程组，在提示符下把终端的前台交还给
+/// init 所在的进程组：init 不接收终端产生的信号，`^C` 只清空当前输入
 const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
 
-/// 存储单个进程的参数和重定向信息
+/// 没有 `PATH` 时使用的查找路径
+const DEFAULT_PATH: &str = "/";
+
+/// 词法单元
+enum Token {
+    Word(String),
+    Pipe,
+    Background,
+    Input,
+    Output,
+    Append,
+    ErrOutput,
+    ErrAppend,
+}
+
+/// 管道中的一条命令及其重定向
+#[derive(Default)]
 struct Command {
-    args_copy: Vec<String>, // 拥有所有权，防止悬垂指针
-    args_addr: Vec<*const u8>,  // 传递给内核的指针数组
-    input: String,
-    output: String,
+    args: Vec<String>,
+    input: Option<String>,
+    /// 标准输出重定向的文件与是否追加
+    output: Option<(String, bool)>,
+    /// 标准错误重定向的文件与是否追加
+    error: Option<(String, bool)>,
 }
 
-impl Command {
-    pub fn new(cmd_str: &str) -> Self {
-        let mut args: Vec<String> = cmd_str
-            .split_whitespace()
-            .map(|arg| format!("{}\0", arg))
-            .collect();
+/// 一行命令：若干以管道相连的命令
+struct Pipeline {
+    commands: Vec<Command>,
+    background: bool,
+}
 
-        let mut input = String::new();
-        let output = String::new();
+/// 后台作业
+struct Job {
+    id: usize,
+    pgid: usize,
+    /// 尚未回收的进程
+    pids: Vec<usize>,
+    cmdline: String,
+}
+
+struct Shell {
+    /// shell 变量，全部作为环境变量传给子进程
+    vars: Vec<(String, String)>,
+    jobs: Vec<Job>,
+    /// 上一条前台命令的退出状态（`$?`）
+    status: i32,
+    /// 提示符下终端的前台进程组，即启动时所在的进程组
+    idle_pgrp: usize,
+}
 
-        // 提取并移除输入重定向 <
-        if let Some(i) = args.iter().position(|arg| arg == "<\0") {
-            if i + 1 < args.len() {
-                input = args[i + 1].clone();
-                args.drain(i..=i + 1);
+impl Shell {
+    fn new() -> Self {
+        let mut shell = Self {
+            vars: Vec::new(),
+            jobs: Vec::new(),
+            status: 0,
+            idle_pgrp: getpgid(0).unwrap_or(0),
+        };
+        let _ = setpgid(0, 0);
+        let _ = tcsetpgrp(0, shell.idle_pgrp);
+        for env in environ() {
+            if let Some((name, value)) = env.split_once('=') {
+                shell.set_var(name, value);
             }
         }
-
-        // 提取并移除输出重定向 >
-        let mut args_addr: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
-        args_addr.push(core::ptr::null());
-
-        Self {
-            args_copy: args,
-            args_addr,
-            input,
-            output,
+        if shell.var("PATH").is_none() {
+            shell.set_var("PATH", DEFAULT_PATH);
         }
+        shell
     }
-}
 
-/// 将文件描述符重定向到标准输入/输出
-fn redirect(old_fd: usize, new_fd: usize) {
-    close(new_fd);
-    dup(old_fd);
-    close(old_fd);
-}
+    fn var(&self, name: &str) -> Option<&str> {
+        self.vars
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
 
-/// 处理文件重定向逻辑
-fn handle_file_redirection(input: &str, output: &str) {
-    if !input.is_empty() {
-        // 去掉末尾的 \0 进行 open 调用
-        let path = &input[..input.len() - 1];
-        let fd = open(path, OpenFlags::RDONLY);
-        if fd != -1 {
-            redirect(fd as usize, 0);
+    fn set_var(&mut self, name: &str, value: &str) {
+        match self.vars.iter_mut().find(|(key, _)| key == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.vars.push((name.to_string(), value.to_string())),
         }
     }
-    if !output.is_empty() {
-        let path = &output[..output.len() - 1];
-        let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
-        if fd != -1 {
-            redirect(fd as usize, 1);
+
+    /// 展开 `$` 之后的变量名，结果追加到 `word`
+    fn expand(&self, chars: &mut Peekable<Chars>, word: &mut String) {
+        let mut name = String::new();
+        match chars.peek() {
+            Some('?') => {
+                chars.next();
+                word.push_str(&format!("{}", self.status));
+                return;
+            }
+            Some('{') => {
+                chars.next();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                    name.push(c);
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+            }
+        }
+        if name.is_empty() {
+            word.push('$');
+        } else {
+            word.push_str(self.var(&name).unwrap_or(""));
         }
     }
-}
-
-
-#[no_mangle]
-fn main() -> i32{
-    println!("Rust Shell Initialized.");
-    let mut line = String::new();
-
-    loop {
-        print!(">> ");
-        line.clear();
 
-        loop {
-            let c = getchar();
+    /// 切分一行命令并展开变量，引号内的内容不会被切分
+    fn tokenize(&self, line: &str) -> Result<Vec<Token>, &'static str> {
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        // 区分空字符串 `""` 与没有单词
+        let mut in_word = false;
+        let mut chars = line.chars().peekable();
+        let flush = |tokens: &mut Vec<Token>, word: &mut String, in_word: &mut bool| {
+            if *in_word {
+                tokens.push(Token::Word(core::mem::take(word)));
+                *in_word = false;
+            }
+        };
+        while let Some(c) = chars.next() {
             match c {
-               CR | LF => {
-                   println!(" ");
-                   break;
-               }
-                BS | DL => {
-                    if !line.is_empty() {
-                        print!("{}", BS as char);
-                        print!(" ");
-                        print!("{}", BS as char);
-                        line.pop();
+                ' ' | '\t' | '\r' => flush(&mut tokens, &mut word, &mut in_word),
+                '\'' => {
+                    in_word = true;
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => word.push(c),
+                            None => return Err("unterminated quote"),
+                        }
+                    }
+                }
+                '"' => {
+                    in_word = true;
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('$') => self.expand(&mut chars, &mut word),
+                            Some('\\') => match chars.next() {
+                                Some(c) => word.push(c),
+                                None => return Err("unterminated quote"),
+                            },
+                            Some(c) => word.push(c),
+                            None => return Err("unterminated quote"),
+                        }
+                    }
+                }
+                '\\' => {
+                    in_word = true;
+                    if let Some(c) = chars.next() {
+                        word.push(c);
                     }
                 }
+                '$' => {
+                    in_word = true;
+                    self.expand(&mut chars, &mut word);
+                }
+                '|' | '&' | '<' | '>' => {
+                    // 紧贴 `>` 的单独一个 2 表示标准错误
+                    let stderr = c == '>' && in_word && word == "2";
+                    if stderr {
+                        word.clear();
+                        in_word = false;
+                    } else {
+                        flush(&mut tokens, &mut word, &mut in_word);
+                    }
+                    let append = c == '>' && chars.peek() == Some(&'>');
+                    if append {
+                        chars.next();
+                    }
+                    tokens.push(match (c, stderr, append) {
+                        ('|', _, _) => Token::Pipe,
+                        ('&', _, _) => Token::Background,
+                        ('<', _, _) => Token::Input,
+                        (_, false, false) => Token::Output,
+                        (_, false, true) => Token::Append,
+                        (_, true, false) => Token::ErrOutput,
+                        (_, true, true) => Token::ErrAppend,
+                    });
+                }
                 _ => {
-                    print!("{}", c as char);
-                    line.push(c as char);
+                    in_word = true;
+                    word.push(c);
                 }
             }
         }
+        flush(&mut tokens, &mut word, &mut in_word);
+        Ok(tokens)
+    }
 
-        if line.is_empty() { continue; }
-
-        // 解析管道命令
-        let cmd_parts: Vec<&str> = line.split('|').collect();
-        let commands: Vec<Command> = cmd_parts.iter().map(|&s| Command::new(s)).collect();
-        let num_cmds = commands.len();
-
-        // 创建 N-1 个管道
-        let pipes: Vec<[usize; 2]> = (0..num_cmds.saturating_sub(1))
-            .map(|_| {
-                let mut fd = [0usize; 2];
-                pipe(&mut fd);
-                fd
+    /// 按 `PATH` 列出命令可能的位置，含 `/` 的命令名原样使用
+    fn command_paths(&self, name: &str) -> Vec<String> {
+        if name.contains('/') {
+            return vec![name.to_string()];
+        }
+        self.var("PATH")
+            .unwrap_or(DEFAULT_PATH)
+            .split(':')
+            .map(|dir| match dir {
+                "" => format!("./{}", name),
+                _ => format!("{}/{}", dir.trim_end_matches('/'), name),
             })
+            .collect()
+    }
+
+    /// 在当前进程中执行外部命令，只在失败时返回退出状态
+    fn exec_external(&self, args: &[String]) -> i32 {
+        let argv: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+        let envs: Vec<String> = self
+            .vars
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
             .collect();
+        let envp: Vec<&str> = envs.iter().map(|env| env.as_str()).collect();
+        for path in self.command_paths(&args[0]) {
+            let err = execve(&path, &argv, &envp);
+            if err != Errno::ENOENT {
+                eprintln!("{}: cannot execute: {}", path, err);
+                return 126;
+            }
+        }
+        eprintln!("{}: command not found", args[0]);
+        127
+    }
 
-        let mut children = Vec::new();
+    fn find_job(&self, arg: Option<&String>) -> Option<usize> {
+        match arg {
+            None => self.jobs.len().checked_sub(1),
+            Some(arg) => {
+                let id: usize = arg.trim_start_matches('%').parse().ok()?;
+                self.jobs.iter().position(|job| job.id == id)
+            }
+        }
+    }
 
+    /// 执行内建命令，返回退出状态
+    fn run_builtin(&mut self, args: &[String]) -> i32 {
+        match args[0].as_str() {
+            "echo" => {
+                let (newline, words) = match args.get(1).map(|arg| arg.as_str()) {
+                    Some("-n") => (false, &args[2..]),
+                    _ => (true, &args[1..]),
+                };
+                print!("{}", words.join(" "));
+                if newline {
+                    println!("");
+                }
+                0
+            }
+            "pwd" => match getcwd() {
+                Ok(cwd) => {
+                    println!("{}", cwd);
+                    0
+                }
+                Err(err) => {
+                    eprintln!("pwd: {}", err);
+                    1
+                }
+            },
+            "cd" => {
+                let target = match args.get(1) {
+                    Some(dir) => dir.clone(),
+                    None => self.var("HOME").unwrap_or("/").to_string(),
+                };
+                if let Err(err) = chdir(&target) {
+                    eprintln!("cd: {}: {}", target, err);
+                    return 1;
+                }
+                0
+            }
+            "exit" => {
+                let code = match args.get(1) {
+                    Some(code) => code.parse().unwrap_or(2),
+                    None => self.status,
+                };
+                exit(code);
+            }
+            "export" => {
+                if args.len() == 1 {
+                    for (name, value) in self.vars.iter() {
+                        println!("export {}={}", name, value);
+                    }
+                }
+                for arg in &args[1..] {
+                    let (name, value) = arg.split_once('=').unwrap_or((arg, ""));
+                    if !valid_name(name) {
+                        eprintln!("export: `{}': not a valid identifier", arg);
+                        return 1;
+                    }
+                    // 没有值的 `export NAME` 不覆盖已有的值
+                    if arg.contains('=') || self.var(name).is_none() {
+                        self.set_var(name, value);
+                    }
+                }
+                0
+            }
+            "unset" => {
+                self.vars.retain(|(name, _)| !args[1..].contains(name));
+                0
+            }
+            "jobs" => {
+                self.reap_jobs();
+                for job in self.jobs.iter() {
+                    println!("[{}]  Running  {}", job.id, job.cmdline);
+                }
+                0
+            }
+            "fg" => {
+                let Some(index) = self.find_job(args.get(1)) else {
+                    eprintln!("fg: no such job");
+                    return 1;
+                };
+                let job = self.jobs.remove(index);
+                println!("{}", job.cmdline);
+                let _ = tcsetpgrp(0, job.pgid);
+                let status = wait_all(&job.pids);
+                let _ = tcsetpgrp(0, self.idle_pgrp);
+                status
+            }
+            "bg" => {
+                let Some(index) = self.find_job(args.get(1)) else {
+                    eprintln!("bg: no such job");
+                    return 1;
+                };
+                // 内核不会停止进程，后台作业一直在运行
+                println!("bg: job {} already in background", self.jobs[index].id);
+                0
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// 回收已经结束的后台作业
+    fn reap_jobs(&mut self) {
+        for job in self.jobs.iter_mut() {
+            job.pids
+                .retain(|&pid| matches!(waitpid_nohang(pid as isize), Ok(None)));
+            if job.pids.is_empty() {
+                println!("[{}]  Done     {}", job.id, job.cmdline);
+            }
+        }
+        self.jobs.retain(|job| !job.pids.is_empty());
+    }
+
+    /// 执行一行命令
+    fn run_line(&mut self, line: &str) {
+        let pipeline = match self.tokenize(line).and_then(parse) {
+            Ok(Some(pipeline)) => pipeline,
+            Ok(None) => return,
+            Err(msg) => {
+                eprintln!("syntax error: {}", msg);
+                self.status = 2;
+                return;
+            }
+        };
+        let commands = &pipeline.commands;
 
+        // 单独的前台内建命令在 shell 自身中执行，cd、export 等才能生效
+        if commands.len() == 1 && !pipeline.background && is_builtin(&commands[0].args[0]) {
+            let saved = [dup(0), dup(1), dup(2)];
+            self.status = if apply_redirections(&commands[0]) {
+                self.run_builtin(&commands[0].args)
+            } else {
+                1
+            };
+            for (fd, saved) in saved.into_iter().enumerate() {
+                if let Ok(saved) = saved {
+                    let _ = dup2(saved, fd);
+                    let _ = close(saved);
+                }
+            }
+            return;
+        }
 
+        // 创建 N-1 个管道
+        let pipes: Vec<(usize, usize)> = match (0..commands.len() - 1).map(|_| pipe()).collect() {
+            Ok(pipes) => pipes,
+            Err(err) => {
+                eprintln!("pipe: {}", err);
+                self.status = 1;
+                return;
+            }
+        };
+
+        let mut children: Vec<usize> = Vec::new();
         for (i, cmd) in commands.iter().enumerate() {
-            let pid = fork();
+            // 整条管道放在以第一个命令为组长的进程组中
+            let pgid = children.first().copied().unwrap_or(0);
+            let pid = match fork() {
+                Ok(pid) => pid,
+                Err(err) => {
+                    eprintln!("fork: {}", err);
+                    break;
+                }
+            };
             if pid == 0 {
-                // 子进程逻辑
-
-                // 1. 处理管道连接
+                let _ = setpgid(0, pgid);
+                // 不是第一个命令，从上一个管道读；不是最后一个命令，向当前管道写
                 if i > 0 {
-                    // 不是第一个命令，从上一个管道读
-                    redirect(pipes[i - 1][0], 0);
+                    let _ = dup2(pipes[i - 1].0, 0);
+                }
+                if i < commands.len() - 1 {
+                    let _ = dup2(pipes[i].1, 1);
+                }
+                // 必须关闭子进程继承的所有管道 FD，否则读端会阻塞
+                for &(read_end, write_end) in &pipes {
+                    let _ = close(read_end);
+                    let _ = close(write_end);
                 }
-                if i < num_cmds - 1 {
-                    // 不是最后一个命令，向当前管道写
-                    redirect(pipes[i][1], 1);
+                if !apply_redirections(cmd) {
+                    exit(1);
                 }
+                if is_builtin(&cmd.args[0]) {
+                    exit(self.run_builtin(&cmd.args));
+                }
+                exit(self.exec_external(&cmd.args));
+            }
+            // 父子进程都设置一次，避免子进程还没运行时组就被使用
+            let _ = setpgid(pid, if pgid == 0 { pid } else { pgid });
+            children.push(pid);
+        }
 
-                // 2. 处理文件重定向
-                handle_file_redirection(&cmd.input, &cmd.output);
+        // 父进程关闭所有管道 FD
+        for (read_end, write_end) in pipes {
+            let _ = close(read_end);
+            let _ = close(write_end);
+        }
+        let Some(&pgid) = children.first() else {
+            return;
+        };
 
-                // 3. 必须关闭子进程继承的所有管道 FD，否则读端会阻塞
-                for p in &pipes {
-                    close(p[0]);
-                    close(p[1]);
-                }
+        if pipeline.background {
+            let id = self.jobs.last().map_or(1, |job| job.id + 1);
+            println!("[{}] {}", id, pgid);
+            self.jobs.push(Job {
+                id,
+                pgid,
+                pids: children,
+                cmdline: line.trim().trim_end_matches('&').trim_end().to_string(),
+            });
+            return;
+        }
 
-                // 4. 执行
-                if exec(cmd.args_copy[0].as_str(), cmd.args_addr.as_slice()) == -1 {
-                    println!("Exec failed: {}", cmd.args_copy[0]);
+        // 让管道成为前台进程组，^C 只发给它；结束后收回前台，
+        // 以免终端一直指向已经不存在、组号可能被复用的进程组
+        let _ = tcsetpgrp(0, pgid);
+        self.status = wait_all(&children);
+        let _ = tcsetpgrp(0, self.idle_pgrp);
+    }
+}
+
+/// 把词法单元组织成管道，空行返回 `None`
+fn parse(tokens: Vec<Token>) -> Result<Option<Pipeline>, &'static str> {
+    let mut commands = vec![Command::default()];
+    let mut background = false;
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        if background {
+            return Err("`&` must end the command line");
+        }
+        let cmd = commands.last_mut().unwrap();
+        match token {
+            Token::Word(word) => cmd.args.push(word),
+            Token::Pipe => {
+                if cmd.args.is_empty() {
+                    return Err("empty command in pipeline");
                 }
-                unreachable!();
-            } else {
-                children.push(pid);
+                commands.push(Command::default());
             }
+            Token::Background => background = true,
+            redirection => {
+                let Some(Token::Word(file)) = tokens.next() else {
+                    return Err("missing file name after redirection");
+                };
+                match redirection {
+                    Token::Input => cmd.input = Some(file),
+                    Token::Output => cmd.output = Some((file, false)),
+                    Token::Append => cmd.output = Some((file, true)),
+                    Token::ErrOutput => cmd.error = Some((file, false)),
+                    Token::ErrAppend => cmd.error = Some((file, true)),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+    let last = commands.last().unwrap();
+    if last.args.is_empty() {
+        if commands.len() == 1 && !background && last.input.is_none() && last.output.is_none() {
+            return Ok(None);
         }
+        return Err("empty command");
+    }
+    Ok(Some(Pipeline {
+        commands,
+        background,
+    }))
+}
 
-        // 父进程清理：关闭所有管道 FD
-        for p in pipes {
-            close(p[0]);
-            close(p[1]);
+fn is_builtin(name: &str) -> bool {
+    matches!(
+        name,
+        "cd" | "pwd" | "exit" | "echo" | "export" | "unset" | "jobs" | "fg" | "bg"
+    )
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// 打开 `path` 并放到 `target` 上
+fn redirect_file(path: &str, flags: OpenFlags, target: usize) -> bool {
+    let fd = match open(path, flags) {
+        Ok(fd) => fd,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return false;
         }
+    };
+    if fd != target {
+        let _ = dup2(fd, target);
+        let _ = close(fd);
+    }
+    true
+}
 
-        // 等待所有子进程
-        for pid in children {
-            let mut status = 0i32;
-            waitpid(pid as usize, &mut status);
+/// 处理命令的文件重定向
+fn apply_redirections(cmd: &Command) -> bool {
+    let write_flags = |append: bool| {
+        OpenFlags::CREATE
+            | OpenFlags::WRONLY
+            | if append {
+                OpenFlags::APPEND
+            } else {
+                OpenFlags::TRUNC
+            }
+    };
+    if let Some(path) = &cmd.input {
+        if !redirect_file(path, OpenFlags::RDONLY, 0) {
+            return false;
+        }
+    }
+    if let Some((path, append)) = &cmd.output {
+        if !redirect_file(path, write_flags(*append), 1) {
+            return false;
+        }
+    }
+    if let Some((path, append)) = &cmd.error {
+        if !redirect_file(path, write_flags(*append), 2) {
+            return false;
         }
     }
-}
\ No newline at end of file
+    true
+}
+
+/// 等待给定的进程全部结束，返回最后一个进程的退出状态
+fn wait_all(pids: &[usize]) -> i32 {
+    let mut status = 0;
+    for &pid in pids {
+        // 被信号杀死时按惯例报告 128 + 信号值
+        status = match waitpid(pid) {
+            Ok(exit) => exit
+                .code()
+                .unwrap_or_else(|| 128 + exit.signal().unwrap_or(0)),
+            Err(_) => 127,
+        };
+    }
+    status
+}
+
+#[no_mangle]
+fn main() -> i32 {
+    println!("Rust Shell Initialized.");
+    let mut shell = Shell::new();
+    let mut line = String::new();
+
+    loop {
+        shell.reap_jobs();
+        print!(">> ");
+        line.clear();
+
+        // 回显、退格和回车换行都由内核终端处理，这里只需读到行尾
+        loop {
+            let c = getchar();
+            match c {
+                LF => break,
+                0 => {}
+                _ => line.push(c as char),
+            }
+        }
+
+        shell.run_line(&line);
+    }
+}
@@ -0,0 +1,115 @@
+//! 系统调用错误码
+//!
+//! 内核失败时返回负的错误码，数值与 Linux 通用 ABI 一致（见内核 `syscall/errno.rs`）。
+//! 库里的封装把它们转成 `Result<T, Errno>`；少数内核路径仍返回 `-1`，会显示为 `EPERM`。
+
+use core::fmt;
+
+/// 系统调用失败时的错误码
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Errno(pub i32);
+
+/// 库中所有可能失败的调用的返回类型
+pub type Result<T> = core::result::Result<T, Errno>;
+
+macro_rules! errno_table {
+    ($($name:ident = $value:literal, $desc:literal;)*) => {
+        impl Errno {
+            $(
+                #[doc = $desc]
+                pub const $name: Errno = Errno($value);
+            )*
+
+            /// 错误码的符号名，例如 `ENOENT`
+            pub fn name(self) -> Option<&'static str> {
+                match self.0 {
+                    $($value => Some(stringify!($name)),)*
+                    _ => None,
+                }
+            }
+
+            /// 错误码的说明
+            pub fn description(self) -> &'static str {
+                match self.0 {
+                    $($value => $desc,)*
+                    _ => "未知错误",
+                }
+            }
+        }
+    };
+}
+
+errno_table! {
+    EPERM = 1, "操作不被允许";
+    ENOENT = 2, "文件或目录不存在";
+    ESRCH = 3, "进程不存在";
+    EINTR = 4, "系统调用被信号中断";
+    EIO = 5, "I/O 错误";
+    E2BIG = 7, "参数列表过长";
+    ENOEXEC = 8, "可执行文件格式错误";
+    EBADF = 9, "错误的文件描述符";
+    ECHILD = 10, "没有子进程";
+    EAGAIN = 11, "资源暂时不可用";
+    ENOMEM = 12, "内存不足";
+    EACCES = 13, "权限不足";
+    EFAULT = 14, "错误的地址";
+    EBUSY = 16, "设备或资源忙";
+    EEXIST = 17, "文件已存在";
+    ENODEV = 19, "没有这个设备";
+    ENOTDIR = 20, "不是目录";
+    EISDIR = 21, "是目录";
+    EINVAL = 22, "无效参数";
+    EMFILE = 24, "打开的文件过多";
+    ENOTTY = 25, "不适用于该设备的 ioctl";
+    ESPIPE = 29, "非法 seek";
+    EPIPE = 32, "管道破裂";
+    ERANGE = 34, "结果超出范围";
+    EDEADLK = 35, "会产生死锁";
+    ENAMETOOLONG = 36, "文件名过长";
+    ENOLCK = 37, "没有可用的记录锁";
+    ENOSYS = 38, "系统调用未实现";
+    ELOOP = 40, "符号链接层数过多";
+    ENOTSOCK = 88, "不是套接字";
+    EDESTADDRREQ = 89, "需要目标地址";
+    EMSGSIZE = 90, "消息过长";
+    EPROTOTYPE = 91, "套接字协议类型错误";
+    ENOPROTOOPT = 92, "不支持的协议选项";
+    EPROTONOSUPPORT = 93, "不支持的协议";
+    EOPNOTSUPP = 95, "不支持的操作";
+    EAFNOSUPPORT = 97, "不支持的地址族";
+    EADDRINUSE = 98, "地址已被占用";
+    EADDRNOTAVAIL = 99, "无法分配请求的地址";
+    ENETUNREACH = 101, "网络不可达";
+    ECONNRESET = 104, "连接被对端重置";
+    ENOBUFS = 105, "缓冲区空间不足";
+    EISCONN = 106, "套接字已连接";
+    ENOTCONN = 107, "套接字未连接";
+    ETIMEDOUT = 110, "连接超时";
+    ECONNREFUSED = 111, "连接被拒绝";
+    EALREADY = 114, "操作已在进行中";
+    EINPROGRESS = 115, "操作正在进行";
+}
+
+impl fmt::Debug for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "Errno({})", self.0),
+        }
+    }
+}
+
+impl fmt::Display for Errno {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({:?})", self.description(), self)
+    }
+}
+
+/// 把系统调用的原始返回值转成 `Result`，负值视为错误码
+pub fn check(ret: isize) -> Result<usize> {
+    if ret < 0 {
+        Err(Errno(-ret as i32))
+    } else {
+        Ok(ret as usize)
+    }
+}
@@ -0,0 +1,440 @@
+//! 文件系统调用
+//!
+//! ## Overview
+//! - 文件描述符的打开、读写、复制与关闭，管道与文件锁
+//! - 目录操作：`getcwd` / `chdir` / `mkdir` / `unlink` / `rmdir`，`read_dir` 迭代目录项
+//! - `fstat` 返回 `Stat`，`mount` / `umount` / `sync` 等与内核文件系统交互的调用
+//!
+//! ## Assumptions
+//! - `OpenFlags` 与 `Stat` 的布局与内核 `fs::OpenFlags`、`UserStat` 一致
+//! - getdents64 每次从目录的当前位置继续，返回 0 表示已读完
+//!
+//! ## Behavior
+//! - 所有调用返回 `Result`，失败时为内核给出的 `Errno`
+//! - 文件描述符以 `usize` 表示，由调用方负责 `close`；`DirEntries` 在析构时关闭目录
+
+use crate::c_string;
+use crate::errno::{check, Errno, Result};
+use crate::syscall::*;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+pub use crate::syscall::AT_FDCWD;
+
+/// 终端 ioctl 命令
+pub const TCGETS: usize = 0x5401;
+pub const TCSETS: usize = 0x5402;
+pub const TIOCGPGRP: usize = 0x540F;
+pub const TIOCSPGRP: usize = 0x5410;
+pub const TIOCGWINSZ: usize = 0x5413;
+
+/// fcntl 命令
+pub const F_DUPFD: usize = 0;
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+pub const F_GETFL: usize = 3;
+pub const F_SETFL: usize = 4;
+pub const F_DUPFD_CLOEXEC: usize = 1030;
+/// `F_GETFD` / `F_SETFD` 的标志位
+pub const FD_CLOEXEC: usize = 1;
+
+/// flock 操作
+pub const LOCK_SH: usize = 1;
+pub const LOCK_EX: usize = 2;
+pub const LOCK_NB: usize = 4;
+pub const LOCK_UN: usize = 8;
+
+/// unlinkat 标志：删除目录
+const AT_REMOVEDIR: u32 = 0x200;
+
+/// `Stat::mode` 中的文件类型
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFIFO: u32 = 0o010000;
+
+bitflags! {
+    pub struct OpenFlags: u32 {
+        // 只读
+        const RDONLY = 0;
+        // 只写
+        const WRONLY = 1 << 0;
+        // 读写
+        const RDWR = 1 << 1;
+        // 创建
+        const CREATE = 1 << 6;
+        // 截断（若存在则以可写方式打开，但是长度清空为0）
+        const TRUNC = 1 << 9;
+        // 追加写
+        const APPEND = 1 << 10;
+        // 非阻塞读写
+        const NONBLOCK = 1 << 11;
+        // execve 时自动关闭
+        const CLOEXEC = 1 << 19;
+        // 目录（O_DIRECTORY = 0x0200000）
+        const DIRECTORY = 1 << 21;
+    }
+}
+
+/// 文件状态，布局与内核的 `struct stat` 一致
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    __pad: u64,
+    pub size: i64,
+    pub blksize: u32,
+    __pad2: i32,
+    pub blocks: u64,
+    pub atime_sec: i64,
+    pub atime_nsec: i64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: i64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: i64,
+    __unused: [u32; 2],
+}
+
+impl Stat {
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+}
+
+pub fn open(path: &str, flags: OpenFlags) -> Result<usize> {
+    openat(AT_FDCWD, path, flags, 0o666)
+}
+
+/// 相对 `dirfd` 打开 `path`，`dirfd` 为 `AT_FDCWD` 时相对当前目录
+pub fn openat(dirfd: isize, path: &str, flags: OpenFlags, mode: u32) -> Result<usize> {
+    let path = c_string(path);
+    check(sys_openat(dirfd, path.as_ptr(), flags.bits, mode))
+}
+
+pub fn close(fd: usize) -> Result<()> {
+    check(sys_close(fd)).map(|_| ())
+}
+
+pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize> {
+    check(sys_read(fd, buf))
+}
+
+pub fn write(fd: usize, buf: &[u8]) -> Result<usize> {
+    check(sys_write(fd, buf))
+}
+
+/// 写完整个 `buf`，写入 0 字节视为 `EIO`
+pub fn write_all(fd: usize, mut buf: &[u8]) -> Result<()> {
+    while !buf.is_empty() {
+        match write(fd, buf)? {
+            0 => return Err(Errno::EIO),
+            n => buf = &buf[n..],
+        }
+    }
+    Ok(())
+}
+
+/// 读到文件末尾
+pub fn read_to_end(fd: usize) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        match read(fd, &mut buf)? {
+            0 => return Ok(data),
+            n => data.extend_from_slice(&buf[..n]),
+        }
+    }
+}
+
+pub fn dup(fd: usize) -> Result<usize> {
+    check(sys_dup(fd))
+}
+
+/// 让 `new` 指向 `old` 打开的文件，`new` 原先打开的文件会被关闭
+pub fn dup2(old: usize, new: usize) -> Result<usize> {
+    check(sys_dup3(old, new, 0))
+}
+
+/// 同 `dup2`，`flags` 只接受 `OpenFlags::CLOEXEC`
+pub fn dup3(old: usize, new: usize, flags: OpenFlags) -> Result<usize> {
+    check(sys_dup3(old, new, flags.bits as usize))
+}
+
+/// 创建管道，返回（读端，写端）
+pub fn pipe() -> Result<(usize, usize)> {
+    pipe2(OpenFlags::empty())
+}
+
+/// 同 `pipe`，`flags` 可含 `CLOEXEC` 与 `NONBLOCK`
+pub fn pipe2(flags: OpenFlags) -> Result<(usize, usize)> {
+    let mut fds = [0i32; 2];
+    check(sys_pipe2(fds.as_mut_ptr(), flags.bits))?;
+    Ok((fds[0] as usize, fds[1] as usize))
+}
+
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> Result<usize> {
+    check(sys_fcntl(fd, cmd, arg))
+}
+
+pub fn flock(fd: usize, operation: usize) -> Result<()> {
+    check(sys_flock(fd, operation)).map(|_| ())
+}
+
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> Result<usize> {
+    check(sys_ioctl(fd, cmd, arg))
+}
+
+/// 读取终端的前台进程组
+pub fn tcgetpgrp(fd: usize) -> Result<usize> {
+    let mut pgid = 0i32;
+    ioctl(fd, TIOCGPGRP, &mut pgid as *mut i32 as usize)?;
+    Ok(pgid as usize)
+}
+
+/// 把终端的前台进程组设为 `pgid`，终端产生的信号只发给前台进程组
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> Result<()> {
+    let pgid = pgid as i32;
+    ioctl(fd, TIOCSPGRP, &pgid as *const i32 as usize).map(|_| ())
+}
+
+pub fn fstat(fd: usize) -> Result<Stat> {
+    let mut stat = Stat::default();
+    check(sys_fstat(fd, &mut stat as *mut Stat as *mut u8))?;
+    Ok(stat)
+}
+
+/// 打开 `path` 并读取其状态
+pub fn stat(path: &str) -> Result<Stat> {
+    let fd = open(path, OpenFlags::RDONLY)?;
+    let stat = fstat(fd);
+    close(fd)?;
+    stat
+}
+
+/// 当前工作目录
+pub fn getcwd() -> Result<String> {
+    let mut buf = vec![0u8; 256];
+    loop {
+        match check(sys_getcwd(buf.as_mut_ptr(), buf.len())) {
+            Ok(_) => break,
+            // 缓冲区不够长时加倍重试
+            Err(Errno::ERANGE) if buf.len() < 4096 => buf.resize(buf.len() * 2, 0),
+            Err(err) => return Err(err),
+        }
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    buf.truncate(len);
+    String::from_utf8(buf).map_err(|_| Errno::EINVAL)
+}
+
+pub fn chdir(path: &str) -> Result<()> {
+    let path = c_string(path);
+    check(sys_chdir(path.as_ptr())).map(|_| ())
+}
+
+pub fn mkdir(path: &str, mode: u32) -> Result<()> {
+    mkdirat(AT_FDCWD, path, mode)
+}
+
+pub fn mkdirat(dirfd: isize, path: &str, mode: u32) -> Result<()> {
+    let path = c_string(path);
+    check(sys_mkdirat(dirfd, path.as_ptr(), mode)).map(|_| ())
+}
+
+pub fn unlink(path: &str) -> Result<()> {
+    let path = c_string(path);
+    check(sys_unlinkat(AT_FDCWD, path.as_ptr(), 0)).map(|_| ())
+}
+
+pub fn rmdir(path: &str) -> Result<()> {
+    let path = c_string(path);
+    check(sys_unlinkat(AT_FDCWD, path.as_ptr(), AT_REMOVEDIR)).map(|_| ())
+}
+
+/// 把 `source` 设备上类型为 `fstype` 的文件系统挂载到 `target`
+pub fn mount(source: &str, target: &str, fstype: &str, flags: usize) -> Result<()> {
+    let source = c_string(source);
+    let target = c_string(target);
+    let fstype = c_string(fstype);
+    check(sys_mount(
+        source.as_ptr(),
+        target.as_ptr(),
+        fstype.as_ptr(),
+        flags,
+        core::ptr::null(),
+    ))
+    .map(|_| ())
+}
+
+pub fn umount(target: &str) -> Result<()> {
+    umount2(target, 0)
+}
+
+pub fn umount2(target: &str, flags: u32) -> Result<()> {
+    let target = c_string(target);
+    check(sys_umount2(target.as_ptr(), flags)).map(|_| ())
+}
+
+/// 把所有文件的脏数据写回磁盘
+pub fn sync() {
+    sys_sync();
+}
+
+/// 把 `fd` 所在文件系统的脏数据写回磁盘
+pub fn syncfs(fd: usize) -> Result<()> {
+    check(sys_syncfs(fd)).map(|_| ())
+}
+
+/// 把 `fd` 的脏数据写回磁盘
+pub fn fsync(fd: usize) -> Result<()> {
+    check(sys_fsync(fd)).map(|_| ())
+}
+
+/// 目录项的文件类型（linux_dirent64 的 `d_type`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    Fifo,
+    CharDevice,
+    Directory,
+    BlockDevice,
+    Regular,
+    Symlink,
+    Socket,
+    Unknown,
+}
+
+impl From<u8> for FileType {
+    fn from(d_type: u8) -> Self {
+        match d_type {
+            1 => FileType::Fifo,
+            2 => FileType::CharDevice,
+            4 => FileType::Directory,
+            6 => FileType::BlockDevice,
+            8 => FileType::Regular,
+            10 => FileType::Symlink,
+            12 => FileType::Socket,
+            _ => FileType::Unknown,
+        }
+    }
+}
+
+/// 一个目录项
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub ino: u64,
+    pub file_type: FileType,
+    pub name: String,
+}
+
+impl DirEntry {
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+}
+
+/// linux_dirent64 中 `d_name` 的偏移：d_ino(8) + d_off(8) + d_reclen(2) + d_type(1)
+const DIRENT64_NAME_OFFSET: usize = 19;
+/// 每次 getdents64 使用的缓冲区大小
+const DIRENT_BUF_SIZE: usize = 512;
+
+/// 目录项迭代器，由 `read_dir` 创建，析构时关闭目录
+pub struct DirEntries {
+    fd: usize,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+    done: bool,
+}
+
+/// 打开目录 `path` 并迭代其中的目录项
+pub fn read_dir(path: &str) -> Result<DirEntries> {
+    let fd = open(path, OpenFlags::RDONLY | OpenFlags::DIRECTORY)?;
+    Ok(DirEntries {
+        fd,
+        buf: vec![0u8; DIRENT_BUF_SIZE],
+        pos: 0,
+        len: 0,
+        done: false,
+    })
+}
+
+impl DirEntries {
+    /// 缓冲区读完后向内核取下一批目录项，返回是否还有数据
+    fn fill(&mut self) -> Result<bool> {
+        let len = check(sys_getdents64(
+            self.fd,
+            self.buf.as_mut_ptr(),
+            self.buf.len(),
+        ))?;
+        self.pos = 0;
+        self.len = len;
+        Ok(len > 0)
+    }
+
+    /// 解析缓冲区中 `pos` 处的一条 linux_dirent64
+    fn parse(&mut self) -> Result<DirEntry> {
+        let record = &self.buf[self.pos..self.len];
+        if record.len() < DIRENT64_NAME_OFFSET {
+            return Err(Errno::EIO);
+        }
+        let ino = u64::from_ne_bytes(record[0..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(record[16..18].try_into().unwrap()) as usize;
+        if reclen < DIRENT64_NAME_OFFSET || reclen > record.len() {
+            return Err(Errno::EIO);
+        }
+        let name = &record[DIRENT64_NAME_OFFSET..reclen];
+        let name_len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        let entry = DirEntry {
+            ino,
+            file_type: FileType::from(record[18]),
+            name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+        };
+        self.pos += reclen;
+        Ok(entry)
+    }
+}
+
+impl Iterator for DirEntries {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.pos >= self.len {
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        let entry = self.parse();
+        // 格式错误的记录之后的数据无法再解析
+        self.done = entry.is_err();
+        Some(entry)
+    }
+}
+
+impl Drop for DirEntries {
+    fn drop(&mut self) {
+        let _ = close(self.fd);
+    }
+}
@@ -1,4 +1,4 @@
-use super::{SignalFlags, getpid, kill};
+use super::{getpid, kill, SIGABRT};
 
 #[panic_handler]
 fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
@@ -13,6 +13,6 @@ fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     } else {
         println!("Panicked: {:?}", err);
     }
-    kill(getpid() as usize, SignalFlags::SIGABRT.bits());
+    let _ = kill(getpid(), SIGABRT);
     unreachable!()
 }
@@ -1,3 +1,22 @@
+//! 用户态运行库
+//!
+//! ## Overview
+//! - 启动入口 `_start`：从 SysV 初始栈取出 argc、argv、envp，初始化堆后调用 `main`
+//! - `errno`：错误码 `Errno` 与 `Result<T>`，所有可能失败的调用都返回它
+//! - `fs`：文件、目录、管道、挂载等文件系统调用，`read_dir` 返回目录项迭代器
+//! - `process`：fork / execve / wait4、进程组、信号、内存映射、uname 等
+//! - `time`：仿照 `std::time` 的 `Instant`、`sleep` 与 `Duration` 转换
+//! - `thread`：基于内核线程的 `Thread::spawn` / `join`
+//! - `sync`：内核提供的互斥锁、条件变量与信号量的类型化封装
+//! - `net`：套接字调用与 `SocketAddr`
+//!
+//! ## Assumptions
+//! - 仅支持 RISC-V，系统调用编号与内核分发表一致
+//! - 路径参数可以带也可以不带结尾的 `\0`，封装会按需补上
+//!
+//! ## Behavior
+//! - 各模块的公开项都在 crate 根重新导出，调用方直接使用 `user::open` 等
+
 #![no_std]
 #![feature(linkage)]
 #![feature(alloc_error_handler)]
@@ -5,22 +24,36 @@
 
 #[macro_use]
 pub mod console;
+pub mod errno;
+pub mod fs;
 mod lang_items;
+pub mod net;
+pub mod process;
+pub mod sync;
 mod syscall;
+pub mod thread;
+pub mod time;
 
 extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
+pub use errno::{Errno, Result};
+pub use fs::*;
+pub use process::*;
+pub use time::{sleep, Instant};
+
 use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
 use core::ptr::addr_of_mut;
-use syscall::*;
 
 const USER_HEAP_SIZE: usize = 32768;
 
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
+/// 启动时内核放在栈上的环境变量指针数组，以空指针结尾
+static mut ENVP: *const *const u8 = core::ptr::null();
+
 #[global_allocator]
 static HEAP: LockedHeap = LockedHeap::empty();
 
@@ -29,143 +62,76 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout);
 }
 
+// 内核按 SysV ABI 把 argc、argv、envp 与辅助向量放在初始栈上，a0 不再是 argc
+core::arch::global_asm!(
+    ".pushsection .text.entry, \"ax\"",
+    ".globl _start",
+    "_start:",
+    "    mv a0, sp",
+    "    tail _start_rust",
+    ".popsection",
+);
+
 #[no_mangle]
-#[link_section = ".text.entry"]
-pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+extern "C" fn _start_rust(sp: *const usize) -> ! {
+    let argc = unsafe { sp.read() };
+    let argv = unsafe { sp.add(1) } as *const *const u8;
     unsafe {
         HEAP.lock()
             .init(addr_of_mut!(HEAP_SPACE) as usize, USER_HEAP_SIZE);
+        // argv 之后隔一个空指针就是 envp
+        ENVP = argv.add(argc + 1);
     }
-    let mut v: Vec<&'static str> = Vec::new();
-    for i in 0..argc {
-        let str_start =
-            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
-        let len = (0usize..)
-            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
-            .unwrap();
-        v.push(
-            core::str::from_utf8(unsafe {
-                core::slice::from_raw_parts(str_start as *const u8, len)
-            })
-                .unwrap(),
-        );
-    }
+    let v: Vec<&'static str> = (0..argc)
+        .map(|i| unsafe { c_str(argv.add(i).read_volatile()) })
+        .collect();
     exit(main(argc, v.as_slice()));
 }
 
-#[linkage = "weak"]
-#[no_mangle]
-fn main(_argc: usize, _argv: &[&str]) -> i32 {
-    panic!("Cannot find main!");
+/// 把以 NUL 结尾的字符串视为 `&str`
+unsafe fn c_str(ptr: *const u8) -> &'static str {
+    let len = (0usize..)
+        .find(|i| ptr.add(*i).read_volatile() == 0)
+        .unwrap();
+    core::str::from_utf8(core::slice::from_raw_parts(ptr, len)).unwrap()
 }
 
-bitflags! {
-    pub struct OpenFlags: u32 {
-        // 只读
-        const RDONLY = 0;
-        // 只写
-        const WRONLY = 1 << 0;
-        // 读写
-        const RDWR = 1 << 1;
-        // 创建
-        const CREATE = 1 << 6;
-        // 截断（若存在则以可写方式打开，但是长度清空为0）
-        const TRUNC = 1 << 10;
-        const DIRECTORY = 1 << 17; // 目录（O_DIRECTORY = 0x0200000）
-    }
+/// 复制一份以 NUL 结尾的字符串交给内核，已带结尾 `\0` 的不再重复添加
+pub(crate) fn c_string(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::from(s.strip_suffix('\0').unwrap_or(s).as_bytes());
+    bytes.push(0);
+    bytes
 }
 
-pub fn dup(fd: usize) -> isize {
-    sys_dup(fd)
-}
-pub fn open(path: &str, flags: OpenFlags) -> isize {
-    sys_open(path, flags.bits)
-}
-pub fn close(fd: usize) -> isize {
-    sys_close(fd)
-}
-pub fn pipe(pipe_fd: &mut [usize]) -> isize {
-    sys_pipe(pipe_fd)
-}
-pub fn read(fd: usize, buf: &mut [u8]) -> isize {
-    sys_read(fd, buf)
-}
-pub fn write(fd: usize, buf: &[u8]) -> isize {
-    sys_write(fd, buf)
-}
-pub fn exit(exit_code: i32) -> ! {
-    sys_exit(exit_code);
-}
-pub fn yield_() -> isize {
-    sys_yield()
-}
-pub fn getpid() -> isize {
-    sys_getpid()
-}
-pub fn fork() -> isize {
-    sys_fork()
-}
-pub fn exec(path: &str, args: &[*const u8]) -> isize {
-    sys_exec(path, args)
-}
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
-        }
+/// 进程启动时的环境变量，每项形如 `NAME=value`
+pub fn environ() -> Vec<&'static str> {
+    let mut envs = Vec::new();
+    let mut envp = unsafe { ENVP };
+    if envp.is_null() {
+        return envs;
     }
-}
-
-pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
     loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
+        let env = unsafe { envp.read_volatile() };
+        if env.is_null() {
+            return envs;
         }
+        envs.push(unsafe { c_str(env) });
+        envp = unsafe { envp.add(1) };
     }
 }
 
-pub fn getcwd(buf: &mut [u8]) -> isize {
-    sys_getcwd(buf)
-}
-
-pub fn chdir(path: &str) -> isize {
-    sys_chdir(path)
-}
-
-pub fn brk(addr: usize) -> isize {
-    sys_brk(addr)
-}
-
-pub fn munmap(start: usize, len: usize) -> isize {
-    sys_munmap(start, len)
-}
-
-pub fn mmap(start:usize,len:usize,prot:usize,flags:usize,fd:isize,off:usize) -> isize {
-    sys_mmap(start,len,prot,flags,fd as usize,off)
-}
-
-pub fn fstat(fd:usize,statbuff:*mut u8) -> isize {
-    sys_fstat(fd,statbuff)
+/// 查找启动时的环境变量 `name`
+pub fn getenv(name: &str) -> Option<&'static str> {
+    environ().into_iter().find_map(|env| {
+        env.strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+    })
 }
 
-pub fn mkdir(dirfd:isize,path: *const u8,mode:u8) -> isize {
-    sys_mkdirat(dirfd,path,mode)
-}
-
-pub fn dup3(old:isize, new:isize, flags:usize) -> isize {
-    sys_dup3(old, new, flags)
-}
-
-pub fn getdents(fd:usize, buf:*mut u8, len:usize) -> isize {
-    sys_getdents(fd,buf, len)
+#[linkage = "weak"]
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    panic!("Cannot find main!");
 }
 
 #[repr(C, align(16))]
@@ -254,6 +220,3 @@ bitflags! {
     }
 }
 
-pub fn kill(pid: usize, signum: i32) -> isize {
-    sys_kill(pid, signum)
-}
\ No newline at end of file
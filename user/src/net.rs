@@ -0,0 +1,220 @@
+//! 套接字调用
+//!
+//! ## Overview
+//! - `SocketAddr`：IPv4 与 Unix 域地址，与用户态 `sockaddr` 字节表示互相转换
+//! - `socket` / `socketpair` / `bind` / `listen` / `accept` / `connect` 建立连接
+//! - `send` / `recv` / `sendto` / `recvfrom` 收发数据，`setsockopt` / `getsockopt` 读写选项
+//!
+//! ## Assumptions
+//! - 常量取值与内核 `net/mod.rs` 一致，即 Linux 通用 ABI
+//!
+//! ## Behavior
+//! - 套接字即文件描述符，可以用 `read` / `write` / `close` 操作
+
+use crate::errno::{check, Errno, Result};
+use crate::syscall::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// 协议族
+pub const AF_UNIX: usize = 1;
+pub const AF_INET: usize = 2;
+
+/// 套接字类型
+pub const SOCK_STREAM: usize = 1;
+pub const SOCK_DGRAM: usize = 2;
+pub const SOCK_SEQPACKET: usize = 5;
+/// `socket` 的 type 参数中可以附带的标志
+pub const SOCK_NONBLOCK: usize = 0o4000;
+pub const SOCK_CLOEXEC: usize = 0o2000000;
+
+/// `shutdown` 的 how 参数
+pub const SHUT_RD: usize = 0;
+pub const SHUT_WR: usize = 1;
+pub const SHUT_RDWR: usize = 2;
+
+/// send / recv 标志
+pub const MSG_PEEK: usize = 0x2;
+pub const MSG_DONTWAIT: usize = 0x40;
+pub const MSG_NOSIGNAL: usize = 0x4000;
+
+/// 选项层级与常用选项
+pub const SOL_SOCKET: usize = 1;
+pub const IPPROTO_TCP: usize = 6;
+pub const SO_REUSEADDR: usize = 2;
+pub const SO_TYPE: usize = 3;
+pub const SO_ERROR: usize = 4;
+pub const SO_SNDBUF: usize = 7;
+pub const SO_RCVBUF: usize = 8;
+pub const TCP_NODELAY: usize = 1;
+
+/// `sockaddr_storage` 的大小，足够容纳内核返回的任何地址
+const SOCKADDR_MAX: usize = 128;
+
+/// 套接字地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SocketAddr {
+    /// IPv4 地址与端口
+    Inet { ip: [u8; 4], port: u16 },
+    /// Unix 域路径，空串表示未命名
+    Unix(String),
+}
+
+impl SocketAddr {
+    pub fn inet(ip: [u8; 4], port: u16) -> Self {
+        SocketAddr::Inet { ip, port }
+    }
+
+    pub fn unix(path: &str) -> Self {
+        SocketAddr::Unix(String::from(path))
+    }
+
+    /// 转换成 `sockaddr_in` / `sockaddr_un` 的字节表示
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SocketAddr::Inet { ip, port } => {
+                let mut bytes = Vec::from((AF_INET as u16).to_ne_bytes());
+                bytes.extend_from_slice(&port.to_be_bytes());
+                bytes.extend_from_slice(ip);
+                bytes.extend_from_slice(&[0; 8]);
+                bytes
+            }
+            SocketAddr::Unix(path) => {
+                let mut bytes = Vec::from((AF_UNIX as u16).to_ne_bytes());
+                bytes.extend_from_slice(path.as_bytes());
+                bytes.push(0);
+                bytes
+            }
+        }
+    }
+
+    /// 解析内核写回的 `sockaddr`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 2 {
+            return Err(Errno::EINVAL);
+        }
+        match u16::from_ne_bytes([bytes[0], bytes[1]]) as usize {
+            AF_INET if bytes.len() >= 8 => Ok(SocketAddr::Inet {
+                ip: [bytes[4], bytes[5], bytes[6], bytes[7]],
+                port: u16::from_be_bytes([bytes[2], bytes[3]]),
+            }),
+            AF_UNIX => {
+                let path = &bytes[2..];
+                let len = path.iter().position(|&b| b == 0).unwrap_or(path.len());
+                Ok(SocketAddr::Unix(
+                    String::from_utf8_lossy(&path[..len]).into_owned(),
+                ))
+            }
+            AF_INET => Err(Errno::EINVAL),
+            _ => Err(Errno::EAFNOSUPPORT),
+        }
+    }
+}
+
+pub fn socket(domain: usize, ty: usize, protocol: usize) -> Result<usize> {
+    check(sys_socket(domain, ty, protocol))
+}
+
+/// 创建一对相互连接的套接字
+pub fn socketpair(domain: usize, ty: usize, protocol: usize) -> Result<(usize, usize)> {
+    let mut fds = [0i32; 2];
+    check(sys_socketpair(domain, ty, protocol, fds.as_mut_ptr()))?;
+    Ok((fds[0] as usize, fds[1] as usize))
+}
+
+pub fn bind(fd: usize, addr: &SocketAddr) -> Result<()> {
+    let addr = addr.to_bytes();
+    check(sys_bind(fd, addr.as_ptr(), addr.len())).map(|_| ())
+}
+
+pub fn listen(fd: usize, backlog: usize) -> Result<()> {
+    check(sys_listen(fd, backlog)).map(|_| ())
+}
+
+/// 接受一个连接，返回新套接字与对端地址
+pub fn accept(fd: usize) -> Result<(usize, SocketAddr)> {
+    accept4(fd, 0)
+}
+
+/// 同 `accept`，`flags` 可含 `SOCK_NONBLOCK` 与 `SOCK_CLOEXEC`
+pub fn accept4(fd: usize, flags: usize) -> Result<(usize, SocketAddr)> {
+    let mut addr = [0u8; SOCKADDR_MAX];
+    let mut len = SOCKADDR_MAX as u32;
+    let new_fd = check(sys_accept4(fd, addr.as_mut_ptr(), &mut len, flags))?;
+    let len = (len as usize).min(SOCKADDR_MAX);
+    Ok((new_fd, SocketAddr::from_bytes(&addr[..len])?))
+}
+
+pub fn connect(fd: usize, addr: &SocketAddr) -> Result<()> {
+    let addr = addr.to_bytes();
+    check(sys_connect(fd, addr.as_ptr(), addr.len())).map(|_| ())
+}
+
+/// 本端地址
+pub fn getsockname(fd: usize) -> Result<SocketAddr> {
+    let mut addr = [0u8; SOCKADDR_MAX];
+    let mut len = SOCKADDR_MAX as u32;
+    check(sys_getsockname(fd, addr.as_mut_ptr(), &mut len))?;
+    SocketAddr::from_bytes(&addr[..(len as usize).min(SOCKADDR_MAX)])
+}
+
+/// 对端地址
+pub fn getpeername(fd: usize) -> Result<SocketAddr> {
+    let mut addr = [0u8; SOCKADDR_MAX];
+    let mut len = SOCKADDR_MAX as u32;
+    check(sys_getpeername(fd, addr.as_mut_ptr(), &mut len))?;
+    SocketAddr::from_bytes(&addr[..(len as usize).min(SOCKADDR_MAX)])
+}
+
+pub fn send(fd: usize, buf: &[u8], flags: usize) -> Result<usize> {
+    check(sys_sendto(fd, buf, flags, core::ptr::null(), 0))
+}
+
+pub fn sendto(fd: usize, buf: &[u8], flags: usize, addr: &SocketAddr) -> Result<usize> {
+    let addr = addr.to_bytes();
+    check(sys_sendto(fd, buf, flags, addr.as_ptr(), addr.len()))
+}
+
+pub fn recv(fd: usize, buf: &mut [u8], flags: usize) -> Result<usize> {
+    check(sys_recvfrom(
+        fd,
+        buf,
+        flags,
+        core::ptr::null_mut(),
+        core::ptr::null_mut(),
+    ))
+}
+
+/// 接收数据并返回发送方地址
+pub fn recvfrom(fd: usize, buf: &mut [u8], flags: usize) -> Result<(usize, SocketAddr)> {
+    let mut addr = [0u8; SOCKADDR_MAX];
+    let mut len = SOCKADDR_MAX as u32;
+    let n = check(sys_recvfrom(fd, buf, flags, addr.as_mut_ptr(), &mut len))?;
+    let len = (len as usize).min(SOCKADDR_MAX);
+    Ok((n, SocketAddr::from_bytes(&addr[..len])?))
+}
+
+/// 设置整数类型的套接字选项
+pub fn setsockopt(fd: usize, level: usize, name: usize, value: i32) -> Result<()> {
+    let len = core::mem::size_of::<i32>();
+    let value = &value as *const i32 as *const u8;
+    check(sys_setsockopt(fd, level, name, value, len)).map(|_| ())
+}
+
+/// 读取整数类型的套接字选项
+pub fn getsockopt(fd: usize, level: usize, name: usize) -> Result<i32> {
+    let mut value = 0i32;
+    let mut len = core::mem::size_of::<i32>() as u32;
+    check(sys_getsockopt(
+        fd,
+        level,
+        name,
+        &mut value as *mut i32 as *mut u8,
+        &mut len,
+    ))?;
+    Ok(value)
+}
+
+pub fn shutdown(fd: usize, how: usize) -> Result<()> {
+    check(sys_shutdown(fd, how)).map(|_| ())
+}
@@ -0,0 +1,270 @@
+//! 进程相关调用
+//!
+//! ## Overview
+//! - 进程的创建、替换与回收：`fork` / `exec` / `execve` / `wait` / `waitpid`
+//! - 进程标识与进程组：`getpid` / `getppid` / `gettid` / `setpgid` / `getpgid`
+//! - 信号发送 `kill`、关机重启 `reboot`、`uname`、`times`
+//! - 地址空间：`brk`、`mmap` / `munmap`
+//!
+//! ## Invariants
+//! - 内核 `exit(code)` 记录的退出状态为 `(code & 0xff) << 8`，被信号杀死时为负的信号值，
+//!   `ExitStatus` 按这一约定解码
+//!
+//! ## Behavior
+//! - `exec` / `execve` 成功时不返回，失败时返回 `Errno`
+//! - 内核 wait4 在没有子进程时返回 `-1`，这里转换成 `ECHILD`
+
+use crate::errno::{check, Errno, Result};
+use crate::syscall::*;
+use crate::{c_string, ENVP};
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// reboot 命令
+pub const RB_AUTOBOOT: u32 = 0x0123_4567;
+pub const RB_HALT_SYSTEM: u32 = 0xcdef_0123;
+pub const RB_POWER_OFF: u32 = 0x4321_fedc;
+
+/// wait4 选项：子进程未退出时立即返回
+pub const WNOHANG: usize = 1;
+
+/// 子进程退出时通知父进程的信号，fork 即 `clone(SIGCHLD)`
+const CLONE_SIGCHLD: usize = 17;
+
+/// reboot 的两个魔数
+const REBOOT_MAGIC1: usize = 0xfee1_dead;
+const REBOOT_MAGIC2: usize = 672274793;
+
+/// 子进程的退出状态
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct ExitStatus(i32);
+
+impl ExitStatus {
+    pub fn from_raw(raw: i32) -> Self {
+        Self(raw)
+    }
+
+    pub fn raw(self) -> i32 {
+        self.0
+    }
+
+    /// 正常退出时的退出码
+    pub fn code(self) -> Option<i32> {
+        (self.0 >= 0).then_some((self.0 >> 8) & 0xff)
+    }
+
+    /// 被信号杀死时的信号值
+    pub fn signal(self) -> Option<i32> {
+        (self.0 < 0).then_some(-self.0)
+    }
+
+    pub fn success(self) -> bool {
+        self.code() == Some(0)
+    }
+}
+
+impl fmt::Debug for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.code(), self.signal()) {
+            (Some(code), _) => write!(f, "exit code {}", code),
+            (_, Some(signal)) => write!(f, "killed by signal {}", signal),
+            _ => unreachable!(),
+        }
+    }
+}
+
+pub fn exit(exit_code: i32) -> ! {
+    sys_exit(exit_code);
+}
+
+pub fn yield_() {
+    sys_yield();
+}
+
+pub fn getpid() -> usize {
+    sys_getpid() as usize
+}
+
+pub fn getppid() -> usize {
+    sys_getppid() as usize
+}
+
+pub fn gettid() -> usize {
+    sys_gettid() as usize
+}
+
+/// 创建子进程，子进程中返回 0，父进程中返回子进程 pid
+pub fn fork() -> Result<usize> {
+    check(sys_clone(CLONE_SIGCHLD))
+}
+
+/// 以 `args` 为 argv 执行新程序，沿用启动时的环境变量；成功时不返回
+pub fn exec(path: &str, args: &[&str]) -> Errno {
+    let args = CStrArray::new(args);
+    let path = c_string(path);
+    let ret = sys_execve(path.as_ptr(), args.as_ptr(), unsafe { ENVP });
+    Errno(-ret as i32)
+}
+
+/// 以 `args` 为 argv、`envs` 为环境变量执行新程序；成功时不返回
+pub fn execve(path: &str, args: &[&str], envs: &[&str]) -> Errno {
+    let args = CStrArray::new(args);
+    let envs = CStrArray::new(envs);
+    let path = c_string(path);
+    let ret = sys_execve(path.as_ptr(), args.as_ptr(), envs.as_ptr());
+    Errno(-ret as i32)
+}
+
+/// 等待任意一个子进程退出，返回其 pid 与退出状态
+pub fn wait() -> Result<(usize, ExitStatus)> {
+    wait4(-1, 0).map(|res| res.unwrap())
+}
+
+/// 等待子进程 `pid` 退出
+pub fn waitpid(pid: usize) -> Result<ExitStatus> {
+    wait4(pid as isize, 0).map(|res| res.unwrap().1)
+}
+
+/// 不阻塞地回收子进程：没有已退出的子进程时返回 `None`
+pub fn waitpid_nohang(pid: isize) -> Result<Option<(usize, ExitStatus)>> {
+    wait4(pid, WNOHANG)
+}
+
+fn wait4(pid: isize, options: usize) -> Result<Option<(usize, ExitStatus)>> {
+    let mut status = 0i32;
+    match sys_wait4(pid, &mut status, options) {
+        -1 => Err(Errno::ECHILD),
+        0 if options & WNOHANG != 0 => Ok(None),
+        ret => check(ret).map(|pid| Some((pid, ExitStatus(status)))),
+    }
+}
+
+pub fn setpgid(pid: usize, pgid: usize) -> Result<()> {
+    check(sys_setpgid(pid, pgid)).map(|_| ())
+}
+
+/// 进程 `pid` 所在的进程组，`pid` 为 0 时指当前进程
+pub fn getpgid(pid: usize) -> Result<usize> {
+    check(sys_getpgid(pid))
+}
+
+/// 向进程 `pid` 发送信号 `signum`
+pub fn kill(pid: usize, signum: i32) -> Result<()> {
+    check(sys_kill(pid, signum)).map(|_| ())
+}
+
+/// 卸载文件系统后关机（`RB_POWER_OFF`）或重启（`RB_AUTOBOOT`），成功时不返回
+pub fn reboot(cmd: u32) -> Errno {
+    let ret = sys_reboot(REBOOT_MAGIC1, REBOOT_MAGIC2, cmd);
+    Errno(-ret as i32)
+}
+
+/// 把程序断点设为 `addr`，返回新的断点；`addr` 为 0 时只查询
+pub fn brk(addr: usize) -> Result<usize> {
+    check(sys_brk(addr))
+}
+
+bitflags! {
+    pub struct ProtFlags: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    pub struct MapFlags: usize {
+        const SHARED = 0x01;
+        const PRIVATE = 0x02;
+        const FIXED = 0x10;
+        const ANONYMOUS = 0x20;
+    }
+}
+
+/// 建立内存映射，`fd` 为 `None` 时为匿名映射；返回映射的起始地址
+pub fn mmap(
+    addr: usize,
+    len: usize,
+    prot: ProtFlags,
+    flags: MapFlags,
+    fd: Option<usize>,
+    offset: usize,
+) -> Result<usize> {
+    let fd = fd.map_or(-1, |fd| fd as isize);
+    check(sys_mmap(addr, len, prot.bits, flags.bits, fd, offset))
+}
+
+pub fn munmap(addr: usize, len: usize) -> Result<()> {
+    check(sys_munmap(addr, len)).map(|_| ())
+}
+
+/// 进程及已回收子进程的 CPU 时间，单位为时钟滴答
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+pub fn times() -> Result<Tms> {
+    let mut tms = Tms::default();
+    check(sys_times(&mut tms as *mut Tms as *mut u8))?;
+    Ok(tms)
+}
+
+/// uname 的结果
+#[derive(Debug, Clone)]
+pub struct UtsName {
+    pub sysname: String,
+    pub nodename: String,
+    pub release: String,
+    pub version: String,
+    pub machine: String,
+    pub domainname: String,
+}
+
+/// `struct utsname` 每个字段的长度
+const UTSNAME_FIELD_LEN: usize = 65;
+
+pub fn uname() -> Result<UtsName> {
+    let mut buf = [0u8; UTSNAME_FIELD_LEN * 6];
+    check(sys_uname(buf.as_mut_ptr()))?;
+    let mut fields = buf.chunks(UTSNAME_FIELD_LEN).map(|field| {
+        let len = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+        String::from_utf8_lossy(&field[..len]).into_owned()
+    });
+    let mut next = || fields.next().unwrap();
+    Ok(UtsName {
+        sysname: next(),
+        nodename: next(),
+        release: next(),
+        version: next(),
+        machine: next(),
+        domainname: next(),
+    })
+}
+
+/// 以空指针结尾的 C 字符串指针数组，字符串的存储由自身持有
+struct CStrArray {
+    _strings: Vec<Vec<u8>>,
+    ptrs: Vec<*const u8>,
+}
+
+impl CStrArray {
+    fn new(strs: &[&str]) -> Self {
+        let strings: Vec<Vec<u8>> = strs.iter().map(|s| c_string(s)).collect();
+        let mut ptrs: Vec<*const u8> = strings.iter().map(|s| s.as_ptr()).collect();
+        ptrs.push(core::ptr::null());
+        Self {
+            _strings: strings,
+            ptrs,
+        }
+    }
+
+    fn as_ptr(&self) -> *const *const u8 {
+        self.ptrs.as_ptr()
+    }
+}
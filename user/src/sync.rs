@@ -0,0 +1,147 @@
+//! 同步原语
+//!
+//! ## Overview
+//! - `Mutex<T>`：内核阻塞互斥锁保护的数据，`lock` 返回 RAII 守卫
+//! - `Condvar`：与 `Mutex` 配合使用的条件变量
+//! - `Semaphore`：计数信号量
+//!
+//! ## Assumptions
+//! - 内核按进程分配锁、条件变量与信号量的 id，没有销毁它们的系统调用，
+//!   因此这些对象析构时内核侧的资源不会回收，适合长期存在的用法
+//!
+//! ## Behavior
+//! - 内核的条件变量每次 signal 只唤醒一个等待者，`notify_one` 直接对应它
+
+use crate::errno::check;
+use crate::syscall::*;
+use core::cell::UnsafeCell;
+use core::ops::{Deref, DerefMut};
+
+/// 创建内核同步对象，失败说明进程已无法继续
+fn create(ret: isize, what: &str) -> usize {
+    match check(ret) {
+        Ok(id) => id,
+        Err(err) => panic!("cannot create {}: {}", what, err),
+    }
+}
+
+/// 互斥锁
+pub struct Mutex<T> {
+    id: usize,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            id: create(sys_mutex_create(true), "mutex"),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// 加锁，守卫析构时解锁
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        sys_mutex_lock(self.id);
+        MutexGuard { mutex: self }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+/// `Mutex::lock` 返回的守卫
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        sys_mutex_unlock(self.mutex.id);
+    }
+}
+
+/// 条件变量
+pub struct Condvar {
+    id: usize,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            id: create(sys_condvar_create(), "condvar"),
+        }
+    }
+
+    /// 释放 `guard` 对应的锁并等待唤醒，返回前重新加锁
+    pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        sys_condvar_wait(self.id, guard.mutex.id);
+        guard
+    }
+
+    /// 在 `condition` 为真期间反复等待
+    pub fn wait_while<'a, T, F>(
+        &self,
+        mut guard: MutexGuard<'a, T>,
+        mut condition: F,
+    ) -> MutexGuard<'a, T>
+    where
+        F: FnMut(&mut T) -> bool,
+    {
+        while condition(&mut *guard) {
+            guard = self.wait(guard);
+        }
+        guard
+    }
+
+    /// 唤醒一个等待者
+    pub fn notify_one(&self) {
+        sys_condvar_signal(self.id);
+    }
+}
+
+impl Default for Condvar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 计数信号量
+pub struct Semaphore {
+    id: usize,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Self {
+            id: create(sys_semaphore_create(count), "semaphore"),
+        }
+    }
+
+    /// P 操作：计数为 0 时阻塞
+    pub fn acquire(&self) {
+        sys_semaphore_down(self.id);
+    }
+
+    /// V 操作
+    pub fn release(&self) {
+        sys_semaphore_up(self.id);
+    }
+}
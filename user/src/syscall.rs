@@ -1,27 +1,78 @@
+//! 系统调用的原始入口
+//!
+//! 编号与内核 `os/src/syscall/mod.rs` 的分发表一一对应，参数按 Linux ABI 直接传递。
+//! 这里的函数只负责 `ecall`，返回内核给出的原始值（失败时为负的错误码），
+//! 类型化的封装见 `fs`、`process`、`time`、`thread`、`sync`、`net` 各模块。
+
 use core::arch::asm;
 
+const SYSCALL_GETCWD: usize = 17;
 const SYSCALL_DUP: usize = 23;
 const SYSCALL_DUP3: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_FLOCK: usize = 32;
 const SYSCALL_MKDIRAT: usize = 34;
-const SYSCALL_OPEN: usize = 56;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_OPENAT: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
-const SYSCALL_PIPE: usize = 59;
-const SYSCALL_GETDENTS: usize = 61;
+const SYSCALL_PIPE2: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
 const SYSCALL_FSTAT: usize = 80;
+const SYSCALL_SYNC: usize = 81;
+const SYSCALL_FSYNC: usize = 82;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_NANOSLEEP: usize = 101;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_REBOOT: usize = 142;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_UNAME: usize = 160;
+const SYSCALL_GET_TIME_OF_DAY: usize = 169;
 const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_GETTID: usize = 178;
+const SYSCALL_SOCKET: usize = 198;
+const SYSCALL_SOCKETPAIR: usize = 199;
+const SYSCALL_BIND: usize = 200;
+const SYSCALL_LISTEN: usize = 201;
+const SYSCALL_CONNECT: usize = 203;
+const SYSCALL_GETSOCKNAME: usize = 204;
+const SYSCALL_GETPEERNAME: usize = 205;
+const SYSCALL_SENDTO: usize = 206;
+const SYSCALL_RECVFROM: usize = 207;
+const SYSCALL_SETSOCKOPT: usize = 208;
+const SYSCALL_GETSOCKOPT: usize = 209;
+const SYSCALL_SHUTDOWN: usize = 210;
 const SYSCALL_BRK: usize = 214;
 const SYSCALL_MUNMAP: usize = 215;
-const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
+const SYSCALL_CLONE: usize = 220;
+const SYSCALL_EXECVE: usize = 221;
 const SYSCALL_MMAP: usize = 222;
-const SYSCALL_WAITPID: usize = 260;
-const SYSCALL_GETCWD: usize = 17;
-const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_ACCEPT4: usize = 242;
+const SYSCALL_WAIT4: usize = 260;
+const SYSCALL_SYNCFS: usize = 267;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+
+/// `*at` 系列调用中表示当前工作目录的 dirfd
+pub const AT_FDCWD: isize = -100;
 
 fn syscall(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
@@ -40,20 +91,101 @@ fn syscall(id: usize, args: [usize; 6]) -> isize {
     ret
 }
 
-// pub fn sys_dup(fd: usize) -> isize {
-//     syscall(SYSCALL_DUP, [fd, 0, 0, 0, 0, 0])
-// }
+// 文件系统
+
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    syscall(SYSCALL_GETCWD, [buf as usize, len, 0, 0, 0, 0])
+}
+
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_dup3(old: usize, new: usize, flags: usize) -> isize {
+    syscall(SYSCALL_DUP3, [old, new, flags, 0, 0, 0])
+}
+
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_FCNTL, [fd, cmd, arg, 0, 0, 0])
+}
+
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, cmd, arg, 0, 0, 0])
+}
+
+pub fn sys_flock(fd: usize, operation: usize) -> isize {
+    syscall(SYSCALL_FLOCK, [fd, operation, 0, 0, 0, 0])
+}
 
-pub fn sys_open(path: &str, flags: u32) -> isize {
-    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0, 0, 0, 0])
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, mode: u32) -> isize {
+    syscall(
+        SYSCALL_MKDIRAT,
+        [dirfd as usize, path as usize, mode as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path as usize, flags as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_umount2(target: *const u8, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UMOUNT2,
+        [target as usize, flags as usize, 0, 0, 0, 0],
+    )
+}
+
+pub fn sys_mount(
+    source: *const u8,
+    target: *const u8,
+    fstype: *const u8,
+    flags: usize,
+    data: *const u8,
+) -> isize {
+    syscall(
+        SYSCALL_MOUNT,
+        [
+            source as usize,
+            target as usize,
+            fstype as usize,
+            flags,
+            data as usize,
+            0,
+        ],
+    )
+}
+
+pub fn sys_chdir(path: *const u8) -> isize {
+    syscall(SYSCALL_CHDIR, [path as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_openat(dirfd: isize, path: *const u8, flags: u32, mode: u32) -> isize {
+    syscall(
+        SYSCALL_OPENAT,
+        [
+            dirfd as usize,
+            path as usize,
+            flags as usize,
+            mode as usize,
+            0,
+            0,
+        ],
+    )
 }
 
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_pipe(pipe: &mut [usize]) -> isize {
-    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0, 0, 0, 0])
+pub fn sys_pipe2(pipe: *mut i32, flags: u32) -> isize {
+    syscall(SYSCALL_PIPE2, [pipe as usize, flags as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
+    syscall(SYSCALL_GETDENTS64, [fd, buf as usize, len, 0, 0, 0])
 }
 
 pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
@@ -64,9 +196,30 @@ pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
 }
 
 pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
-    syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len(), 0, 0, 0])
+    syscall(
+        SYSCALL_WRITE,
+        [fd, buffer.as_ptr() as usize, buffer.len(), 0, 0, 0],
+    )
+}
+
+pub fn sys_fstat(fd: usize, statbuf: *mut u8) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, statbuf as usize, 0, 0, 0, 0])
+}
+
+pub fn sys_sync() -> isize {
+    syscall(SYSCALL_SYNC, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_fsync(fd: usize) -> isize {
+    syscall(SYSCALL_FSYNC, [fd, 0, 0, 0, 0, 0])
 }
 
+pub fn sys_syncfs(fd: usize) -> isize {
+    syscall(SYSCALL_SYNCFS, [fd, 0, 0, 0, 0, 0])
+}
+
+// 进程与线程
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0, 0, 0, 0]);
     panic!("sys_exit never returns!");
@@ -80,61 +233,232 @@ pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0, 0, 0, 0])
 }
 
+pub fn sys_reboot(magic1: usize, magic2: usize, cmd: u32) -> isize {
+    syscall(SYSCALL_REBOOT, [magic1, magic2, cmd as usize, 0, 0, 0])
+}
+
+pub fn sys_times(tms: *mut u8) -> isize {
+    syscall(SYSCALL_TIMES, [tms as usize, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0, 0, 0, 0])
+}
+
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_uname(buf: *mut u8) -> isize {
+    syscall(SYSCALL_UNAME, [buf as usize, 0, 0, 0, 0, 0])
+}
+
 pub fn sys_getpid() -> isize {
     syscall(SYSCALL_GETPID, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_fork() -> isize {
-    syscall(SYSCALL_FORK, [0, 0, 0, 0, 0, 0])
+pub fn sys_getppid() -> isize {
+    syscall(SYSCALL_GETPPID, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_mmap(start:usize,len:usize,prot:usize,flags:usize,fd:usize,off:usize) -> isize {
-    syscall(SYSCALL_MMAP, [start, len, prot, flags , fd, off])
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
+pub fn sys_brk(addr: usize) -> isize {
+    syscall(SYSCALL_BRK, [addr, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [start, len, 0, 0, 0, 0])
+}
+
+pub fn sys_mmap(
+    start: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: isize,
+    offset: usize,
+) -> isize {
+    syscall(SYSCALL_MMAP, [start, len, prot, flags, fd as usize, offset])
+}
+
+/// 低 8 位为子进程退出时发给父进程的信号，`fork` 即 `clone(SIGCHLD)`
+pub fn sys_clone(flags: usize) -> isize {
+    syscall(SYSCALL_CLONE, [flags, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_execve(path: *const u8, args: *const *const u8, envs: *const *const u8) -> isize {
     syscall(
-        SYSCALL_EXEC,
-        [path.as_ptr() as usize, args.as_ptr() as usize, 0, 0, 0, 0],
+        SYSCALL_EXECVE,
+        [path as usize, args as usize, envs as usize, 0, 0, 0],
     )
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0, 0, 0, 0])
+pub fn sys_wait4(pid: isize, status: *mut i32, options: usize) -> isize {
+    syscall(
+        SYSCALL_WAIT4,
+        [pid as usize, status as usize, options, 0, 0, 0],
+    )
 }
 
-pub fn sys_getcwd(buf: &mut [u8]) -> isize {
-    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0, 0, 0, 0])
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry, arg, 0, 0, 0, 0])
 }
 
-pub fn sys_chdir(path: &str) -> isize {
-    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0, 0, 0, 0])
+pub fn sys_waittid(tid: usize) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_brk(addr:usize) -> isize {
-    syscall(SYSCALL_BRK, [addr, 0, 0, 0, 0, 0])
+// 时间
+
+pub fn sys_nanosleep(req: *const u8, rem: *mut u8) -> isize {
+    syscall(SYSCALL_NANOSLEEP, [req as usize, rem as usize, 0, 0, 0, 0])
 }
 
-pub fn sys_munmap(start:usize, len:usize) -> isize {
-    syscall(SYSCALL_MUNMAP, [start, len, 0, 0, 0, 0])
+pub fn sys_gettimeofday(tv: *mut u8) -> isize {
+    syscall(SYSCALL_GET_TIME_OF_DAY, [tv as usize, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_fstat(fd: usize, statbuff: *mut u8) -> isize {
-    syscall(SYSCALL_FSTAT, [fd, statbuff as usize,  0, 0, 0, 0])
+// 同步原语
+
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [blocking as usize, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_mkdirat(dirfd: isize,path: *const u8,mode: u8) -> isize {
-    syscall(SYSCALL_MKDIRAT, [dirfd as usize, path as usize, mode as usize, 0, 0, 0])
+pub fn sys_mutex_lock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [id, 0, 0, 0, 0, 0])
 }
 
-pub fn sys_dup(fd: usize) -> isize {
-    syscall(SYSCALL_DUP, [fd, 0, 0, 0, 0, 0])
+pub fn sys_mutex_unlock(id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [id, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_semaphore_create(count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [count, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_semaphore_up(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [id, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_semaphore_down(id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [id, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_condvar_signal(id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [id, 0, 0, 0, 0, 0])
+}
+
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0, 0, 0, 0])
+}
+
+// 网络
+
+pub fn sys_socket(domain: usize, ty: usize, protocol: usize) -> isize {
+    syscall(SYSCALL_SOCKET, [domain, ty, protocol, 0, 0, 0])
+}
+
+pub fn sys_socketpair(domain: usize, ty: usize, protocol: usize, sv: *mut i32) -> isize {
+    syscall(
+        SYSCALL_SOCKETPAIR,
+        [domain, ty, protocol, sv as usize, 0, 0],
+    )
 }
 
-pub fn sys_dup3(old:isize, new:isize, flags:usize) -> isize {
-    syscall(SYSCALL_DUP3, [old as usize, new as usize, flags, 0, 0, 0])
+pub fn sys_bind(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    syscall(SYSCALL_BIND, [fd, addr as usize, addrlen, 0, 0, 0])
 }
 
-pub fn sys_getdents(fd:usize, buf:*mut u8, len:usize) -> isize {
-    syscall(SYSCALL_GETDENTS,[fd, buf as usize, len, 0, 0, 0])
-}
\ No newline at end of file
+pub fn sys_listen(fd: usize, backlog: usize) -> isize {
+    syscall(SYSCALL_LISTEN, [fd, backlog, 0, 0, 0, 0])
+}
+
+pub fn sys_accept4(fd: usize, addr: *mut u8, addrlen: *mut u32, flags: usize) -> isize {
+    syscall(
+        SYSCALL_ACCEPT4,
+        [fd, addr as usize, addrlen as usize, flags, 0, 0],
+    )
+}
+
+pub fn sys_connect(fd: usize, addr: *const u8, addrlen: usize) -> isize {
+    syscall(SYSCALL_CONNECT, [fd, addr as usize, addrlen, 0, 0, 0])
+}
+
+pub fn sys_getsockname(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    syscall(
+        SYSCALL_GETSOCKNAME,
+        [fd, addr as usize, addrlen as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_getpeername(fd: usize, addr: *mut u8, addrlen: *mut u32) -> isize {
+    syscall(
+        SYSCALL_GETPEERNAME,
+        [fd, addr as usize, addrlen as usize, 0, 0, 0],
+    )
+}
+
+pub fn sys_sendto(fd: usize, buf: &[u8], flags: usize, addr: *const u8, addrlen: usize) -> isize {
+    syscall(
+        SYSCALL_SENDTO,
+        [
+            fd,
+            buf.as_ptr() as usize,
+            buf.len(),
+            flags,
+            addr as usize,
+            addrlen,
+        ],
+    )
+}
+
+pub fn sys_recvfrom(
+    fd: usize,
+    buf: &mut [u8],
+    flags: usize,
+    addr: *mut u8,
+    addrlen: *mut u32,
+) -> isize {
+    syscall(
+        SYSCALL_RECVFROM,
+        [
+            fd,
+            buf.as_mut_ptr() as usize,
+            buf.len(),
+            flags,
+            addr as usize,
+            addrlen as usize,
+        ],
+    )
+}
+
+pub fn sys_setsockopt(fd: usize, level: usize, name: usize, value: *const u8, len: usize) -> isize {
+    syscall(
+        SYSCALL_SETSOCKOPT,
+        [fd, level, name, value as usize, len, 0],
+    )
+}
+
+pub fn sys_getsockopt(
+    fd: usize,
+    level: usize,
+    name: usize,
+    value: *mut u8,
+    len: *mut u32,
+) -> isize {
+    syscall(
+        SYSCALL_GETSOCKOPT,
+        [fd, level, name, value as usize, len as usize, 0],
+    )
+}
+
+pub fn sys_shutdown(fd: usize, how: usize) -> isize {
+    syscall(SYSCALL_SHUTDOWN, [fd, how, 0, 0, 0, 0])
+}
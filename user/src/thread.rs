@@ -0,0 +1,76 @@
+//! 线程
+//!
+//! ## Overview
+//! - `Thread::spawn`：在当前进程中创建内核线程运行闭包，闭包的返回值作为线程退出码
+//! - `Thread::join`：等待线程退出并取回退出码
+//!
+//! ## Safety
+//! - 闭包被装箱后以裸指针交给新线程，由新线程的入口 `thread_start` 取回并释放
+//! - 新线程没有合法的返回地址，入口函数必须以 `exit` 结束
+//!
+//! ## Behavior
+//! - 内核的 waittid 不阻塞，`join` 在线程运行期间让出 CPU 轮询
+//! - 线程里调用 `exit` 只结束该线程；主线程退出时整个进程结束
+
+use crate::errno::{check, Errno, Result};
+use crate::process::{exit, gettid, yield_};
+use crate::syscall::*;
+use alloc::boxed::Box;
+
+type ThreadMain = Box<dyn FnOnce() -> i32 + Send + 'static>;
+
+/// 已创建的线程
+pub struct Thread {
+    tid: usize,
+}
+
+impl Thread {
+    /// 创建线程运行 `f`
+    pub fn spawn<F>(f: F) -> Result<Thread>
+    where
+        F: FnOnce() -> i32 + Send + 'static,
+    {
+        let main: Box<ThreadMain> = Box::new(Box::new(f));
+        let arg = Box::into_raw(main);
+        match check(sys_thread_create(thread_start as usize, arg as usize)) {
+            Ok(tid) => Ok(Thread { tid }),
+            Err(err) => {
+                // 线程没有创建出来，闭包由这里释放
+                drop(unsafe { Box::from_raw(arg) });
+                Err(err)
+            }
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.tid
+    }
+
+    /// 等待线程退出，返回其退出码
+    pub fn join(self) -> Result<i32> {
+        loop {
+            match sys_waittid(self.tid) {
+                -2 => yield_(),
+                -1 => return Err(Errno::ESRCH),
+                // 与进程一样，退出码保存在第 8～15 位
+                status => return Ok((status as i32 >> 8) & 0xff),
+            }
+        }
+    }
+}
+
+/// 新线程的入口，内核把 `arg` 放在 a0
+extern "C" fn thread_start(arg: usize) -> ! {
+    let main = unsafe { Box::from_raw(arg as *mut ThreadMain) };
+    exit(main());
+}
+
+/// 当前线程的 id
+pub fn current_id() -> usize {
+    gettid()
+}
+
+/// 让出 CPU
+pub fn yield_now() {
+    yield_();
+}
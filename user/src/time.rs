@@ -0,0 +1,128 @@
+//! 时间相关调用
+//!
+//! ## Overview
+//! - `sleep` / `nanosleep`：以 `Duration` 为参数的睡眠
+//! - `gettimeofday`：内核时钟的当前读数
+//! - `Instant`：仿照 `std::time::Instant` 的单调时间点，用来测量经过的时间
+//!
+//! ## Assumptions
+//! - 内核的 gettimeofday 由启动以来的时钟滴答换算而来，不对应真实的日历时间，
+//!   因此只提供 `Instant`，不提供 `SystemTime`
+//!
+//! ## Behavior
+//! - nanosleep 被信号打断时返回 `EINTR`，并给出剩余时间
+
+use crate::errno::{check, Errno, Result};
+use crate::syscall::*;
+use core::ops::{Add, Sub};
+
+pub use core::time::Duration;
+
+#[repr(C)]
+#[derive(Default)]
+struct TimeVal {
+    sec: usize,
+    usec: usize,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct TimeSpec {
+    sec: usize,
+    nsec: usize,
+}
+
+impl From<Duration> for TimeSpec {
+    fn from(dur: Duration) -> Self {
+        Self {
+            sec: dur.as_secs() as usize,
+            nsec: dur.subsec_nanos() as usize,
+        }
+    }
+}
+
+/// 睡眠 `dur`；被信号打断时返回 `Err((EINTR, 剩余时间))`
+pub fn nanosleep(dur: Duration) -> core::result::Result<(), (Errno, Duration)> {
+    let req = TimeSpec::from(dur);
+    let mut rem = TimeSpec::default();
+    match sys_nanosleep(
+        &req as *const TimeSpec as *const u8,
+        &mut rem as *mut TimeSpec as *mut u8,
+    ) {
+        0 => Ok(()),
+        // 内核被信号打断时返回 -1
+        -1 => Err((Errno::EINTR, Duration::new(rem.sec as u64, rem.nsec as u32))),
+        ret => Err((Errno(-ret as i32), Duration::ZERO)),
+    }
+}
+
+/// 睡眠 `dur`，被信号打断时提前返回
+pub fn sleep(dur: Duration) {
+    let _ = nanosleep(dur);
+}
+
+/// 睡眠 `ms` 毫秒
+pub fn sleep_ms(ms: u64) {
+    sleep(Duration::from_millis(ms));
+}
+
+/// 内核时钟的当前读数
+pub fn gettimeofday() -> Result<Duration> {
+    let mut tv = TimeVal::default();
+    check(sys_gettimeofday(&mut tv as *mut TimeVal as *mut u8))?;
+    Ok(Duration::new(tv.sec as u64, tv.usec as u32 * 1000))
+}
+
+/// 单调递增的时间点
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Self {
+        Self(gettimeofday().expect("gettimeofday failed"))
+    }
+
+    /// 自 `earlier` 以来经过的时间，`earlier` 更晚时为 0
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    /// 自该时间点以来经过的时间
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    pub fn checked_add(&self, dur: Duration) -> Option<Instant> {
+        self.0.checked_add(dur).map(Instant)
+    }
+
+    pub fn checked_sub(&self, dur: Duration) -> Option<Instant> {
+        self.0.checked_sub(dur).map(Instant)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, dur: Duration) -> Instant {
+        self.checked_add(dur)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, dur: Duration) -> Instant {
+        self.checked_sub(dur)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, other: Instant) -> Duration {
+        self.duration_since(other)
+    }
+}